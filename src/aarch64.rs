@@ -0,0 +1,196 @@
+//! AArch64 instruction selection, following the AAPCS64 calling convention.
+//!
+//! The argument registers are not allocated, and `x16` and `x17`, which the
+//! convention keeps for scratch use between calls, hold spilled values and
+//! constants while an instruction needs them.
+
+use std::fmt;
+
+use backend::{self, Abi, Assembly, Base, Isa, Source};
+use ir::{BinaryOp, Module, UnaryOp};
+use Type;
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Hash)]
+pub struct Reg(u8);
+
+impl Reg {
+    // The low 32 bits
+    fn w(self) -> String {
+        format!("w{}", self.0)
+    }
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "x{}", self.0)
+    }
+}
+
+const X16: Reg = Reg(16);
+
+fn condition(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Eq => "eq",
+        BinaryOp::Ne => "ne",
+        BinaryOp::Lt => "lt",
+        BinaryOp::Le => "le",
+        BinaryOp::Gt => "gt",
+        BinaryOp::Ge => "ge",
+        BinaryOp::ULt => "lo",
+        BinaryOp::ULe => "ls",
+        BinaryOp::UGt => "hi",
+        BinaryOp::UGe => "hs",
+        _ => unreachable!("{} is not a comparison", op.name())
+    }
+}
+
+// Whether a value fits the unsigned 12-bit immediate of arithmetic and compares
+fn fits_imm12(value: i64) -> bool {
+    (0..4096).contains(&value)
+}
+
+fn base(base: Base) -> &'static str {
+    match base {
+        Base::StackPointer => "sp",
+        Base::FramePointer => "x29"
+    }
+}
+
+fn source(source: Source<Reg>) -> String {
+    match source {
+        Source::Reg(register) => register.to_string(),
+        Source::Imm(value) => format!("#{}", value)
+    }
+}
+
+pub struct Aarch64;
+
+impl Isa for Aarch64 {
+    type Reg = Reg;
+
+    const ABI: Abi<Reg> = Abi {
+        argument_registers: &[Reg(0), Reg(1), Reg(2), Reg(3), Reg(4), Reg(5), Reg(6), Reg(7)],
+        return_register: Reg(0),
+        caller_saved: &[Reg(9), Reg(10), Reg(11), Reg(12), Reg(13), Reg(14), Reg(15)],
+        callee_saved: &[Reg(19), Reg(20), Reg(21), Reg(22), Reg(23), Reg(24), Reg(25), Reg(26), Reg(27), Reg(28)],
+        scratch: [Reg(16), Reg(17)]
+    };
+
+    fn prologue(asm: &mut Assembly, frame_size: usize) {
+        asm.emit("stp x29, x30, [sp, #-16]!");
+        asm.emit("mov x29, sp");
+
+        if fits_imm12(frame_size as i64) {
+            asm.emit(&format!("sub sp, sp, #{}", frame_size));
+        } else {
+            Aarch64::load_immediate(asm, X16, frame_size as i64);
+            asm.emit(&format!("sub sp, sp, {}", X16));
+        }
+    }
+
+    fn epilogue(asm: &mut Assembly) {
+        asm.emit("mov sp, x29");
+        asm.emit("ldp x29, x30, [sp], #16");
+        asm.emit("ret");
+    }
+
+    // Stack arguments are above the frame record
+    fn incoming_argument(index: usize) -> i64 {
+        16 + index as i64 * 8
+    }
+
+    // Builds a constant 16 bits at a time
+    fn load_immediate(asm: &mut Assembly, dst: Reg, value: i64) {
+        if (-65536..65536).contains(&value) {
+            return asm.emit(&format!("mov {}, #{}", dst, value));
+        }
+
+        let mut first = true;
+
+        for shift in (0..64).step_by(16) {
+            let chunk = (value as u64 >> shift) & 0xffff;
+
+            if chunk != 0 {
+                let mnemonic = if first { "movz" } else { "movk" };
+                asm.emit(&format!("{} {}, #{}, lsl #{}", mnemonic, dst, chunk, shift));
+                first = false;
+            }
+        }
+    }
+
+    fn load(asm: &mut Assembly, dst: Reg, from: Base, offset: i64) {
+        asm.emit(&format!("ldr {}, [{}, #{}]", dst, base(from), offset));
+    }
+
+    fn store(asm: &mut Assembly, src: Reg, to: Base, offset: i64) {
+        asm.emit(&format!("str {}, [{}, #{}]", src, base(to), offset));
+    }
+
+    fn mov(asm: &mut Assembly, dst: Reg, src: Reg) {
+        asm.emit(&format!("mov {}, {}", dst, src));
+    }
+
+    fn accepts_immediate(op: BinaryOp, value: i64) -> bool {
+        match op {
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::UDiv => false,
+            _ => fits_imm12(value)
+        }
+    }
+
+    fn unary(asm: &mut Assembly, op: UnaryOp, dst: Reg, src: Reg) {
+        let mnemonic = match op {
+            UnaryOp::Neg => "neg",
+            UnaryOp::Not => "mvn"
+        };
+
+        asm.emit(&format!("{} {}, {}", mnemonic, dst, src));
+    }
+
+    fn binary(asm: &mut Assembly, op: BinaryOp, dst: Reg, lhs: Reg, rhs: Source<Reg>) {
+        let mnemonic = match op {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "mul",
+            BinaryOp::Div => "sdiv",
+            BinaryOp::UDiv => "udiv",
+            // Register shift amounts are taken modulo 64 too
+            BinaryOp::Shl => "lsl",
+            BinaryOp::Sar => "asr",
+            BinaryOp::Shr => "lsr",
+            _ => {
+                asm.emit(&format!("cmp {}, {}", lhs, source(rhs)));
+                return asm.emit(&format!("cset {}, {}", dst, condition(op)));
+            }
+        };
+
+        asm.emit(&format!("{} {}, {}, {}", mnemonic, dst, lhs, source(rhs)));
+    }
+
+    fn extend(asm: &mut Assembly, ty: Type, dst: Reg, src: Reg) {
+        match ty {
+            Type::Char => asm.emit(&format!("sxtb {}, {}", dst, src.w())),
+            Type::Int => asm.emit(&format!("sxtw {}, {}", dst, src.w())),
+            // Writing the low 32 bits clears the rest
+            _ => asm.emit(&format!("mov {}, {}", dst.w(), src.w()))
+        }
+    }
+
+    fn branch(asm: &mut Assembly, condition: Reg, if_zero: bool, label: &str) {
+        let mnemonic = if if_zero { "cbz" } else { "cbnz" };
+
+        asm.emit(&format!("{} {}, {}", mnemonic, condition, label));
+    }
+
+    fn jump(asm: &mut Assembly, label: &str) {
+        asm.emit(&format!("b {}", label));
+    }
+
+    fn call(asm: &mut Assembly, function: &str) {
+        asm.emit(&format!("bl {}", function));
+    }
+}
+
+/// Generates AArch64 assembly for a module that is out of SSA form.
+pub fn generate(module: &Module) -> String {
+    backend::generate::<Aarch64>(module)
+}
@@ -0,0 +1,465 @@
+//! Code generation from IR for load/store machines, shared by the AArch64
+//! and RISC-V backends.
+//!
+//! A backend describes its calling convention with an `Abi` and selects
+//! instructions through the `Isa` trait. This module does the rest: register
+//! allocation, the frame layout, moving parameters and arguments, and saving
+//! registers around calls.
+//!
+//! The frame is addressed from the stack pointer, which stays put after the
+//! prologue: outgoing stack arguments at the bottom, then spill slots, then
+//! slots to save caller-saved and callee-saved registers in. The prologue
+//! keeps the frame pointer pointing just past them.
+
+use std::fmt;
+
+use ir::{self, BinaryOp, BlockId, Function, Module, Terminator, UnaryOp, VReg};
+use regalloc::{self, Allocation, Location};
+use Type;
+
+/// The registers a calling convention gives each purpose.
+pub struct Abi<R: 'static> {
+    pub argument_registers: &'static [R],
+    pub return_register: R,
+    /// Registers the allocator may use that calls do not preserve.
+    pub caller_saved: &'static [R],
+    pub callee_saved: &'static [R],
+    /// Registers never allocated, that an instruction can load its operands
+    /// into.
+    pub scratch: [R; 2]
+}
+
+impl<R: Copy + Eq> Abi<R> {
+    /// Coalesces the copies in a function and allocates its registers.
+    pub fn allocate(&self, function: &Function) -> (Function, Allocation<R>) {
+        let mut function = function.clone();
+        regalloc::coalesce(&mut function);
+
+        let allocation = regalloc::allocate(&function, self.caller_saved, self.callee_saved);
+
+        (function, allocation)
+    }
+
+    /// The number of slots a frame needs: the spill slots, then one for each
+    /// caller-saved register to keep it in across calls, then one for each
+    /// callee-saved register the function uses.
+    pub fn frame_slots(&self, allocation: &Allocation<R>) -> usize {
+        allocation.spill_slots + self.caller_saved.len() + allocation.callee_saved_used.len()
+    }
+
+    pub fn caller_save_slot(&self, allocation: &Allocation<R>, register: R) -> usize {
+        let index = self.caller_saved.iter().position(|&other| other == register).unwrap();
+
+        allocation.spill_slots + index
+    }
+
+    /// The slot for the callee-saved register at `index` in
+    /// `callee_saved_used`.
+    pub fn callee_save_slot(&self, allocation: &Allocation<R>, index: usize) -> usize {
+        allocation.spill_slots + self.caller_saved.len() + index
+    }
+
+    /// Where each parameter that is used arrives. Callers need not extend
+    /// arguments narrower than a word, so the type says how to extend it.
+    pub fn parameters(&self, function: &Function, allocation: &Allocation<R>) -> Vec<(VReg, Type, Incoming<R>)> {
+        function.params.iter().enumerate()
+            .filter(|&(_, &(reg, _))| allocation.location(reg).is_some())
+            .map(|(index, &(reg, ty))| {
+                let incoming = match self.argument_registers.get(index) {
+                    Some(&register) => Incoming::Reg(register),
+                    None => Incoming::Stack(index - self.argument_registers.len())
+                };
+
+                (reg, ty, incoming)
+            })
+            .collect()
+    }
+}
+
+/// Where a parameter is passed.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum Incoming<R> {
+    Reg(R),
+    /// The index among the arguments passed on the stack, counting from 0.
+    Stack(usize)
+}
+
+/// The blocks of a function in the order their code is laid out, each with
+/// the block after it, which its terminator can fall through to.
+pub fn block_order(function: &Function) -> Vec<(BlockId, Option<BlockId>)> {
+    let blocks: Vec<BlockId> = function.block_ids().collect();
+
+    blocks.iter().enumerate()
+        .map(|(position, &id)| (id, blocks.get(position + 1).cloned()))
+        .collect()
+}
+
+/// A register an address is relative to.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum Base {
+    StackPointer,
+    FramePointer
+}
+
+/// The second operand of an instruction.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum Source<R> {
+    Reg(R),
+    Imm(i64)
+}
+
+/// Assembly text as it is built.
+#[derive(Debug,Default)]
+pub struct Assembly {
+    buf: String
+}
+
+impl Assembly {
+    pub fn emit(&mut self, line: &str) {
+        self.buf.push_str(line);
+        self.buf.push('\n');
+    }
+}
+
+/// Instruction selection for one machine. Each method appends the
+/// instructions for one operation; registers may be the same unless noted.
+pub trait Isa {
+    type Reg: Copy + Eq + fmt::Display + 'static;
+
+    const ABI: Abi<Self::Reg>;
+
+    /// Saves the return address and frame pointer, points the frame pointer
+    /// at them, and reserves `frame_size` bytes below.
+    fn prologue(asm: &mut Assembly, frame_size: usize);
+    /// Undoes the prologue and returns.
+    fn epilogue(asm: &mut Assembly);
+    /// Where the stack argument `index`, counting from 0, is relative to the
+    /// frame pointer.
+    fn incoming_argument(index: usize) -> i64;
+
+    fn load_immediate(asm: &mut Assembly, dst: Self::Reg, value: i64);
+    fn load(asm: &mut Assembly, dst: Self::Reg, base: Base, offset: i64);
+    fn store(asm: &mut Assembly, src: Self::Reg, base: Base, offset: i64);
+    fn mov(asm: &mut Assembly, dst: Self::Reg, src: Self::Reg);
+
+    /// Whether `binary` takes `value` as the right operand of `op` directly.
+    fn accepts_immediate(op: BinaryOp, value: i64) -> bool;
+    fn unary(asm: &mut Assembly, op: UnaryOp, dst: Self::Reg, src: Self::Reg);
+    /// Shift counts are already reduced modulo 64.
+    fn binary(asm: &mut Assembly, op: BinaryOp, dst: Self::Reg, lhs: Self::Reg, rhs: Source<Self::Reg>);
+    /// Extends the low bits of `src` as a conversion to `ty` would.
+    fn extend(asm: &mut Assembly, ty: Type, dst: Self::Reg, src: Self::Reg);
+
+    fn branch(asm: &mut Assembly, condition: Self::Reg, if_zero: bool, label: &str);
+    fn jump(asm: &mut Assembly, label: &str);
+    fn call(asm: &mut Assembly, function: &str);
+}
+
+// Where an operand is found
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+enum Value<R> {
+    Reg(R),
+    /// A byte offset from the stack pointer.
+    Stack(usize),
+    Imm(i64)
+}
+
+struct FunctionGenerator<'a, I: Isa> {
+    function: &'a Function,
+    allocation: Allocation<I::Reg>,
+    // Bytes at the bottom of the frame for arguments passed on the stack
+    outgoing: usize,
+    asm: Assembly
+}
+
+impl<'a, I: Isa> FunctionGenerator<'a, I> {
+    fn slot(&self, slot: usize) -> Value<I::Reg> {
+        Value::Stack(self.outgoing + slot * 8)
+    }
+
+    fn caller_save_slot(&self, register: I::Reg) -> Value<I::Reg> {
+        self.slot(I::ABI.caller_save_slot(&self.allocation, register))
+    }
+
+    fn callee_save_slot(&self, index: usize) -> Value<I::Reg> {
+        self.slot(I::ABI.callee_save_slot(&self.allocation, index))
+    }
+
+    fn frame_size(&self) -> usize {
+        (self.outgoing + I::ABI.frame_slots(&self.allocation) * 8).div_ceil(16) * 16
+    }
+
+    fn location(&self, reg: VReg) -> Value<I::Reg> {
+        match self.allocation.location(reg) {
+            Some(Location::Reg(register)) => Value::Reg(register),
+            Some(Location::Stack(slot)) => self.slot(slot),
+            None => panic!("{} in function {} was not allocated", reg, self.function.name)
+        }
+    }
+
+    fn value(&self, operand: ir::Operand) -> Value<I::Reg> {
+        match operand {
+            ir::Operand::Reg(reg) => self.location(reg),
+            ir::Operand::Imm(value) => Value::Imm(value)
+        }
+    }
+
+    fn label(&self, block: BlockId) -> String {
+        format!(".L{}.{}", self.function.name, block)
+    }
+
+    // The register holding a value, loading it into `scratch` if need be
+    fn load(&mut self, value: Value<I::Reg>, scratch: I::Reg) -> I::Reg {
+        match value {
+            Value::Reg(register) => register,
+            Value::Stack(offset) => {
+                I::load(&mut self.asm, scratch, Base::StackPointer, offset as i64);
+                scratch
+            },
+            Value::Imm(value) => {
+                I::load_immediate(&mut self.asm, scratch, value);
+                scratch
+            }
+        }
+    }
+
+    fn store(&mut self, register: I::Reg, dst: Value<I::Reg>) {
+        match dst {
+            Value::Reg(dst) if dst == register => {},
+            Value::Reg(dst) => I::mov(&mut self.asm, dst, register),
+            Value::Stack(offset) => I::store(&mut self.asm, register, Base::StackPointer, offset as i64),
+            Value::Imm(_) => panic!("cannot store to an immediate")
+        }
+    }
+
+    // The register to compute a result for `dst` in
+    fn target(dst: Value<I::Reg>) -> I::Reg {
+        match dst {
+            Value::Reg(register) => register,
+            _ => I::ABI.scratch[0]
+        }
+    }
+
+    fn mov(&mut self, src: Value<I::Reg>, dst: Value<I::Reg>) {
+        if src == dst {
+            return;
+        }
+
+        let register = match dst {
+            Value::Reg(register) => match src {
+                Value::Reg(src) => {
+                    I::mov(&mut self.asm, register, src);
+                    return;
+                },
+                _ => register
+            },
+            _ => I::ABI.scratch[0]
+        };
+
+        let register = self.load(src, register);
+        self.store(register, dst);
+    }
+
+    fn generate(mut self) -> String {
+        let function = self.function;
+
+        self.asm.emit(&format!(".globl {}", function.name));
+        self.asm.emit(&format!("{}:", function.name));
+
+        let frame_size = self.frame_size();
+        I::prologue(&mut self.asm, frame_size);
+
+        for (index, register) in self.allocation.callee_saved_used.clone().into_iter().enumerate() {
+            let slot = self.callee_save_slot(index);
+            self.store(register, slot);
+        }
+
+        // The argument registers are never allocated, so the parameters can
+        // be moved one at a time
+        for (reg, ty, incoming) in I::ABI.parameters(function, &self.allocation) {
+            let location = self.location(reg);
+
+            match incoming {
+                Incoming::Reg(register) => self.store(register, location),
+                Incoming::Stack(index) => {
+                    let register = FunctionGenerator::<I>::target(location);
+
+                    I::load(&mut self.asm, register, Base::FramePointer, I::incoming_argument(index));
+                    self.store(register, location);
+                }
+            }
+
+            self.cast(ty, location, location);
+        }
+
+        for (id, next) in block_order(function) {
+            let label = self.label(id);
+            self.asm.emit(&format!("{}:", label));
+
+            for (index, instr) in function.block(id).instrs.iter().enumerate() {
+                self.generate_instr(id, index, instr);
+            }
+
+            self.generate_terminator(&function.block(id).terminator, next);
+        }
+
+        self.asm.buf
+    }
+
+    fn cast(&mut self, ty: Type, src: Value<I::Reg>, dst: Value<I::Reg>) {
+        if let Type::Long | Type::Void = ty {
+            return self.mov(src, dst);
+        }
+
+        let source = self.load(src, I::ABI.scratch[0]);
+        let target = FunctionGenerator::<I>::target(dst);
+
+        I::extend(&mut self.asm, ty, target, source);
+        self.store(target, dst);
+    }
+
+    fn generate_instr(&mut self, block: BlockId, index: usize, instr: &ir::Instr) {
+        let abi = &I::ABI;
+
+        match *instr {
+            ir::Instr::Copy { dst, src } => {
+                let (src, dst) = (self.value(src), self.location(dst));
+                self.mov(src, dst);
+            },
+            ir::Instr::Unary { op, dst, src } => {
+                let dst = self.location(dst);
+                let source = self.value(src);
+                let source = self.load(source, abi.scratch[0]);
+                let target = FunctionGenerator::<I>::target(dst);
+
+                I::unary(&mut self.asm, op, target, source);
+                self.store(target, dst);
+            },
+            ir::Instr::Binary { op, dst, lhs, rhs } => self.generate_binary(op, dst, lhs, rhs),
+            ir::Instr::Cast { dst, ty, src } => {
+                let (src, dst) = (self.value(src), self.location(dst));
+                self.cast(ty, src, dst);
+            },
+            ir::Instr::Call { dst, ref function, ref args } => {
+                let saved = self.allocation.saved_at_call(block, index).to_vec();
+
+                for &register in &saved {
+                    let slot = self.caller_save_slot(register);
+                    self.store(register, slot);
+                }
+
+                for (slot, &arg) in args.iter().skip(abi.argument_registers.len()).enumerate() {
+                    let value = self.value(arg);
+                    let register = self.load(value, abi.scratch[0]);
+                    self.store(register, Value::Stack(slot * 8));
+                }
+
+                for (&arg, &register) in args.iter().zip(abi.argument_registers.iter()) {
+                    let value = self.value(arg);
+                    self.mov(value, Value::Reg(register));
+                }
+
+                I::call(&mut self.asm, function);
+
+                if let Some(dst) = dst {
+                    if self.allocation.location(dst).is_some() {
+                        let dst = self.location(dst);
+                        self.store(abi.return_register, dst);
+                    }
+                }
+
+                for &register in &saved {
+                    let slot = self.caller_save_slot(register);
+                    self.mov(slot, Value::Reg(register));
+                }
+            },
+            ir::Instr::Phi { .. } => panic!("phi in function {} reached code generation", self.function.name)
+        }
+    }
+
+    fn generate_binary(&mut self, op: BinaryOp, dst: VReg, lhs: ir::Operand, rhs: ir::Operand) {
+        let dst = self.location(dst);
+        let (lhs, rhs) = (self.value(lhs), self.value(rhs));
+        let target = FunctionGenerator::<I>::target(dst);
+
+        let left = self.load(lhs, I::ABI.scratch[0]);
+
+        let right = match (op, rhs) {
+            (BinaryOp::Shl, Value::Imm(count)) | (BinaryOp::Sar, Value::Imm(count)) | (BinaryOp::Shr, Value::Imm(count)) => {
+                Source::Imm(count & 63)
+            },
+            (_, Value::Imm(value)) if I::accepts_immediate(op, value) => Source::Imm(value),
+            _ => Source::Reg(self.load(rhs, I::ABI.scratch[1]))
+        };
+
+        I::binary(&mut self.asm, op, target, left, right);
+        self.store(target, dst);
+    }
+
+    fn generate_terminator(&mut self, terminator: &Terminator, next: Option<BlockId>) {
+        match *terminator {
+            Terminator::Return(value) => {
+                if let Some(value) = value {
+                    let value = self.value(value);
+                    self.mov(value, Value::Reg(I::ABI.return_register));
+                }
+
+                for (index, register) in self.allocation.callee_saved_used.clone().into_iter().enumerate() {
+                    let slot = self.callee_save_slot(index);
+                    self.mov(slot, Value::Reg(register));
+                }
+
+                I::epilogue(&mut self.asm);
+            },
+            Terminator::Jump(target) => self.jump(target, next),
+            Terminator::Branch { condition, then_block, else_block } => {
+                let register = match self.value(condition) {
+                    Value::Imm(0) => return self.jump(else_block, next),
+                    Value::Imm(_) => return self.jump(then_block, next),
+                    condition => self.load(condition, I::ABI.scratch[0])
+                };
+
+                // Fall through to whichever block comes next
+                if next == Some(then_block) {
+                    let label = self.label(else_block);
+                    I::branch(&mut self.asm, register, true, &label);
+                } else {
+                    let label = self.label(then_block);
+                    I::branch(&mut self.asm, register, false, &label);
+                    self.jump(else_block, next);
+                }
+            }
+        }
+    }
+
+    fn jump(&mut self, target: BlockId, next: Option<BlockId>) {
+        if next != Some(target) {
+            let label = self.label(target);
+            I::jump(&mut self.asm, &label);
+        }
+    }
+}
+
+/// Generates assembly for a module that is out of SSA form.
+pub fn generate<I: Isa>(module: &Module) -> String {
+    module.functions.iter().map(|function| {
+        let (function, allocation) = I::ABI.allocate(function);
+
+        // Stack arguments go at the bottom of the frame, in a 16 byte aligned area
+        let stack_args = function.blocks.iter()
+            .flat_map(|block| block.instrs.iter())
+            .map(|instr| match *instr {
+                ir::Instr::Call { ref args, .. } => args.len().saturating_sub(I::ABI.argument_registers.len()),
+                _ => 0
+            })
+            .max()
+            .unwrap_or(0);
+
+        FunctionGenerator::<I> {
+            function: &function,
+            allocation,
+            outgoing: (stack_args * 8).div_ceil(16) * 16,
+            asm: Assembly::default()
+        }.generate()
+    }).collect()
+}
@@ -0,0 +1,484 @@
+//! x86 code generation from IR, used when optimizing, and for 32-bit code.
+//!
+//! Virtual registers are assigned to machine registers by `regalloc`, with
+//! `%rax`, `%rcx` and `%rdx` kept back as scratch registers for the
+//! instructions that need particular ones, such as division and shifts.
+//! The data layout decides the word size, and with it the calling
+//! convention: System V on x86-64, and cdecl on i386.
+
+use backend::{self, Abi, Incoming};
+use ir::{self, BinaryOp, BlockId, Function, Module, Terminator, UnaryOp, VReg};
+use regalloc::{Allocation, Location};
+use target::DataLayout;
+use x86::{Condition, Instr, Label, Operand, Reg};
+use Type;
+
+const SYSTEM_V: Abi<Reg> = Abi {
+    argument_registers: &[Reg::Rdi, Reg::Rsi, Reg::Rdx, Reg::Rcx, Reg::R8, Reg::R9],
+    return_register: Reg::Rax,
+    caller_saved: &[Reg::Rdi, Reg::Rsi, Reg::R8, Reg::R9, Reg::R10, Reg::R11],
+    callee_saved: &[Reg::Rbx, Reg::R12, Reg::R13, Reg::R14, Reg::R15],
+    scratch: [Reg::Rax, Reg::Rcx]
+};
+
+// Every argument is passed on the stack, and the registers a call may
+// clobber are all kept for scratch use
+const CDECL: Abi<Reg> = Abi {
+    argument_registers: &[],
+    return_register: Reg::Rax,
+    caller_saved: &[],
+    callee_saved: &[Reg::Rbx, Reg::Rsi, Reg::Rdi],
+    scratch: [Reg::Rax, Reg::Rcx]
+};
+
+const RAX: Operand = Operand::Reg(Reg::Rax);
+const RCX: Operand = Operand::Reg(Reg::Rcx);
+const RSP: Operand = Operand::Reg(Reg::Rsp);
+const RBP: Operand = Operand::Reg(Reg::Rbp);
+
+fn condition(op: BinaryOp) -> Condition {
+    match op {
+        BinaryOp::Eq => Condition::E,
+        BinaryOp::Ne => Condition::Ne,
+        BinaryOp::Lt => Condition::L,
+        BinaryOp::Le => Condition::Le,
+        BinaryOp::Gt => Condition::G,
+        BinaryOp::Ge => Condition::Ge,
+        BinaryOp::ULt => Condition::B,
+        BinaryOp::ULe => Condition::Be,
+        BinaryOp::UGt => Condition::A,
+        BinaryOp::UGe => Condition::Ae,
+        _ => unreachable!("{} is not a comparison", op.name())
+    }
+}
+
+struct FunctionGenerator<'a> {
+    function: &'a Function,
+    layout: DataLayout,
+    abi: &'static Abi<Reg>,
+    allocation: Allocation<Reg>,
+    instrs: Vec<Instr>
+}
+
+impl<'a> FunctionGenerator<'a> {
+    fn word(&self) -> i64 {
+        self.layout.word_size as i64
+    }
+
+    // Slots go down from the frame pointer
+    fn frame_slot(&self, slot: usize) -> Operand {
+        Operand::Mem { base: Reg::Rbp, offset: -self.word() * (slot as i64 + 1) }
+    }
+
+    fn caller_save_slot(&self, register: Reg) -> Operand {
+        self.frame_slot(self.abi.caller_save_slot(&self.allocation, register))
+    }
+
+    fn callee_save_slot(&self, index: usize) -> Operand {
+        self.frame_slot(self.abi.callee_save_slot(&self.allocation, index))
+    }
+
+    fn location(&self, reg: VReg) -> Operand {
+        match self.allocation.location(reg) {
+            Some(Location::Reg(register)) => Operand::Reg(register),
+            Some(Location::Stack(slot)) => self.frame_slot(slot),
+            None => panic!("{} in function {} was not allocated", reg, self.function.name)
+        }
+    }
+
+    // Values fit a 32-bit word as either signed or unsigned, and are written
+    // as signed so that every one is a valid immediate
+    fn value(&self, operand: ir::Operand) -> Operand {
+        match operand {
+            ir::Operand::Reg(reg) => self.location(reg),
+            ir::Operand::Imm(value) if self.word() == 4 => Operand::Imm(value as i32 as i64),
+            ir::Operand::Imm(value) => Operand::Imm(value)
+        }
+    }
+
+    fn label(&self, block: BlockId) -> Label {
+        Label(format!(".L{}.{}", self.function.name, block))
+    }
+
+    fn emit(&mut self, instr: Instr) {
+        self.instrs.push(instr);
+    }
+
+    fn mov(&mut self, src: Operand, dst: Operand) {
+        if src == dst {
+            return;
+        }
+
+        // Memory to memory moves and wide immediates to memory need a register
+        let through_register = match (src, dst) {
+            (Operand::Mem { .. }, Operand::Mem { .. }) => true,
+            (Operand::Imm(_), Operand::Mem { .. }) => !src.fits_imm32(),
+            _ => false
+        };
+
+        if through_register {
+            self.emit(Instr::Mov { src, dst: RAX });
+            self.emit(Instr::Mov { src: RAX, dst });
+        } else {
+            self.emit(Instr::Mov { src, dst });
+        }
+    }
+
+    // An operand that can be used as the source of an arithmetic instruction
+    fn source(&mut self, operand: ir::Operand) -> Operand {
+        let value = self.value(operand);
+
+        if value.fits_imm32() {
+            value
+        } else {
+            self.mov(value, RCX);
+            RCX
+        }
+    }
+
+    // Performs copies that happen all at once, ordering them so none
+    // overwrites a value another still needs, and breaking cycles with %rax
+    fn parallel_move(&mut self, mut moves: Vec<(Operand, Operand)>) {
+        moves.retain(|&(src, dst)| src != dst);
+
+        while !moves.is_empty() {
+            let ready = moves.iter().position(|&(_, dst)| !moves.iter().any(|&(src, _)| src == dst));
+
+            match ready {
+                Some(index) => {
+                    let (src, dst) = moves.remove(index);
+                    self.mov(src, dst);
+                },
+                None => {
+                    let saved = moves[0].1;
+                    self.mov(saved, RAX);
+
+                    for &mut (ref mut src, _) in moves.iter_mut() {
+                        if *src == saved {
+                            *src = RAX;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn generate(mut self) -> Vec<Instr> {
+        let function = self.function;
+        let word = self.layout.word_size;
+        let alignment = self.layout.stack_alignment;
+        let slots = self.abi.frame_slots(&self.allocation);
+
+        // The return address and saved frame pointer sit above the frame
+        let frame_size = (slots * word + 2 * word).div_ceil(alignment) * alignment - 2 * word;
+
+        self.emit(Instr::Global(function.name.clone()));
+        self.emit(Instr::Label(Label(function.name.clone())));
+        self.emit(Instr::Push(RBP));
+        self.emit(Instr::Mov { src: RSP, dst: RBP });
+        self.emit(Instr::Sub { src: Operand::Imm(frame_size as i64), dst: RSP });
+
+        for (index, register) in self.allocation.callee_saved_used.clone().into_iter().enumerate() {
+            let slot = self.callee_save_slot(index);
+            self.mov(Operand::Reg(register), slot);
+        }
+
+        // Argument registers are allocated on x86-64, so the parameters are
+        // moved all at once. The ones on the stack were pushed by the caller,
+        // above the return address.
+        let parameters = self.abi.parameters(function, &self.allocation);
+
+        let moves = parameters.iter()
+            .map(|&(reg, _, incoming)| {
+                let source = match incoming {
+                    Incoming::Reg(register) => Operand::Reg(register),
+                    Incoming::Stack(index) => Operand::Mem { base: Reg::Rbp, offset: self.word() * (2 + index as i64) }
+                };
+
+                (source, self.location(reg))
+            })
+            .collect();
+
+        self.parallel_move(moves);
+
+        for (reg, ty, _) in parameters {
+            let location = self.location(reg);
+            self.cast(ty, location, location);
+        }
+
+        for (id, next) in backend::block_order(function) {
+            let label = self.label(id);
+            self.emit(Instr::Label(label));
+
+            for (index, instr) in function.block(id).instrs.iter().enumerate() {
+                self.generate_instr(id, index, instr);
+            }
+
+            self.generate_terminator(&function.block(id).terminator, next);
+        }
+
+        self.instrs
+    }
+
+    // Converts `src` to `ty` into `dst`, going through %rax unless `dst` is a register
+    fn cast(&mut self, ty: Type, src: Operand, dst: Operand) {
+        if ty == Type::Void || ty.size() >= self.layout.word_size {
+            return self.mov(src, dst);
+        }
+
+        // Immediates cannot be extended, but they are already normalized
+        let src = match src {
+            Operand::Imm(_) => {
+                self.mov(src, RAX);
+                RAX
+            },
+            // On i386 only %eax to %edx have a low byte
+            Operand::Reg(Reg::Rsi) | Operand::Reg(Reg::Rdi) if self.word() == 4 => {
+                self.mov(src, RAX);
+                RAX
+            },
+            _ => src
+        };
+
+        let target = match dst {
+            Operand::Reg(register) => register,
+            _ => Reg::Rax
+        };
+
+        self.emit(match ty {
+            Type::Char => Instr::Movsx8 { src, dst: target },
+            Type::Int => Instr::Movsx32 { src, dst: target },
+            _ => Instr::Movzx32 { src, dst: target }
+        });
+        self.mov(Operand::Reg(target), dst);
+    }
+
+    fn generate_instr(&mut self, block: BlockId, index: usize, instr: &ir::Instr) {
+        match *instr {
+            ir::Instr::Copy { dst, src } => {
+                let (src, dst) = (self.value(src), self.location(dst));
+                self.mov(src, dst);
+            },
+            ir::Instr::Unary { op, dst, src } => {
+                let dst = self.location(dst);
+                let target = if let Operand::Reg(_) = dst { dst } else { RAX };
+                let src = self.value(src);
+
+                self.mov(src, target);
+                self.emit(match op {
+                    UnaryOp::Neg => Instr::Neg(target),
+                    UnaryOp::Not => Instr::Not(target)
+                });
+                self.mov(target, dst);
+            },
+            ir::Instr::Binary { op, dst, lhs, rhs } => self.generate_binary(op, dst, lhs, rhs),
+            ir::Instr::Cast { dst, ty, src } => {
+                let (src, dst) = (self.value(src), self.location(dst));
+                self.cast(ty, src, dst);
+            },
+            ir::Instr::Call { dst, ref function, ref args } => {
+                let saved = self.allocation.saved_at_call(block, index).to_vec();
+
+                for &register in &saved {
+                    let slot = self.caller_save_slot(register);
+                    self.mov(Operand::Reg(register), slot);
+                }
+
+                let stack_args = args.len().saturating_sub(self.abi.argument_registers.len());
+                let (word, alignment) = (self.layout.word_size, self.layout.stack_alignment);
+                let stack_size = (stack_args * word).div_ceil(alignment) * alignment;
+
+                // The frame keeps %rsp aligned, so only the arguments need padding
+                let padding = (stack_size - stack_args * word) as i64;
+
+                if padding > 0 {
+                    self.emit(Instr::Sub { src: Operand::Imm(padding), dst: RSP });
+                }
+
+                for &arg in args.iter().skip(self.abi.argument_registers.len()).rev() {
+                    let value = self.value(arg);
+
+                    if value.fits_imm32() {
+                        self.emit(Instr::Push(value));
+                    } else {
+                        self.mov(value, RAX);
+                        self.emit(Instr::Push(RAX));
+                    }
+                }
+
+                let moves = args.iter().zip(self.abi.argument_registers.iter())
+                    .map(|(&arg, &register)| (self.value(arg), Operand::Reg(register)))
+                    .collect();
+
+                self.parallel_move(moves);
+                self.emit(Instr::Call(function.clone()));
+
+                if stack_size > 0 {
+                    self.emit(Instr::Add { src: Operand::Imm(stack_size as i64), dst: RSP });
+                }
+
+                if let Some(dst) = dst {
+                    if self.allocation.location(dst).is_some() {
+                        let dst = self.location(dst);
+                        self.mov(RAX, dst);
+                    }
+                }
+
+                for &register in &saved {
+                    let slot = self.caller_save_slot(register);
+                    self.mov(slot, Operand::Reg(register));
+                }
+            },
+            ir::Instr::Phi { .. } => panic!("phi in function {} reached code generation", self.function.name)
+        }
+    }
+
+    fn generate_binary(&mut self, op: BinaryOp, dst: VReg, lhs: ir::Operand, rhs: ir::Operand) {
+        let dst = self.location(dst);
+        let (lhs, rhs_value) = (self.value(lhs), self.value(rhs));
+
+        // Compute in the destination register unless that would overwrite the
+        // right operand before it is read
+        let target = match dst {
+            Operand::Reg(register) if dst != rhs_value => register,
+            _ => Reg::Rax
+        };
+
+        match op {
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul => {
+                let rhs = self.source(rhs);
+
+                self.mov(lhs, Operand::Reg(target));
+                self.emit(match op {
+                    BinaryOp::Add => Instr::Add { src: rhs, dst: Operand::Reg(target) },
+                    BinaryOp::Sub => Instr::Sub { src: rhs, dst: Operand::Reg(target) },
+                    _ => Instr::Imul { src: rhs, dst: target }
+                });
+                self.mov(Operand::Reg(target), dst);
+            },
+            BinaryOp::Div | BinaryOp::UDiv => {
+                let divisor = match rhs_value {
+                    Operand::Imm(_) => {
+                        self.mov(rhs_value, RCX);
+                        RCX
+                    },
+                    _ => rhs_value
+                };
+
+                self.mov(lhs, RAX);
+
+                if op == BinaryOp::Div {
+                    self.emit(Instr::Cqo);
+                    self.emit(Instr::Idiv(divisor));
+                } else {
+                    self.emit(Instr::Zero(Reg::Rdx));
+                    self.emit(Instr::Div(divisor));
+                }
+
+                self.mov(RAX, dst);
+            },
+            BinaryOp::Shl | BinaryOp::Sar | BinaryOp::Shr => {
+                let count = match rhs_value {
+                    Operand::Imm(count) => Operand::Imm(count & (self.word() * 8 - 1)),
+                    _ => {
+                        self.mov(rhs_value, RCX);
+                        RCX
+                    }
+                };
+
+                let target = Operand::Reg(target);
+
+                self.mov(lhs, target);
+                self.emit(match op {
+                    BinaryOp::Shl => Instr::Shl { count, dst: target },
+                    BinaryOp::Sar => Instr::Sar { count, dst: target },
+                    _ => Instr::Shr { count, dst: target }
+                });
+                self.mov(target, dst);
+            },
+            _ => {
+                let rhs = self.source(rhs);
+
+                let left = match lhs {
+                    Operand::Reg(_) => lhs,
+                    _ => {
+                        self.mov(lhs, RAX);
+                        RAX
+                    }
+                };
+
+                self.emit(Instr::Cmp { src: rhs, dst: left });
+                self.emit(Instr::Set(condition(op), Reg::Rax));
+
+                let target = if let Operand::Reg(register) = dst { register } else { Reg::Rax };
+
+                self.emit(Instr::Movzx8 { src: Reg::Rax, dst: target });
+                self.mov(Operand::Reg(target), dst);
+            }
+        }
+    }
+
+    fn generate_terminator(&mut self, terminator: &Terminator, next: Option<BlockId>) {
+        match *terminator {
+            Terminator::Return(value) => {
+                if let Some(value) = value {
+                    let value = self.value(value);
+                    self.mov(value, RAX);
+                }
+
+                for (index, register) in self.allocation.callee_saved_used.clone().into_iter().enumerate() {
+                    let slot = self.callee_save_slot(index);
+                    self.mov(slot, Operand::Reg(register));
+                }
+
+                self.emit(Instr::Mov { src: RBP, dst: RSP });
+                self.emit(Instr::Pop(RBP));
+                self.emit(Instr::Ret);
+            },
+            Terminator::Jump(target) => self.jump(target, next),
+            Terminator::Branch { condition, then_block, else_block } => {
+                match self.value(condition) {
+                    Operand::Imm(0) => return self.jump(else_block, next),
+                    Operand::Imm(_) => return self.jump(then_block, next),
+                    condition @ Operand::Reg(_) => self.emit(Instr::Test { src: condition, dst: condition }),
+                    condition => self.emit(Instr::Cmp { src: Operand::Imm(0), dst: condition })
+                }
+
+                // Fall through to whichever block comes next
+                if next == Some(then_block) {
+                    let label = self.label(else_block);
+                    self.emit(Instr::Jcc(Condition::E, label));
+                } else {
+                    let label = self.label(then_block);
+                    self.emit(Instr::Jcc(Condition::Ne, label));
+                    self.jump(else_block, next);
+                }
+            }
+        }
+    }
+
+    fn jump(&mut self, target: BlockId, next: Option<BlockId>) {
+        if next != Some(target) {
+            let label = self.label(target);
+            self.emit(Instr::Jmp(label));
+        }
+    }
+}
+
+/// Generates x86 instructions for a module that is out of SSA form and was
+/// lowered for the given data layout.
+pub fn generate(module: &Module, layout: &DataLayout) -> Vec<Instr> {
+    let abi = if layout.word_size == 4 { &CDECL } else { &SYSTEM_V };
+
+    module.functions.iter().flat_map(|function| {
+        let (function, allocation) = abi.allocate(function);
+
+        FunctionGenerator {
+            function: &function,
+            layout: *layout,
+            abi,
+            allocation,
+            instrs: Vec::new()
+        }.generate()
+    }).collect()
+}
@@ -0,0 +1,141 @@
+//! Compile-time evaluation of integer constant expressions.
+//!
+//! Values are carried as `i64` holding exactly what an object of the
+//! expression's type would hold, so `unsigned int` values are zero extended
+//! and everything narrower than `long` is sign extended. Arithmetic follows C:
+//! unsigned results wrap silently, while signed results that do not fit are
+//! wrapped and reported as an overflow.
+
+use Expr;
+use ExprKind;
+use UnaryOperator;
+use BinaryOperator;
+use Type;
+use Span;
+
+/// The result of folding a single operation.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub struct Folded {
+    pub value: i64,
+    /// Set when a signed operation overflowed and `value` had to be wrapped.
+    pub overflow: bool
+}
+
+/// Truncates `value` to the width of `ty`, the way a conversion to `ty` would.
+pub fn wrap(value: i128, ty: Type) -> i64 {
+    match ty {
+        Type::Char => value as i8 as i64,
+        Type::Int => value as i32 as i64,
+        Type::UnsignedInt => value as u32 as i64,
+        Type::Long | Type::Void => value as i64
+    }
+}
+
+pub fn fits(value: i128, ty: Type) -> bool {
+    wrap(value, ty) as i128 == value
+}
+
+/// Builds the literal for a folded value. Negative values become a negated
+/// constant, since the AST has no negative literals.
+pub fn literal(value: i64, ty: Type, span: Span) -> Expr {
+    let constant = Expr {
+        kind: ExprKind::Constant(value.unsigned_abs() as usize),
+        span,
+        ty: Some(ty)
+    };
+
+    if value >= 0 {
+        return constant;
+    }
+
+    Expr {
+        kind: ExprKind::Unary(UnaryOperator::Negation, Box::new(constant)),
+        span,
+        ty: Some(ty)
+    }
+}
+
+/// The value of a type checked literal, as built by `literal`.
+pub fn literal_value(expr: &Expr) -> Option<i64> {
+    let ty = expr.ty?;
+
+    match expr.kind {
+        ExprKind::Constant(value) if value <= i64::MAX as usize => Some(wrap(value as i128, ty)),
+        ExprKind::Unary(UnaryOperator::Negation, ref operand) => match operand.kind {
+            ExprKind::Constant(value) => Some(wrap(-(value as i128), ty)),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+/// Evaluates a type checked expression, or returns `None` if it is not a
+/// constant or its value is undefined, as with division by zero.
+pub fn evaluate(expr: &Expr) -> Option<i64> {
+    fold_with(expr, evaluate).map(|folded| folded.value)
+}
+
+/// Folds the operation at the root of `expr`, whose operands must already
+/// have been folded to literals.
+pub fn fold_node(expr: &Expr) -> Option<Folded> {
+    fold_with(expr, literal_value)
+}
+
+fn fold_with<F>(expr: &Expr, operand_value: F) -> Option<Folded> where F: Fn(&Expr) -> Option<i64> {
+    let ty = expr.ty?;
+    let operand = |expr: &Expr| operand_value(expr).map(|value| value as i128);
+
+    // The exact result, and whether it has to fit the type without wrapping
+    let (value, checked) = match expr.kind {
+        ExprKind::Constant(_) => (literal_value(expr)? as i128, false),
+        ExprKind::Variable(_, _) | ExprKind::Assignment(_, _) | ExprKind::Call(_, _) => return None,
+        ExprKind::ImplicitCast(_, ref inner) => (operand(inner)?, false),
+        ExprKind::Unary(operator, ref inner) => {
+            let value = operand(inner)?;
+
+            match operator {
+                UnaryOperator::Negation => (-value, true),
+                UnaryOperator::BitwiseComplement => (!value, true),
+                UnaryOperator::LogicalNegation => ((value == 0) as i128, false)
+            }
+        },
+        // Only the left operand has to be constant when it decides the result
+        ExprKind::Binary(BinaryOperator::LogicalAnd, ref lhs, ref rhs) => {
+            let value = operand(lhs)? != 0 && operand(rhs)? != 0;
+
+            (value as i128, false)
+        },
+        ExprKind::Binary(BinaryOperator::LogicalOr, ref lhs, ref rhs) => {
+            let value = operand(lhs)? != 0 || operand(rhs)? != 0;
+
+            (value as i128, false)
+        },
+        ExprKind::Binary(operator, ref lhs, ref rhs) => {
+            let (lhs, rhs) = (operand(lhs)?, operand(rhs)?);
+            let width = ty.size() as i128 * 8;
+
+            match operator {
+                BinaryOperator::Addition => (lhs + rhs, true),
+                BinaryOperator::Subtraction => (lhs - rhs, true),
+                BinaryOperator::Multiplication => (lhs * rhs, true),
+                BinaryOperator::Division if rhs == 0 => return None,
+                BinaryOperator::Division => (lhs / rhs, true),
+                BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight if rhs < 0 || rhs >= width => return None,
+                BinaryOperator::ShiftLeft => (lhs << rhs, true),
+                BinaryOperator::ShiftRight => (lhs >> rhs, false),
+                BinaryOperator::LessThan => ((lhs < rhs) as i128, false),
+                BinaryOperator::LessThanOrEqual => ((lhs <= rhs) as i128, false),
+                BinaryOperator::GreaterThan => ((lhs > rhs) as i128, false),
+                BinaryOperator::GreaterThanOrEqual => ((lhs >= rhs) as i128, false),
+                BinaryOperator::Equal => ((lhs == rhs) as i128, false),
+                BinaryOperator::NotEqual => ((lhs != rhs) as i128, false),
+                BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr => unreachable!()
+            }
+        }
+    };
+
+    Some(Folded {
+        value: wrap(value, ty),
+        overflow: checked && ty.is_signed() && !fits(value, ty)
+    })
+}
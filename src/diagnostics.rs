@@ -0,0 +1,225 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use Span;
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy,PartialOrd,Ord)]
+pub enum Severity {
+    Warning,
+    Error
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error")
+        }
+    }
+}
+
+/// A warning that can be switched on with `-W<name>` and off with `-Wno-<name>`.
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Hash)]
+pub enum Warning {
+    UnusedVariable,
+    UnusedParameter,
+    Conversion,
+    SignCompare,
+    UnreachableCode,
+    Parentheses,
+    ReturnType,
+    ShiftCountOverflow,
+    DivisionByZero,
+    IntegerOverflow
+}
+
+// Which flag turns a warning on, if it is not on by default
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+enum WarningGroup {
+    Default,
+    All,
+    Extra,
+    Explicit
+}
+
+impl Warning {
+    pub const ALL: [Warning; 10] = [
+        Warning::UnusedVariable,
+        Warning::UnusedParameter,
+        Warning::Conversion,
+        Warning::SignCompare,
+        Warning::UnreachableCode,
+        Warning::Parentheses,
+        Warning::ReturnType,
+        Warning::ShiftCountOverflow,
+        Warning::DivisionByZero,
+        Warning::IntegerOverflow
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Warning::UnusedVariable => "unused-variable",
+            Warning::UnusedParameter => "unused-parameter",
+            Warning::Conversion => "conversion",
+            Warning::SignCompare => "sign-compare",
+            Warning::UnreachableCode => "unreachable-code",
+            Warning::Parentheses => "parentheses",
+            Warning::ReturnType => "return-type",
+            Warning::ShiftCountOverflow => "shift-count-overflow",
+            Warning::DivisionByZero => "division-by-zero",
+            Warning::IntegerOverflow => "integer-overflow"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Warning> {
+        Warning::ALL.iter().cloned().find(|warning| warning.name() == name)
+    }
+
+    fn group(self) -> WarningGroup {
+        match self {
+            Warning::ReturnType | Warning::ShiftCountOverflow |
+            Warning::DivisionByZero | Warning::IntegerOverflow => WarningGroup::Default,
+            Warning::UnusedVariable | Warning::Parentheses => WarningGroup::All,
+            Warning::UnusedParameter | Warning::SignCompare | Warning::UnreachableCode => WarningGroup::Extra,
+            Warning::Conversion => WarningGroup::Explicit
+        }
+    }
+}
+
+/// The set of enabled warnings, built up from `-W` flags in command line order.
+#[derive(Debug,Clone)]
+pub struct WarningOptions {
+    enabled: HashSet<Warning>,
+    pub warnings_as_errors: bool
+}
+
+impl Default for WarningOptions {
+    fn default() -> WarningOptions {
+        WarningOptions {
+            enabled: WarningOptions::group(WarningGroup::Default).collect(),
+            warnings_as_errors: false
+        }
+    }
+}
+
+impl WarningOptions {
+    fn group(group: WarningGroup) -> impl Iterator<Item = Warning> {
+        Warning::ALL.iter().cloned().filter(move |warning| warning.group() == group)
+    }
+
+    /// Applies a flag such as `-Wall` or `-Wno-unused-parameter`, returning
+    /// false if it does not name a known warning.
+    pub fn apply_flag(&mut self, flag: &str) -> bool {
+        let name = match flag.strip_prefix("-W") {
+            Some(name) => name,
+            None => return false
+        };
+
+        match name {
+            "all" => self.enabled.extend(WarningOptions::group(WarningGroup::All)),
+            "extra" => self.enabled.extend(WarningOptions::group(WarningGroup::Extra)),
+            "error" => self.warnings_as_errors = true,
+            "no-error" => self.warnings_as_errors = false,
+            _ => {
+                let (enable, name) = match name.strip_prefix("no-") {
+                    Some(name) => (false, name),
+                    None => (true, name)
+                };
+
+                let warning = match Warning::from_name(name) {
+                    Some(warning) => warning,
+                    None => return false
+                };
+
+                if enable {
+                    self.enabled.insert(warning);
+                } else {
+                    self.enabled.remove(&warning);
+                }
+            }
+        }
+
+        true
+    }
+
+    pub fn is_enabled(&self, warning: Warning) -> bool {
+        self.enabled.contains(&warning)
+    }
+
+    /// Drops disabled warnings and, under `-Werror`, turns the rest into errors.
+    pub fn apply(&self, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        diagnostics.into_iter()
+            .filter(|diagnostic| diagnostic.warning.is_none_or(|warning| self.is_enabled(warning)))
+            .map(|mut diagnostic| {
+                if self.warnings_as_errors {
+                    diagnostic.severity = Severity::Error;
+                }
+
+                diagnostic
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    /// The warning flag controlling this diagnostic, if it is one.
+    pub warning: Option<Warning>
+}
+
+impl Diagnostic {
+    pub fn error<S: Into<String>>(message: S, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+            span,
+            warning: None
+        }
+    }
+
+    pub fn warning<S: Into<String>>(warning: Warning, message: S, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            message: message.into(),
+            span,
+            warning: Some(warning)
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Renders the diagnostic as `file:line:column: severity: message`,
+    /// followed by the offending source line with the span underlined.
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        let start = self.span.start.min(source.len());
+        let line_start = source[..start].rfind('\n').map_or(0, |index| index + 1);
+        let line_end = source[start..].find('\n').map_or(source.len(), |index| start + index);
+        let line_number = source[..start].matches('\n').count() + 1;
+        let column = source[line_start..start].chars().count() + 1;
+
+        let line = &source[line_start..line_end];
+        let underline_end = self.span.end.clamp(start, line_end);
+        let underline_length = source[start..underline_end].chars().count().max(1);
+
+        let flag = match (self.warning, self.severity) {
+            (Some(warning), Severity::Warning) => format!(" [-W{}]", warning.name()),
+            (Some(warning), Severity::Error) => format!(" [-Werror,-W{}]", warning.name()),
+            (None, _) => String::new()
+        };
+
+        let mut rendered = format!("{}:{}:{}: {}: {}{}\n", file_name, line_number, column, self.severity, self.message, flag);
+        rendered.push_str(&format!("    {}\n", line.trim_end_matches('\r')));
+        rendered.push_str(&format!("    {}^{}\n", " ".repeat(column - 1), "~".repeat(underline_length - 1)));
+
+        rendered
+    }
+}
+
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(Diagnostic::is_error)
+}
@@ -0,0 +1,213 @@
+//! Writing ELF64 relocatable object files for x86-64, so that the integrated
+//! assembler's code can be linked by the system linker.
+//!
+//! The object always has `.text`, `.data` and `.rodata`, the last two empty
+//! until the language has objects with static storage, and a
+//! `.note.GNU-stack` so that linkers do not give the program an executable
+//! stack.
+
+use encoder::{Code, RelocationKind};
+
+const ET_REL: u16 = 1;
+const EM_X86_64: u16 = 62;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+
+const SHF_WRITE: u64 = 0x1;
+const SHF_ALLOC: u64 = 0x2;
+const SHF_EXECINSTR: u64 = 0x4;
+const SHF_INFO_LINK: u64 = 0x40;
+
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STT_NOTYPE: u8 = 0;
+const STT_FUNC: u8 = 2;
+const STT_SECTION: u8 = 3;
+const STT_FILE: u8 = 4;
+const SHN_ABS: u16 = 0xfff1;
+
+const R_X86_64_PC32: u64 = 2;
+const R_X86_64_PLT32: u64 = 4;
+
+const HEADER_SIZE: usize = 64;
+const SECTION_HEADER_SIZE: usize = 64;
+const SYMBOL_SIZE: usize = 24;
+const RELOCATION_SIZE: usize = 24;
+
+// The indices of the sections, in the order they are written
+const TEXT: u16 = 1;
+const DATA: u16 = 3;
+const RODATA: u16 = 4;
+const SYMTAB: u16 = 6;
+const STRTAB: u16 = 7;
+const SHSTRTAB: u16 = 8;
+
+// A string table, which starts with the empty string
+struct Strings(Vec<u8>);
+
+impl Strings {
+    fn new() -> Strings {
+        Strings(vec![0])
+    }
+
+    fn add(&mut self, string: &str) -> u32 {
+        let offset = self.0.len() as u32;
+
+        self.0.extend_from_slice(string.as_bytes());
+        self.0.push(0);
+
+        offset
+    }
+}
+
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u64,
+    offset: usize,
+    size: usize,
+    link: u16,
+    info: u32,
+    align: u64,
+    entry_size: usize
+}
+
+fn pad(buf: &mut Vec<u8>, align: usize) {
+    while !buf.len().is_multiple_of(align) {
+        buf.push(0);
+    }
+}
+
+fn symbol(buf: &mut Vec<u8>, name: u32, binding: u8, kind: u8, section: u16, value: usize, size: usize) {
+    buf.extend_from_slice(&name.to_le_bytes());
+    buf.push(binding << 4 | kind);
+    buf.push(0);
+    buf.extend_from_slice(&section.to_le_bytes());
+    buf.extend_from_slice(&(value as u64).to_le_bytes());
+    buf.extend_from_slice(&(size as u64).to_le_bytes());
+}
+
+/// Writes code as a relocatable object. `source` names the file it was
+/// compiled from, as assemblers record it.
+pub fn write(code: &Code, source: &str) -> Vec<u8> {
+    let mut strings = Strings::new();
+    let mut symbols = vec![0; SYMBOL_SIZE];
+
+    symbol(&mut symbols, strings.add(source), STB_LOCAL, STT_FILE, SHN_ABS, 0, 0);
+
+    for &section in &[TEXT, DATA, RODATA] {
+        symbol(&mut symbols, 0, STB_LOCAL, STT_SECTION, section, 0, 0);
+    }
+
+    // Locals come first, and the symbol table's info is the first global
+    let first_global = symbols.len() / SYMBOL_SIZE;
+    let mut names: Vec<&str> = Vec::new();
+
+    for function in &code.symbols {
+        symbol(&mut symbols, strings.add(&function.name), STB_GLOBAL, STT_FUNC, TEXT, function.offset, function.size);
+        names.push(&function.name);
+    }
+
+    for name in code.undefined_symbols() {
+        symbol(&mut symbols, strings.add(name), STB_GLOBAL, STT_NOTYPE, 0, 0, 0);
+        names.push(name);
+    }
+
+    let mut relocations = Vec::new();
+
+    for relocation in &code.relocations {
+        let index = first_global + names.iter().position(|&name| name == relocation.symbol).unwrap();
+        let kind = match relocation.kind {
+            RelocationKind::Pc32 => R_X86_64_PC32,
+            RelocationKind::Plt32 => R_X86_64_PLT32
+        };
+
+        relocations.extend_from_slice(&(relocation.offset as u64).to_le_bytes());
+        relocations.extend_from_slice(&((index as u64) << 32 | kind).to_le_bytes());
+        relocations.extend_from_slice(&relocation.addend.to_le_bytes());
+    }
+
+    // Section names are all known up front, so the table can name itself
+    let mut section_names = Strings::new();
+    let names: Vec<u32> = [".text", ".rela.text", ".data", ".rodata", ".note.GNU-stack", ".symtab", ".strtab", ".shstrtab"]
+        .iter().map(|name| section_names.add(name)).collect();
+
+    let sections = [
+        (SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR, &code.bytes[..], 0, 0, 16, 0),
+        (SHT_RELA, SHF_INFO_LINK, &relocations[..], SYMTAB, TEXT as u32, 8, RELOCATION_SIZE),
+        (SHT_PROGBITS, SHF_WRITE | SHF_ALLOC, &[][..], 0, 0, 1, 0),
+        (SHT_PROGBITS, SHF_ALLOC, &[][..], 0, 0, 1, 0),
+        (SHT_PROGBITS, 0, &[][..], 0, 0, 1, 0),
+        (SHT_SYMTAB, 0, &symbols[..], STRTAB, first_global as u32, 8, SYMBOL_SIZE),
+        (SHT_STRTAB, 0, &strings.0[..], 0, 0, 1, 0),
+        (SHT_STRTAB, 0, &section_names.0[..], 0, 0, 1, 0)
+    ];
+
+    let mut buf = vec![0; HEADER_SIZE];
+    let mut headers = Vec::new();
+
+    for (&name, &(kind, flags, contents, link, info, align, entry_size)) in names.iter().zip(&sections) {
+        pad(&mut buf, align);
+
+        headers.push(SectionHeader {
+            name,
+            kind,
+            flags,
+            offset: buf.len(),
+            size: contents.len(),
+            link,
+            info,
+            align: align as u64,
+            entry_size
+        });
+
+        buf.extend_from_slice(contents);
+    }
+
+    pad(&mut buf, 8);
+
+    let section_headers = buf.len();
+
+    // The null section
+    buf.extend_from_slice(&[0; SECTION_HEADER_SIZE]);
+
+    for header in &headers {
+        buf.extend_from_slice(&header.name.to_le_bytes());
+        buf.extend_from_slice(&header.kind.to_le_bytes());
+        buf.extend_from_slice(&header.flags.to_le_bytes());
+        buf.extend_from_slice(&0u64.to_le_bytes());
+        buf.extend_from_slice(&(header.offset as u64).to_le_bytes());
+        buf.extend_from_slice(&(header.size as u64).to_le_bytes());
+        buf.extend_from_slice(&(header.link as u32).to_le_bytes());
+        buf.extend_from_slice(&header.info.to_le_bytes());
+        buf.extend_from_slice(&header.align.to_le_bytes());
+        buf.extend_from_slice(&(header.entry_size as u64).to_le_bytes());
+    }
+
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+
+    // Identification: 64-bit, little endian, current version, System V ABI
+    header.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+    header.extend_from_slice(&[0; 8]);
+    header.extend_from_slice(&ET_REL.to_le_bytes());
+    header.extend_from_slice(&EM_X86_64.to_le_bytes());
+    header.extend_from_slice(&1u32.to_le_bytes());
+    // No entry point or program headers
+    header.extend_from_slice(&0u64.to_le_bytes());
+    header.extend_from_slice(&0u64.to_le_bytes());
+    header.extend_from_slice(&(section_headers as u64).to_le_bytes());
+    header.extend_from_slice(&0u32.to_le_bytes());
+    header.extend_from_slice(&(HEADER_SIZE as u16).to_le_bytes());
+    header.extend_from_slice(&0u16.to_le_bytes());
+    header.extend_from_slice(&0u16.to_le_bytes());
+    header.extend_from_slice(&(SECTION_HEADER_SIZE as u16).to_le_bytes());
+    header.extend_from_slice(&(headers.len() as u16 + 1).to_le_bytes());
+    header.extend_from_slice(&SHSTRTAB.to_le_bytes());
+
+    buf[..HEADER_SIZE].copy_from_slice(&header);
+
+    buf
+}
@@ -0,0 +1,382 @@
+//! x86-64 machine code encoding, for assembling without an external
+//! assembler.
+//!
+//! Operands are always 64 bits wide except where an instruction names a
+//! narrower part of a register. Branches always take a 32-bit displacement,
+//! which saves the passes that picking the shorter form would need, and
+//! every call goes through a relocation against the function's symbol, the
+//! way an assembler treats calls to global symbols.
+
+use std::collections::HashMap;
+
+use x86::{Condition, Instr, Label, Operand, Reg};
+
+/// How the linker fills in a 32-bit field that refers to a symbol.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum RelocationKind {
+    /// The symbol's address relative to the field.
+    Pc32,
+    /// The address of the symbol's PLT entry, or the symbol itself when it
+    /// is linked in statically, relative to the field.
+    Plt32
+}
+
+#[derive(Debug,PartialEq,Eq,Clone)]
+pub struct Relocation {
+    pub offset: usize,
+    pub symbol: String,
+    pub kind: RelocationKind,
+    pub addend: i64
+}
+
+/// A function defined in the code.
+#[derive(Debug,PartialEq,Eq,Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub offset: usize,
+    pub size: usize
+}
+
+/// Machine code with the functions it defines and the references to
+/// symbols left for the linker.
+#[derive(Debug,PartialEq,Eq,Clone,Default)]
+pub struct Code {
+    pub bytes: Vec<u8>,
+    pub symbols: Vec<Symbol>,
+    pub relocations: Vec<Relocation>
+}
+
+impl Code {
+    /// The symbols referred to but not defined, in the order first referred to.
+    pub fn undefined_symbols(&self) -> Vec<&str> {
+        let mut undefined: Vec<&str> = Vec::new();
+
+        for relocation in &self.relocations {
+            let name = &relocation.symbol[..];
+
+            if !self.symbols.iter().any(|symbol| symbol.name == name) && !undefined.contains(&name) {
+                undefined.push(name);
+            }
+        }
+
+        undefined
+    }
+}
+
+fn number(reg: Reg) -> u8 {
+    match reg {
+        Reg::Rax => 0,
+        Reg::Rcx => 1,
+        Reg::Rdx => 2,
+        Reg::Rbx => 3,
+        Reg::Rsp => 4,
+        Reg::Rbp => 5,
+        Reg::Rsi => 6,
+        Reg::Rdi => 7,
+        Reg::R8 => 8,
+        Reg::R9 => 9,
+        Reg::R10 => 10,
+        Reg::R11 => 11,
+        Reg::R12 => 12,
+        Reg::R13 => 13,
+        Reg::R14 => 14,
+        Reg::R15 => 15
+    }
+}
+
+fn condition_code(condition: Condition) -> u8 {
+    match condition {
+        Condition::B => 0x2,
+        Condition::Ae => 0x3,
+        Condition::E => 0x4,
+        Condition::Ne => 0x5,
+        Condition::Be => 0x6,
+        Condition::A => 0x7,
+        Condition::L => 0xc,
+        Condition::Ge => 0xd,
+        Condition::Le => 0xe,
+        Condition::G => 0xf
+    }
+}
+
+fn fits_i8(value: i64) -> bool {
+    value as i8 as i64 == value
+}
+
+fn imm32(value: i64) -> i32 {
+    assert!(value as i32 as i64 == value, "immediate {} does not fit in 32 bits", value);
+
+    value as i32
+}
+
+// The register field of ModRM for instructions that use it to extend the opcode
+const ADD: u8 = 0;
+const AND: u8 = 4;
+const SUB: u8 = 5;
+const CMP: u8 = 7;
+
+// Operand sizes, which decide the REX prefix
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+enum Size {
+    Byte,
+    Double,
+    Quad
+}
+
+struct Encoder {
+    code: Code,
+    labels: HashMap<Label, usize>,
+    // Displacement fields waiting for their label's offset
+    fixups: Vec<(usize, Label)>
+}
+
+impl Encoder {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.code.bytes.extend_from_slice(bytes);
+    }
+
+    fn imm32(&mut self, value: i64) {
+        self.bytes(&imm32(value).to_le_bytes());
+    }
+
+    // Emits an instruction with a ModRM byte, whose register field is a
+    // register or an opcode extension and whose other operand is `rm`
+    fn modrm(&mut self, size: Size, opcode: &[u8], reg: u8, rm: Operand) {
+        let (base, offset) = match rm {
+            Operand::Reg(rm) => (number(rm), None),
+            Operand::Mem { base, offset } => (number(base), Some(offset)),
+            Operand::Imm(value) => panic!("immediate {} used as a register or memory operand", value)
+        };
+
+        let mut rex = 0x40;
+
+        if size == Size::Quad {
+            rex |= 0x08;
+        }
+
+        rex |= (reg >> 3) << 2;
+        rex |= base >> 3;
+
+        // Without a REX prefix the byte registers 4 to 7 are %ah to %bh
+        let byte_register = |number: u8| size == Size::Byte && (4..8).contains(&number);
+        let needs_byte_rex = byte_register(reg) || offset.is_none() && byte_register(base);
+
+        if rex != 0x40 || needs_byte_rex {
+            self.bytes(&[rex]);
+        }
+
+        self.bytes(opcode);
+
+        let reg = (reg & 7) << 3;
+
+        match offset {
+            None => self.bytes(&[0xc0 | reg | (base & 7)]),
+            Some(offset) => {
+                // %rbp and %r13 as a base with no displacement mean something else
+                let mode = if offset == 0 && base & 7 != 5 {
+                    0x00
+                } else if fits_i8(offset) {
+                    0x40
+                } else {
+                    0x80
+                };
+
+                self.bytes(&[mode | reg | (base & 7)]);
+
+                // %rsp and %r12 as a base need a SIB byte
+                if base & 7 == 4 {
+                    self.bytes(&[0x24]);
+                }
+
+                match mode {
+                    0x40 => self.bytes(&[offset as u8]),
+                    0x80 => self.imm32(offset),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // A REX prefix for an instruction whose register is in the opcode
+    fn rex_for_opcode_register(&mut self, wide: bool, reg: Reg) {
+        let rex = 0x40 | if wide { 0x08 } else { 0 } | number(reg) >> 3;
+
+        if rex != 0x40 {
+            self.bytes(&[rex]);
+        }
+    }
+
+    // add, and, sub and cmp, which share their encodings
+    fn arithmetic(&mut self, extension: u8, src: Operand, dst: Operand) {
+        let base = extension << 3;
+
+        match (src, dst) {
+            (Operand::Imm(value), dst) if fits_i8(value) => {
+                self.modrm(Size::Quad, &[0x83], extension, dst);
+                self.bytes(&[value as u8]);
+            },
+            (Operand::Imm(value), dst) => {
+                self.modrm(Size::Quad, &[0x81], extension, dst);
+                self.imm32(value);
+            },
+            (Operand::Reg(src), dst) => self.modrm(Size::Quad, &[base | 0x01], number(src), dst),
+            (src, Operand::Reg(dst)) => self.modrm(Size::Quad, &[base | 0x03], number(dst), src),
+            _ => panic!("{} has no form from memory to memory", extension)
+        }
+    }
+
+    // Instructions of a single operand with the opcode F7
+    fn unary(&mut self, extension: u8, operand: Operand) {
+        self.modrm(Size::Quad, &[0xf7], extension, operand);
+    }
+
+    fn shift(&mut self, extension: u8, count: Operand, dst: Operand) {
+        match count {
+            Operand::Imm(1) => self.modrm(Size::Quad, &[0xd1], extension, dst),
+            Operand::Imm(value) => {
+                self.modrm(Size::Quad, &[0xc1], extension, dst);
+                self.bytes(&[value as u8]);
+            },
+            Operand::Reg(Reg::Rcx) => self.modrm(Size::Quad, &[0xd3], extension, dst),
+            _ => panic!("shift count {:?} is not an immediate or %cl", count)
+        }
+    }
+
+    // A 32-bit displacement to a label, filled in once every label is known
+    fn branch(&mut self, opcode: &[u8], label: &Label) {
+        self.bytes(opcode);
+        self.fixups.push((self.code.bytes.len(), label.clone()));
+        self.bytes(&[0; 4]);
+    }
+
+    fn encode(&mut self, instr: &Instr) {
+        match *instr {
+            Instr::Global(ref name) => {
+                self.code.symbols.push(Symbol {
+                    name: name.clone(),
+                    offset: self.code.bytes.len(),
+                    size: 0
+                });
+            },
+            Instr::Label(ref label) => {
+                let offset = self.code.bytes.len();
+
+                if self.labels.insert(label.clone(), offset).is_some() {
+                    panic!("label {} is defined twice", label);
+                }
+            },
+            Instr::Mov { src: Operand::Imm(value), dst: Operand::Reg(dst) } if value as i32 as i64 != value => {
+                self.rex_for_opcode_register(true, dst);
+                self.bytes(&[0xb8 | (number(dst) & 7)]);
+                self.bytes(&value.to_le_bytes());
+            },
+            Instr::Mov { src: Operand::Imm(value), dst } => {
+                self.modrm(Size::Quad, &[0xc7], 0, dst);
+                self.imm32(value);
+            },
+            Instr::Mov { src: Operand::Reg(src), dst } => self.modrm(Size::Quad, &[0x89], number(src), dst),
+            Instr::Mov { src, dst: Operand::Reg(dst) } => self.modrm(Size::Quad, &[0x8b], number(dst), src),
+            Instr::Mov { .. } => panic!("mov has no form from memory to memory"),
+            Instr::Movsx8 { src, dst } => self.modrm(Size::Quad, &[0x0f, 0xbe], number(dst), src),
+            Instr::Movsx32 { src, dst } => self.modrm(Size::Quad, &[0x63], number(dst), src),
+            Instr::Movzx8 { src, dst } => self.modrm(Size::Quad, &[0x0f, 0xb6], number(dst), Operand::Reg(src)),
+            Instr::Movzx32 { src: Operand::Imm(value), dst } => {
+                self.rex_for_opcode_register(false, dst);
+                self.bytes(&[0xb8 | (number(dst) & 7)]);
+                self.bytes(&(value as u32).to_le_bytes());
+            },
+            Instr::Movzx32 { src: Operand::Reg(src), dst } => self.modrm(Size::Double, &[0x89], number(src), Operand::Reg(dst)),
+            Instr::Movzx32 { src, dst } => self.modrm(Size::Double, &[0x8b], number(dst), src),
+            Instr::Zero(reg) => self.modrm(Size::Double, &[0x31], number(reg), Operand::Reg(reg)),
+            Instr::Add { src, dst } => self.arithmetic(ADD, src, dst),
+            Instr::Sub { src, dst } => self.arithmetic(SUB, src, dst),
+            Instr::And { src, dst } => self.arithmetic(AND, src, dst),
+            Instr::Cmp { src, dst } => self.arithmetic(CMP, src, dst),
+            Instr::Test { src: Operand::Imm(value), dst } => {
+                self.modrm(Size::Quad, &[0xf7], 0, dst);
+                self.imm32(value);
+            },
+            Instr::Test { src: Operand::Reg(src), dst } => self.modrm(Size::Quad, &[0x85], number(src), dst),
+            Instr::Test { src, dst: Operand::Reg(dst) } => self.modrm(Size::Quad, &[0x85], number(dst), src),
+            Instr::Test { .. } => panic!("test has no form from memory to memory"),
+            Instr::Imul { src: Operand::Imm(value), dst } if fits_i8(value) => {
+                self.modrm(Size::Quad, &[0x6b], number(dst), Operand::Reg(dst));
+                self.bytes(&[value as u8]);
+            },
+            Instr::Imul { src: Operand::Imm(value), dst } => {
+                self.modrm(Size::Quad, &[0x69], number(dst), Operand::Reg(dst));
+                self.imm32(value);
+            },
+            Instr::Imul { src, dst } => self.modrm(Size::Quad, &[0x0f, 0xaf], number(dst), src),
+            Instr::Neg(operand) => self.unary(3, operand),
+            Instr::Not(operand) => self.unary(2, operand),
+            Instr::Idiv(operand) => self.unary(7, operand),
+            Instr::Div(operand) => self.unary(6, operand),
+            Instr::Shl { count, dst } => self.shift(4, count, dst),
+            Instr::Sar { count, dst } => self.shift(7, count, dst),
+            Instr::Shr { count, dst } => self.shift(5, count, dst),
+            Instr::Cqo => self.bytes(&[0x48, 0x99]),
+            Instr::Set(condition, reg) => self.modrm(Size::Byte, &[0x0f, 0x90 | condition_code(condition)], 0, Operand::Reg(reg)),
+            Instr::Jmp(ref label) => self.branch(&[0xe9], label),
+            Instr::Jcc(condition, ref label) => self.branch(&[0x0f, 0x80 | condition_code(condition)], label),
+            Instr::Call(ref name) => {
+                self.bytes(&[0xe8]);
+                self.code.relocations.push(Relocation {
+                    offset: self.code.bytes.len(),
+                    symbol: name.clone(),
+                    kind: RelocationKind::Plt32,
+                    addend: -4
+                });
+                self.bytes(&[0; 4]);
+            },
+            Instr::Ret => self.bytes(&[0xc3]),
+            Instr::Push(Operand::Reg(reg)) => {
+                self.rex_for_opcode_register(false, reg);
+                self.bytes(&[0x50 | (number(reg) & 7)]);
+            },
+            Instr::Push(Operand::Imm(value)) if fits_i8(value) => self.bytes(&[0x6a, value as u8]),
+            Instr::Push(Operand::Imm(value)) => {
+                self.bytes(&[0x68]);
+                self.imm32(value);
+            },
+            Instr::Push(operand) => self.modrm(Size::Double, &[0xff], 6, operand),
+            Instr::Pop(Operand::Reg(reg)) => {
+                self.rex_for_opcode_register(false, reg);
+                self.bytes(&[0x58 | (number(reg) & 7)]);
+            },
+            Instr::Pop(operand) => self.modrm(Size::Double, &[0x8f], 0, operand)
+        }
+    }
+}
+
+/// Encodes instructions for x86-64. Only functions named by a `Global` are
+/// symbols; other labels are resolved and dropped.
+pub fn encode(instrs: &[Instr]) -> Code {
+    let mut encoder = Encoder {
+        code: Code::default(),
+        labels: HashMap::new(),
+        fixups: Vec::new()
+    };
+
+    for instr in instrs {
+        encoder.encode(instr);
+    }
+
+    let Encoder { mut code, labels, fixups } = encoder;
+
+    for (offset, label) in fixups {
+        let target = *labels.get(&label).unwrap_or_else(|| panic!("jump to undefined label {}", label));
+        let displacement = target as i64 - (offset as i64 + 4);
+
+        code.bytes[offset..offset + 4].copy_from_slice(&imm32(displacement).to_le_bytes());
+    }
+
+    // A function ends where the next one starts
+    let ends: Vec<usize> = code.symbols.iter().skip(1).map(|symbol| symbol.offset).chain(Some(code.bytes.len())).collect();
+
+    for (symbol, end) in code.symbols.iter_mut().zip(ends) {
+        symbol.size = end - symbol.offset;
+    }
+
+    code
+}
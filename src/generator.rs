@@ -1,172 +1,386 @@
-use AST;
+use std::collections::HashMap;
 
-use Symbol;
-use StatementType;
+use visit::{self, Visitor};
+use TranslationUnit;
+use FunctionDef;
+use DeclId;
+use Decl;
+use Stmt;
+use StmtKind;
+use Expr;
+use ExprKind;
 use UnaryOperator;
 use BinaryOperator;
+use Type;
+use x86::{Condition, Instr, Label, Operand, Reg};
+
+const ARGUMENT_REGISTERS: [Reg; 6] = [Reg::Rdi, Reg::Rsi, Reg::Rdx, Reg::Rcx, Reg::R8, Reg::R9];
+
+const RAX: Operand = Operand::Reg(Reg::Rax);
+const RCX: Operand = Operand::Reg(Reg::Rcx);
+const RDX: Operand = Operand::Reg(Reg::Rdx);
+const RSP: Operand = Operand::Reg(Reg::Rsp);
+const RBP: Operand = Operand::Reg(Reg::Rbp);
+
+// Every value lives in a full 64 bit register, kept sign or zero extended
+// from the width of its type, so 64 bit arithmetic gives the right answer
+// and only the result needs truncating back to its type.
+fn normalize(ty: Type) -> Vec<Instr> {
+    match ty {
+        Type::Char => vec![Instr::Movsx8 { src: RAX, dst: Reg::Rax }],
+        Type::Int => vec![Instr::Movsx32 { src: RAX, dst: Reg::Rax }],
+        Type::UnsignedInt => vec![Instr::Movzx32 { src: RAX, dst: Reg::Rax }],
+        Type::Long | Type::Void => Vec::new()
+    }
+}
+
+fn local(offset: isize) -> Operand {
+    Operand::Mem { base: Reg::Rbp, offset: offset as i64 }
+}
+
+// Evaluates `first` into %rax, saves it on the stack while `second` is
+// evaluated, then pops it into `register`
+fn operands(first: Vec<Instr>, second: Vec<Instr>, register: Reg) -> Vec<Instr> {
+    let mut code = first;
+    code.push(Instr::Push(RAX));
+    code.extend(second);
+    code.push(Instr::Pop(Operand::Reg(register)));
+
+    code
+}
+
+fn epilogue() -> Vec<Instr> {
+    vec![
+        Instr::Mov { src: RBP, dst: RSP },
+        Instr::Pop(RBP),
+        Instr::Ret
+    ]
+}
 
 struct Generator {
-    buf: String,
-    expression_stack: Vec<String>
+    instrs: Vec<Instr>,
+    expression_stack: Vec<Vec<Instr>>,
+    variables: HashMap<DeclId, isize>,
+    stack_index: isize,
+    label_counter: usize
 }
 
 impl Generator {
     fn new() -> Generator {
         Generator {
-            buf: String::new(),
-            expression_stack: Vec::new()
+            instrs: Vec::new(),
+            expression_stack: Vec::new(),
+            variables: HashMap::new(),
+            stack_index: 0,
+            label_counter: 0
         }
     }
 
-    fn generate(mut self, ast: &AST) -> String {
-        if ast.symbol != Symbol::Program {
-            panic!("Root node in AST is not a program node\r\n{:#?}", ast);
-        }
+    fn generate(mut self, translation_unit: &TranslationUnit) -> Vec<Instr> {
+        self.visit_translation_unit(translation_unit);
 
-        let mut fun_index = 0;
-        loop {
-            if ast.children.len() == fun_index { break }
+        self.instrs
+    }
 
-            let current_fun_node = &ast.children[fun_index];
+    fn generate_expression(&mut self, expr: &Expr) -> Vec<Instr> {
+        self.visit_expr(expr);
 
-            let fun_name = match &current_fun_node.symbol {
-                &Symbol::Function(ref value) => value,
-                _ => panic!("Not a function")
-            };
+        self.expression_stack.pop().unwrap()
+    }
 
-            self.buf.push_str(&format!(".globl {}\n{}:\n", fun_name, fun_name)[..]);
-            self.postorder_traversal(&current_fun_node);
+    fn variable_offset(&self, expr: &Expr) -> isize {
+        let decl_id = expr.decl_id().expect("variable was not resolved by semantic analysis");
 
-            fun_index += 1;
-        }
+        self.variables[&decl_id]
+    }
 
-        self.buf
+    fn allocate_variable(&mut self, decl_id: DeclId) -> isize {
+        self.stack_index -= 8;
+        self.variables.insert(decl_id, self.stack_index);
+
+        self.stack_index
     }
 
-    fn visit_node(&mut self, ast: &AST) {
-        match ast.symbol {
-            Symbol::Constant(value) => {
-                self.expression_stack.push(format!("movq ${}, %rax\n", value));
+    fn next_label(&mut self) -> Label {
+        self.label_counter += 1;
+
+        Label(format!("_L{}", self.label_counter))
+    }
+
+    fn visit_node(&mut self, expr: &Expr) {
+        let code = match expr.kind {
+            ExprKind::Constant(value) => vec![Instr::Mov { src: Operand::Imm(value as i64), dst: RAX }],
+            ExprKind::Variable(_, _) => vec![Instr::Mov { src: local(self.variable_offset(expr)), dst: RAX }],
+            ExprKind::Assignment(ref target, _) => {
+                let mut code = self.expression_stack.pop().unwrap();
+                code.push(Instr::Mov { src: RAX, dst: local(self.variable_offset(target)) });
+
+                code
             },
-            Symbol::UnaryOperator(ref unary_operator) => {
-                match unary_operator {
-                    &UnaryOperator::Negation => {
-                        let op1 = self.expression_stack.pop().unwrap();
+            ExprKind::ImplicitCast(ty, _) => {
+                let mut code = self.expression_stack.pop().unwrap();
+                code.extend(normalize(ty));
 
-                        self.expression_stack.push(format!("{}neg %rax\n", op1));
-                    },
-                    &UnaryOperator::BitwiseComplement => {
-                        let op1 = self.expression_stack.pop().unwrap();
+                code
+            },
+            ExprKind::Call(ref name, ref args) => {
+                let values = self.expression_stack.split_off(self.expression_stack.len() - args.len());
+                let stack_args = args.len().saturating_sub(ARGUMENT_REGISTERS.len());
+
+                // Align the stack dynamically, since expression temporaries may
+                // already be pushed; the old stack pointer is saved above the arguments
+                let mut code = vec![
+                    Instr::Mov { src: RSP, dst: RAX },
+                    Instr::And { src: Operand::Imm(-16), dst: RSP }
+                ];
+
+                if stack_args % 2 == 0 {
+                    code.push(Instr::Sub { src: Operand::Imm(8), dst: RSP });
+                }
+
+                code.push(Instr::Push(RAX));
+
+                for value in values.into_iter().rev() {
+                    code.extend(value);
+                    code.push(Instr::Push(RAX));
+                }
+
+                for &register in ARGUMENT_REGISTERS.iter().take(args.len()) {
+                    code.push(Instr::Pop(Operand::Reg(register)));
+                }
+
+                code.push(Instr::Call(name.clone()));
 
-                        self.expression_stack.push(format!("{}not %rax\n", op1));
+                if stack_args > 0 {
+                    code.push(Instr::Add { src: Operand::Imm(stack_args as i64 * 8), dst: RSP });
+                }
+
+                code.push(Instr::Pop(RSP));
+                code.extend(normalize(expr.ty()));
+
+                code
+            },
+            ExprKind::Unary(unary_operator, _) => {
+                let mut code = self.expression_stack.pop().unwrap();
+
+                match unary_operator {
+                    UnaryOperator::Negation => {
+                        code.push(Instr::Neg(RAX));
+                        code.extend(normalize(expr.ty()));
                     },
-                    &UnaryOperator::LogicalNegation => {
-                        let op1 = self.expression_stack.pop().unwrap();
-                        
-                        self.expression_stack.push(format!("{}cmpq $0, %rax\nmovq $0, %rax\nsete %al\n", op1));
+                    UnaryOperator::BitwiseComplement => {
+                        code.push(Instr::Not(RAX));
+                        code.extend(normalize(expr.ty()));
+                    },
+                    UnaryOperator::LogicalNegation => {
+                        code.extend(vec![
+                            Instr::Cmp { src: Operand::Imm(0), dst: RAX },
+                            Instr::Mov { src: Operand::Imm(0), dst: RAX },
+                            Instr::Set(Condition::E, Reg::Rax)
+                        ]);
                     }
-                };
+                }
+
+                code
             },
-            Symbol::BinaryOperator(ref binary_operator) => {
+            ExprKind::Binary(binary_operator, _, _) => {
+                let op2 = self.expression_stack.pop().unwrap();
+                let op1 = self.expression_stack.pop().unwrap();
+
+                let comparison = match binary_operator {
+                    BinaryOperator::Equal => Some(Condition::E),
+                    BinaryOperator::NotEqual => Some(Condition::Ne),
+                    BinaryOperator::GreaterThanOrEqual => Some(Condition::Ge),
+                    BinaryOperator::GreaterThan => Some(Condition::G),
+                    BinaryOperator::LessThanOrEqual => Some(Condition::Le),
+                    BinaryOperator::LessThan => Some(Condition::L),
+                    _ => None
+                };
+
                 match binary_operator {
-                    &BinaryOperator::Addition => {
-                        let op2 = self.expression_stack.pop().unwrap();
-                        let op1 = self.expression_stack.pop().unwrap();
+                    BinaryOperator::Addition | BinaryOperator::Multiplication => {
+                        let mut code = operands(op1, op2, Reg::Rcx);
+                        code.push(match binary_operator {
+                            BinaryOperator::Addition => Instr::Add { src: RCX, dst: RAX },
+                            _ => Instr::Imul { src: RCX, dst: Reg::Rax }
+                        });
+                        code.extend(normalize(expr.ty()));
 
-                        self.expression_stack.push(format!("{}push %rax\n{}pop %rbx\naddq %rbx, %rax\n", op1, op2));
+                        code
                     },
-                    &BinaryOperator::Multiplication => {
-                        let op2 = self.expression_stack.pop().unwrap();
-                        let op1 = self.expression_stack.pop().unwrap();
+                    // The right operand is evaluated first so the left one ends up in %rax
+                    BinaryOperator::Subtraction | BinaryOperator::Division |
+                    BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => {
+                        let mut code = operands(op2, op1, Reg::Rcx);
 
-                        self.expression_stack.push(format!("{}push %rax\n{}pop %rbx\nimul %rbx, %rax\n", op1, op2));
-                    },
-                    &BinaryOperator::Subtraction => {
-                        let op1 = self.expression_stack.pop().unwrap();
-                        let op2 = self.expression_stack.pop().unwrap();
+                        match binary_operator {
+                            BinaryOperator::Subtraction => code.push(Instr::Sub { src: RCX, dst: RAX }),
+                            BinaryOperator::Division => code.extend(vec![Instr::Cqo, Instr::Idiv(RCX)]),
+                            BinaryOperator::ShiftLeft => code.push(Instr::Shl { count: RCX, dst: RAX }),
+                            _ if expr.ty().is_signed() => code.push(Instr::Sar { count: RCX, dst: RAX }),
+                            _ => code.push(Instr::Shr { count: RCX, dst: RAX })
+                        }
 
-                        self.expression_stack.push(format!("{}push %rax\n{}pop %rbx\nsubq %rbx, %rax\n", op1, op2));
-                    },
-                    &BinaryOperator::Division => {
-                        let op1 = self.expression_stack.pop().unwrap();
-                        let op2 = self.expression_stack.pop().unwrap();
+                        code.extend(normalize(expr.ty()));
 
-                        self.expression_stack.push(format!("{}push %rax\n{}pop %rbx\nmovq $0,%rdx\nidivq %rbx\n", op1, op2));
+                        code
                     },
-                    &BinaryOperator::Equal => {
-                        let op2 = self.expression_stack.pop().unwrap();
-                        let op1 = self.expression_stack.pop().unwrap();
+                    BinaryOperator::LogicalOr | BinaryOperator::LogicalAnd => {
+                        let end_label = self.next_label();
+                        let is_or = binary_operator == BinaryOperator::LogicalOr;
 
-                        self.expression_stack.push(format!("{}push %rax\n{}pop %rdx\ncmpq %rax,%rdx\nmovq $0,%rax\nsete %al\n", op1, op2));
-                    },
-                    &BinaryOperator::NotEqual => {
-                        let op2 = self.expression_stack.pop().unwrap();
-                        let op1 = self.expression_stack.pop().unwrap();
+                        let mut code = op1;
+                        code.extend(vec![
+                            Instr::Cmp { src: Operand::Imm(0), dst: RAX },
+                            Instr::Mov { src: Operand::Imm(if is_or { 1 } else { 0 }), dst: RAX },
+                            Instr::Jcc(if is_or { Condition::Ne } else { Condition::E }, end_label.clone())
+                        ]);
+                        code.extend(op2);
+                        code.extend(vec![
+                            Instr::Cmp { src: Operand::Imm(0), dst: RAX },
+                            Instr::Mov { src: Operand::Imm(0), dst: RAX },
+                            Instr::Set(Condition::Ne, Reg::Rax),
+                            Instr::Label(end_label)
+                        ]);
 
-                        self.expression_stack.push(format!("{}push %rax\n{}pop %rdx\ncmpq %rax,%rdx\nmovq $0,%rax\nsetne %al\n", op1, op2));
+                        code
                     },
-                    &BinaryOperator::GreaterThanOrEqual => {
-                        let op2 = self.expression_stack.pop().unwrap();
-                        let op1 = self.expression_stack.pop().unwrap();
+                    _ => {
+                        // The left operand is compared from %rdx
+                        let mut code = operands(op1, op2, Reg::Rdx);
+                        code.extend(vec![
+                            Instr::Cmp { src: RAX, dst: RDX },
+                            Instr::Mov { src: Operand::Imm(0), dst: RAX },
+                            Instr::Set(comparison.unwrap(), Reg::Rax)
+                        ]);
 
-                        self.expression_stack.push(format!("{}push %rax\n{}pop %rdx\ncmpq %rax,%rdx\nmovq $0,%rax\nsetge %al\n", op1, op2));
-                    },
-                    &BinaryOperator::GreaterThan => {
-                        let op2 = self.expression_stack.pop().unwrap();
-                        let op1 = self.expression_stack.pop().unwrap();
+                        code
+                    }
+                }
+            }
+        };
 
-                        self.expression_stack.push(format!("{}push %rax\n{}pop %rdx\ncmpq %rax,%rdx\nmovq $0,%rax\nsetg %al\n", op1, op2));
-                    },
-                    &BinaryOperator::LessThanOrEqual => {
-                        let op2 = self.expression_stack.pop().unwrap();
-                        let op1 = self.expression_stack.pop().unwrap();
+        self.expression_stack.push(code);
+    }
+}
 
-                        self.expression_stack.push(format!("{}push %rax\n{}pop %rdx\ncmpq %rax,%rdx\nmovq $0,%rax\nsetle %al\n", op1, op2));
-                    },
-                    &BinaryOperator::LessThan => {
-                        let op2 = self.expression_stack.pop().unwrap();
-                        let op1 = self.expression_stack.pop().unwrap();
+impl Visitor for Generator {
+    fn visit_function_def(&mut self, function: &FunctionDef) {
+        // Prototypes only matter to semantic analysis
+        if !function.is_definition() {
+            return;
+        }
 
-                        self.expression_stack.push(format!("{}push %rax\n{}pop %rdx\ncmpq %rax,%rdx\nmovq $0,%rax\nsetl %al\n", op1, op2));
-                    },
-                    &BinaryOperator::LogicalOr => {
-                        let op2 = self.expression_stack.pop().unwrap();
-                        let op1 = self.expression_stack.pop().unwrap();
+        self.variables.clear();
+        self.stack_index = 0;
 
-                        self.expression_stack.push(format!("{}push %rax\n{}pop %rdx\norq %rax,%rdx\nmovq $0,%rax\nsetne %al\n", op1, op2));
-                    },
-                    &BinaryOperator::LogicalAnd => {
-                        let op2 = self.expression_stack.pop().unwrap();
-                        let op1 = self.expression_stack.pop().unwrap();
+        let frame_size = ((function.decls().len() + function.params.len()) * 8).div_ceil(16) * 16;
 
-                        self.expression_stack.push(format!("{}push %rax\n{}pop %rdx\ncmpq $0,%rdx\nsetne %cl\ncmpq $0,%rax\nsetne %al\n andb %cl, %al\n", op1, op2));
-                    },
-                    _ => panic!("Not supported")
-                };
+        self.instrs.extend(vec![
+            Instr::Global(function.name.clone()),
+            Instr::Label(Label(function.name.clone())),
+            Instr::Push(RBP),
+            Instr::Mov { src: RSP, dst: RBP },
+            Instr::Sub { src: Operand::Imm(frame_size as i64), dst: RSP }
+        ]);
+
+        // Spill every parameter into the frame; the seventh onwards were pushed by the caller
+        for (index, param) in function.params.iter().enumerate() {
+            let source = match ARGUMENT_REGISTERS.get(index) {
+                Some(&register) => Operand::Reg(register),
+                None => local(16 + (index - ARGUMENT_REGISTERS.len()) as isize * 8)
+            };
+            let offset = self.allocate_variable(param.id);
+
+            self.instrs.push(Instr::Mov { src: source, dst: RAX });
+            self.instrs.extend(normalize(param.ty));
+            self.instrs.push(Instr::Mov { src: RAX, dst: local(offset) });
+        }
+
+        visit::walk_function_def(self, function);
+
+        // Falling off the end of a function returns 0, which is what C requires of main
+        self.instrs.push(Instr::Mov { src: Operand::Imm(0), dst: RAX });
+        self.instrs.extend(epilogue());
+    }
+
+    fn visit_decl(&mut self, decl: &Decl) {
+        let offset = self.allocate_variable(decl.id);
+
+        if let Some(ref init) = decl.init {
+            let value = self.generate_expression(init);
+
+            self.instrs.extend(value);
+            self.instrs.push(Instr::Mov { src: RAX, dst: local(offset) });
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt.kind {
+            StmtKind::Return(ref expr) => {
+                if let Some(ref expr) = *expr {
+                    let value = self.generate_expression(expr);
+
+                    self.instrs.extend(value);
+                }
+
+                self.instrs.extend(epilogue());
             },
-            Symbol::Statement(ref statement_type) => {
-                match statement_type {
-                    &StatementType::Return => {
-                        self.buf.push_str(&format!("{}ret\n", self.expression_stack[self.expression_stack.len() - 1]));
-                    },
-                    _ => { panic!("Unsupported statement type: {:#?}", statement_type); }
+            StmtKind::Expr(ref expr) => {
+                let value = self.generate_expression(expr);
+
+                self.instrs.extend(value);
+            },
+            StmtKind::Compound(_) => visit::walk_stmt(self, stmt),
+            StmtKind::If(ref condition, ref then_branch, ref else_branch) => {
+                let condition = self.generate_expression(condition);
+                let else_label = self.next_label();
+                let end_label = self.next_label();
+
+                self.instrs.extend(condition);
+                self.instrs.push(Instr::Cmp { src: Operand::Imm(0), dst: RAX });
+                self.instrs.push(Instr::Jcc(Condition::E, else_label.clone()));
+                self.visit_stmt(then_branch);
+                self.instrs.push(Instr::Jmp(end_label.clone()));
+                self.instrs.push(Instr::Label(else_label));
+
+                if let Some(ref else_branch) = *else_branch {
+                    self.visit_stmt(else_branch);
                 }
+
+                self.instrs.push(Instr::Label(end_label));
             },
-            _ => { }
-        };
+            StmtKind::While(ref condition, ref body) => {
+                let condition = self.generate_expression(condition);
+                let start_label = self.next_label();
+                let end_label = self.next_label();
+
+                self.instrs.push(Instr::Label(start_label.clone()));
+                self.instrs.extend(condition);
+                self.instrs.push(Instr::Cmp { src: Operand::Imm(0), dst: RAX });
+                self.instrs.push(Instr::Jcc(Condition::E, end_label.clone()));
+                self.visit_stmt(body);
+                self.instrs.push(Instr::Jmp(start_label));
+                self.instrs.push(Instr::Label(end_label));
+            }
+        }
     }
 
-    fn postorder_traversal(&mut self, ast: &AST) {
-        for child in &ast.children {
-            self.postorder_traversal(child);
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr.kind {
+            // The assignment target is a location, not a value, so only the right hand side is evaluated
+            ExprKind::Assignment(_, ref value) => self.visit_expr(value),
+            _ => visit::walk_expr(self, expr)
         }
-        
-        self.visit_node(ast);
+
+        self.visit_node(expr);
     }
 }
 
-pub fn generate(ast: AST) -> String {
+/// Generates x86-64 instructions for a translation unit that has passed
+/// semantic analysis without errors.
+pub fn generate(translation_unit: TranslationUnit) -> Vec<Instr> {
     let generator = Generator::new();
 
-    generator.generate(&ast)
-}
\ No newline at end of file
+    generator.generate(&translation_unit)
+}
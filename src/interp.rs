@@ -0,0 +1,298 @@
+//! A tree-walking interpreter for programs that have passed semantic
+//! analysis, to run them where there is no toolchain and as an oracle for
+//! the backends.
+//!
+//! Values are held the way `constant` holds them, as what an object of the
+//! expression's type under the target's data layout would hold. Instead of
+//! giving undefined behavior a meaning, the interpreter stops at it with an
+//! error at the offending expression: signed overflow, division by zero,
+//! out of range shift counts, reads of uninitialized variables and calls
+//! that run off the end of a function returning a value. Like GCC, it wraps
+//! conversions to signed types and left shifts into the sign bit. The
+//! language has no arrays or pointers yet, so no access can be out of
+//! bounds.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::panic;
+use std::thread;
+
+use constant;
+use diagnostics::Diagnostic;
+use target::DataLayout;
+use TranslationUnit;
+use FunctionDef;
+use BlockItem;
+use DeclId;
+use Stmt;
+use StmtKind;
+use Expr;
+use ExprKind;
+use UnaryOperator;
+use BinaryOperator;
+use Type;
+use Span;
+
+// Deep enough for any sensible recursion, but well short of overflowing
+// the interpreter's own stack
+const MAX_CALL_DEPTH: usize = 10000;
+
+// Each call takes several frames of the interpreter's own, large ones when
+// it is built without optimization
+const STACK_SIZE: usize = 256 << 20;
+
+// How control leaves a statement
+enum Flow {
+    Next,
+    Return(i64)
+}
+
+struct Interpreter<'a, W: Write> {
+    translation_unit: &'a TranslationUnit,
+    layout: DataLayout,
+    output: W,
+    // The variables of each active call, innermost last; None until assigned
+    frames: Vec<HashMap<DeclId, Option<i64>>>
+}
+
+impl<'a, W: Write> Interpreter<'a, W> {
+    fn representation(&self, ty: Type) -> Type {
+        self.layout.representation(ty)
+    }
+
+    // The value a conversion to `ty` gives
+    fn wrap(&self, value: i128, ty: Type) -> i64 {
+        constant::wrap(value, self.representation(ty))
+    }
+
+    // The value of a signed operation, which must fit its type
+    fn checked(&self, value: i128, ty: Type, span: Span) -> Result<i64, Diagnostic> {
+        let representation = self.representation(ty);
+
+        if representation.is_signed() && !constant::fits(value, representation) {
+            return Err(Diagnostic::error(format!("signed overflow: {} does not fit in '{}'", value, ty), span));
+        }
+
+        Ok(constant::wrap(value, representation))
+    }
+
+    fn frame(&mut self) -> &mut HashMap<DeclId, Option<i64>> {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn call(&mut self, name: &str, args: Vec<i64>, span: Span) -> Result<i64, Diagnostic> {
+        let function = match self.translation_unit.function(name) {
+            Some(function) => function,
+            None => return self.call_library(name, args, span)
+        };
+
+        if self.frames.len() == MAX_CALL_DEPTH {
+            return Err(Diagnostic::error(format!("call to '{}' is more than {} calls deep", name, MAX_CALL_DEPTH), span));
+        }
+
+        let frame = function.params.iter().enumerate().map(|(index, param)| (param.id, args.get(index).cloned())).collect();
+
+        self.frames.push(frame);
+        let flow = self.execute_items(function.body.iter().flatten());
+        self.frames.pop();
+
+        // Only main returns 0 when control reaches its closing brace; any other
+        // function that returns a value leaves its caller with garbage
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            Flow::Next if function.return_type == Type::Void || name == "main" => Ok(0),
+            Flow::Next => Err(Diagnostic::error(format!("'{}' reached the end of its body without returning a value", name), span))
+        }
+    }
+
+    // The library functions the interpreter provides itself
+    fn call_library(&mut self, name: &str, args: Vec<i64>, span: Span) -> Result<i64, Diagnostic> {
+        match name {
+            "putchar" if args.len() == 1 => {
+                let written = self.output.write_all(&[args[0] as u8]);
+
+                // EOF, as putchar returns when it cannot write
+                Ok(if written.is_ok() { args[0] & 255 } else { -1 })
+            },
+            _ => Err(Diagnostic::error(format!("'{}' is not defined in this file, so it cannot be interpreted", name), span))
+        }
+    }
+
+    fn execute_items<'b, I: Iterator<Item = &'b BlockItem>>(&mut self, items: I) -> Result<Flow, Diagnostic> {
+        for item in items {
+            let flow = match *item {
+                BlockItem::Decl(ref decl) => {
+                    let value = match decl.init {
+                        Some(ref init) => Some(self.evaluate(init)?),
+                        None => None
+                    };
+
+                    // A variable declared in a loop is uninitialized again on each iteration
+                    self.frame().insert(decl.id, value);
+
+                    Flow::Next
+                },
+                BlockItem::Stmt(ref stmt) => self.execute(stmt)?
+            };
+
+            if let Flow::Return(_) = flow {
+                return Ok(flow);
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<Flow, Diagnostic> {
+        match stmt.kind {
+            // Semantic analysis only allows a bare return in a void function
+            StmtKind::Return(ref expr) => {
+                let value = match *expr {
+                    Some(ref expr) => self.evaluate(expr)?,
+                    None => 0
+                };
+
+                Ok(Flow::Return(value))
+            },
+            StmtKind::Expr(ref expr) => {
+                self.evaluate(expr)?;
+
+                Ok(Flow::Next)
+            },
+            StmtKind::Compound(ref items) => self.execute_items(items.iter()),
+            StmtKind::If(ref condition, ref then_branch, ref else_branch) => {
+                if self.evaluate(condition)? != 0 {
+                    self.execute(then_branch)
+                } else if let Some(ref else_branch) = *else_branch {
+                    self.execute(else_branch)
+                } else {
+                    Ok(Flow::Next)
+                }
+            },
+            StmtKind::While(ref condition, ref body) => {
+                while self.evaluate(condition)? != 0 {
+                    if let Flow::Return(value) = self.execute(body)? {
+                        return Ok(Flow::Return(value));
+                    }
+                }
+
+                Ok(Flow::Next)
+            }
+        }
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<i64, Diagnostic> {
+        if let Some(value) = constant::literal_value(expr) {
+            return Ok(self.wrap(value as i128, expr.ty()));
+        }
+
+        match expr.kind {
+            ExprKind::Constant(_) => unreachable!("constants are literals"),
+            ExprKind::Variable(ref name, decl_id) => {
+                let decl_id = decl_id.expect("variable was not resolved by semantic analysis");
+
+                self.frame()[&decl_id].ok_or_else(|| Diagnostic::error(format!("variable '{}' is used uninitialized", name), expr.span))
+            },
+            ExprKind::Assignment(ref target, ref value) => {
+                let value = self.evaluate(value)?;
+                let decl_id = target.decl_id().expect("variable was not resolved by semantic analysis");
+
+                self.frame().insert(decl_id, Some(value));
+
+                Ok(value)
+            },
+            ExprKind::ImplicitCast(ty, ref operand) => {
+                let value = self.evaluate(operand)?;
+
+                Ok(self.wrap(value as i128, ty))
+            },
+            ExprKind::Call(ref name, ref args) => {
+                let mut values = Vec::new();
+
+                for arg in args {
+                    values.push(self.evaluate(arg)?);
+                }
+
+                let value = self.call(name, values, expr.span)?;
+
+                Ok(self.wrap(value as i128, expr.ty()))
+            },
+            ExprKind::Unary(operator, ref operand) => {
+                let value = self.evaluate(operand)? as i128;
+
+                match operator {
+                    UnaryOperator::Negation if expr.ty().is_signed() => self.checked(-value, expr.ty(), expr.span),
+                    UnaryOperator::Negation => Ok(self.wrap(-value, expr.ty())),
+                    UnaryOperator::BitwiseComplement => Ok(self.wrap(!value, expr.ty())),
+                    UnaryOperator::LogicalNegation => Ok((value == 0) as i64)
+                }
+            },
+            ExprKind::Binary(BinaryOperator::LogicalAnd, ref lhs, ref rhs) => {
+                Ok((self.evaluate(lhs)? != 0 && self.evaluate(rhs)? != 0) as i64)
+            },
+            ExprKind::Binary(BinaryOperator::LogicalOr, ref lhs, ref rhs) => {
+                Ok((self.evaluate(lhs)? != 0 || self.evaluate(rhs)? != 0) as i64)
+            },
+            ExprKind::Binary(operator, ref lhs, ref rhs) => {
+                let ty = lhs.ty();
+                let (a, b) = (self.evaluate(lhs)? as i128, self.evaluate(rhs)? as i128);
+
+                let value = match operator {
+                    BinaryOperator::Addition => a + b,
+                    BinaryOperator::Subtraction => a - b,
+                    BinaryOperator::Multiplication => a * b,
+                    BinaryOperator::Division if b == 0 => return Err(Diagnostic::error("division by zero", expr.span)),
+                    BinaryOperator::Division => a / b,
+                    BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => {
+                        let bits = self.representation(ty).size() as i128 * 8;
+
+                        if !(0..bits).contains(&b) {
+                            return Err(Diagnostic::error(format!("shift count {} is out of range for '{}'", b, ty), expr.span));
+                        }
+
+                        // Unsigned values are held zero extended, so shifting right is logical
+                        if operator == BinaryOperator::ShiftLeft {
+                            return Ok(self.wrap(a << b, ty));
+                        }
+
+                        a >> b
+                    },
+                    BinaryOperator::Equal => (a == b) as i128,
+                    BinaryOperator::NotEqual => (a != b) as i128,
+                    BinaryOperator::LessThan => (a < b) as i128,
+                    BinaryOperator::LessThanOrEqual => (a <= b) as i128,
+                    BinaryOperator::GreaterThan => (a > b) as i128,
+                    BinaryOperator::GreaterThanOrEqual => (a >= b) as i128,
+                    BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr => unreachable!()
+                };
+
+                self.checked(value, expr.ty(), expr.span)
+            }
+        }
+    }
+}
+
+/// Runs a translation unit that has passed semantic analysis without
+/// errors with the target's data layout, writing what it prints to
+/// `output`, and returns what `main` returns. Of the library, only
+/// `putchar` can be called.
+pub fn run<W: Write + Send>(translation_unit: &TranslationUnit, layout: &DataLayout, output: W) -> Result<i64, Diagnostic> {
+    let main: &FunctionDef = translation_unit.function("main")
+        .ok_or_else(|| Diagnostic::error("there is no 'main' function to run", Span::default()))?;
+
+    let mut interpreter = Interpreter {
+        translation_unit,
+        layout: *layout,
+        output,
+        frames: Vec::new()
+    };
+
+    thread::scope(|scope| {
+        let running = thread::Builder::new().stack_size(STACK_SIZE).spawn_scoped(scope, move || {
+            // Parameters of main are left uninitialized, since there is no command line
+            interpreter.call(&main.name, Vec::new(), main.span)
+        }).expect("could not start the interpreter's thread");
+
+        running.join().unwrap_or_else(|payload| panic::resume_unwind(payload))
+    })
+}
@@ -0,0 +1,591 @@
+//! A three-address intermediate representation between the AST and the
+//! backends.
+//!
+//! Each function is a control flow graph of basic blocks. Values live in an
+//! unbounded set of virtual registers, each a full machine word holding a
+//! value normalized to its C type: `cast` truncates a word to a type and
+//! extends it back, and every other instruction works on whole words. Locals
+//! are virtual registers too, so a register may be assigned more than once,
+//! until `ssa::construct` renames them into SSA form with phi instructions.
+
+use std::error::Error;
+use std::fmt;
+
+use Type;
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Hash,PartialOrd,Ord)]
+pub struct VReg(pub usize);
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Hash,PartialOrd,Ord)]
+pub struct BlockId(pub usize);
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Hash,PartialOrd,Ord)]
+pub enum Operand {
+    Reg(VReg),
+    Imm(i64)
+}
+
+impl Operand {
+    pub fn as_reg(self) -> Option<VReg> {
+        match self {
+            Operand::Reg(reg) => Some(reg),
+            Operand::Imm(_) => None
+        }
+    }
+
+    pub fn as_imm(self) -> Option<i64> {
+        match self {
+            Operand::Imm(value) => Some(value),
+            Operand::Reg(_) => None
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Hash)]
+pub enum UnaryOp {
+    Neg,
+    Not
+}
+
+/// Signedness is part of the operation rather than the operands, so a
+/// backend never needs to know the C types involved.
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Hash)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    UDiv,
+    Shl,
+    Sar,
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    ULt,
+    ULe,
+    UGt,
+    UGe
+}
+
+impl UnaryOp {
+    pub fn name(self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "neg",
+            UnaryOp::Not => "not"
+        }
+    }
+
+    pub fn evaluate(self, value: i64) -> i64 {
+        match self {
+            UnaryOp::Neg => value.wrapping_neg(),
+            UnaryOp::Not => !value
+        }
+    }
+}
+
+impl BinaryOp {
+    pub fn name(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "mul",
+            BinaryOp::Div => "div",
+            BinaryOp::UDiv => "udiv",
+            BinaryOp::Shl => "shl",
+            BinaryOp::Sar => "sar",
+            BinaryOp::Shr => "shr",
+            BinaryOp::Eq => "eq",
+            BinaryOp::Ne => "ne",
+            BinaryOp::Lt => "lt",
+            BinaryOp::Le => "le",
+            BinaryOp::Gt => "gt",
+            BinaryOp::Ge => "ge",
+            BinaryOp::ULt => "ult",
+            BinaryOp::ULe => "ule",
+            BinaryOp::UGt => "ugt",
+            BinaryOp::UGe => "uge"
+        }
+    }
+
+    /// Computes the operation on two words, or returns `None` where the
+    /// machine result is undefined: division by zero and shifts by a count
+    /// outside the word.
+    pub fn evaluate(self, lhs: i64, rhs: i64) -> Option<i64> {
+        let value = match self {
+            BinaryOp::Add => lhs.wrapping_add(rhs),
+            BinaryOp::Sub => lhs.wrapping_sub(rhs),
+            BinaryOp::Mul => lhs.wrapping_mul(rhs),
+            BinaryOp::Div => lhs.checked_div(rhs).or(if rhs == -1 { Some(lhs.wrapping_neg()) } else { None })?,
+            BinaryOp::UDiv => (lhs as u64).checked_div(rhs as u64)? as i64,
+            BinaryOp::Shl | BinaryOp::Sar | BinaryOp::Shr if !(0..64).contains(&rhs) => return None,
+            BinaryOp::Shl => lhs << rhs,
+            BinaryOp::Sar => lhs >> rhs,
+            BinaryOp::Shr => ((lhs as u64) >> rhs) as i64,
+            BinaryOp::Eq => (lhs == rhs) as i64,
+            BinaryOp::Ne => (lhs != rhs) as i64,
+            BinaryOp::Lt => (lhs < rhs) as i64,
+            BinaryOp::Le => (lhs <= rhs) as i64,
+            BinaryOp::Gt => (lhs > rhs) as i64,
+            BinaryOp::Ge => (lhs >= rhs) as i64,
+            BinaryOp::ULt => ((lhs as u64) < rhs as u64) as i64,
+            BinaryOp::ULe => (lhs as u64 <= rhs as u64) as i64,
+            BinaryOp::UGt => (lhs as u64 > rhs as u64) as i64,
+            BinaryOp::UGe => (lhs as u64 >= rhs as u64) as i64
+        };
+
+        Some(value)
+    }
+
+    pub fn is_commutative(self) -> bool {
+        matches!(self, BinaryOp::Add | BinaryOp::Mul | BinaryOp::Eq | BinaryOp::Ne)
+    }
+
+    /// Whether the result is a 0 or 1 truth value.
+    pub fn is_comparison(self) -> bool {
+        matches!(self, BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge |
+            BinaryOp::ULt | BinaryOp::ULe | BinaryOp::UGt | BinaryOp::UGe)
+    }
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub enum Instr {
+    Copy { dst: VReg, src: Operand },
+    Unary { op: UnaryOp, dst: VReg, src: Operand },
+    Binary { op: BinaryOp, dst: VReg, lhs: Operand, rhs: Operand },
+    /// Truncates `src` to `ty` and extends it back to a full word.
+    Cast { dst: VReg, ty: Type, src: Operand },
+    Call { dst: Option<VReg>, function: String, args: Vec<Operand> },
+    /// Only in SSA form, at the start of a block: takes the operand paired
+    /// with whichever predecessor control arrived from.
+    Phi { dst: VReg, sources: Vec<(BlockId, Operand)> }
+}
+
+impl Instr {
+    pub fn dst(&self) -> Option<VReg> {
+        match *self {
+            Instr::Copy { dst, .. } | Instr::Unary { dst, .. } | Instr::Binary { dst, .. } |
+            Instr::Cast { dst, .. } | Instr::Phi { dst, .. } => Some(dst),
+            Instr::Call { dst, .. } => dst
+        }
+    }
+
+    pub fn dst_mut(&mut self) -> Option<&mut VReg> {
+        match *self {
+            Instr::Copy { ref mut dst, .. } | Instr::Unary { ref mut dst, .. } | Instr::Binary { ref mut dst, .. } |
+            Instr::Cast { ref mut dst, .. } | Instr::Phi { ref mut dst, .. } => Some(dst),
+            Instr::Call { ref mut dst, .. } => dst.as_mut()
+        }
+    }
+
+    pub fn is_phi(&self) -> bool {
+        matches!(*self, Instr::Phi { .. })
+    }
+
+    /// Whether the instruction does anything besides computing its result.
+    pub fn has_side_effects(&self) -> bool {
+        matches!(*self, Instr::Call { .. })
+    }
+
+    pub fn operands(&self) -> Vec<Operand> {
+        match *self {
+            Instr::Copy { src, .. } | Instr::Unary { src, .. } | Instr::Cast { src, .. } => vec![src],
+            Instr::Binary { lhs, rhs, .. } => vec![lhs, rhs],
+            Instr::Call { ref args, .. } => args.clone(),
+            Instr::Phi { ref sources, .. } => sources.iter().map(|&(_, source)| source).collect()
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match *self {
+            Instr::Copy { ref mut src, .. } | Instr::Unary { ref mut src, .. } | Instr::Cast { ref mut src, .. } => vec![src],
+            Instr::Binary { ref mut lhs, ref mut rhs, .. } => vec![lhs, rhs],
+            Instr::Call { ref mut args, .. } => args.iter_mut().collect(),
+            Instr::Phi { ref mut sources, .. } => sources.iter_mut().map(|&mut (_, ref mut source)| source).collect()
+        }
+    }
+
+    pub fn uses(&self) -> Vec<VReg> {
+        self.operands().into_iter().filter_map(Operand::as_reg).collect()
+    }
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub enum Terminator {
+    Return(Option<Operand>),
+    Jump(BlockId),
+    Branch { condition: Operand, then_block: BlockId, else_block: BlockId }
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match *self {
+            Terminator::Return(_) => Vec::new(),
+            Terminator::Jump(target) => vec![target],
+            Terminator::Branch { then_block, else_block, .. } => vec![then_block, else_block]
+        }
+    }
+
+    pub fn operands(&self) -> Vec<Operand> {
+        match *self {
+            Terminator::Return(value) => value.into_iter().collect(),
+            Terminator::Jump(_) => Vec::new(),
+            Terminator::Branch { condition, .. } => vec![condition]
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match *self {
+            Terminator::Return(ref mut value) => value.iter_mut().collect(),
+            Terminator::Jump(_) => Vec::new(),
+            Terminator::Branch { ref mut condition, .. } => vec![condition]
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct Block {
+    pub instrs: Vec<Instr>,
+    pub terminator: Terminator
+}
+
+/// A function definition. Block 0 is the entry block.
+#[derive(Debug,PartialEq,Clone)]
+pub struct Function {
+    pub name: String,
+    pub return_type: Type,
+    pub params: Vec<(VReg, Type)>,
+    pub blocks: Vec<Block>,
+    pub vreg_count: usize
+}
+
+impl Function {
+    pub const ENTRY: BlockId = BlockId(0);
+
+    pub fn new_vreg(&mut self) -> VReg {
+        let reg = VReg(self.vreg_count);
+        self.vreg_count += 1;
+
+        reg
+    }
+
+    pub fn block(&self, id: BlockId) -> &Block {
+        &self.blocks[id.0]
+    }
+
+    pub fn block_ids(&self) -> impl DoubleEndedIterator<Item = BlockId> {
+        (0..self.blocks.len()).map(BlockId)
+    }
+
+    pub fn successors(&self, id: BlockId) -> Vec<BlockId> {
+        self.block(id).terminator.successors()
+    }
+
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+
+        for id in self.block_ids() {
+            for successor in self.successors(id) {
+                predecessors[successor.0].push(id);
+            }
+        }
+
+        predecessors
+    }
+
+    /// Blocks reachable from the entry, in reverse postorder.
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut postorder = Vec::new();
+        // Each entry is a block and how many of its successors have been visited
+        let mut stack = vec![(Function::ENTRY, 0)];
+        visited[0] = true;
+
+        while let Some(&mut (id, ref mut next)) = stack.last_mut() {
+            // Visiting successors backwards puts the first one first in the result
+            let mut successors = self.successors(id);
+            successors.reverse();
+
+            if *next < successors.len() {
+                let successor = successors[*next];
+                *next += 1;
+
+                if !visited[successor.0] {
+                    visited[successor.0] = true;
+                    stack.push((successor, 0));
+                }
+            } else {
+                postorder.push(id);
+                stack.pop();
+            }
+        }
+
+        postorder.reverse();
+        postorder
+    }
+
+    /// Drops blocks that cannot be reached from the entry and renumbers the
+    /// rest in reverse postorder.
+    pub fn remove_unreachable_blocks(&mut self) {
+        let order = self.reverse_postorder();
+
+        let mut renumbered = vec![None; self.blocks.len()];
+
+        for (index, id) in order.iter().enumerate() {
+            renumbered[id.0] = Some(BlockId(index));
+        }
+
+        let renumber = |id: &mut BlockId| *id = renumbered[id.0].expect("reachable block jumps to an unreachable one");
+        let mut blocks = Vec::with_capacity(order.len());
+
+        for id in order {
+            let mut block = self.blocks[id.0].clone();
+
+            for instr in &mut block.instrs {
+                if let Instr::Phi { ref mut sources, .. } = *instr {
+                    sources.retain(|&(source, _)| renumbered[source.0].is_some());
+
+                    for &mut (ref mut source, _) in sources.iter_mut() {
+                        renumber(source);
+                    }
+                }
+            }
+
+            match block.terminator {
+                Terminator::Return(_) => {},
+                Terminator::Jump(ref mut target) => renumber(target),
+                Terminator::Branch { ref mut then_block, ref mut else_block, .. } => {
+                    renumber(then_block);
+                    renumber(else_block);
+                }
+            }
+
+            blocks.push(block);
+        }
+
+        self.blocks = blocks;
+    }
+}
+
+/// A function that is called but not defined in this module.
+#[derive(Debug,PartialEq,Clone)]
+pub struct Declaration {
+    pub name: String,
+    pub return_type: Type,
+    pub params: Vec<Type>
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct Module {
+    pub functions: Vec<Function>,
+    pub declarations: Vec<Declaration>
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operand::Reg(reg) => write!(f, "{}", reg),
+            Operand::Imm(value) => write!(f, "{}", value)
+        }
+    }
+}
+
+impl fmt::Display for VReg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instr::Copy { dst, src } => write!(f, "{} = copy {}", dst, src),
+            Instr::Unary { op, dst, src } => write!(f, "{} = {} {}", dst, op.name(), src),
+            Instr::Binary { op, dst, lhs, rhs } => write!(f, "{} = {} {}, {}", dst, op.name(), lhs, rhs),
+            Instr::Cast { dst, ty, src } => write!(f, "{} = cast {} {}", dst, ty, src),
+            Instr::Call { dst, ref function, ref args } => {
+                if let Some(dst) = dst {
+                    write!(f, "{} = ", dst)?;
+                }
+
+                let args: Vec<String> = args.iter().map(Operand::to_string).collect();
+
+                write!(f, "call {}({})", function, args.join(", "))
+            },
+            Instr::Phi { dst, ref sources } => {
+                let sources: Vec<String> = sources.iter().map(|&(block, source)| format!("[{}, {}]", block, source)).collect();
+
+                write!(f, "{} = phi {}", dst, sources.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Terminator::Return(Some(value)) => write!(f, "ret {}", value),
+            Terminator::Return(None) => write!(f, "ret"),
+            Terminator::Jump(target) => write!(f, "jmp {}", target),
+            Terminator::Branch { condition, then_block, else_block } => write!(f, "br {}, {}, {}", condition, then_block, else_block)
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let params: Vec<String> = self.params.iter().map(|&(reg, ty)| format!("{} {}", ty, reg)).collect();
+
+        writeln!(f, "function {} {}({}) {{", self.return_type, self.name, params.join(", "))?;
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "{}:", BlockId(index))?;
+
+            for instr in &block.instrs {
+                writeln!(f, "    {}", instr)?;
+            }
+
+            writeln!(f, "    {}", block.terminator)?;
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for declaration in &self.declarations {
+            let params: Vec<&str> = declaration.params.iter().map(|ty| ty.name()).collect();
+
+            writeln!(f, "declare {} {}({})", declaration.return_type, declaration.name, params.join(", "))?;
+        }
+
+        for (index, function) in self.functions.iter().enumerate() {
+            if index > 0 || !self.declarations.is_empty() {
+                writeln!(f)?;
+            }
+
+            write!(f, "{}", function)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct VerifyError {
+    pub function: String,
+    pub message: String
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "in function {}: {}", self.function, self.message)
+    }
+}
+
+impl Error for VerifyError {}
+
+/// Checks the structural invariants every pass may rely on: block and
+/// register references are in range, parameters are distinct, and returns
+/// agree with the function's return type.
+pub fn verify_function(function: &Function) -> Result<(), VerifyError> {
+    let error = |message: String| Err(VerifyError {
+        function: function.name.clone(),
+        message
+    });
+
+    if function.blocks.is_empty() {
+        return error("function has no blocks".to_string());
+    }
+
+    let mut params: Vec<VReg> = function.params.iter().map(|&(reg, _)| reg).collect();
+    params.sort();
+    params.dedup();
+
+    if params.len() != function.params.len() {
+        return error("a register is used for more than one parameter".to_string());
+    }
+
+    let check_reg = |reg: VReg| reg.0 < function.vreg_count;
+
+    if !params.iter().cloned().all(check_reg) {
+        return error("parameter register out of range".to_string());
+    }
+
+    // Checked first, since finding the predecessors relies on it
+    for id in function.block_ids() {
+        if let Some(target) = function.successors(id).into_iter().find(|target| target.0 >= function.blocks.len()) {
+            return error(format!("{}: jump to missing block {}", id, target));
+        }
+    }
+
+    let predecessors = function.predecessors();
+
+    for id in function.block_ids() {
+        let block = function.block(id);
+
+        if block.instrs.iter().skip_while(|instr| instr.is_phi()).any(Instr::is_phi) {
+            return error(format!("{}: phi after a non-phi instruction", id));
+        }
+
+        for instr in &block.instrs {
+            if let Instr::Phi { ref sources, .. } = *instr {
+                let mut incoming: Vec<BlockId> = sources.iter().map(|&(block, _)| block).collect();
+                let mut expected = predecessors[id.0].clone();
+                incoming.sort();
+                expected.sort();
+                expected.dedup();
+
+                if incoming != expected {
+                    return error(format!("{}: sources of '{}' do not match the predecessors", id, instr));
+                }
+            }
+
+            let registers = instr.dst().into_iter().chain(instr.uses());
+
+            if let Some(reg) = registers.into_iter().find(|&reg| !check_reg(reg)) {
+                return error(format!("{}: {} in '{}' is out of range", id, reg, instr));
+            }
+
+            if let Instr::Cast { ty: Type::Void, .. } = *instr {
+                return error(format!("{}: cast to void in '{}'", id, instr));
+            }
+        }
+
+        let terminator = &block.terminator;
+
+        if let Some(reg) = terminator.operands().into_iter().filter_map(Operand::as_reg).find(|&reg| !check_reg(reg)) {
+            return error(format!("{}: {} in '{}' is out of range", id, reg, terminator));
+        }
+
+        match *terminator {
+            Terminator::Return(Some(_)) if function.return_type == Type::Void => {
+                return error(format!("{}: void function returns a value", id));
+            },
+            Terminator::Return(None) if function.return_type != Type::Void => {
+                return error(format!("{}: non-void function returns without a value", id));
+            },
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+pub fn verify(module: &Module) -> Result<(), VerifyError> {
+    for function in &module.functions {
+        verify_function(function)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! Running the integrated assembler's code in this process, without writing
+//! an object file or linking.
+//!
+//! The code is copied into memory that is writable but not executable,
+//! relocated, and then made executable but not writable. Calls to functions
+//! the program does not define go through stubs after the code that jump to
+//! the address `dlsym` finds, since the library may be mapped too far away
+//! for a call's 32-bit displacement.
+
+use std::ffi::{c_void, CString};
+use std::ptr;
+
+use encoder::{Code, RelocationKind};
+
+const PROT_READ: i32 = 0x1;
+const PROT_WRITE: i32 = 0x2;
+const PROT_EXEC: i32 = 0x4;
+const MAP_PRIVATE: i32 = 0x02;
+const MAP_ANONYMOUS: i32 = 0x20;
+const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+const RTLD_DEFAULT: *mut c_void = ptr::null_mut();
+
+// jmp *0(%rip), followed by the address to jump to
+const STUB: [u8; 6] = [0xff, 0x25, 0, 0, 0, 0];
+const STUB_SIZE: usize = 14;
+
+extern "C" {
+    fn mmap(addr: *mut c_void, len: usize, prot: i32, flags: i32, fd: i32, offset: i64) -> *mut c_void;
+    fn mprotect(addr: *mut c_void, len: usize, prot: i32) -> i32;
+    fn munmap(addr: *mut c_void, len: usize) -> i32;
+    fn dlsym(handle: *mut c_void, symbol: *const i8) -> *mut c_void;
+    fn fflush(stream: *mut c_void) -> i32;
+}
+
+// Memory mapped for the code, unmapped when dropped
+struct Mapping {
+    address: *mut u8,
+    len: usize
+}
+
+impl Mapping {
+    fn new(len: usize) -> Result<Mapping, String> {
+        let address = unsafe { mmap(ptr::null_mut(), len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0) };
+
+        if address == MAP_FAILED {
+            return Err(format!("could not map {} bytes for the code", len));
+        }
+
+        Ok(Mapping { address: address as *mut u8, len })
+    }
+
+    fn make_executable(&self) -> Result<(), String> {
+        match unsafe { mprotect(self.address as *mut c_void, self.len, PROT_READ | PROT_EXEC) } {
+            0 => Ok(()),
+            _ => Err("could not make the code executable".to_string())
+        }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { munmap(self.address as *mut c_void, self.len) };
+    }
+}
+
+fn resolve(name: &str) -> Result<usize, String> {
+    let symbol = CString::new(name).map_err(|_| format!("'{}' is not a valid symbol name", name))?;
+    let address = unsafe { dlsym(RTLD_DEFAULT, symbol.as_ptr()) };
+
+    if address.is_null() {
+        return Err(format!("undefined reference to '{}'", name));
+    }
+
+    Ok(address as usize)
+}
+
+/// Loads code into this process and calls its `main`, returning what it
+/// returns. The code runs with this process's privileges and can bring it
+/// down, as a program linked from it could only bring down itself.
+pub fn run(code: &Code) -> Result<i32, String> {
+    let main = code.symbols.iter().find(|symbol| symbol.name == "main")
+        .ok_or_else(|| "there is no 'main' function to run".to_string())?;
+
+    let undefined = code.undefined_symbols();
+    let stubs = code.bytes.len();
+    let mapping = Mapping::new(stubs + undefined.len() * STUB_SIZE)?;
+    let base = mapping.address as usize;
+
+    let memory = unsafe { std::slice::from_raw_parts_mut(mapping.address, mapping.len) };
+    memory[..stubs].copy_from_slice(&code.bytes);
+
+    let mut addresses = Vec::new();
+
+    for (index, name) in undefined.iter().enumerate() {
+        let stub = stubs + index * STUB_SIZE;
+
+        memory[stub..stub + STUB.len()].copy_from_slice(&STUB);
+        memory[stub + STUB.len()..stub + STUB_SIZE].copy_from_slice(&(resolve(name)? as u64).to_le_bytes());
+
+        addresses.push((*name, base + stub));
+    }
+
+    for symbol in &code.symbols {
+        addresses.push((&symbol.name, base + symbol.offset));
+    }
+
+    for relocation in &code.relocations {
+        let target = match relocation.kind {
+            RelocationKind::Plt32 => addresses.iter().find(|&&(name, _)| name == relocation.symbol).unwrap().1,
+            // Data has to be reached directly, without a stub
+            RelocationKind::Pc32 => match code.symbols.iter().find(|symbol| symbol.name == relocation.symbol) {
+                Some(symbol) => base + symbol.offset,
+                None => resolve(&relocation.symbol)?
+            }
+        };
+
+        let field = base + relocation.offset;
+        let displacement = target as i64 + relocation.addend - field as i64;
+
+        if displacement as i32 as i64 != displacement {
+            return Err(format!("'{}' is too far away to be reached from the code", relocation.symbol));
+        }
+
+        memory[relocation.offset..relocation.offset + 4].copy_from_slice(&(displacement as i32).to_le_bytes());
+    }
+
+    mapping.make_executable()?;
+
+    let status = unsafe {
+        let main: extern "C" fn() -> i32 = std::mem::transmute(base + main.offset);
+        main()
+    };
+
+    // What the program printed through stdio would otherwise wait for exit
+    unsafe { fflush(ptr::null_mut()) };
+
+    Ok(status)
+}
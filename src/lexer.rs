@@ -1,231 +1,255 @@
+use std::error::Error;
+use std::fmt;
+
 use Token;
 use Keyword;
+use Span;
 
-#[derive(Debug)]
-struct Lexer {
-    buf: String,
-    char_indices: Vec<(usize, char)>,
-    len: usize,
-    pos: usize
+#[derive(Debug,PartialEq,Clone)]
+pub struct SpannedToken<'a> {
+    pub token: Token<'a>,
+    pub span: Span
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub enum LexErrorKind {
+    UnexpectedCharacter(char),
+    IntegerLiteralTooLarge
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub span: Span
 }
 
-impl Lexer {
-    pub fn new(buf: String) -> Lexer {
-        let char_indices: Vec<(usize, char)> = buf.char_indices().collect();
-        let len = char_indices.len();
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            LexErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character {:?}", c),
+            LexErrorKind::IntegerLiteralTooLarge => write!(f, "integer literal is too large")
+        }
+    }
+}
 
+impl Error for LexError {}
+
+/// Lexes a source buffer lazily, one token per call to `next`.
+///
+/// Identifiers borrow from the source, so no token owns any allocation.
+#[derive(Debug,Clone)]
+pub struct Lexer<'a> {
+    buf: &'a str,
+    pos: usize
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(buf: &'a str) -> Lexer<'a> {
         Lexer {
             buf,
-            char_indices,
-            len,
             pos: 0
         }
     }
 
-    fn scan_until<F>(&mut self, mut current_byte_offset: usize, at_end_function: F) -> usize where F: Fn(char) -> bool {
-        self.pos += 1;
+    fn peek_char(&self) -> Option<char> {
+        self.buf[self.pos..].chars().next()
+    }
 
-        loop {
-            if self.pos > self.len {
-                if self.pos > 0 {
-                    current_byte_offset += self.char_indices[self.pos - 1].1.len_utf8();
-                }
-                break
-            }
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek_char()?;
+        self.pos += c.len_utf8();
 
-            let indice = self.char_indices[self.pos];
-            current_byte_offset = indice.0;
-            if at_end_function(indice.1) {
-                break
-            } 
+        Some(c)
+    }
 
-            self.pos += 1;
+    fn bump_if(&mut self, expected: char) -> bool {
+        if self.peek_char() == Some(expected) {
+            self.pos += expected.len_utf8();
+            true
+        } else {
+            false
         }
-
-        current_byte_offset
     }
 
-    pub fn lex(mut self) -> Vec<Token> {
-        let mut tokens: Vec<Token> = Vec::new();
-
-        loop {
-            if self.pos >= self.len {
+    fn scan_while<F>(&mut self, at_continue_function: F) where F: Fn(char) -> bool {
+        while let Some(c) = self.peek_char() {
+            if !at_continue_function(c) {
                 break
             }
 
-            let (current_byte_offset, current_char) = self.char_indices[self.pos];
-
-            // println!("current_byte_offset: {}\r\ncurrent_char: {}", current_byte_offset, current_char);
-
-            let token: Token = match current_char {
-                ' ' => {
-                    self.pos += 1;
-                    Token::Space
-                },
-                '\n' | '\r' => {
-                    self.pos += 1;
-                    Token::NewLine
-                },
-                '(' => {
-                    self.pos += 1;
-                    Token::OpenParen
-                },
-                ')' => {
-                    self.pos += 1;
-                    Token::CloseParen
-                },
-                '{' => {
-                    self.pos += 1;
-                    Token::OpenBrace
-                },
-                '}' => {
-                    self.pos += 1;
-                    Token::CloseBrace
-                },
-                ';' => {
-                    self.pos +=1;
-                    Token::Semicolon
-                },
-                '-' => {
-                    self.pos += 1;
-                    Token::Minus
-                },
-                '~' => {
-                    self.pos += 1;
-                    Token::BitwiseComplementOperator
-                },
-                '!' => {
-                    let end_byte_offset = self.scan_until(current_byte_offset, |c| {
-                        match c {
-                            '=' => false,
-                            _ => true
-                        }
-                    });
-
-                    match &self.buf[current_byte_offset..end_byte_offset] {
-                        "!=" => Token::NotEqual,
-                        "!" => Token::LogicalNegationOperator,
-                        _ => panic!("Unknown token {}", &self.buf[current_byte_offset..end_byte_offset])
-                    }
-                },
-                '+' => {
-                    self.pos += 1;
-                    Token::Addition
-                },
-                '*' => {
-                    self.pos += 1;
-                    Token::Multiplication
-                },
-                '/' => {
-                    self.pos += 1;
-                    Token::Division
-                },
-                '&' => {
-                    let end_byte_offset = self.scan_until(current_byte_offset, |c| {
-                        match c {
-                            '&' => false,
-                            _ => true
-                        }
-                    });
-
-                    match &self.buf[current_byte_offset..end_byte_offset] {
-                        "&&" => Token::LogicalAnd,
-                        "&" => Token::BitwiseAnd,
-                        _ => panic!("Unknown token {}", &self.buf[current_byte_offset..end_byte_offset])
-                    }
-                },
-                '|' => {
-                    let end_byte_offset = self.scan_until(current_byte_offset, |c| {
-                        match c {
-                            '|' => false,
-                            _ => true
-                        }
-                    });
-
-                    match &self.buf[current_byte_offset..end_byte_offset] {
-                        "||" => Token::LogicalOr,
-                        "|" => Token::BitwiseOr,
-                        _ => panic!("Unknown token {}", &self.buf[current_byte_offset..end_byte_offset])
-                    }
-                },
-                '=' => {
-                    let end_byte_offset = self.scan_until(current_byte_offset, |c| {
-                        match c {
-                            '=' => false,
-                            _ => true
-                        }
-                    });
-
-                    match &self.buf[current_byte_offset..end_byte_offset] {
-                        "==" => Token::Equal,
-                        _ => panic!("Unknown token {}", &self.buf[current_byte_offset..end_byte_offset])
-                    
-                    }
-                },
-                '<' => {
-                    let end_byte_offset = self.scan_until(current_byte_offset, |c| {
-                        match c {
-                            '=' => false,
-                            _ => true
-                        }
-                    });
-
-                    match &self.buf[current_byte_offset..end_byte_offset] {
-                        "<" => Token::LessThan,
-                        "<=" => Token::LessThanOrEqual,
-                        _ => panic!("Unknown token {}", &self.buf[current_byte_offset..end_byte_offset])
-                    
-                    }
-                },
-                '>' => {
-                    let end_byte_offset = self.scan_until(current_byte_offset, |c| {
-                        match c {
-                            '=' => false,
-                            _ => true
-                        }
-                    });
-
-                    match &self.buf[current_byte_offset..end_byte_offset] {
-                        ">" => Token::GreaterThan,
-                        ">=" => Token::GreaterThanOrEqual,
-                        _ => panic!("Unknown token {}", &self.buf[current_byte_offset..end_byte_offset])
-                    
-                    }
-                },
-                c if c.is_alphabetic() => {
-                    let end_byte_offset = self.scan_until(current_byte_offset, |c| {
-                        match c {
-                            c if c.is_alphabetic() => false,
-                            _ => true
-                        }
-                    });
-
-                    match &self.buf[current_byte_offset..end_byte_offset] {
-                        "int" => Token::Keyword(Keyword::Int),
-                        "return" => Token::Keyword(Keyword::Return),
-                        _ => Token::Identifier(self.buf[current_byte_offset..end_byte_offset].to_string())
-                    }
-                },
-                c if c.is_numeric() => {
-                    let end_byte_offset = self.scan_until(current_byte_offset, |c| {
-                        match c {
-                            c if c.is_numeric() => false,
-                            _ => true
-                        }
-                    });
-
-                    Token::IntegerLiteral(self.buf[current_byte_offset..end_byte_offset].parse::<usize>().unwrap())
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn lex_token(&mut self) -> Option<Result<SpannedToken<'a>, LexError>> {
+        let start = self.pos;
+        let current_char = self.bump()?;
+
+        let token: Token<'a> = match current_char {
+            ' ' | '\t' => Token::Space,
+            '\n' | '\r' => Token::NewLine,
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            '{' => Token::OpenBrace,
+            '}' => Token::CloseBrace,
+            ';' => Token::Semicolon,
+            ',' => Token::Comma,
+            '-' => Token::Minus,
+            '~' => Token::BitwiseComplementOperator,
+            '!' => {
+                if self.bump_if('=') {
+                    Token::NotEqual
+                } else {
+                    Token::LogicalNegationOperator
+                }
+            },
+            '+' => Token::Addition,
+            '*' => Token::Multiplication,
+            '/' => Token::Division,
+            '&' => {
+                if self.bump_if('&') {
+                    Token::LogicalAnd
+                } else {
+                    Token::BitwiseAnd
+                }
+            },
+            '|' => {
+                if self.bump_if('|') {
+                    Token::LogicalOr
+                } else {
+                    Token::BitwiseOr
+                }
+            },
+            '=' => {
+                if self.bump_if('=') {
+                    Token::Equal
+                } else {
+                    Token::Assignment
+                }
+            },
+            '<' => {
+                if self.bump_if('<') {
+                    Token::ShiftLeft
+                } else if self.bump_if('=') {
+                    Token::LessThanOrEqual
+                } else {
+                    Token::LessThan
+                }
+            },
+            '>' => {
+                if self.bump_if('>') {
+                    Token::ShiftRight
+                } else if self.bump_if('=') {
+                    Token::GreaterThanOrEqual
+                } else {
+                    Token::GreaterThan
+                }
+            },
+            c if is_identifier_start(c) => {
+                self.scan_while(is_identifier_continue);
+
+                let identifier = &self.buf[start..self.pos];
+
+                match keyword(identifier) {
+                    Some(keyword) => Token::Keyword(keyword),
+                    None => Token::Identifier(identifier)
+                }
+            },
+            c if c.is_ascii_digit() => {
+                self.scan_while(|c| c.is_ascii_digit());
+
+                match self.buf[start..self.pos].parse::<usize>() {
+                    Ok(value) => Token::IntegerLiteral(value),
+                    Err(_) => return Some(Err(self.error(LexErrorKind::IntegerLiteralTooLarge, start)))
                 }
-                _ => Token::NewLine
-            };
+            },
+            c => return Some(Err(self.error(LexErrorKind::UnexpectedCharacter(c), start)))
+        };
+
+        Some(Ok(SpannedToken {
+            token,
+            span: Span::new(start, self.pos)
+        }))
+    }
 
-            tokens.push(token);
+    fn error(&self, kind: LexErrorKind, start: usize) -> LexError {
+        LexError {
+            kind,
+            span: Span::new(start, self.pos)
         }
+    }
+}
 
-        tokens
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<SpannedToken<'a>, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lex_token()
     }
 }
 
-pub fn lex(file_contents: String) -> Vec<Token> { 
-    Lexer::new(file_contents).lex() 
-}
\ No newline at end of file
+fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn keyword(identifier: &str) -> Option<Keyword> {
+    let keyword = match identifier {
+        "auto" => Keyword::Auto,
+        "break" => Keyword::Break,
+        "case" => Keyword::Case,
+        "char" => Keyword::Char,
+        "const" => Keyword::Const,
+        "continue" => Keyword::Continue,
+        "default" => Keyword::Default,
+        "do" => Keyword::Do,
+        "double" => Keyword::Double,
+        "else" => Keyword::Else,
+        "enum" => Keyword::Enum,
+        "extern" => Keyword::Extern,
+        "float" => Keyword::Float,
+        "for" => Keyword::For,
+        "goto" => Keyword::Goto,
+        "if" => Keyword::If,
+        "inline" => Keyword::Inline,
+        "int" => Keyword::Int,
+        "long" => Keyword::Long,
+        "register" => Keyword::Register,
+        "restrict" => Keyword::Restrict,
+        "return" => Keyword::Return,
+        "short" => Keyword::Short,
+        "signed" => Keyword::Signed,
+        "sizeof" => Keyword::Sizeof,
+        "static" => Keyword::Static,
+        "struct" => Keyword::Struct,
+        "switch" => Keyword::Switch,
+        "typedef" => Keyword::Typedef,
+        "union" => Keyword::Union,
+        "unsigned" => Keyword::Unsigned,
+        "void" => Keyword::Void,
+        "volatile" => Keyword::Volatile,
+        "while" => Keyword::While,
+        "_Alignas" => Keyword::Alignas,
+        "_Alignof" => Keyword::Alignof,
+        "_Atomic" => Keyword::Atomic,
+        "_Bool" => Keyword::Bool,
+        "_Complex" => Keyword::Complex,
+        "_Generic" => Keyword::Generic,
+        "_Imaginary" => Keyword::Imaginary,
+        "_Noreturn" => Keyword::Noreturn,
+        "_Static_assert" => Keyword::StaticAssert,
+        "_Thread_local" => Keyword::ThreadLocal,
+        _ => return None
+    };
+
+    Some(keyword)
+}
+
+pub fn lex(file_contents: &str) -> Result<Vec<Token<'_>>, LexError> {
+    Lexer::new(file_contents)
+        .map(|result| result.map(|spanned| spanned.token))
+        .collect()
+}
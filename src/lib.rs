@@ -1,42 +1,317 @@
+use std::fmt;
+
 pub mod lexer;
 pub mod parser;
+pub mod diagnostics;
+pub mod sema;
+pub mod constant;
+pub mod ir;
+pub mod lower;
+pub mod ssa;
+pub mod opt;
+pub mod regalloc;
+pub mod target;
+pub mod x86;
+pub mod peephole;
+pub mod encoder;
+pub mod elf;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+pub mod jit;
 pub mod generator;
+pub mod codegen;
+pub mod backend;
+pub mod aarch64;
+pub mod riscv64;
+pub mod wasm32;
+pub mod llvm;
+pub mod transpile;
+pub mod interp;
+pub mod visit;
+pub mod printer;
+#[cfg(feature = "serialize")]
+pub mod serialize;
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct TranslationUnit {
+    pub functions: Vec<FunctionDef>
+}
+
+impl TranslationUnit {
+    pub fn function(&self, name: &str) -> Option<&FunctionDef> {
+        self.functions.iter().find(|function| function.name == name && function.is_definition())
+    }
+}
 
-#[derive(Debug,Clone)]
-pub struct AST {
-    symbol: Symbol,
-    children: Vec<AST>
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Hash)]
+pub enum Type {
+    Void,
+    Char,
+    Int,
+    UnsignedInt,
+    Long
 }
 
-impl AST {
-    fn new(symbol: Symbol, children: Vec<AST>) -> AST {
-        AST {
-            symbol,
-            children
+impl Type {
+    pub fn size(self) -> usize {
+        match self {
+            Type::Void => 0,
+            Type::Char => 1,
+            Type::Int | Type::UnsignedInt => 4,
+            Type::Long => 8
+        }
+    }
+
+    pub fn is_signed(self) -> bool {
+        match self {
+            Type::Char | Type::Int | Type::Long => true,
+            Type::Void | Type::UnsignedInt => false
         }
-    } 
-} 
+    }
 
+    pub fn is_integer(self) -> bool {
+        self != Type::Void
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Type::Void => "void",
+            Type::Char => "char",
+            Type::Int => "int",
+            Type::UnsignedInt => "unsigned int",
+            Type::Long => "long"
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Identifies a variable or parameter declaration within a translation unit.
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Hash,PartialOrd,Ord)]
+pub struct DeclId(pub usize);
+
+/// A function definition, or a prototype when `body` is `None`.
 #[derive(Debug,PartialEq,Clone)]
-pub enum StatementType {
-    Return,
-    VariableDeclaration,
-    VariableAssignment
+pub struct FunctionDef {
+    pub name: String,
+    pub return_type: Type,
+    pub params: Vec<Param>,
+    pub body: Option<Vec<BlockItem>>,
+    pub span: Span
+}
+
+impl FunctionDef {
+    pub fn is_definition(&self) -> bool {
+        self.body.is_some()
+    }
+
+    pub fn param_types(&self) -> Vec<Type> {
+        self.params.iter().map(|param| param.ty).collect()
+    }
+
+    /// Every local declaration in the body, including those in nested blocks.
+    pub fn decls(&self) -> Vec<&Decl> {
+        fn collect<'a>(items: &'a [BlockItem], decls: &mut Vec<&'a Decl>) {
+            for item in items {
+                match *item {
+                    BlockItem::Decl(ref decl) => decls.push(decl),
+                    BlockItem::Stmt(ref stmt) => collect_stmt(stmt, decls)
+                }
+            }
+        }
+
+        fn collect_stmt<'a>(stmt: &'a Stmt, decls: &mut Vec<&'a Decl>) {
+            match stmt.kind {
+                StmtKind::Compound(ref items) => collect(items, decls),
+                StmtKind::If(_, ref then_branch, ref else_branch) => {
+                    collect_stmt(then_branch, decls);
+
+                    if let Some(ref else_branch) = *else_branch {
+                        collect_stmt(else_branch, decls);
+                    }
+                },
+                StmtKind::While(_, ref body) => collect_stmt(body, decls),
+                StmtKind::Return(_) | StmtKind::Expr(_) => {}
+            }
+        }
+
+        let mut decls = Vec::new();
+
+        if let Some(ref body) = self.body {
+            collect(body, &mut decls);
+        }
+
+        decls
+    }
+
+    pub fn stmts(&self) -> impl Iterator<Item = &Stmt> {
+        self.body.iter().flatten().filter_map(BlockItem::as_stmt)
+    }
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct Param {
+    pub id: DeclId,
+    pub name: Option<String>,
+    pub ty: Type,
+    pub span: Span
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub enum BlockItem {
+    Decl(Decl),
+    Stmt(Stmt)
+}
+
+impl BlockItem {
+    pub fn as_decl(&self) -> Option<&Decl> {
+        match *self {
+            BlockItem::Decl(ref decl) => Some(decl),
+            BlockItem::Stmt(_) => None
+        }
+    }
+
+    pub fn as_stmt(&self) -> Option<&Stmt> {
+        match *self {
+            BlockItem::Stmt(ref stmt) => Some(stmt),
+            BlockItem::Decl(_) => None
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct Decl {
+    pub id: DeclId,
+    pub name: String,
+    pub ty: Type,
+    pub init: Option<Expr>,
+    pub span: Span
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub enum StmtKind {
+    Return(Option<Expr>),
+    Expr(Expr),
+    Compound(Vec<BlockItem>),
+    If(Expr, Box<Stmt>, Option<Box<Stmt>>),
+    While(Expr, Box<Stmt>)
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Stmt {
+        Stmt {
+            kind,
+            span
+        }
+    }
+
+    pub fn expr(&self) -> Option<&Expr> {
+        match self.kind {
+            StmtKind::Return(ref expr) => expr.as_ref(),
+            StmtKind::Expr(ref expr) | StmtKind::If(ref expr, _, _) | StmtKind::While(ref expr, _) => Some(expr),
+            StmtKind::Compound(_) => None
+        }
+    }
+}
+
+/// An expression node. `ty` is filled in by semantic analysis.
+#[derive(Debug,PartialEq,Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+    pub ty: Option<Type>
 }
 
 #[derive(Debug,PartialEq,Clone)]
+pub enum ExprKind {
+    Constant(usize),
+    /// A variable reference, resolved to its declaration by semantic analysis.
+    Variable(String, Option<DeclId>),
+    Assignment(Box<Expr>, Box<Expr>),
+    Unary(UnaryOperator, Box<Expr>),
+    Binary(BinaryOperator, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    /// A conversion inserted by semantic analysis; it has no source syntax.
+    ImplicitCast(Type, Box<Expr>)
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Expr {
+        Expr {
+            kind,
+            span,
+            ty: None
+        }
+    }
+
+    /// The type assigned by semantic analysis.
+    pub fn ty(&self) -> Type {
+        self.ty.expect("expression has not been type checked")
+    }
+
+    pub fn as_constant(&self) -> Option<usize> {
+        match self.kind {
+            ExprKind::Constant(value) => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn as_variable(&self) -> Option<&str> {
+        match self.kind {
+            ExprKind::Variable(ref name, _) => Some(name),
+            _ => None
+        }
+    }
+
+    pub fn decl_id(&self) -> Option<DeclId> {
+        match self.kind {
+            ExprKind::Variable(_, decl_id) => decl_id,
+            _ => None
+        }
+    }
+
+    /// Looks through implicit conversions to the expression as written.
+    pub fn without_implicit_casts(&self) -> &Expr {
+        match self.kind {
+            ExprKind::ImplicitCast(_, ref inner) => inner.without_implicit_casts(),
+            _ => self
+        }
+    }
+
+    pub fn children(&self) -> Vec<&Expr> {
+        match self.kind {
+            ExprKind::Constant(_) | ExprKind::Variable(_, _) => Vec::new(),
+            ExprKind::Unary(_, ref operand) | ExprKind::ImplicitCast(_, ref operand) => vec![operand],
+            ExprKind::Assignment(ref lhs, ref rhs) | ExprKind::Binary(_, ref lhs, ref rhs) => vec![lhs, rhs],
+            ExprKind::Call(_, ref args) => args.iter().collect()
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
 pub enum UnaryOperator {
     Negation,
     BitwiseComplement,
     LogicalNegation
 }
 
-#[derive(Debug,PartialEq,Clone)]
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
 pub enum BinaryOperator {
     Addition,
     Subtraction,
     Multiplication,
     Division,
+    ShiftLeft,
+    ShiftRight,
     LessThan,
     LessThanOrEqual,
     GreaterThan,
@@ -47,38 +322,115 @@ pub enum BinaryOperator {
     LogicalOr
 }
 
-#[derive(Debug,PartialEq,Clone)]
-pub enum Symbol {
-    Program,
-    Function(String),
-    Statement(StatementType),
-    Expression,
-    LogicalAndExpression,
-    EqualityExpression,
-    RelationalExpression,
-    AdditiveExpression,
-    Term,
-    Factor,
-    Constant(usize),
-    UnaryOperator(UnaryOperator),
-    BinaryOperator(BinaryOperator)
+impl UnaryOperator {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            UnaryOperator::Negation => "-",
+            UnaryOperator::BitwiseComplement => "~",
+            UnaryOperator::LogicalNegation => "!"
+        }
+    }
 }
 
-#[derive(Debug,PartialEq,Clone)]
+impl BinaryOperator {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOperator::Addition => "+",
+            BinaryOperator::Subtraction => "-",
+            BinaryOperator::Multiplication => "*",
+            BinaryOperator::Division => "/",
+            BinaryOperator::ShiftLeft => "<<",
+            BinaryOperator::ShiftRight => ">>",
+            BinaryOperator::LessThan => "<",
+            BinaryOperator::LessThanOrEqual => "<=",
+            BinaryOperator::GreaterThan => ">",
+            BinaryOperator::GreaterThanOrEqual => ">=",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::LogicalAnd => "&&",
+            BinaryOperator::LogicalOr => "||"
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Clone,Copy)]
 pub enum Keyword {
+    Auto,
+    Break,
+    Case,
+    Char,
+    Const,
+    Continue,
+    Default,
+    Do,
+    Double,
+    Else,
+    Enum,
+    Extern,
+    Float,
+    For,
+    Goto,
+    If,
+    Inline,
     Int,
-    Return
+    Long,
+    Register,
+    Restrict,
+    Return,
+    Short,
+    Signed,
+    Sizeof,
+    Static,
+    Struct,
+    Switch,
+    Typedef,
+    Union,
+    Unsigned,
+    Void,
+    Volatile,
+    While,
+    Alignas,
+    Alignof,
+    Atomic,
+    Bool,
+    Complex,
+    Generic,
+    Imaginary,
+    Noreturn,
+    StaticAssert,
+    ThreadLocal
 }
 
-#[derive(Debug,PartialEq,Clone)]
-pub enum Token {
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span {
+            start,
+            end
+        }
+    }
+
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start, other.end)
+    }
+}
+
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum Token<'a> {
     OpenBrace,
     CloseBrace,
     OpenParen,
     CloseParen,
     Semicolon,
+    Comma,
+    Assignment,
     Keyword(Keyword),
-    Identifier(String),
+    Identifier(&'a str),
     IntegerLiteral(usize),
     NewLine,
     Space,
@@ -97,5 +449,7 @@ pub enum Token {
     LessThan,
     LessThanOrEqual,
     GreaterThan,
-    GreaterThanOrEqual
+    GreaterThanOrEqual,
+    ShiftLeft,
+    ShiftRight
 }
\ No newline at end of file
@@ -0,0 +1,441 @@
+//! Textual LLVM IR generation from the AST, as an alternative to the x86-64
+//! generator for checking our semantics against `lli` and `clang`.
+//!
+//! Every parameter and local variable gets an `alloca` in the entry block
+//! and is loaded and stored where it is used, which `mem2reg` turns into
+//! SSA form. Pointers are typed, as LLVM 16 and earlier read them. Signed
+//! arithmetic wraps, the way every other backend computes it, rather than
+//! carrying `nsw`.
+
+use std::collections::HashMap;
+
+use constant;
+use target::{DataLayout, Target};
+use visit::{self, Visitor};
+use TranslationUnit;
+use FunctionDef;
+use DeclId;
+use Decl;
+use Stmt;
+use StmtKind;
+use Expr;
+use ExprKind;
+use UnaryOperator;
+use BinaryOperator;
+use Type;
+
+// `char` arguments and results are sign extended by the caller or callee,
+// as the C ABIs for LLVM's targets expect
+fn parameter(ty: &str) -> String {
+    match ty {
+        "i8" => "i8 signext".to_string(),
+        ty => ty.to_string()
+    }
+}
+
+// The attribute of a result comes before its type
+fn result(ty: &str) -> String {
+    match ty {
+        "i8" => "signext i8".to_string(),
+        ty => ty.to_string()
+    }
+}
+
+fn comparison(operator: BinaryOperator, signed: bool) -> &'static str {
+    match operator {
+        BinaryOperator::Equal => "eq",
+        BinaryOperator::NotEqual => "ne",
+        BinaryOperator::LessThan => if signed { "slt" } else { "ult" },
+        BinaryOperator::LessThanOrEqual => if signed { "sle" } else { "ule" },
+        BinaryOperator::GreaterThan => if signed { "sgt" } else { "ugt" },
+        BinaryOperator::GreaterThanOrEqual => if signed { "sge" } else { "uge" },
+        _ => unreachable!("{} is not a comparison", operator.symbol())
+    }
+}
+
+struct Generator {
+    layout: DataLayout,
+    buf: String,
+    // The alloca of each variable
+    variables: HashMap<DeclId, String>,
+    // The label of the block instructions are appended to, or None after a
+    // terminator until a new block starts
+    block: Option<String>,
+    return_type: Type,
+    value_counter: usize,
+    label_counter: usize
+}
+
+impl Generator {
+    fn new(layout: DataLayout) -> Generator {
+        Generator {
+            layout,
+            buf: String::new(),
+            variables: HashMap::new(),
+            block: None,
+            return_type: Type::Void,
+            value_counter: 0,
+            label_counter: 0
+        }
+    }
+
+    fn generate(mut self, translation_unit: &TranslationUnit, target: Target) -> String {
+        self.buf.push_str(&format!("target triple = \"{}\"\n", target.triple()));
+
+        let mut declared: Vec<&str> = Vec::new();
+
+        for function in &translation_unit.functions {
+            if translation_unit.function(&function.name).is_none() && !declared.contains(&&function.name[..]) {
+                let params: Vec<String> = function.params.iter().map(|param| parameter(self.ty(param.ty))).collect();
+
+                self.buf.push_str(&format!("\ndeclare {} @{}({})\n",
+                    result(self.ty(function.return_type)), function.name, params.join(", ")));
+                declared.push(&function.name);
+            }
+        }
+
+        self.visit_translation_unit(translation_unit);
+
+        self.buf
+    }
+
+    fn ty(&self, ty: Type) -> &'static str {
+        match self.layout.representation(ty) {
+            Type::Void => "void",
+            Type::Char => "i8",
+            Type::Int | Type::UnsignedInt => "i32",
+            Type::Long => "i64"
+        }
+    }
+
+    fn next_value(&mut self) -> String {
+        self.value_counter += 1;
+
+        format!("%t{}", self.value_counter)
+    }
+
+    fn next_label(&mut self) -> String {
+        self.label_counter += 1;
+
+        format!("L{}", self.label_counter)
+    }
+
+    // Code following a return still gets generated, into a block nothing branches to
+    fn emit(&mut self, line: &str) {
+        if self.block.is_none() {
+            let label = self.next_label();
+            self.start_block(label);
+        }
+
+        self.buf.push_str("  ");
+        self.buf.push_str(line);
+        self.buf.push('\n');
+    }
+
+    fn terminate(&mut self, line: &str) {
+        self.emit(line);
+        self.block = None;
+    }
+
+    // Starts a block, branching to it from the current one if that falls through
+    fn start_block(&mut self, label: String) {
+        if self.block.is_some() {
+            self.terminate(&format!("br label %{}", label));
+        }
+
+        self.buf.push_str(&format!("{}:\n", label));
+        self.block = Some(label);
+    }
+
+    // Emits an instruction and names its result
+    fn value(&mut self, instr: String) -> String {
+        let value = self.next_value();
+        self.emit(&format!("{} = {}", value, instr));
+
+        value
+    }
+
+    // Whether a value of type `ty` is not zero, as an `i1`
+    fn truth(&mut self, value: &str, ty: Type) -> String {
+        self.value(format!("icmp ne {} {}, 0", self.ty(ty), value))
+    }
+
+    fn branch(&mut self, condition: &Expr, then_label: &str, else_label: &str) {
+        let value = self.generate_expression(condition);
+        let truth = self.truth(&value, condition.ty());
+
+        self.terminate(&format!("br i1 {}, label %{}, label %{}", truth, then_label, else_label));
+    }
+
+    fn variable(&self, expr: &Expr) -> String {
+        let decl_id = expr.decl_id().expect("variable was not resolved by semantic analysis");
+
+        self.variables[&decl_id].clone()
+    }
+
+    // Integer types of the same size are the same LLVM type whatever their
+    // signedness, so only a change of width needs an instruction
+    fn convert(&mut self, value: String, from: Type, to: Type) -> String {
+        let (from_size, to_size) = (self.layout.representation(from).size(), self.layout.representation(to).size());
+        let (from_ty, to_ty) = (self.ty(from), self.ty(to));
+
+        if from_size == to_size {
+            value
+        } else if from_size > to_size {
+            self.value(format!("trunc {} {} to {}", from_ty, value, to_ty))
+        } else if from.is_signed() {
+            self.value(format!("sext {} {} to {}", from_ty, value, to_ty))
+        } else {
+            self.value(format!("zext {} {} to {}", from_ty, value, to_ty))
+        }
+    }
+
+    // Generates the code for an expression and returns the operand holding its value
+    fn generate_expression(&mut self, expr: &Expr) -> String {
+        // LLVM integers have no signedness, so unsigned constants are written as signed ones
+        if let Some(value) = constant::literal_value(expr) {
+            let ty = match self.layout.representation(expr.ty()) {
+                Type::UnsignedInt => Type::Int,
+                ty => ty
+            };
+
+            return constant::wrap(value as i128, ty).to_string();
+        }
+
+        match expr.kind {
+            ExprKind::Constant(_) => unreachable!("constants are literals"),
+            ExprKind::Variable(_, _) => {
+                let ty = self.ty(expr.ty());
+                let variable = self.variable(expr);
+
+                self.value(format!("load {}, {}* {}", ty, ty, variable))
+            },
+            ExprKind::Assignment(ref target, ref value) => {
+                let value = self.generate_expression(value);
+                let ty = self.ty(expr.ty());
+                let variable = self.variable(target);
+
+                self.emit(&format!("store {} {}, {}* {}", ty, value, ty, variable));
+
+                value
+            },
+            ExprKind::ImplicitCast(ty, ref operand) => {
+                let value = self.generate_expression(operand);
+
+                self.convert(value, operand.ty(), ty)
+            },
+            ExprKind::Call(ref name, ref args) => {
+                let args: Vec<String> = args.iter()
+                    .map(|arg| format!("{} {}", parameter(self.ty(arg.ty())), self.generate_expression(arg)))
+                    .collect();
+                let call = format!("call {} @{}({})", result(self.ty(expr.ty())), name, args.join(", "));
+
+                // A void call cannot be given a name, and nothing uses its result
+                if expr.ty() == Type::Void {
+                    self.emit(&call);
+                    return String::new();
+                }
+
+                self.value(call)
+            },
+            ExprKind::Unary(operator, ref operand) => {
+                let value = self.generate_expression(operand);
+                let ty = self.ty(operand.ty());
+
+                match operator {
+                    UnaryOperator::Negation => self.value(format!("sub {} 0, {}", ty, value)),
+                    UnaryOperator::BitwiseComplement => self.value(format!("xor {} {}, -1", ty, value)),
+                    UnaryOperator::LogicalNegation => {
+                        let zero = self.value(format!("icmp eq {} {}, 0", ty, value));
+                        self.value(format!("zext i1 {} to i32", zero))
+                    }
+                }
+            },
+            ExprKind::Binary(BinaryOperator::LogicalAnd, ref lhs, ref rhs) => self.generate_logical(true, lhs, rhs),
+            ExprKind::Binary(BinaryOperator::LogicalOr, ref lhs, ref rhs) => self.generate_logical(false, lhs, rhs),
+            ExprKind::Binary(operator, ref lhs, ref rhs) => {
+                let signed = lhs.ty().is_signed();
+                let ty = self.ty(lhs.ty());
+                let lhs_value = self.generate_expression(lhs);
+                let mut rhs_value = self.generate_expression(rhs);
+
+                let mnemonic = match operator {
+                    BinaryOperator::Addition => "add",
+                    BinaryOperator::Subtraction => "sub",
+                    BinaryOperator::Multiplication => "mul",
+                    BinaryOperator::Division => if signed { "sdiv" } else { "udiv" },
+                    BinaryOperator::ShiftLeft => "shl",
+                    BinaryOperator::ShiftRight => if signed { "ashr" } else { "lshr" },
+                    _ => {
+                        let result = self.value(format!("icmp {} {} {}, {}", comparison(operator, signed), ty, lhs_value, rhs_value));
+                        return self.value(format!("zext i1 {} to i32", result));
+                    }
+                };
+
+                // The count of a shift is promoted separately, so it may be another width
+                if operator == BinaryOperator::ShiftLeft || operator == BinaryOperator::ShiftRight {
+                    rhs_value = self.convert(rhs_value, rhs.ty(), lhs.ty());
+                }
+
+                self.value(format!("{} {} {}, {}", mnemonic, ty, lhs_value, rhs_value))
+            }
+        }
+    }
+
+    // `&&` and `||` branch around their right operand, and a phi where the
+    // paths meet picks the left operand's verdict or the right one's truth
+    fn generate_logical(&mut self, is_and: bool, lhs: &Expr, rhs: &Expr) -> String {
+        let rhs_label = self.next_label();
+        let end_label = self.next_label();
+
+        let value = self.generate_expression(lhs);
+        let truth = self.truth(&value, lhs.ty());
+        let lhs_label = self.block.clone().unwrap();
+
+        if is_and {
+            self.terminate(&format!("br i1 {}, label %{}, label %{}", truth, rhs_label, end_label));
+        } else {
+            self.terminate(&format!("br i1 {}, label %{}, label %{}", truth, end_label, rhs_label));
+        }
+
+        self.start_block(rhs_label);
+        let value = self.generate_expression(rhs);
+        let rhs_truth = self.truth(&value, rhs.ty());
+        let rhs_label = self.block.clone().unwrap();
+
+        self.start_block(end_label);
+        let result = self.value(format!("phi i1 [ {}, %{} ], [ {}, %{} ]", !is_and, lhs_label, rhs_truth, rhs_label));
+
+        self.value(format!("zext i1 {} to i32", result))
+    }
+}
+
+impl Visitor for Generator {
+    fn visit_function_def(&mut self, function: &FunctionDef) {
+        // Prototypes were declared up front
+        if !function.is_definition() {
+            return;
+        }
+
+        self.variables.clear();
+        self.value_counter = 0;
+        self.label_counter = 0;
+        self.return_type = function.return_type;
+
+        let params: Vec<String> = function.params.iter().enumerate()
+            .map(|(index, param)| format!("{} %arg{}", parameter(self.ty(param.ty)), index))
+            .collect();
+
+        self.buf.push_str(&format!("\ndefine {} @{}({}) {{\n", result(self.ty(function.return_type)), function.name, params.join(", ")));
+        self.start_block("entry".to_string());
+
+        // Every variable gets its place in the entry block, where mem2reg looks for them
+        for (index, param) in function.params.iter().enumerate() {
+            let variable = format!("%{}.{}", param.name.as_deref().unwrap_or("arg"), param.id.0);
+            let ty = self.ty(param.ty);
+
+            self.emit(&format!("{} = alloca {}", variable, ty));
+            self.emit(&format!("store {} %arg{}, {}* {}", ty, index, ty, variable));
+            self.variables.insert(param.id, variable);
+        }
+
+        for decl in function.decls() {
+            let variable = format!("%{}.{}", decl.name, decl.id.0);
+
+            self.emit(&format!("{} = alloca {}", variable, self.ty(decl.ty)));
+            self.variables.insert(decl.id, variable);
+        }
+
+        visit::walk_function_def(self, function);
+
+        // The last block still needs a terminator when control can reach the
+        // closing brace, and returning 0 there is what main must do
+        if self.block.is_some() {
+            match function.return_type {
+                Type::Void => self.terminate("ret void"),
+                ty => {
+                    let line = format!("ret {} 0", self.ty(ty));
+                    self.terminate(&line);
+                }
+            }
+        }
+
+        self.buf.push_str("}\n");
+    }
+
+    fn visit_decl(&mut self, decl: &Decl) {
+        if let Some(ref init) = decl.init {
+            let value = self.generate_expression(init);
+            let ty = self.ty(decl.ty);
+
+            self.emit(&format!("store {} {}, {}* {}", ty, value, ty, self.variables[&decl.id]));
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt.kind {
+            StmtKind::Return(ref expr) => {
+                match *expr {
+                    Some(ref expr) => {
+                        let value = self.generate_expression(expr);
+                        let line = format!("ret {} {}", self.ty(self.return_type), value);
+
+                        self.terminate(&line);
+                    },
+                    None => self.terminate("ret void")
+                }
+            },
+            StmtKind::Expr(ref expr) => {
+                self.generate_expression(expr);
+            },
+            StmtKind::Compound(_) => visit::walk_stmt(self, stmt),
+            StmtKind::If(ref condition, ref then_branch, ref else_branch) => {
+                let then_label = self.next_label();
+                let end_label = self.next_label();
+                let else_label = if else_branch.is_some() { self.next_label() } else { end_label.clone() };
+
+                self.branch(condition, &then_label, &else_label);
+
+                self.start_block(then_label);
+                self.visit_stmt(then_branch);
+
+                if let Some(ref else_branch) = *else_branch {
+                    if self.block.is_some() {
+                        self.terminate(&format!("br label %{}", end_label));
+                    }
+
+                    self.start_block(else_label);
+                    self.visit_stmt(else_branch);
+                }
+
+                self.start_block(end_label);
+            },
+            StmtKind::While(ref condition, ref body) => {
+                let header_label = self.next_label();
+                let body_label = self.next_label();
+                let end_label = self.next_label();
+
+                self.start_block(header_label.clone());
+                self.branch(condition, &body_label, &end_label);
+
+                self.start_block(body_label);
+                self.visit_stmt(body);
+
+                if self.block.is_some() {
+                    self.terminate(&format!("br label %{}", header_label));
+                }
+
+                self.start_block(end_label);
+            }
+        }
+    }
+}
+
+/// Generates an LLVM IR module for a translation unit that has passed
+/// semantic analysis without errors, with the types of the target's data
+/// layout.
+pub fn generate(translation_unit: &TranslationUnit, target: Target) -> String {
+    let generator = Generator::new(target.data_layout());
+
+    generator.generate(translation_unit, target)
+}
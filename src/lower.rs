@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+
+use constant;
+use ir::{self, Block, BlockId, Instr, Operand, Terminator, VReg};
+use target::DataLayout;
+use TranslationUnit;
+use FunctionDef;
+use BlockItem;
+use Decl;
+use DeclId;
+use Stmt;
+use StmtKind;
+use Expr;
+use ExprKind;
+use UnaryOperator;
+use BinaryOperator;
+use Type;
+
+// A block still being filled in; the terminator is set when it is finished
+struct PartialBlock {
+    instrs: Vec<Instr>,
+    terminator: Option<Terminator>
+}
+
+struct Lowerer {
+    blocks: Vec<PartialBlock>,
+    // The block instructions are appended to, or None after a terminator
+    // until control flow reaches a new block
+    current: Option<BlockId>,
+    vreg_count: usize,
+    variables: HashMap<DeclId, VReg>,
+    layout: DataLayout
+}
+
+impl Lowerer {
+    fn new(layout: DataLayout) -> Lowerer {
+        Lowerer {
+            blocks: Vec::new(),
+            current: None,
+            vreg_count: 0,
+            variables: HashMap::new(),
+            layout
+        }
+    }
+
+    fn new_vreg(&mut self) -> VReg {
+        let reg = VReg(self.vreg_count);
+        self.vreg_count += 1;
+
+        reg
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(PartialBlock {
+            instrs: Vec::new(),
+            terminator: None
+        });
+
+        BlockId(self.blocks.len() - 1)
+    }
+
+    fn switch_to(&mut self, block: BlockId) {
+        self.current = Some(block);
+    }
+
+    // Code following a return still gets lowered, into a block nothing jumps to
+    fn current_block(&mut self) -> BlockId {
+        match self.current {
+            Some(block) => block,
+            None => {
+                let block = self.new_block();
+                self.current = Some(block);
+
+                block
+            }
+        }
+    }
+
+    fn emit(&mut self, instr: Instr) {
+        let block = self.current_block();
+
+        self.blocks[block.0].instrs.push(instr);
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        let block = self.current_block();
+
+        self.blocks[block.0].terminator = Some(terminator);
+        self.current = None;
+    }
+
+    fn variable(&self, expr: &Expr) -> VReg {
+        let decl_id = expr.decl_id().expect("variable was not resolved by semantic analysis");
+
+        self.variables[&decl_id]
+    }
+
+    fn lower_function(mut self, function: &FunctionDef) -> ir::Function {
+        let params = function.params.iter().map(|param| {
+            let reg = self.new_vreg();
+            self.variables.insert(param.id, reg);
+
+            (reg, self.layout.representation(param.ty))
+        }).collect();
+
+        let entry = self.new_block();
+        self.switch_to(entry);
+
+        for item in function.body.iter().flatten() {
+            self.lower_block_item(item);
+        }
+
+        // Falling off the end returns 0, which is what C requires of main
+        let fallthrough = match function.return_type {
+            Type::Void => Terminator::Return(None),
+            _ => Terminator::Return(Some(Operand::Imm(0)))
+        };
+
+        if self.current.is_some() {
+            self.terminate(fallthrough.clone());
+        }
+
+        let blocks = self.blocks.into_iter().map(|block| Block {
+            instrs: block.instrs,
+            terminator: block.terminator.unwrap_or_else(|| fallthrough.clone())
+        }).collect();
+
+        let mut lowered = ir::Function {
+            name: function.name.clone(),
+            return_type: self.layout.representation(function.return_type),
+            params,
+            blocks,
+            vreg_count: self.vreg_count
+        };
+
+        lowered.remove_unreachable_blocks();
+        lowered
+    }
+
+    fn lower_block_item(&mut self, item: &BlockItem) {
+        match *item {
+            BlockItem::Decl(ref decl) => self.lower_decl(decl),
+            BlockItem::Stmt(ref stmt) => self.lower_stmt(stmt)
+        }
+    }
+
+    fn lower_decl(&mut self, decl: &Decl) {
+        let reg = self.new_vreg();
+        self.variables.insert(decl.id, reg);
+
+        if let Some(ref init) = decl.init {
+            let value = self.lower_expr(init);
+
+            self.emit(Instr::Copy { dst: reg, src: value });
+        }
+    }
+
+    fn lower_stmt(&mut self, stmt: &Stmt) {
+        match stmt.kind {
+            StmtKind::Return(ref expr) => {
+                let value = expr.as_ref().map(|expr| self.lower_expr(expr));
+
+                self.terminate(Terminator::Return(value));
+            },
+            StmtKind::Expr(ref expr) => {
+                self.lower_expr(expr);
+            },
+            StmtKind::Compound(ref items) => {
+                for item in items {
+                    self.lower_block_item(item);
+                }
+            },
+            StmtKind::If(ref condition, ref then_branch, ref else_branch) => {
+                let then_block = self.new_block();
+                let end_block = self.new_block();
+                let else_block = if else_branch.is_some() { self.new_block() } else { end_block };
+
+                let condition = self.lower_expr(condition);
+                self.terminate(Terminator::Branch { condition, then_block, else_block });
+
+                self.switch_to(then_block);
+                self.lower_stmt(then_branch);
+                self.terminate(Terminator::Jump(end_block));
+
+                if let Some(ref else_branch) = *else_branch {
+                    self.switch_to(else_block);
+                    self.lower_stmt(else_branch);
+                    self.terminate(Terminator::Jump(end_block));
+                }
+
+                self.switch_to(end_block);
+            },
+            StmtKind::While(ref condition, ref body) => {
+                let header_block = self.new_block();
+                let body_block = self.new_block();
+                let end_block = self.new_block();
+
+                self.terminate(Terminator::Jump(header_block));
+
+                self.switch_to(header_block);
+                let condition = self.lower_expr(condition);
+                self.terminate(Terminator::Branch { condition, then_block: body_block, else_block: end_block });
+
+                self.switch_to(body_block);
+                self.lower_stmt(body);
+                self.terminate(Terminator::Jump(header_block));
+
+                self.switch_to(end_block);
+            }
+        }
+    }
+
+    // Truncates the result of an operation back to the type of its expression
+    fn normalize(&mut self, value: VReg, ty: Type) -> Operand {
+        let ty = self.layout.representation(ty);
+
+        if ty == Type::Long {
+            return Operand::Reg(value);
+        }
+
+        let dst = self.new_vreg();
+        self.emit(Instr::Cast { dst, ty, src: Operand::Reg(value) });
+
+        Operand::Reg(dst)
+    }
+
+    fn lower_expr(&mut self, expr: &Expr) -> Operand {
+        if let Some(value) = constant::literal_value(expr) {
+            return Operand::Imm(constant::wrap(value as i128, self.layout.representation(expr.ty())));
+        }
+
+        match expr.kind {
+            ExprKind::Constant(_) => unreachable!("constants are literals"),
+            ExprKind::Variable(_, _) => Operand::Reg(self.variable(expr)),
+            ExprKind::Assignment(ref target, ref value) => {
+                let value = self.lower_expr(value);
+                let dst = self.variable(target);
+
+                self.emit(Instr::Copy { dst, src: value });
+
+                Operand::Reg(dst)
+            },
+            ExprKind::ImplicitCast(ty, ref operand) => {
+                let src = self.lower_expr(operand);
+                let dst = self.new_vreg();
+
+                self.emit(Instr::Cast { dst, ty: self.layout.representation(ty), src });
+
+                Operand::Reg(dst)
+            },
+            ExprKind::Call(ref name, ref args) => {
+                let args = args.iter().map(|arg| self.lower_expr(arg)).collect();
+
+                if expr.ty() == Type::Void {
+                    self.emit(Instr::Call { dst: None, function: name.clone(), args });
+
+                    // A void call is only ever an expression statement
+                    return Operand::Imm(0);
+                }
+
+                let dst = self.new_vreg();
+                self.emit(Instr::Call { dst: Some(dst), function: name.clone(), args });
+
+                self.normalize(dst, expr.ty())
+            },
+            ExprKind::Unary(operator, ref operand) => {
+                let src = self.lower_expr(operand);
+                let dst = self.new_vreg();
+
+                match operator {
+                    UnaryOperator::Negation => {
+                        self.emit(Instr::Unary { op: ir::UnaryOp::Neg, dst, src });
+                        self.normalize(dst, expr.ty())
+                    },
+                    UnaryOperator::BitwiseComplement => {
+                        self.emit(Instr::Unary { op: ir::UnaryOp::Not, dst, src });
+                        self.normalize(dst, expr.ty())
+                    },
+                    UnaryOperator::LogicalNegation => {
+                        self.emit(Instr::Binary { op: ir::BinaryOp::Eq, dst, lhs: src, rhs: Operand::Imm(0) });
+                        Operand::Reg(dst)
+                    }
+                }
+            },
+            ExprKind::Binary(BinaryOperator::LogicalAnd, ref lhs, ref rhs) => self.lower_logical(true, lhs, rhs),
+            ExprKind::Binary(BinaryOperator::LogicalOr, ref lhs, ref rhs) => self.lower_logical(false, lhs, rhs),
+            ExprKind::Binary(operator, ref lhs, ref rhs) => {
+                let signed = lhs.ty().is_signed();
+                let lhs = self.lower_expr(lhs);
+                let rhs = self.lower_expr(rhs);
+                let dst = self.new_vreg();
+
+                let (op, normalize) = match operator {
+                    BinaryOperator::Addition => (ir::BinaryOp::Add, true),
+                    BinaryOperator::Subtraction => (ir::BinaryOp::Sub, true),
+                    BinaryOperator::Multiplication => (ir::BinaryOp::Mul, true),
+                    BinaryOperator::ShiftLeft => (ir::BinaryOp::Shl, true),
+                    // Neither can leave the range of a normalized operand, barring overflow
+                    BinaryOperator::Division => (if signed { ir::BinaryOp::Div } else { ir::BinaryOp::UDiv }, false),
+                    BinaryOperator::ShiftRight => (if signed { ir::BinaryOp::Sar } else { ir::BinaryOp::Shr }, false),
+                    BinaryOperator::Equal => (ir::BinaryOp::Eq, false),
+                    BinaryOperator::NotEqual => (ir::BinaryOp::Ne, false),
+                    BinaryOperator::LessThan => (if signed { ir::BinaryOp::Lt } else { ir::BinaryOp::ULt }, false),
+                    BinaryOperator::LessThanOrEqual => (if signed { ir::BinaryOp::Le } else { ir::BinaryOp::ULe }, false),
+                    BinaryOperator::GreaterThan => (if signed { ir::BinaryOp::Gt } else { ir::BinaryOp::UGt }, false),
+                    BinaryOperator::GreaterThanOrEqual => (if signed { ir::BinaryOp::Ge } else { ir::BinaryOp::UGe }, false),
+                    BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr => unreachable!()
+                };
+
+                self.emit(Instr::Binary { op, dst, lhs, rhs });
+
+                if normalize {
+                    self.normalize(dst, expr.ty())
+                } else {
+                    Operand::Reg(dst)
+                }
+            }
+        }
+    }
+
+    // `&&` and `||` only evaluate their right operand when the left one does
+    // not already decide the result, so they become control flow
+    fn lower_logical(&mut self, is_and: bool, lhs: &Expr, rhs: &Expr) -> Operand {
+        let result = self.new_vreg();
+        let rhs_block = self.new_block();
+        let short_circuit_block = self.new_block();
+        let end_block = self.new_block();
+
+        let condition = self.lower_expr(lhs);
+
+        let (then_block, else_block) = if is_and {
+            (rhs_block, short_circuit_block)
+        } else {
+            (short_circuit_block, rhs_block)
+        };
+
+        self.terminate(Terminator::Branch { condition, then_block, else_block });
+
+        self.switch_to(rhs_block);
+        let rhs = self.lower_expr(rhs);
+        self.emit(Instr::Binary { op: ir::BinaryOp::Ne, dst: result, lhs: rhs, rhs: Operand::Imm(0) });
+        self.terminate(Terminator::Jump(end_block));
+
+        self.switch_to(short_circuit_block);
+        self.emit(Instr::Copy { dst: result, src: Operand::Imm(if is_and { 0 } else { 1 }) });
+        self.terminate(Terminator::Jump(end_block));
+
+        self.switch_to(end_block);
+
+        Operand::Reg(result)
+    }
+}
+
+/// Lowers a translation unit that has passed semantic analysis to IR for a
+/// target with the given data layout. Prototypes of functions that are not
+/// defined become declarations.
+pub fn lower(translation_unit: &TranslationUnit, layout: &DataLayout) -> ir::Module {
+    let functions = translation_unit.functions.iter()
+        .filter(|function| function.is_definition())
+        .map(|function| Lowerer::new(*layout).lower_function(function))
+        .collect();
+
+    let mut declarations: Vec<ir::Declaration> = Vec::new();
+
+    for function in &translation_unit.functions {
+        let defined = translation_unit.function(&function.name).is_some();
+        let declared = declarations.iter().any(|declaration| declaration.name == function.name);
+
+        if !defined && !declared {
+            declarations.push(ir::Declaration {
+                name: function.name.clone(),
+                return_type: layout.representation(function.return_type),
+                params: function.param_types().into_iter().map(|ty| layout.representation(ty)).collect()
+            });
+        }
+    }
+
+    ir::Module {
+        functions,
+        declarations
+    }
+}
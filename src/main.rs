@@ -2,67 +2,410 @@ extern crate cc;
 
 use cc::lexer;
 use cc::parser;
+use cc::sema;
+use cc::lower;
+use cc::ir;
+use cc::opt;
+use cc::diagnostics::{self, WarningOptions};
 use cc::generator;
+use cc::codegen;
+use cc::peephole;
+use cc::encoder;
+use cc::elf;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+use cc::jit;
+use cc::x86;
+use cc::aarch64;
+use cc::riscv64;
+use cc::wasm32;
+use cc::llvm;
+use cc::transpile;
+use cc::interp;
+use cc::target::Target;
+use cc::printer;
+#[cfg(feature = "serialize")]
+use cc::serialize;
+use cc::TranslationUnit;
 
 use std::env;
 use std::fs::File;
-use std::io::prelude::*;
+use std::io::{self, prelude::*};
 use std::path::Path;
-use std::process::Command;
+use std::process::{self, Command};
+
+struct Options {
+    file_name: String,
+    compile_only: bool,
+    dump_ast: Option<String>,
+    emit: Option<String>,
+    integrated_as: bool,
+    interpret: bool,
+    jit: bool,
+    opt_level: opt::Level,
+    peephole: bool,
+    syntax: x86::Syntax,
+    target: Target,
+    warnings: WarningOptions
+}
+
+fn parse_args() -> Options {
+    let mut file_name = None;
+    let mut compile_only = false;
+    let mut dump_ast = None;
+    let mut emit = None;
+    let mut integrated_as = false;
+    let mut interpret = false;
+    let mut opt_level = opt::Level::O0;
+    let mut peephole = true;
+    let mut syntax = x86::Syntax::Att;
+    let mut target = Target::X86_64;
+    let mut warnings = WarningOptions::default();
+
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    // Compiling a program also runs it, so `cc run` only differs in what it accepts
+    let run = args.first().is_some_and(|arg| arg == "run");
+    let jit = args.first().is_some_and(|arg| arg == "jit");
+
+    if run || jit {
+        args.remove(0);
+    }
+
+    for arg in args {
+        if let Some(format) = arg.strip_prefix("--dump-ast=") {
+            dump_ast = Some(format.to_string());
+        } else if let Some(kind) = arg.strip_prefix("--emit=") {
+            emit = Some(kind.to_string());
+        } else if let Some(level) = opt::Level::from_flag(&arg) {
+            opt_level = level;
+        } else if let Some(name) = arg.strip_prefix("--target=") {
+            target = Target::from_name(name)
+                .unwrap_or_else(|| panic!("Unknown target {}, expected x86_64-linux-gnu, aarch64-linux-gnu, riscv64-linux-gnu, i386-linux-gnu or wasm32", name));
+        } else if let Some(name) = arg.strip_prefix("-masm=") {
+            syntax = x86::Syntax::from_name(name)
+                .unwrap_or_else(|| panic!("Unknown assembler syntax {}, expected att or intel", name));
+        } else if arg == "--interp" && run {
+            interpret = true;
+        } else if arg == "-c" {
+            compile_only = true;
+        } else if arg == "-integrated-as" {
+            integrated_as = true;
+        } else if arg == "-m32" {
+            target = Target::I386;
+        } else if arg == "-fno-peephole" {
+            peephole = false;
+        } else if arg.starts_with("-W") {
+            if !warnings.apply_flag(&arg) {
+                panic!("Unknown warning option {}", arg);
+            }
+        } else if arg.starts_with('-') {
+            panic!("Unknown option {}", arg);
+        } else {
+            file_name = Some(arg);
+        }
+    }
+
+    if integrated_as && target != Target::X86_64 {
+        panic!("-integrated-as only assembles x86-64");
+    }
+
+    if jit && target != Target::X86_64 {
+        panic!("cc jit only runs x86-64 code");
+    }
+
+    Options {
+        file_name: file_name.expect("Usage: cc [run [--interp] | jit] [-c] [-integrated-as] [-O0|-O1|-O2] [-m32] [-fno-peephole] [-masm=att|intel] [--target=<triple>] [-Wall] [-Wextra] [-W[no-]<warning>] [-Werror] [--dump-ast=json|sexpr] [--emit=c|checked-c|ir|llvm] <file.c>"),
+        compile_only,
+        dump_ast,
+        emit,
+        integrated_as,
+        interpret,
+        jit,
+        opt_level,
+        peephole,
+        syntax,
+        target,
+        warnings
+    }
+}
+
+#[cfg(feature = "serialize")]
+fn dump_ast(ast: &TranslationUnit, format_name: &str) {
+    let format = serialize::Format::from_name(format_name)
+        .unwrap_or_else(|| panic!("Unknown AST dump format {}, expected json or sexpr", format_name));
+
+    print!("{}", serialize::dump(ast, format));
+}
+
+#[cfg(not(feature = "serialize"))]
+fn dump_ast(_ast: &TranslationUnit, _format_name: &str) {
+    panic!("--dump-ast requires cc to be built with the serialize feature");
+}
+
+// Type checks the program, reporting diagnostics and exiting if there were errors
+fn analyze(ast: &mut TranslationUnit, options: &Options, contents: &str) {
+    let diagnostics = options.warnings.apply(sema::analyze(ast));
+
+    for diagnostic in &diagnostics {
+        eprint!("{}", diagnostic.render(&options.file_name, contents));
+    }
+
+    if diagnostics::has_errors(&diagnostics) {
+        process::exit(1);
+    }
+}
+
+// Runs the program without compiling it, exiting with the status main returns
+fn interpret(ast: &TranslationUnit, options: &Options, contents: &str) -> ! {
+    let result = interp::run(ast, &options.target.data_layout(), io::stdout());
+
+    io::stdout().flush().unwrap();
+
+    match result {
+        Ok(status) => process::exit(status as i32),
+        Err(diagnostic) => {
+            eprint!("{}", diagnostic.render(&options.file_name, contents));
+            process::exit(1);
+        }
+    }
+}
+
+// Lowers the program to IR and optimizes it, checking the IR after each step
+fn lower_and_optimize(ast: &TranslationUnit, level: opt::Level, target: Target) -> ir::Module {
+    let mut module = lower::lower(ast, &target.data_layout());
+
+    if let Err(error) = ir::verify(&module) {
+        panic!("IR verification failed {}", error);
+    }
+
+    opt::optimize(&mut module, level);
+
+    if let Err(error) = ir::verify(&module) {
+        panic!("IR verification failed after optimization {}", error);
+    }
+
+    module
+}
+
+fn generate_x86(ast: TranslationUnit, options: &Options) -> Vec<x86::Instr> {
+    // Without optimization the AST is compiled directly, which keeps the
+    // output close to the source, but only for x86-64
+    let instrs = match options.opt_level {
+        opt::Level::O0 if options.target == Target::X86_64 => generator::generate(ast),
+        level => codegen::generate(&lower_and_optimize(&ast, level, options.target), &options.target.data_layout())
+    };
+
+    if options.peephole {
+        peephole::optimize(instrs)
+    } else {
+        instrs
+    }
+}
+
+// Runs the program's machine code in this process, exiting with the status main returns
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn jit(ast: TranslationUnit, options: &Options) -> ! {
+    let code = encoder::encode(&generate_x86(ast, options));
+
+    match jit::run(&code) {
+        Ok(status) => process::exit(status),
+        Err(error) => {
+            eprintln!("{}: error: {}", options.file_name, error);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+fn jit(_ast: TranslationUnit, _options: &Options) -> ! {
+    panic!("cc jit only runs on x86-64 Linux");
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let options = parse_args();
 
-    let file_name = &args[1];
+    let file_name = &options.file_name;
     let input_file = Path::new(file_name);
 
-    let mut file = File::open(&input_file)
-        .expect(&format!("Could not open file {}", file_name));
+    let mut file = File::open(input_file)
+        .unwrap_or_else(|_| panic!("Could not open file {}", file_name));
 
     let mut contents = String::new();
 
     file.read_to_string(&mut contents)
-        .expect(&format!("Could not read file {}", file_name));
-    
+        .unwrap_or_else(|_| panic!("Could not read file {}", file_name));
+
+    if let Some(ref format_name) = options.dump_ast {
+        dump_ast(&parser::parse(&contents), format_name);
+        return;
+    }
+
+    if let Some(ref kind) = options.emit {
+        match &kind[..] {
+            "c" => print!("{}", printer::print(&parser::parse(&contents))),
+            "ir" => {
+                let mut ast = parser::parse(&contents);
+                analyze(&mut ast, &options, &contents);
+                print!("{}", lower_and_optimize(&ast, options.opt_level, options.target));
+            },
+            "llvm" => {
+                let mut ast = parser::parse(&contents);
+                analyze(&mut ast, &options, &contents);
+                print!("{}", llvm::generate(&ast, options.target));
+            },
+            "checked-c" => {
+                let mut ast = parser::parse(&contents);
+                analyze(&mut ast, &options, &contents);
+                print!("{}", transpile::generate(&ast, &contents, options.target));
+            },
+            _ => panic!("Unknown --emit kind {}, expected c, checked-c, ir or llvm", kind)
+        }
+
+        return;
+    }
+
+    if options.interpret {
+        let mut ast = parser::parse(&contents);
+        analyze(&mut ast, &options, &contents);
+        interpret(&ast, &options, &contents);
+    }
+
+    if options.jit {
+        let mut ast = parser::parse(&contents);
+        analyze(&mut ast, &options, &contents);
+        jit(ast, &options);
+    }
+
     println!("Compiling file:\r\n\r\n{}\r\n", contents);
 
-    let tokens = lexer::lex(contents);
+    let tokens = lexer::lex(&contents)
+        .unwrap_or_else(|error| panic!("Could not lex {}: {} at {}..{}", file_name, error, error.span.start, error.span.end));
 
     println!("Lexing complete: {:#?}", tokens);
 
-    let ast = parser::parse(tokens);
+    let mut ast = parser::parse(&contents);
 
     println!("Parsing complete: {:#?}", ast);
 
-    let assembly = generator::generate(ast);
+    analyze(&mut ast, &options, &contents);
+
+    // Machine code from the integrated assembler, for the object file
+    let mut code = None;
+
+    let assembly = match options.target {
+        Target::X86_64 | Target::I386 => {
+            let instrs = generate_x86(ast, &options);
+
+            if options.integrated_as {
+                code = Some(encoder::encode(&instrs));
+            }
+
+            x86::print(&instrs, options.syntax, &options.target.data_layout())
+        },
+        target => {
+            if options.syntax != x86::Syntax::Att {
+                panic!("-masm only applies to x86-64");
+            }
+
+            let module = lower_and_optimize(&ast, options.opt_level, target);
+
+            match target {
+                Target::Riscv64 => riscv64::generate(&module),
+                Target::Wasm32 => wasm32::generate(&module),
+                _ => aarch64::generate(&module)
+            }
+        }
+    };
 
     println!("Generation complete:\r\n{}", assembly);
     let base_path = &file_name.to_string()[0..file_name.len() - 2];
-    let out_file = format!("{}.s", base_path);
+    let object_file = format!("{}.o", base_path);
+
+    let (out_file, out_bytes) = match code {
+        Some(ref code) => (object_file.clone(), elf::write(code, file_name)),
+        None => (format!("{}.{}", base_path, options.target.assembly_extension()), assembly.into_bytes())
+    };
 
     let out_path = Path::new(&out_file);
     println!("Writing output to {}", out_path.display());
 
-    let mut file = match File::create(&out_path) {
+    let mut file = match File::create(out_path) {
         Err(why) => panic!("Could not create {}: {}", out_path.display(), why),
         Ok(file) => file
     };
 
-    match file.write_all(assembly.as_bytes()) {
+    match file.write_all(&out_bytes) {
         Err(why) => panic!("Could not write to {}: {}", out_path.display(), why),
         Ok(_) => println!("Success!")
     };
 
-    Command::new("gcc")
-        .arg(&out_file)
-        .arg("-o")
-        .arg(base_path)
-        .output()
-        .expect("Failed to execute gcc");
-    
-    let status = Command::new(&format!("./{}", base_path))
-        .status()
-        .expect("Failed to execute");
-
-    println!("{} exited with: {}", &format!("./{}", base_path), status);
-}
\ No newline at end of file
+    if options.target == Target::Wasm32 {
+        println!("{} is for a WebAssembly runtime to load, so it was not linked", out_file);
+        return;
+    }
+
+    if options.compile_only && options.integrated_as {
+        return;
+    }
+
+    let compiler = options.target.compiler();
+    let mut command = Command::new(&compiler);
+    command.args(options.target.compiler_flags());
+
+    if options.compile_only {
+        command.arg("-c").arg(&out_file).arg("-o").arg(&object_file);
+    } else {
+        command.arg(&out_file).arg("-o").arg(base_path);
+    }
+
+    // Emulators run programs for other targets most easily without a sysroot
+    if !options.target.is_native() {
+        command.arg("-static");
+    }
+
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(_) if options.target.is_native() => panic!("Failed to execute {}", compiler),
+        Err(_) => {
+            println!("{} is not installed, so {} was not linked", compiler, out_file);
+            return;
+        }
+    };
+
+    if options.compile_only {
+        if !output.status.success() {
+            panic!("{} could not assemble {}: {}", compiler, out_file, String::from_utf8_lossy(&output.stderr));
+        }
+
+        println!("Assembled {}", object_file);
+        return;
+    }
+
+    // gcc only links 32-bit programs when its multilib support is installed
+    if options.target == Target::I386 && !output.status.success() {
+        println!("{} -m32 could not link {}, so it was not run; is multilib installed?", compiler, out_file);
+        return;
+    }
+
+    let program = format!("./{}", base_path);
+
+    let mut command = match options.target.emulator() {
+        Some(emulator) => {
+            let mut command = Command::new(emulator);
+            command.arg(&program);
+            command
+        },
+        None => Command::new(&program)
+    };
+
+    let status = match command.status() {
+        Ok(status) => status,
+        Err(_) if !options.target.is_native() => {
+            println!("{} is not installed, so {} was not run", options.target.emulator().unwrap(), program);
+            return;
+        },
+        Err(_) => panic!("Failed to execute")
+    };
+
+    println!("{} exited with: {}", program, status);
+}
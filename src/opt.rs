@@ -0,0 +1,602 @@
+//! Optimization passes over IR functions in SSA form.
+//!
+//! `-O1` runs sparse conditional constant propagation, copy propagation and
+//! dead code elimination. `-O2` adds common subexpression elimination and
+//! loop-invariant code motion.
+
+use std::collections::{HashMap, HashSet};
+
+use constant;
+use ir::{self, BinaryOp, BlockId, Function, Instr, Module, Operand, Terminator, VReg};
+use ssa::{self, Dominators};
+use Type;
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy,PartialOrd,Ord)]
+pub enum Level {
+    O0,
+    O1,
+    O2
+}
+
+impl Level {
+    pub fn from_flag(flag: &str) -> Option<Level> {
+        match flag {
+            "-O0" => Some(Level::O0),
+            "-O" | "-O1" => Some(Level::O1),
+            "-O2" | "-O3" => Some(Level::O2),
+            _ => None
+        }
+    }
+}
+
+// Replaces every use of a register, including in phis and terminators
+fn replace_uses(function: &mut Function, replacements: &HashMap<VReg, Operand>) {
+    if replacements.is_empty() {
+        return;
+    }
+
+    let replace = |operand: &mut Operand| {
+        while let Operand::Reg(reg) = *operand {
+            match replacements.get(&reg) {
+                Some(&replacement) if replacement != *operand => *operand = replacement,
+                _ => break
+            }
+        }
+    };
+
+    for block in &mut function.blocks {
+        for instr in &mut block.instrs {
+            instr.operands_mut().into_iter().for_each(replace);
+        }
+
+        block.terminator.operands_mut().into_iter().for_each(replace);
+    }
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+enum Lattice {
+    // Not yet known to be assigned at all
+    Top,
+    Constant(i64),
+    Varying
+}
+
+impl Lattice {
+    fn meet(self, other: Lattice) -> Lattice {
+        match (self, other) {
+            (Lattice::Top, value) | (value, Lattice::Top) => value,
+            (Lattice::Constant(a), Lattice::Constant(b)) if a == b => Lattice::Constant(a),
+            _ => Lattice::Varying
+        }
+    }
+}
+
+struct Sccp<'a> {
+    function: &'a Function,
+    values: HashMap<VReg, Lattice>,
+    executable_edges: HashSet<(BlockId, BlockId)>,
+    executable_blocks: HashSet<BlockId>,
+    // Where each register is used, as (block, instruction index), with the
+    // terminator at the index past the last instruction
+    users: HashMap<VReg, Vec<(BlockId, usize)>>,
+    flow_worklist: Vec<(BlockId, BlockId)>,
+    ssa_worklist: Vec<(BlockId, usize)>
+}
+
+impl<'a> Sccp<'a> {
+    fn value(&self, operand: Operand) -> Lattice {
+        match operand {
+            Operand::Imm(value) => Lattice::Constant(value),
+            Operand::Reg(reg) => self.values.get(&reg).cloned().unwrap_or(Lattice::Top)
+        }
+    }
+
+    fn set(&mut self, reg: VReg, value: Lattice) {
+        let old = self.value(Operand::Reg(reg));
+
+        if old != value {
+            self.values.insert(reg, value);
+
+            if let Some(users) = self.users.get(&reg) {
+                self.ssa_worklist.extend(users.iter().cloned());
+            }
+        }
+    }
+
+    fn evaluate(&self, block: BlockId, instr: &Instr) -> Lattice {
+        let operands: Vec<Lattice> = instr.operands().into_iter().map(|operand| self.value(operand)).collect();
+
+        if let Instr::Phi { ref sources, .. } = *instr {
+            return sources.iter().filter(|&&(source, _)| self.executable_edges.contains(&(source, block)))
+                .fold(Lattice::Top, |value, &(_, operand)| value.meet(self.value(operand)));
+        }
+
+        if let Instr::Call { .. } = *instr {
+            return Lattice::Varying;
+        }
+
+        if operands.contains(&Lattice::Varying) {
+            return Lattice::Varying;
+        }
+
+        let constants: Vec<i64> = operands.iter().filter_map(|&value| match value {
+            Lattice::Constant(value) => Some(value),
+            _ => None
+        }).collect();
+
+        if constants.len() < operands.len() {
+            return Lattice::Top;
+        }
+
+        let value = match *instr {
+            Instr::Copy { .. } => Some(constants[0]),
+            Instr::Unary { op, .. } => Some(op.evaluate(constants[0])),
+            Instr::Binary { op, .. } => op.evaluate(constants[0], constants[1]),
+            Instr::Cast { ty, .. } => Some(constant::wrap(constants[0] as i128, ty)),
+            Instr::Call { .. } | Instr::Phi { .. } => unreachable!()
+        };
+
+        // Undefined operations are left for the program to perform
+        value.map_or(Lattice::Varying, Lattice::Constant)
+    }
+
+    fn visit_instr(&mut self, block: BlockId, index: usize) {
+        let instrs = &self.function.block(block).instrs;
+
+        if index == instrs.len() {
+            self.visit_terminator(block);
+            return;
+        }
+
+        let instr = &instrs[index];
+
+        if let Some(dst) = instr.dst() {
+            let value = self.evaluate(block, instr);
+            self.set(dst, value);
+        }
+    }
+
+    fn visit_terminator(&mut self, block: BlockId) {
+        match self.function.block(block).terminator {
+            Terminator::Return(_) => {},
+            Terminator::Jump(target) => self.flow_worklist.push((block, target)),
+            Terminator::Branch { condition, then_block, else_block } => match self.value(condition) {
+                Lattice::Top => {},
+                Lattice::Constant(0) => self.flow_worklist.push((block, else_block)),
+                Lattice::Constant(_) => self.flow_worklist.push((block, then_block)),
+                Lattice::Varying => {
+                    self.flow_worklist.push((block, then_block));
+                    self.flow_worklist.push((block, else_block));
+                }
+            }
+        }
+    }
+
+    fn run(&mut self) {
+        for &(param, _) in &self.function.params {
+            self.values.insert(param, Lattice::Varying);
+        }
+
+        self.executable_blocks.insert(Function::ENTRY);
+        self.visit_block(Function::ENTRY);
+
+        while !self.flow_worklist.is_empty() || !self.ssa_worklist.is_empty() {
+            while let Some((from, to)) = self.flow_worklist.pop() {
+                if !self.executable_edges.insert((from, to)) {
+                    continue;
+                }
+
+                if self.executable_blocks.insert(to) {
+                    self.visit_block(to);
+                } else {
+                    // Only the phis can see the newly executable edge
+                    let phis = self.function.block(to).instrs.iter().take_while(|instr| instr.is_phi()).count();
+
+                    for index in 0..phis {
+                        self.visit_instr(to, index);
+                    }
+                }
+            }
+
+            while let Some((block, index)) = self.ssa_worklist.pop() {
+                if self.executable_blocks.contains(&block) {
+                    self.visit_instr(block, index);
+                }
+            }
+        }
+    }
+
+    fn visit_block(&mut self, block: BlockId) {
+        for index in 0..=self.function.block(block).instrs.len() {
+            self.visit_instr(block, index);
+        }
+    }
+}
+
+/// Sparse conditional constant propagation, after Wegman and Zadeck:
+/// registers proven constant are replaced by their values, and branches on
+/// constants become jumps, dropping the blocks that can no longer run.
+pub fn sccp(function: &mut Function) {
+    let mut users: HashMap<VReg, Vec<(BlockId, usize)>> = HashMap::new();
+
+    for id in function.block_ids() {
+        let block = function.block(id);
+
+        for (index, instr) in block.instrs.iter().enumerate() {
+            for reg in instr.uses() {
+                users.entry(reg).or_default().push((id, index));
+            }
+        }
+
+        for reg in block.terminator.operands().into_iter().filter_map(Operand::as_reg) {
+            users.entry(reg).or_default().push((id, block.instrs.len()));
+        }
+    }
+
+    let (values, executable_edges, executable_blocks) = {
+        let mut sccp = Sccp {
+            function,
+            values: HashMap::new(),
+            executable_edges: HashSet::new(),
+            executable_blocks: HashSet::new(),
+            users,
+            flow_worklist: Vec::new(),
+            ssa_worklist: Vec::new()
+        };
+
+        sccp.run();
+
+        (sccp.values, sccp.executable_edges, sccp.executable_blocks)
+    };
+
+    let constants: HashMap<VReg, Operand> = values.iter().filter_map(|(&reg, &value)| match value {
+        Lattice::Constant(value) => Some((reg, Operand::Imm(value))),
+        _ => None
+    }).collect();
+
+    replace_uses(function, &constants);
+
+    for id in function.block_ids().collect::<Vec<_>>() {
+        let block = &mut function.blocks[id.0];
+
+        if !executable_blocks.contains(&id) {
+            continue;
+        }
+
+        block.instrs.retain(|instr| instr.has_side_effects() || instr.dst().is_none_or(|dst| !constants.contains_key(&dst)));
+
+        for instr in &mut block.instrs {
+            if let Instr::Phi { ref mut sources, .. } = *instr {
+                sources.retain(|&(source, _)| executable_edges.contains(&(source, id)));
+            }
+        }
+
+        if let Terminator::Branch { condition: Operand::Imm(condition), then_block, else_block } = block.terminator {
+            block.terminator = Terminator::Jump(if condition != 0 { then_block } else { else_block });
+        }
+    }
+
+    function.remove_unreachable_blocks();
+}
+
+/// Replaces registers that are copies of another operand, or phis that
+/// merge a single value, with that operand.
+pub fn propagate_copies(function: &mut Function) {
+    loop {
+        let mut replacements = HashMap::new();
+
+        for block in &function.blocks {
+            for instr in &block.instrs {
+                match *instr {
+                    Instr::Copy { dst, src } => {
+                        replacements.insert(dst, src);
+                    },
+                    Instr::Phi { dst, ref sources } => {
+                        let mut values = sources.iter().map(|&(_, source)| source).filter(|&source| source != Operand::Reg(dst));
+
+                        if let Some(first) = values.next() {
+                            if values.all(|value| value == first) {
+                                replacements.insert(dst, first);
+                            }
+                        }
+                    },
+                    _ => {}
+                }
+            }
+        }
+
+        if replacements.is_empty() {
+            return;
+        }
+
+        replace_uses(function, &replacements);
+
+        for block in &mut function.blocks {
+            block.instrs.retain(|instr| instr.dst().is_none_or(|dst| !replacements.contains_key(&dst)));
+        }
+    }
+}
+
+/// Removes instructions whose results are never needed, including cycles of
+/// phis that only feed each other.
+pub fn eliminate_dead_code(function: &mut Function) {
+    let mut definitions: HashMap<VReg, &Instr> = HashMap::new();
+    let mut worklist: Vec<VReg> = Vec::new();
+
+    for block in &function.blocks {
+        for instr in &block.instrs {
+            if let Some(dst) = instr.dst() {
+                definitions.insert(dst, instr);
+            }
+
+            if instr.has_side_effects() {
+                worklist.extend(instr.uses());
+            }
+        }
+
+        worklist.extend(block.terminator.operands().into_iter().filter_map(Operand::as_reg));
+    }
+
+    let mut live = HashSet::new();
+
+    while let Some(reg) = worklist.pop() {
+        if live.insert(reg) {
+            if let Some(instr) = definitions.get(&reg) {
+                worklist.extend(instr.uses());
+            }
+        }
+    }
+
+    for block in &mut function.blocks {
+        block.instrs.retain(|instr| instr.has_side_effects() || instr.dst().is_none_or(|dst| live.contains(&dst)));
+    }
+}
+
+/// Merges each block into its predecessor when that is the only way in and
+/// the predecessor has nowhere else to go.
+pub fn merge_blocks(function: &mut Function) {
+    loop {
+        let predecessors = function.predecessors();
+
+        let merge = function.block_ids().find(|&id| {
+            let block = function.block(id);
+
+            match block.terminator {
+                Terminator::Jump(target) => target != id && target != Function::ENTRY &&
+                    predecessors[target.0].len() == 1 && !function.block(target).instrs.iter().any(Instr::is_phi),
+                _ => false
+            }
+        });
+
+        let id = match merge {
+            Some(id) => id,
+            None => break
+        };
+
+        let target = function.successors(id)[0];
+        let merged = ::std::mem::replace(&mut function.blocks[target.0], ir::Block {
+            instrs: Vec::new(),
+            terminator: Terminator::Return(None)
+        });
+
+        // Phis after the merged block now see control arrive from its predecessor
+        for successor in merged.terminator.successors() {
+            for instr in &mut function.blocks[successor.0].instrs {
+                if let Instr::Phi { ref mut sources, .. } = *instr {
+                    for &mut (ref mut source, _) in sources.iter_mut() {
+                        if *source == target {
+                            *source = id;
+                        }
+                    }
+                }
+            }
+        }
+
+        let block = &mut function.blocks[id.0];
+        block.instrs.extend(merged.instrs);
+        block.terminator = merged.terminator;
+
+        function.remove_unreachable_blocks();
+    }
+}
+
+// An instruction's operation and operands, equal for instructions that
+// always compute the same value
+#[derive(Debug,PartialEq,Eq,Hash)]
+enum Expression {
+    Unary(ir::UnaryOp, Operand),
+    Binary(BinaryOp, Operand, Operand),
+    Cast(Type, Operand)
+}
+
+fn expression(instr: &Instr) -> Option<Expression> {
+    match *instr {
+        Instr::Unary { op, src, .. } => Some(Expression::Unary(op, src)),
+        Instr::Binary { op, lhs, rhs, .. } if op.is_commutative() && rhs < lhs => {
+            Some(Expression::Binary(op, rhs, lhs))
+        },
+        Instr::Binary { op, lhs, rhs, .. } => Some(Expression::Binary(op, lhs, rhs)),
+        Instr::Cast { ty, src, .. } => Some(Expression::Cast(ty, src)),
+        _ => None
+    }
+}
+
+struct ValueNumbering<'a> {
+    dominators: &'a Dominators,
+    // Expressions computed so far, one scope per dominator tree level
+    available: Vec<HashMap<Expression, VReg>>,
+    // Registers whose instruction became a copy of an earlier result
+    replaced: HashMap<VReg, Operand>
+}
+
+impl<'a> ValueNumbering<'a> {
+    fn number_subtree(&mut self, function: &mut Function, id: BlockId) {
+        self.available.push(HashMap::new());
+
+        for instr in &mut function.blocks[id.0].instrs {
+            // Later expressions have to refer to the earlier result to match it
+            for operand in instr.operands_mut() {
+                if let Some(&replacement) = operand.as_reg().and_then(|reg| self.replaced.get(&reg)) {
+                    *operand = replacement;
+                }
+            }
+
+            let (dst, expression) = match (instr.dst(), expression(instr)) {
+                (Some(dst), Some(expression)) => (dst, expression),
+                _ => continue
+            };
+
+            match self.available.iter().rev().find_map(|scope| scope.get(&expression)) {
+                Some(&existing) => {
+                    *instr = Instr::Copy { dst, src: Operand::Reg(existing) };
+                    self.replaced.insert(dst, Operand::Reg(existing));
+                },
+                None => {
+                    self.available.last_mut().unwrap().insert(expression, dst);
+                }
+            }
+        }
+
+        for &child in self.dominators.children(id) {
+            self.number_subtree(function, child);
+        }
+
+        self.available.pop();
+    }
+}
+
+/// Common subexpression elimination: an expression already computed in a
+/// dominating position is replaced by a copy of the earlier result.
+pub fn eliminate_common_subexpressions(function: &mut Function) {
+    let dominators = Dominators::compute(function);
+
+    let mut numbering = ValueNumbering {
+        dominators: &dominators,
+        available: Vec::new(),
+        replaced: HashMap::new()
+    };
+
+    numbering.number_subtree(function, Function::ENTRY);
+    propagate_copies(function);
+}
+
+// Operations that are safe to run where the original program might not have,
+// because they cannot trap
+fn can_speculate(instr: &Instr) -> bool {
+    match *instr {
+        Instr::Binary { op: BinaryOp::Div, rhs: Operand::Imm(rhs), .. } => rhs != 0 && rhs != -1,
+        Instr::Binary { op: BinaryOp::UDiv, rhs: Operand::Imm(rhs), .. } => rhs != 0,
+        Instr::Binary { op: BinaryOp::Div, .. } | Instr::Binary { op: BinaryOp::UDiv, .. } => false,
+        Instr::Copy { .. } | Instr::Unary { .. } | Instr::Binary { .. } | Instr::Cast { .. } => true,
+        Instr::Call { .. } | Instr::Phi { .. } => false
+    }
+}
+
+/// Loop-invariant code motion: instructions in a natural loop whose operands
+/// are all assigned outside it move to the block before the loop. Only loops
+/// entered from a single block that always continues into the loop have
+/// such a block to move code to.
+pub fn hoist_loop_invariants(function: &mut Function) {
+    let dominators = Dominators::compute(function);
+    let predecessors = function.predecessors();
+
+    // Inner loops come later in reverse postorder, so hoisting from them first
+    // lets their invariants move on out of enclosing loops
+    let mut headers: Vec<BlockId> = function.reverse_postorder();
+    headers.reverse();
+
+    for header in headers {
+        let latches: Vec<BlockId> = predecessors[header.0].iter().cloned()
+            .filter(|&predecessor| dominators.dominates(header, predecessor))
+            .collect();
+
+        if latches.is_empty() {
+            continue;
+        }
+
+        // The body is everything that reaches a latch without passing the header
+        let mut body: HashSet<BlockId> = HashSet::new();
+        body.insert(header);
+        let mut worklist = latches.clone();
+
+        while let Some(id) = worklist.pop() {
+            if body.insert(id) {
+                worklist.extend(predecessors[id.0].iter().cloned());
+            }
+        }
+
+        let entries: Vec<BlockId> = predecessors[header.0].iter().cloned().filter(|id| !body.contains(id)).collect();
+
+        let preheader = match entries[..] {
+            [entry] if function.successors(entry).len() == 1 => entry,
+            _ => continue
+        };
+
+        let mut body: Vec<BlockId> = body.into_iter().collect();
+        body.sort();
+
+        let mut defined_in_loop: HashSet<VReg> = body.iter()
+            .flat_map(|&id| function.block(id).instrs.iter().filter_map(Instr::dst))
+            .collect();
+
+        let invariant = |instr: &Instr, defined_in_loop: &HashSet<VReg>| {
+            can_speculate(instr) && instr.uses().iter().all(|reg| !defined_in_loop.contains(reg))
+        };
+
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for &id in &body {
+                let mut index = 0;
+
+                while index < function.blocks[id.0].instrs.len() {
+                    if invariant(&function.blocks[id.0].instrs[index], &defined_in_loop) {
+                        let instr = function.blocks[id.0].instrs.remove(index);
+                        defined_in_loop.remove(&instr.dst().unwrap());
+                        function.blocks[preheader.0].instrs.push(instr);
+                        changed = true;
+                    } else {
+                        index += 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Optimizes a function at the given level. Above `-O0` the function goes
+/// through SSA form and comes back out of it.
+pub fn optimize_function(function: &mut Function, level: Level) {
+    if level == Level::O0 {
+        return;
+    }
+
+    ssa::construct(function);
+
+    sccp(function);
+    propagate_copies(function);
+
+    if level >= Level::O2 {
+        eliminate_common_subexpressions(function);
+        hoist_loop_invariants(function);
+        sccp(function);
+        propagate_copies(function);
+    }
+
+    eliminate_dead_code(function);
+    merge_blocks(function);
+
+    if let Err(message) = ssa::verify(function) {
+        panic!("invalid SSA in function {} after optimization: {}", function.name, message);
+    }
+
+    ssa::destruct(function);
+}
+
+pub fn optimize(module: &mut Module, level: Level) {
+    for function in &mut module.functions {
+        optimize_function(function, level);
+    }
+}
@@ -1,207 +1,458 @@
+use std::collections::VecDeque;
+
+use lexer::{Lexer, LexError, SpannedToken};
 use Token;
 use Keyword;
-use AST;
-use Symbol;
-use StatementType;
+use Span;
+use Type;
+use DeclId;
+use TranslationUnit;
+use FunctionDef;
+use Param;
+use BlockItem;
+use Decl;
+use Stmt;
+use StmtKind;
+use Expr;
+use ExprKind;
 use UnaryOperator;
 use BinaryOperator;
 
-struct Parser {
-    tokens: Vec<Token>
+/// Buffers tokens pulled from a `Lexer` so the parser can look `k` tokens ahead
+/// without re-lexing. Whitespace tokens are dropped unless trivia is requested.
+pub struct TokenCursor<'a> {
+    lexer: Lexer<'a>,
+    lookahead: VecDeque<SpannedToken<'a>>,
+    trivia: bool
+}
+
+impl<'a> TokenCursor<'a> {
+    pub fn new(lexer: Lexer<'a>) -> TokenCursor<'a> {
+        TokenCursor {
+            lexer,
+            lookahead: VecDeque::new(),
+            trivia: false
+        }
+    }
+
+    /// Keeps the whitespace tokens, for tools that reproduce the source.
+    pub fn with_trivia(lexer: Lexer<'a>) -> TokenCursor<'a> {
+        TokenCursor {
+            trivia: true,
+            ..TokenCursor::new(lexer)
+        }
+    }
+
+    fn fill(&mut self, count: usize) -> Result<(), LexError> {
+        while self.lookahead.len() < count {
+            let spanned = match self.lexer.next() {
+                Some(result) => result?,
+                None => break
+            };
+
+            if !self.trivia && is_trivia(&spanned.token) {
+                continue
+            }
+
+            self.lookahead.push_back(spanned);
+        }
+
+        Ok(())
+    }
+
+    pub fn peek_nth(&mut self, n: usize) -> Result<Option<&SpannedToken<'a>>, LexError> {
+        self.fill(n + 1)?;
+
+        Ok(self.lookahead.get(n))
+    }
+
+    pub fn next_token(&mut self) -> Result<Option<SpannedToken<'a>>, LexError> {
+        self.fill(1)?;
+
+        Ok(self.lookahead.pop_front())
+    }
+}
+
+fn is_type_specifier(keyword: Keyword) -> bool {
+    matches!(keyword, Keyword::Void | Keyword::Char | Keyword::Short | Keyword::Int | Keyword::Long | Keyword::Signed | Keyword::Unsigned)
+}
+
+fn is_trivia(token: &Token) -> bool {
+    matches!(*token, Token::Space | Token::NewLine)
+}
+
+struct Parser<'a> {
+    cursor: TokenCursor<'a>,
+    decl_count: usize
 } 
 
-impl Parser {
-    fn new(tokens: Vec<Token>) -> Parser {
+impl<'a> Parser<'a> {
+    fn new(lexer: Lexer<'a>) -> Parser<'a> {
         Parser {
-            tokens
+            cursor: TokenCursor::new(lexer),
+            decl_count: 0
         }
     }
 
-    fn next_token(&mut self) -> Token {
-        let mut token = self.tokens.remove(0);
+    fn next_spanned(&mut self) -> SpannedToken<'a> {
+        match self.cursor.next_token() {
+            Ok(Some(spanned)) => spanned,
+            Ok(None) => panic!("Unexpected end of input"),
+            Err(error) => panic!("Lexing failed: {} at {}..{}", error, error.span.start, error.span.end)
+        }
+    }
 
-        loop {
-            if token != Token::Space && token != Token::NewLine {
-                break
-            }
+    fn next_token(&mut self) -> Token<'a> {
+        self.next_spanned().token
+    }
 
-            token = self.tokens.remove(0);
+    fn peek(&mut self) -> Option<Token<'a>> {
+        self.peek_nth(0)
+    }
+
+    fn peek_nth(&mut self, n: usize) -> Option<Token<'a>> {
+        match self.cursor.peek_nth(n) {
+            Ok(spanned) => spanned.map(|spanned| spanned.token),
+            Err(error) => panic!("Lexing failed: {} at {}..{}", error, error.span.start, error.span.end)
         }
-        
-        token
     }
 
-    fn peek(&mut self) -> Token {
-        let mut idx = 0;
+    fn expect(&mut self, expected: Token, description: &str) -> Span {
+        let spanned = self.next_spanned();
 
-        loop {
-            let token = &self.tokens[idx];
+        if spanned.token != expected {
+            panic!("Expected {}, got {:?}", description, spanned.token);
+        }
 
-            if token != &Token::Space && token != &Token::NewLine {
-                return (*token).clone();
-            }
+        spanned.span
+    }
 
-            idx += 1;
+    fn parse_identifier(&mut self) -> (String, Span) {
+        let spanned = self.next_spanned();
+
+        match spanned.token {
+            Token::Identifier(value) => (value.to_string(), spanned.span),
+            _ => panic!("Expected identifier, got: {:?}", spanned.token)
         }
     }
 
-    fn next_is_space(&mut self) -> bool {
-        let token = &self.tokens[0];
-        
-        match token {
-            &Token::NewLine => true,
-            &Token::Space => true,
+    fn next_decl_id(&mut self) -> DeclId {
+        let id = DeclId(self.decl_count);
+        self.decl_count += 1;
+
+        id
+    }
+
+    fn peek_is_type_specifier(&mut self) -> bool {
+        match self.peek() {
+            Some(Token::Keyword(keyword)) => is_type_specifier(keyword),
             _ => false
         }
     }
 
-    fn parse_program(&mut self) -> AST {
-        let function = self.parse_function();
+    fn parse_type(&mut self) -> (Type, Span) {
+        let mut keywords = Vec::new();
+        let mut span: Option<Span> = None;
 
-        AST::new(Symbol::Program, vec![function])
-    }
+        while self.peek_is_type_specifier() {
+            let spanned = self.next_spanned();
 
-    fn parse_function(&mut self) -> AST {
-        let token = self.next_token();
+            if let Token::Keyword(keyword) = spanned.token {
+                keywords.push(keyword);
+            }
 
-        if token != Token::Keyword(Keyword::Int) {
-            panic!("Invalid function, expected 'int', got {:?}", token);
+            span = Some(match span {
+                Some(span) => span.to(spanned.span),
+                None => spanned.span
+            });
         }
 
-        let token = self.next_token();
+        let span = match span {
+            Some(span) => span,
+            None => panic!("Expected a type, got {:?}", self.peek())
+        };
 
-        let function_name = match token {
-            Token::Identifier(value) => value,
-            _ => panic!(format!("Expected identifier, got: {:?}", token))
+        let count = |keyword: Keyword| keywords.iter().filter(|&&k| k == keyword).count();
+
+        let ty = match (count(Keyword::Void), count(Keyword::Char), count(Keyword::Short), count(Keyword::Int), count(Keyword::Long), count(Keyword::Signed), count(Keyword::Unsigned)) {
+            (1, 0, 0, 0, 0, 0, 0) => Type::Void,
+            (0, 1, 0, 0, 0, _, 0) => Type::Char,
+            (0, 0, 0, _, 1, _, 0) => Type::Long,
+            (0, 0, 0, _, 0, 0, 1) => Type::UnsignedInt,
+            (0, 0, 0, 1, 0, _, 0) | (0, 0, 0, 0, 0, 1, 0) => Type::Int,
+            _ => panic!("Unsupported type specifier {:?}", keywords)
         };
 
-        let token = self.next_token();
+        if count(Keyword::Int) > 1 || count(Keyword::Signed) > 1 {
+            panic!("Unsupported type specifier {:?}", keywords);
+        }
+
+        (ty, span)
+    }
+
+    fn parse_program(&mut self) -> TranslationUnit {
+        let mut functions = Vec::new();
 
-        if token != Token::OpenParen {
-            panic!("Expected '(', got {:?}", token);
+        while self.peek().is_some() {
+            functions.push(self.parse_function());
         }
 
-        let token = self.next_token();
+        TranslationUnit {
+            functions
+        }
+    }
+
+    fn parse_function(&mut self) -> FunctionDef {
+        let (return_type, start) = self.parse_type();
+
+        let (function_name, _) = self.parse_identifier();
+
+        self.expect(Token::OpenParen, "'('");
+
+        let params = self.parse_params();
 
-        if token != Token::CloseParen {
-            panic!("Expected ')', got {:?}", token);
+        self.expect(Token::CloseParen, "')'");
+
+        if self.peek() == Some(Token::Semicolon) {
+            let end = self.next_spanned().span;
+
+            return FunctionDef {
+                name: function_name,
+                return_type,
+                params,
+                body: None,
+                span: start.to(end)
+            };
         }
 
-        let token = self.next_token();
+        let (body, end) = self.parse_block();
 
-        if token != Token::OpenBrace {
-            panic!("Expected '{{', got {:?}", token);
+        FunctionDef {
+            name: function_name,
+            return_type,
+            params,
+            body: Some(body),
+            span: start.to(end)
         }
+    }
 
-        let statement = self.parse_statement();
+    fn parse_params(&mut self) -> Vec<Param> {
+        let mut params = Vec::new();
 
-        let token = self.next_token();
+        if self.peek() == Some(Token::CloseParen) {
+            return params;
+        }
 
-        if token != Token::CloseBrace {
-            panic!("Expected '}}', got {:?}", token);
+        if self.peek() == Some(Token::Keyword(Keyword::Void)) && self.peek_nth(1) == Some(Token::CloseParen) {
+            self.next_token();
+            return params;
         }
 
-        AST::new(Symbol::Function(function_name), vec![statement])
+        loop {
+            let (ty, start) = self.parse_type();
+
+            let (name, span) = match self.peek() {
+                Some(Token::Identifier(_)) => {
+                    let (name, end) = self.parse_identifier();
+                    (Some(name), start.to(end))
+                },
+                _ => (None, start)
+            };
+
+            params.push(Param {
+                id: self.next_decl_id(),
+                name,
+                ty,
+                span
+            });
+
+            if self.peek() != Some(Token::Comma) {
+                break
+            }
+
+            self.next_token();
+        }
+
+        params
     }
 
-    fn parse_statement(&mut self) -> AST {
-        let token = self.next_token();
+    fn parse_block(&mut self) -> (Vec<BlockItem>, Span) {
+        let start = self.expect(Token::OpenBrace, "'{'");
+
+        let mut items = Vec::new();
 
-        if token != Token::Keyword(Keyword::Return) {
-            panic!("Expected 'return', got {:?}", token);
+        while self.peek() != Some(Token::CloseBrace) {
+            items.push(self.parse_block_item());
         }
 
-        if self.next_is_space() == false {
-            panic!("Expected whitespace");
+        let end = self.expect(Token::CloseBrace, "'}'");
+
+        (items, start.to(end))
+    }
+
+    fn parse_block_item(&mut self) -> BlockItem {
+        if self.peek_is_type_specifier() {
+            BlockItem::Decl(self.parse_declaration())
+        } else {
+            BlockItem::Stmt(self.parse_statement())
         }
+    }
+
+    fn parse_declaration(&mut self) -> Decl {
+        let (ty, start) = self.parse_type();
+        let (name, _) = self.parse_identifier();
 
-        let expression = self.parse_expression();
+        let init = if self.peek() == Some(Token::Assignment) {
+            self.next_token();
+            Some(self.parse_expression())
+        } else {
+            None
+        };
 
-        let token = self.next_token();
+        let end = self.expect(Token::Semicolon, "';'");
 
-        if token != Token::Semicolon {
-            panic!("Expected ';', got {:?}", token);
+        Decl {
+            id: self.next_decl_id(),
+            name,
+            ty,
+            init,
+            span: start.to(end)
         }
+    }
 
-        AST::new(Symbol::Statement(StatementType::Return), vec![expression])
-    } 
-    
-    fn parse_expression(&mut self) -> AST {
-        let logical_and_expression = self.parse_logical_and_expression();
+    fn parse_statement(&mut self) -> Stmt {
+        match self.peek() {
+            Some(Token::Keyword(Keyword::Return)) => {
+                let start = self.next_spanned().span;
 
-        let mut last_result: Option<AST> = None;
+                let expression = if self.peek() == Some(Token::Semicolon) {
+                    None
+                } else {
+                    Some(self.parse_expression())
+                };
 
-        let mut next = self.peek();
+                let end = self.expect(Token::Semicolon, "';'");
 
-        while next == Token::LogicalOr {
-            let token = self.next_token();
-            let next_logical_and_expression = self.parse_logical_and_expression();
+                Stmt::new(StmtKind::Return(expression), start.to(end))
+            },
+            Some(Token::OpenBrace) => {
+                let (items, span) = self.parse_block();
 
-            let first_child = match last_result {
-                Some(ref ast) => (*ast).clone(),
-                None => logical_and_expression.clone()
-            };
+                Stmt::new(StmtKind::Compound(items), span)
+            },
+            Some(Token::Keyword(Keyword::If)) => {
+                let start = self.next_spanned().span;
+                let condition = self.parse_condition();
+                let then_branch = self.parse_statement();
+
+                let else_branch = if self.peek() == Some(Token::Keyword(Keyword::Else)) {
+                    self.next_token();
+                    Some(Box::new(self.parse_statement()))
+                } else {
+                    None
+                };
 
-            last_result = Some(AST::new(Symbol::BinaryOperator(BinaryOperator::LogicalOr), vec![first_child, next_logical_and_expression]));
+                let end = match else_branch {
+                    Some(ref else_branch) => else_branch.span,
+                    None => then_branch.span
+                };
 
-            next = self.peek();
+                Stmt::new(StmtKind::If(condition, Box::new(then_branch), else_branch), start.to(end))
+            },
+            Some(Token::Keyword(Keyword::While)) => {
+                let start = self.next_spanned().span;
+                let condition = self.parse_condition();
+                let body = self.parse_statement();
+                let span = start.to(body.span);
+
+                Stmt::new(StmtKind::While(condition, Box::new(body)), span)
+            },
+            _ => {
+                let expression = self.parse_expression();
+
+                let end = self.expect(Token::Semicolon, "';'");
+                let span = expression.span.to(end);
+
+                Stmt::new(StmtKind::Expr(expression), span)
+            }
         }
+    }
+
+    // The parenthesized controlling expression of an if or while statement
+    fn parse_condition(&mut self) -> Expr {
+        self.expect(Token::OpenParen, "'('");
+
+        let condition = self.parse_expression();
+
+        self.expect(Token::CloseParen, "')'");
 
-        match last_result {
-            Some(ast) => ast,
-            None => logical_and_expression
+        condition
+    }
+
+    fn parse_expression(&mut self) -> Expr {
+        let lhs = self.parse_logical_or_expression();
+
+        if self.peek() != Some(Token::Assignment) {
+            return lhs;
         }
+
+        self.next_token();
+
+        let rhs = self.parse_expression();
+        let span = lhs.span.to(rhs.span);
+
+        Expr::new(ExprKind::Assignment(Box::new(lhs), Box::new(rhs)), span)
     }
 
-    fn parse_logical_and_expression(&mut self) -> AST {
-        let equality_expression = self.parse_equality_expression();
+    fn parse_logical_or_expression(&mut self) -> Expr {
+        let mut result = self.parse_logical_and_expression();
+
+        let mut next = self.peek();
+
+        while next == Some(Token::LogicalOr) {
+            self.next_token();
+            let next_logical_and_expression = self.parse_logical_and_expression();
+
+            result = binary(BinaryOperator::LogicalOr, result, next_logical_and_expression);
+
+            next = self.peek();
+        }
+
+        result
+    }
 
-        let mut last_result: Option<AST> = None;
+    fn parse_logical_and_expression(&mut self) -> Expr {
+        let mut result = self.parse_equality_expression();
 
         let mut next = self.peek();
 
-        while next == Token::LogicalAnd {
-            let token = self.next_token();
+        while next == Some(Token::LogicalAnd) {
+            self.next_token();
             let next_equality_expression = self.parse_equality_expression();
 
-            let first_child = match last_result {
-                Some(ref ast) => (*ast).clone(),
-                None => equality_expression.clone()
-            };
-
-            last_result = Some(AST::new(Symbol::BinaryOperator(BinaryOperator::LogicalAnd), vec![first_child, next_equality_expression]));
+            result = binary(BinaryOperator::LogicalAnd, result, next_equality_expression);
 
             next = self.peek();
         }
 
-        match last_result {
-            Some(ast) => ast,
-            None => equality_expression
-        }
+        result
     }
 
-    fn parse_equality_expression(&mut self) -> AST {
-        let relational_expression = self.parse_relational_expression();
-
-        let mut last_result: Option<AST> = None;
+    fn parse_equality_expression(&mut self) -> Expr {
+        let mut result = self.parse_relational_expression();
 
         let mut next = self.peek();
 
-        while next == Token::Equal || next == Token::NotEqual {
+        while next == Some(Token::Equal) || next == Some(Token::NotEqual) {
             let token = self.next_token();
             let next_relational_expression = self.parse_relational_expression();
 
-            let first_child = match last_result {
-                Some(ref ast) => (*ast).clone(),
-                None => relational_expression.clone()
-            };
-
-            last_result = match token {
+            result = match token {
                 Token::Equal => {
-                    Some(AST::new(Symbol::BinaryOperator(BinaryOperator::Equal), vec![first_child, next_relational_expression]))
+                    binary(BinaryOperator::Equal, result, next_relational_expression)
                 },
                 Token::NotEqual => {
-                    Some(AST::new(Symbol::BinaryOperator(BinaryOperator::NotEqual), vec![first_child, next_relational_expression]))
+                    binary(BinaryOperator::NotEqual, result, next_relational_expression)
                 },
                 _ => { panic!("Could not parse {:?} in expression", token); }
             };
@@ -209,40 +460,30 @@ impl Parser {
             next = self.peek();
         }
 
-        match last_result {
-            Some(ast) => ast,
-            None => relational_expression
-        }
+        result
     }
 
-    fn parse_relational_expression(&mut self) -> AST {
-        let additive_expression = self.parse_additive_expression();
-
-        let mut last_result: Option<AST> = None;
+    fn parse_relational_expression(&mut self) -> Expr {
+        let mut result = self.parse_shift_expression();
 
         let mut next = self.peek();
 
-        while next == Token::LessThan || next == Token::LessThanOrEqual || next == Token::GreaterThan || next == Token::GreaterThanOrEqual {
+        while next == Some(Token::LessThan) || next == Some(Token::LessThanOrEqual) || next == Some(Token::GreaterThan) || next == Some(Token::GreaterThanOrEqual) {
             let token = self.next_token();
-            let next_additive_expression = self.parse_additive_expression();
+            let next_shift_expression = self.parse_shift_expression();
 
-            let first_child = match last_result {
-                Some(ref ast) => (*ast).clone(),
-                None => additive_expression.clone()
-            };
-
-            last_result = match token {
+            result = match token {
                 Token::LessThan => {
-                    Some(AST::new(Symbol::BinaryOperator(BinaryOperator::LessThan), vec![first_child, next_additive_expression]))
+                    binary(BinaryOperator::LessThan, result, next_shift_expression)
                 },
                 Token::LessThanOrEqual => {
-                    Some(AST::new(Symbol::BinaryOperator(BinaryOperator::LessThanOrEqual), vec![first_child, next_additive_expression]))
+                    binary(BinaryOperator::LessThanOrEqual, result, next_shift_expression)
                 },
                 Token::GreaterThan => {
-                    Some(AST::new(Symbol::BinaryOperator(BinaryOperator::GreaterThan), vec![first_child, next_additive_expression]))
+                    binary(BinaryOperator::GreaterThan, result, next_shift_expression)
                 },
                 Token::GreaterThanOrEqual => {
-                    Some(AST::new(Symbol::BinaryOperator(BinaryOperator::GreaterThanOrEqual), vec![first_child, next_additive_expression]))
+                    binary(BinaryOperator::GreaterThanOrEqual, result, next_shift_expression)
                 },
                 _ => { panic!("Could not parse {:?} in expression", token); }
             };
@@ -250,34 +491,49 @@ impl Parser {
             next = self.peek();
         }
 
-        match last_result {
-            Some(ast) => ast,
-            None => additive_expression
-        }
+        result
     }
 
-    fn parse_additive_expression(&mut self) -> AST {
-        let term = self.parse_term();
-
-        let mut last_result: Option<AST> = None;
+    fn parse_shift_expression(&mut self) -> Expr {
+        let mut result = self.parse_additive_expression();
 
         let mut next = self.peek();
 
-        while next == Token::Addition || next == Token::Minus {
+        while next == Some(Token::ShiftLeft) || next == Some(Token::ShiftRight) {
             let token = self.next_token();
-            let next_term = self.parse_term();
+            let next_additive_expression = self.parse_additive_expression();
 
-            let first_child = match last_result {
-                Some(ref ast) => (*ast).clone(),
-                None => term.clone()
+            result = match token {
+                Token::ShiftLeft => {
+                    binary(BinaryOperator::ShiftLeft, result, next_additive_expression)
+                },
+                Token::ShiftRight => {
+                    binary(BinaryOperator::ShiftRight, result, next_additive_expression)
+                },
+                _ => { panic!("Could not parse {:?} in expression", token); }
             };
 
-            last_result = match token {
+            next = self.peek();
+        }
+
+        result
+    }
+
+    fn parse_additive_expression(&mut self) -> Expr {
+        let mut result = self.parse_term();
+
+        let mut next = self.peek();
+
+        while next == Some(Token::Addition) || next == Some(Token::Minus) {
+            let token = self.next_token();
+            let next_term = self.parse_term();
+
+            result = match token {
                 Token::Addition => {
-                    Some(AST::new(Symbol::BinaryOperator(BinaryOperator::Addition), vec![first_child, next_term]))
+                    binary(BinaryOperator::Addition, result, next_term)
                 },
                 Token::Minus => {
-                    Some(AST::new(Symbol::BinaryOperator(BinaryOperator::Subtraction), vec![first_child, next_term]))
+                    binary(BinaryOperator::Subtraction, result, next_term)
                 },
                 _ => { panic!("Could not parse {:?} in expression", token); }
             };
@@ -285,39 +541,24 @@ impl Parser {
             next = self.peek();
         }
 
-        // match last_result {
-        //     Some(ast) => AST::new(Symbol::Expression, vec![ast]),
-        //     None => AST::new(Symbol::Expression, vec![term])
-        // }
-
-        match last_result {
-            Some(ast) => ast,
-            None => term
-        }
+        result
     }
 
-    fn parse_term(&mut self) -> AST {
-        let factor = self.parse_factor();
-
-        let mut last_result: Option<AST> = None;
+    fn parse_term(&mut self) -> Expr {
+        let mut result = self.parse_factor();
 
         let mut next = self.peek();
 
-        while next == Token::Multiplication || next == Token::Division {
+        while next == Some(Token::Multiplication) || next == Some(Token::Division) {
             let token = self.next_token();
             let next_factor = self.parse_factor();
 
-            let first_child = match last_result {
-                Some(ref ast) => (*ast).clone(),
-                None => factor.clone()
-            };
-
-            last_result = match token {
+            result = match token {
                 Token::Division => {
-                    Some(AST::new(Symbol::BinaryOperator(BinaryOperator::Division), vec![first_child, next_factor]))
+                    binary(BinaryOperator::Division, result, next_factor)
                 },
                 Token::Multiplication => {
-                    Some(AST::new(Symbol::BinaryOperator(BinaryOperator::Multiplication), vec![first_child, next_factor]))
+                    binary(BinaryOperator::Multiplication, result, next_factor)
                 },
                 _ => { panic!("Could not parse {:?} in term", token); }
             };
@@ -325,64 +566,85 @@ impl Parser {
             next = self.peek();
         }
 
-        match last_result {
-            Some(ast) => ast,
-            None => factor
-        }
-
-        // match last_result {
-        //     Some(ast) => AST::new(Symbol::Term, vec![ast]),
-        //     None => AST::new(Symbol::Term, vec![factor])
-        // }
+        result
     }
 
-    fn parse_factor(&mut self) -> AST {
-        let token = self.next_token();
+    fn parse_factor(&mut self) -> Expr {
+        let spanned = self.next_spanned();
 
-        match token {
+        match spanned.token {
             Token::OpenParen => {
-                let expression = self.parse_expression();
+                let mut expression = self.parse_expression();
 
-                let token = self.next_token();
+                let close = self.next_spanned();
 
-                if token != Token::CloseParen {
-                    panic!("Expected ')', but got {:?}", token);
+                if close.token != Token::CloseParen {
+                    panic!("Expected ')', but got {:?}", close.token);
                 }
 
+                expression.span = spanned.span.to(close.span);
+
                 expression
-                // AST::new(Symbol::Factor, vec![expression])
             },
             Token::Minus | Token::BitwiseComplementOperator | Token::LogicalNegationOperator => {
                 let factor = self.parse_factor();
 
-                let unary_operation = match &token {
-                    &Token::Minus => UnaryOperator::Negation,
-                    &Token::BitwiseComplementOperator => UnaryOperator::BitwiseComplement,
-                    &Token::LogicalNegationOperator => UnaryOperator::LogicalNegation,
+                let unary_operation = match spanned.token {
+                    Token::Minus => UnaryOperator::Negation,
+                    Token::BitwiseComplementOperator => UnaryOperator::BitwiseComplement,
+                    Token::LogicalNegationOperator => UnaryOperator::LogicalNegation,
                     _ => panic!("Should never go here")
                 };
 
-                let unary_operation = AST::new(Symbol::UnaryOperator(unary_operation), vec![factor]);
+                let span = spanned.span.to(factor.span);
 
-                unary_operation
-                // AST::new(Symbol::Factor, vec![unary_operation])
+                Expr::new(ExprKind::Unary(unary_operation, Box::new(factor)), span)
             },
             Token::IntegerLiteral(value) => {
-                let integer = AST::new(Symbol::Constant(value), Vec::new());
-                integer
-              //  AST::new(Symbol::Factor, vec![integer])
+                Expr::new(ExprKind::Constant(value), spanned.span)
+            },
+            Token::Identifier(name) => {
+                if self.peek() != Some(Token::OpenParen) {
+                    return Expr::new(ExprKind::Variable(name.to_string(), None), spanned.span);
+                }
+
+                self.next_token();
+
+                let mut args = Vec::new();
+
+                if self.peek() != Some(Token::CloseParen) {
+                    loop {
+                        args.push(self.parse_expression());
+
+                        if self.peek() != Some(Token::Comma) {
+                            break
+                        }
+
+                        self.next_token();
+                    }
+                }
+
+                let end = self.expect(Token::CloseParen, "')'");
+
+                Expr::new(ExprKind::Call(name.to_string(), args), spanned.span.to(end))
             },
-            _ => { panic!("Invalid factor {:?}", token); }
+            _ => { panic!("Invalid factor {:?}", spanned.token); }
         }
     }
 
-    pub fn parse(&mut self) -> AST {
+    pub fn parse(&mut self) -> TranslationUnit {
        self.parse_program()
     } 
 }  
 
-pub fn parse(tokens: Vec<Token>) -> AST {
-    let mut parser = Parser::new(tokens);
+fn binary(operator: BinaryOperator, lhs: Expr, rhs: Expr) -> Expr {
+    let span = lhs.span.to(rhs.span);
+
+    Expr::new(ExprKind::Binary(operator, Box::new(lhs), Box::new(rhs)), span)
+}
+
+pub fn parse(source: &str) -> TranslationUnit {
+    let mut parser = Parser::new(Lexer::new(source));
 
     parser.parse()
-}
\ No newline at end of file
+}
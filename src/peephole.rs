@@ -0,0 +1,460 @@
+//! Peephole optimization of generated x86-64 code.
+//!
+//! Each rewrite looks at a short window of instructions, using liveness only
+//! where it must prove a register or the flags are no longer needed. The
+//! rewrites expose one another, so they are repeated until none applies.
+
+use std::collections::{HashMap, HashSet};
+
+use x86::{Condition, Instr, Label, Operand, Reg};
+
+/// Rewrites instructions into cheaper ones that do the same thing.
+pub fn optimize(mut instrs: Vec<Instr>) -> Vec<Instr> {
+    loop {
+        let mut changed = eliminate_push_pop(&mut instrs);
+        changed |= simplify_arithmetic(&mut instrs);
+        changed |= fuse_compare_and_branch(&mut instrs);
+        changed |= thread_jumps(&mut instrs);
+        changed |= remove_unreachable(&mut instrs);
+
+        if !changed {
+            return instrs;
+        }
+    }
+}
+
+fn label_indices(instrs: &[Instr]) -> HashMap<Label, usize> {
+    instrs.iter().enumerate()
+        .filter_map(|(index, instr)| match *instr {
+            Instr::Label(ref label) => Some((label.clone(), index)),
+            _ => None
+        })
+        .collect()
+}
+
+// Drops the instructions a pass marked dead, all in one go. Passes only mark
+// instructions at or behind the one they are looking at, so what they look
+// ahead at is never dead.
+fn remove_dead(instrs: &mut Vec<Instr>, live: &[bool]) {
+    let mut live = live.iter();
+    instrs.retain(|_| *live.next().unwrap());
+}
+
+// Whether the flags are overwritten before anything reads them, looking no
+// further than the next label or jump
+fn flags_dead(instrs: &[Instr]) -> bool {
+    for instr in instrs {
+        if instr.reads_flags() {
+            return false;
+        }
+
+        if instr.writes_flags() || *instr == Instr::Ret {
+            return true;
+        }
+
+        if let Instr::Label(_) | Instr::Global(_) | Instr::Jmp(_) = *instr {
+            return false;
+        }
+    }
+
+    true
+}
+
+// Whether `register` is written before it is read on every path from `start`
+fn register_dead(instrs: &[Instr], labels: &HashMap<Label, usize>, register: Reg, start: usize) -> bool {
+    let mut worklist = vec![start];
+    let mut visited = HashSet::new();
+
+    while let Some(mut index) = worklist.pop() {
+        while visited.insert(index) {
+            let instr = match instrs.get(index) {
+                Some(instr) => instr,
+                None => break
+            };
+
+            // A `set` whose byte is then zero extended replaces the whole register
+            if let Instr::Set(_, set) = *instr {
+                if set == register && matches!(instrs.get(index + 1), Some(&Instr::Movzx8 { src, .. }) if src == register) {
+                    break;
+                }
+            }
+
+            if instr.reads().contains(&register) {
+                return false;
+            }
+
+            if instr.writes().contains(&register) {
+                break;
+            }
+
+            if let Some(target) = instr.jump_target() {
+                match labels.get(target) {
+                    Some(&target) => worklist.push(target),
+                    None => return false
+                }
+            }
+
+            if !instr.falls_through() {
+                break;
+            }
+
+            index += 1;
+        }
+    }
+
+    true
+}
+
+// Whether a pushed value can be moved straight to where it is popped past
+// this instruction
+fn keeps_stack(instr: &Instr) -> bool {
+    match *instr {
+        Instr::Label(_) | Instr::Global(_) | Instr::Call(_) | Instr::Push(_) | Instr::Pop(_) => false,
+        _ => !instr.is_branch() && !instr.reads().contains(&Reg::Rsp) && !instr.writes().contains(&Reg::Rsp)
+    }
+}
+
+// `push X; ...; pop %r` becomes `movq X, %r; ...` when nothing in between
+// uses %r or the stack, and disappears when X is %r. The pop is the one
+// instruction marked dead ahead, but a later push cannot look past it, since
+// nothing in between was a push.
+fn eliminate_push_pop(instrs: &mut Vec<Instr>) -> bool {
+    let mut live = vec![true; instrs.len()];
+    let mut changed = false;
+    let mut index = 0;
+
+    while index < instrs.len() {
+        let value = match instrs[index] {
+            Instr::Push(value) if !value.registers().contains(&Reg::Rsp) => value,
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+
+        let pop = instrs[index + 1..].iter()
+            .position(|instr| !keeps_stack(instr))
+            .map(|offset| index + 1 + offset)
+            .filter(|&pop| match instrs[pop] {
+                Instr::Pop(Operand::Reg(register)) if register != Reg::Rsp => {
+                    !instrs[index + 1..pop].iter().any(|instr| instr.reads().contains(&register) || instr.writes().contains(&register))
+                },
+                _ => false
+            });
+
+        if let Some(pop) = pop {
+            let register = match instrs[pop] {
+                Instr::Pop(register) => register,
+                _ => unreachable!()
+            };
+
+            live[pop] = false;
+
+            if value == register {
+                live[index] = false;
+            } else {
+                instrs[index] = Instr::Mov { src: value, dst: register };
+            }
+
+            changed = true;
+        }
+
+        index += 1;
+    }
+
+    remove_dead(instrs, &live);
+
+    changed
+}
+
+// Zeroes registers with `xor` and multiplies by powers of two with shifts,
+// where the flags they set differently are not read. A constant loaded just
+// to be added or multiplied is used as an immediate instead.
+fn simplify_arithmetic(instrs: &mut Vec<Instr>) -> bool {
+    let mut live = vec![true; instrs.len()];
+    let mut changed = false;
+    let mut index = 0;
+
+    while index < instrs.len() {
+        let commuted = match (&instrs[index], instrs.get(index + 1)) {
+            (&Instr::Mov { src: Operand::Imm(value), dst: Operand::Reg(loaded) }, Some(&Instr::Add { src: Operand::Reg(other), dst: Operand::Reg(dst) })) if loaded == dst && other != dst && Operand::Imm(value).fits_imm32() => {
+                Some((other, Instr::Add { src: Operand::Imm(value), dst: Operand::Reg(dst) }))
+            },
+            (&Instr::Mov { src: Operand::Imm(value), dst: Operand::Reg(loaded) }, Some(&Instr::Imul { src: Operand::Reg(other), dst })) if loaded == dst && other != dst && Operand::Imm(value).fits_imm32() => {
+                Some((other, Instr::Imul { src: Operand::Imm(value), dst }))
+            },
+            _ => None
+        };
+
+        if let Some((other, instr)) = commuted {
+            let dst = match instr {
+                Instr::Add { dst, .. } => dst,
+                Instr::Imul { dst, .. } => Operand::Reg(dst),
+                _ => unreachable!()
+            };
+
+            instrs[index] = Instr::Mov { src: Operand::Reg(other), dst };
+            instrs[index + 1] = instr;
+            changed = true;
+        }
+
+        // Moving a value back to where it was just copied from does nothing
+        let moved_back = match (&instrs[index], instrs.get(index + 1)) {
+            (&Instr::Mov { src, dst: Operand::Reg(copy) }, Some(&Instr::Mov { src: Operand::Reg(back), dst })) => {
+                copy == back && src == dst
+            },
+            _ => false
+        };
+
+        // The first move takes the second's place, to be looked at with what follows
+        if moved_back {
+            instrs.swap(index, index + 1);
+            live[index] = false;
+            index += 1;
+            changed = true;
+            continue;
+        }
+
+        let replacement = match instrs[index] {
+            Instr::Mov { src: Operand::Imm(0), dst: Operand::Reg(register) } => Some(Some(Instr::Zero(register))),
+            Instr::Imul { src: Operand::Imm(1), .. } => Some(None),
+            Instr::Imul { src: Operand::Imm(factor), dst } if factor > 0 && (factor as u64).is_power_of_two() => {
+                Some(Some(Instr::Shl { count: Operand::Imm(factor.trailing_zeros() as i64), dst: Operand::Reg(dst) }))
+            },
+            _ => None
+        };
+
+        match replacement {
+            Some(Some(instr)) if flags_dead(&instrs[index + 1..]) => {
+                instrs[index] = instr;
+                changed = true;
+            },
+            Some(None) if flags_dead(&instrs[index + 1..]) => {
+                live[index] = false;
+                changed = true;
+            },
+            _ => {}
+        }
+
+        index += 1;
+    }
+
+    remove_dead(instrs, &live);
+
+    changed
+}
+
+// Matches a comparison result materialized with `set` and then tested, as
+// in `movq $0, %rax; sete %al; cmpq $0, %rax; je L`, returning the end of the
+// window, the branch it becomes and the registers it wrote
+fn compare_and_branch(instrs: &[Instr], start: usize) -> Option<(usize, Instr, Vec<Reg>)> {
+    let mut index = start;
+
+    if instrs[index] == (Instr::Mov { src: Operand::Imm(0), dst: Operand::Reg(Reg::Rax) }) {
+        index += 1;
+    }
+
+    let condition = match instrs.get(index) {
+        Some(&Instr::Set(condition, Reg::Rax)) => condition,
+        _ => return None
+    };
+
+    let mut register = Reg::Rax;
+    let mut written = vec![Reg::Rax];
+    index += 1;
+
+    // Extensions keep a 0 or 1 as it is
+    loop {
+        match instrs.get(index) {
+            Some(&Instr::Movsx8 { src: Operand::Reg(src), dst }) |
+            Some(&Instr::Movsx32 { src: Operand::Reg(src), dst }) |
+            Some(&Instr::Movzx32 { src: Operand::Reg(src), dst }) if src == register && dst == register => {},
+            Some(&Instr::Movzx8 { src, dst }) if src == register => {
+                register = dst;
+                written.push(dst);
+            },
+            _ => break
+        }
+
+        index += 1;
+    }
+
+    let tested = match instrs.get(index) {
+        Some(&Instr::Test { src: Operand::Reg(src), dst: Operand::Reg(dst) }) => src == register && dst == register,
+        Some(&Instr::Cmp { src: Operand::Imm(0), dst: Operand::Reg(dst) }) => dst == register,
+        _ => false
+    };
+
+    if !tested {
+        return None;
+    }
+
+    let branch = match instrs.get(index + 1) {
+        Some(&Instr::Jcc(Condition::E, ref label)) => Instr::Jcc(condition.negate(), label.clone()),
+        Some(&Instr::Jcc(Condition::Ne, ref label)) => Instr::Jcc(condition, label.clone()),
+        _ => return None
+    };
+
+    Some((index + 1, branch, written))
+}
+
+// Branches on the flags of the original comparison when the materialized
+// result is not needed afterwards
+fn fuse_compare_and_branch(instrs: &mut Vec<Instr>) -> bool {
+    let labels = label_indices(instrs);
+    let mut fusions = Vec::new();
+    let mut index = 0;
+
+    while index < instrs.len() {
+        if let Some((end, branch, written)) = compare_and_branch(instrs, index) {
+            let target = labels.get(branch.jump_target().unwrap()).cloned();
+
+            let dead = target.is_some() && written.iter().all(|&register| {
+                register_dead(instrs, &labels, register, end + 1) && register_dead(instrs, &labels, register, target.unwrap())
+            });
+
+            if dead {
+                fusions.push((index, end, branch));
+                index = end + 1;
+                continue;
+            }
+        }
+
+        index += 1;
+    }
+
+    let changed = !fusions.is_empty();
+    let mut live = vec![true; instrs.len()];
+
+    for (start, end, branch) in fusions {
+        instrs[start] = branch;
+
+        for dead in &mut live[start + 1..end + 1] {
+            *dead = false;
+        }
+    }
+
+    remove_dead(instrs, &live);
+
+    changed
+}
+
+// Where control ends up after jumping to `label`, skipping jumps to jumps
+fn final_target(instrs: &[Instr], labels: &HashMap<Label, usize>, label: &Label) -> Label {
+    let mut label = label.clone();
+    let mut seen = HashSet::new();
+
+    while seen.insert(label.clone()) {
+        let next = labels.get(&label)
+            .and_then(|&index| instrs[index..].iter().find(|instr| !matches!(**instr, Instr::Label(_))));
+
+        match next {
+            Some(Instr::Jmp(next)) => label = next.clone(),
+            _ => break
+        }
+    }
+
+    label
+}
+
+// Whether `label` is defined before any instruction after `index`
+fn labels_next(instrs: &[Instr], index: usize, label: &Label) -> bool {
+    instrs[index + 1..].iter()
+        .take_while(|instr| matches!(**instr, Instr::Label(_)))
+        .any(|instr| *instr == Instr::Label(label.clone()))
+}
+
+// Retargets jumps to jumps, drops jumps to the next instruction and inverts
+// a conditional branch over an unconditional jump
+fn thread_jumps(instrs: &mut Vec<Instr>) -> bool {
+    let labels = label_indices(instrs);
+    let mut changed = false;
+
+    for index in 0..instrs.len() {
+        let threaded = match instrs[index] {
+            Instr::Jmp(ref label) => Some(Instr::Jmp(final_target(instrs, &labels, label))),
+            Instr::Jcc(condition, ref label) => Some(Instr::Jcc(condition, final_target(instrs, &labels, label))),
+            _ => None
+        };
+
+        if let Some(threaded) = threaded {
+            if threaded != instrs[index] {
+                instrs[index] = threaded;
+                changed = true;
+            }
+        }
+    }
+
+    let mut live = vec![true; instrs.len()];
+
+    for index in 0..instrs.len() {
+        let redundant = match instrs[index] {
+            Instr::Jmp(ref label) | Instr::Jcc(_, ref label) => labels_next(instrs, index, label),
+            _ => false
+        };
+
+        if redundant {
+            live[index] = false;
+            changed = true;
+            continue;
+        }
+
+        let inverted = match (&instrs[index], instrs.get(index + 1)) {
+            (&Instr::Jcc(condition, ref over), Some(Instr::Jmp(target))) if labels_next(instrs, index + 1, over) => {
+                Some(Instr::Jcc(condition.negate(), target.clone()))
+            },
+            _ => None
+        };
+
+        // The inverted branch replaces the jump, and is looked at again in its place
+        if let Some(inverted) = inverted {
+            instrs[index + 1] = inverted;
+            live[index] = false;
+            changed = true;
+        }
+    }
+
+    remove_dead(instrs, &live);
+
+    changed
+}
+
+// Drops labels nothing jumps to, other than the ones naming functions, and
+// the code after a jump or return that no label makes reachable again
+fn remove_unreachable(instrs: &mut Vec<Instr>) -> bool {
+    let targets: HashSet<Label> = instrs.iter()
+        .filter_map(|instr| instr.jump_target().cloned())
+        .collect();
+
+    let length = instrs.len();
+    let mut after_global = false;
+    let mut reachable = true;
+
+    instrs.retain(|instr| {
+        let keep = match *instr {
+            Instr::Global(_) => true,
+            Instr::Label(ref label) => {
+                let keep = targets.contains(label) || after_global;
+
+                if keep {
+                    reachable = true;
+                }
+
+                keep
+            },
+            _ => reachable
+        };
+
+        if keep {
+            if !instr.falls_through() {
+                reachable = false;
+            }
+
+            after_global = matches!(*instr, Instr::Global(_));
+        }
+
+        keep
+    });
+
+    instrs.len() != length
+}
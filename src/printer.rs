@@ -0,0 +1,311 @@
+use visit::{self, Visitor, VisitorMut};
+use TranslationUnit;
+use FunctionDef;
+use Param;
+use Decl;
+use Stmt;
+use StmtKind;
+use Expr;
+use ExprKind;
+use UnaryOperator;
+use BinaryOperator;
+use Span;
+
+const INDENT: &str = "    ";
+
+// Binding strength of each expression form, loosest first. Parentheses are
+// only emitted where a child binds more loosely than its position requires.
+const ASSIGNMENT_PRECEDENCE: u8 = 1;
+const UNARY_PRECEDENCE: u8 = 9;
+const PRIMARY_PRECEDENCE: u8 = 10;
+
+fn binary_precedence(operator: BinaryOperator) -> u8 {
+    match operator {
+        BinaryOperator::LogicalOr => 2,
+        BinaryOperator::LogicalAnd => 3,
+        BinaryOperator::Equal | BinaryOperator::NotEqual => 4,
+        BinaryOperator::LessThan | BinaryOperator::LessThanOrEqual |
+        BinaryOperator::GreaterThan | BinaryOperator::GreaterThanOrEqual => 5,
+        BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => 6,
+        BinaryOperator::Addition | BinaryOperator::Subtraction => 7,
+        BinaryOperator::Multiplication | BinaryOperator::Division => 8
+    }
+}
+
+fn precedence(expr: &Expr) -> u8 {
+    match expr.kind {
+        ExprKind::Constant(_) | ExprKind::Variable(_, _) | ExprKind::Call(_, _) => PRIMARY_PRECEDENCE,
+        ExprKind::Unary(_, _) => UNARY_PRECEDENCE,
+        ExprKind::Binary(operator, _, _) => binary_precedence(operator),
+        ExprKind::Assignment(_, _) => ASSIGNMENT_PRECEDENCE,
+        ExprKind::ImplicitCast(_, ref operand) => precedence(operand)
+    }
+}
+
+// Whether an `else` printed after this statement would bind to an if inside it
+fn ends_in_open_if(stmt: &Stmt) -> bool {
+    match stmt.kind {
+        StmtKind::If(_, _, None) => true,
+        StmtKind::If(_, _, Some(ref else_branch)) => ends_in_open_if(else_branch),
+        StmtKind::While(_, ref body) => ends_in_open_if(body),
+        _ => false
+    }
+}
+
+fn print_param(param: &Param) -> String {
+    match param.name {
+        Some(ref name) => format!("{} {}", param.ty, name),
+        None => param.ty.to_string()
+    }
+}
+
+struct Printer {
+    buf: String,
+    depth: usize,
+    // Text that the next line starts with, used to keep `else if` on one line
+    prefix: String
+}
+
+impl Printer {
+    fn new() -> Printer {
+        Printer {
+            buf: String::new(),
+            depth: 0,
+            prefix: String::new()
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.buf.push_str(INDENT);
+        }
+
+        self.buf.push_str(&self.prefix);
+        self.buf.push_str(text);
+        self.buf.push('\n');
+        self.prefix.clear();
+    }
+
+    // Prints the body of an if, else or while under `header`. Blocks open on
+    // the header line and are left for the caller to close, so that an
+    // `else` can follow the closing brace; returns whether that is needed.
+    fn nested(&mut self, header: &str, stmt: &Stmt, braced: bool) -> bool {
+        match stmt.kind {
+            StmtKind::Compound(ref items) => {
+                self.line(&format!("{} {{", header));
+
+                self.depth += 1;
+                for item in items {
+                    self.visit_block_item(item);
+                }
+                self.depth -= 1;
+
+                true
+            },
+            StmtKind::If(_, _, _) if header.ends_with("else") => {
+                self.prefix = format!("{} ", header);
+                self.visit_stmt(stmt);
+
+                false
+            },
+            _ if braced => {
+                self.line(&format!("{} {{", header));
+
+                self.depth += 1;
+                self.visit_stmt(stmt);
+                self.depth -= 1;
+
+                true
+            },
+            _ => {
+                self.line(header);
+
+                self.depth += 1;
+                self.visit_stmt(stmt);
+                self.depth -= 1;
+
+                false
+            }
+        }
+    }
+}
+
+impl Visitor for Printer {
+    fn visit_translation_unit(&mut self, translation_unit: &TranslationUnit) {
+        for (index, function) in translation_unit.functions.iter().enumerate() {
+            if index > 0 {
+                self.buf.push('\n');
+            }
+
+            self.visit_function_def(function);
+        }
+    }
+
+    fn visit_function_def(&mut self, function: &FunctionDef) {
+        let params = if function.params.is_empty() {
+            "void".to_string()
+        } else {
+            function.params.iter().map(print_param).collect::<Vec<_>>().join(", ")
+        };
+
+        let signature = format!("{} {}({})", function.return_type, function.name, params);
+
+        if !function.is_definition() {
+            self.line(&format!("{};", signature));
+            return;
+        }
+
+        self.line(&format!("{} {{", signature));
+
+        self.depth += 1;
+        visit::walk_function_def(self, function);
+        self.depth -= 1;
+
+        self.line("}");
+    }
+
+    fn visit_decl(&mut self, decl: &Decl) {
+        let line = match decl.init {
+            Some(ref init) => format!("{} {} = {};", decl.ty, decl.name, print_expr_at(init, ASSIGNMENT_PRECEDENCE)),
+            None => format!("{} {};", decl.ty, decl.name)
+        };
+
+        self.line(&line);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        let line = match stmt.kind {
+            StmtKind::Return(Some(ref expr)) => format!("return {};", print_expr(expr)),
+            StmtKind::Return(None) => "return;".to_string(),
+            StmtKind::Expr(ref expr) => format!("{};", print_expr(expr)),
+            StmtKind::If(ref condition, ref then_branch, ref else_branch) => {
+                let header = format!("if ({})", print_expr(condition));
+
+                // Brace a then branch that would otherwise capture the else
+                let dangling = else_branch.is_some() && ends_in_open_if(then_branch);
+                let mut open = self.nested(&header, then_branch, dangling);
+
+                if let Some(ref else_branch) = *else_branch {
+                    let header = if open { "} else" } else { "else" };
+
+                    open = self.nested(header, else_branch, false);
+                }
+
+                if !open {
+                    return;
+                }
+
+                "}".to_string()
+            },
+            StmtKind::While(ref condition, ref body) => {
+                let header = format!("while ({})", print_expr(condition));
+
+                if !self.nested(&header, body, false) {
+                    return;
+                }
+
+                "}".to_string()
+            },
+            StmtKind::Compound(_) => {
+                self.line("{");
+
+                self.depth += 1;
+                visit::walk_stmt(self, stmt);
+                self.depth -= 1;
+
+                "}".to_string()
+            }
+        };
+
+        self.line(&line);
+    }
+}
+
+/// Prints an expression with the fewest parentheses that still parse back
+/// to the same tree.
+pub fn print_expr(expr: &Expr) -> String {
+    print_expr_at(expr, ASSIGNMENT_PRECEDENCE)
+}
+
+// Prints `expr` in a position that needs at least `minimum` binding strength.
+fn print_expr_at(expr: &Expr, minimum: u8) -> String {
+    let text = match expr.kind {
+        ExprKind::Constant(value) => value.to_string(),
+        ExprKind::Variable(ref name, _) => name.clone(),
+        ExprKind::Call(ref name, ref args) => {
+            let args: Vec<String> = args.iter().map(|arg| print_expr_at(arg, ASSIGNMENT_PRECEDENCE)).collect();
+
+            format!("{}({})", name, args.join(", "))
+        },
+        // Implicit conversions have no spelling, so the operand is printed as written
+        ExprKind::ImplicitCast(_, ref operand) => return print_expr_at(operand, minimum),
+        ExprKind::Assignment(ref target, ref value) => {
+            format!("{} = {}", print_expr_at(target, UNARY_PRECEDENCE), print_expr_at(value, ASSIGNMENT_PRECEDENCE))
+        },
+        ExprKind::Unary(operator, ref operand) => {
+            let operand = print_expr_at(operand, UNARY_PRECEDENCE);
+
+            // Keep `- -x` from printing as the decrement operator
+            if operator == UnaryOperator::Negation && operand.starts_with('-') {
+                format!("- {}", operand)
+            } else {
+                format!("{}{}", operator.symbol(), operand)
+            }
+        },
+        ExprKind::Binary(operator, ref lhs, ref rhs) => {
+            let precedence = binary_precedence(operator);
+
+            // All binary operators are left associative, so a right operand of
+            // equal precedence has to keep its parentheses
+            format!("{} {} {}", print_expr_at(lhs, precedence), operator.symbol(), print_expr_at(rhs, precedence + 1))
+        }
+    };
+
+    if precedence(expr) < minimum {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+pub fn print(translation_unit: &TranslationUnit) -> String {
+    let mut printer = Printer::new();
+
+    printer.visit_translation_unit(translation_unit);
+
+    printer.buf
+}
+
+struct SpanEraser;
+
+impl VisitorMut for SpanEraser {
+    fn visit_function_def_mut(&mut self, function: &mut FunctionDef) {
+        function.span = Span::default();
+        visit::walk_function_def_mut(self, function);
+    }
+
+    fn visit_param_mut(&mut self, param: &mut Param) {
+        param.span = Span::default();
+    }
+
+    fn visit_decl_mut(&mut self, decl: &mut Decl) {
+        decl.span = Span::default();
+        visit::walk_decl_mut(self, decl);
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        stmt.span = Span::default();
+        visit::walk_stmt_mut(self, stmt);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        expr.span = Span::default();
+        visit::walk_expr_mut(self, expr);
+    }
+}
+
+/// Resets every span in the tree, so that a reprinted and reparsed tree can
+/// be compared structurally with the original.
+pub fn erase_spans(translation_unit: &mut TranslationUnit) {
+    SpanEraser.visit_translation_unit_mut(translation_unit);
+}
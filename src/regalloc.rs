@@ -0,0 +1,498 @@
+//! Linear scan register allocation, after Poletto and Sarkar.
+//!
+//! Works on functions out of SSA form. Each virtual register gets a single
+//! live interval over a linear numbering of the instructions, covering every
+//! point where it might be live. Copies between registers whose intervals
+//! only touch are coalesced first, so both sides share one register. Values
+//! live across a call prefer callee-saved registers; any left in
+//! caller-saved ones are reported per call, for the backend to save and
+//! restore around it.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::iter;
+use std::mem;
+
+use ir::{BlockId, Function, Instr, Operand, VReg};
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Hash)]
+pub enum Location<R> {
+    Reg(R),
+    /// A spill slot, numbered from 0.
+    Stack(usize)
+}
+
+#[derive(Debug,Clone)]
+pub struct Allocation<R> {
+    locations: HashMap<VReg, Location<R>>,
+    pub spill_slots: usize,
+    /// Callee-saved registers that were handed out, in the order given.
+    pub callee_saved_used: Vec<R>,
+    // Caller-saved registers holding a value that is needed after each call
+    saved_at_calls: HashMap<(BlockId, usize), Vec<R>>
+}
+
+impl<R: Copy> Allocation<R> {
+    /// Where a register lives, or `None` if it is never used.
+    pub fn location(&self, reg: VReg) -> Option<Location<R>> {
+        self.locations.get(&reg).cloned()
+    }
+
+    /// Caller-saved registers that must survive the call at `index` in `block`.
+    pub fn saved_at_call(&self, block: BlockId, index: usize) -> &[R] {
+        self.saved_at_calls.get(&(block, index)).map_or(&[], |registers| &registers[..])
+    }
+}
+
+// A set of registers with a bit for each. Liveness has to consider every
+// register at every block, which is too slow to do one hash at a time.
+#[derive(Debug,PartialEq,Clone)]
+struct RegSet {
+    words: Vec<u64>
+}
+
+impl RegSet {
+    fn new(size: usize) -> RegSet {
+        RegSet {
+            words: vec![0; size.div_ceil(64)]
+        }
+    }
+
+    fn contains(&self, reg: VReg) -> bool {
+        self.words[reg.0 / 64] & (1 << (reg.0 % 64)) != 0
+    }
+
+    fn insert(&mut self, reg: VReg) {
+        self.words[reg.0 / 64] |= 1 << (reg.0 % 64);
+    }
+
+    fn union_with(&mut self, other: &RegSet) {
+        for (word, &other) in self.words.iter_mut().zip(&other.words) {
+            *word |= other;
+        }
+    }
+
+    fn difference_with(&mut self, other: &RegSet) {
+        for (word, &other) in self.words.iter_mut().zip(&other.words) {
+            *word &= !other;
+        }
+    }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = VReg> + 'a {
+        self.words.iter().enumerate().flat_map(|(index, &word)| {
+            let mut word = word;
+
+            iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+
+                Some(VReg(index * 64 + bit))
+            })
+        })
+    }
+}
+
+// Registers read by each block before it assigns them, and everything it assigns
+fn uses_and_defs(function: &Function) -> (Vec<RegSet>, Vec<RegSet>) {
+    let mut uses = Vec::new();
+    let mut defs = Vec::new();
+
+    for block in &function.blocks {
+        let mut block_uses = RegSet::new(function.vreg_count);
+        let mut block_defs = RegSet::new(function.vreg_count);
+
+        for instr in &block.instrs {
+            for reg in instr.uses() {
+                if !block_defs.contains(reg) {
+                    block_uses.insert(reg);
+                }
+            }
+
+            if let Some(dst) = instr.dst() {
+                block_defs.insert(dst);
+            }
+        }
+
+        for reg in block.terminator.operands().into_iter().filter_map(Operand::as_reg) {
+            if !block_defs.contains(reg) {
+                block_uses.insert(reg);
+            }
+        }
+
+        uses.push(block_uses);
+        defs.push(block_defs);
+    }
+
+    (uses, defs)
+}
+
+// The registers live on entry to and on exit from each block
+fn liveness(function: &Function) -> (Vec<RegSet>, Vec<RegSet>) {
+    let (uses, defs) = uses_and_defs(function);
+    let mut live_in = vec![RegSet::new(function.vreg_count); function.blocks.len()];
+    let mut live_out = live_in.clone();
+
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+
+        for id in function.block_ids().rev() {
+            let mut out = RegSet::new(function.vreg_count);
+
+            for successor in function.successors(id) {
+                out.union_with(&live_in[successor.0]);
+            }
+
+            let mut new_in = out.clone();
+            new_in.difference_with(&defs[id.0]);
+            new_in.union_with(&uses[id.0]);
+
+            if new_in != live_in[id.0] || out != live_out[id.0] {
+                live_in[id.0] = new_in;
+                live_out[id.0] = out;
+                changed = true;
+            }
+        }
+    }
+
+    (live_in, live_out)
+}
+
+/// The registers live on exit from each block.
+pub fn live_out(function: &Function) -> Vec<HashSet<VReg>> {
+    liveness(function).1.iter().map(|live| live.iter().collect()).collect()
+}
+
+#[derive(Debug,Clone,Copy)]
+struct Interval {
+    start: usize,
+    end: usize
+}
+
+impl Interval {
+    fn extend(&mut self, position: usize) {
+        self.start = self.start.min(position);
+        self.end = self.end.max(position);
+    }
+}
+
+// Where a call instruction is, both in its block and in the numbering
+#[derive(Debug,Clone,Copy)]
+struct CallSite {
+    block: BlockId,
+    index: usize,
+    position: usize
+}
+
+// Numbers each block's start, instructions and terminator in layout order,
+// and returns the interval of every register that appears, by register
+fn intervals(function: &Function) -> (Vec<Option<Interval>>, Vec<CallSite>) {
+    let (live_in, live_out) = liveness(function);
+    let mut intervals: Vec<Option<Interval>> = vec![None; function.vreg_count];
+    let mut calls = Vec::new();
+
+    let mark = |intervals: &mut Vec<Option<Interval>>, reg: VReg, position: usize| {
+        intervals[reg.0].get_or_insert(Interval { start: position, end: position }).extend(position);
+    };
+
+    // Parameters are all written on entry, so even one that is never used
+    // must not share a register with another
+    for &(param, _) in &function.params {
+        mark(&mut intervals, param, 0);
+        mark(&mut intervals, param, 1);
+    }
+
+    let mut position = 0;
+
+    for id in function.block_ids() {
+        let block = function.block(id);
+        let start = position;
+
+        for reg in live_in[id.0].iter() {
+            mark(&mut intervals, reg, start);
+        }
+
+        for (index, instr) in block.instrs.iter().enumerate() {
+            position += 1;
+
+            for reg in instr.uses() {
+                mark(&mut intervals, reg, position);
+            }
+
+            if let Some(dst) = instr.dst() {
+                mark(&mut intervals, dst, position);
+            }
+
+            if let Instr::Call { .. } = *instr {
+                calls.push(CallSite { block: id, index, position });
+            }
+        }
+
+        position += 1;
+
+        for reg in block.terminator.operands().into_iter().filter_map(Operand::as_reg) {
+            mark(&mut intervals, reg, position);
+        }
+
+        for reg in live_out[id.0].iter() {
+            mark(&mut intervals, reg, position);
+        }
+
+        position += 1;
+    }
+
+    (intervals, calls)
+}
+
+// Registers merged by coalescing, as a union-find whose classes each keep
+// the intervals of their members
+struct Classes {
+    parent: Vec<usize>,
+    // By root, the members' intervals as (start, end). Members of a class
+    // never overlap, so those that start later also end later.
+    members: Vec<BTreeSet<(usize, usize)>>
+}
+
+impl Classes {
+    fn new(intervals: &[Option<Interval>]) -> Classes {
+        Classes {
+            parent: (0..intervals.len()).collect(),
+            members: intervals.iter().map(|interval| interval.iter().map(|interval| (interval.start, interval.end)).collect()).collect()
+        }
+    }
+
+    fn find(&mut self, reg: VReg) -> VReg {
+        let mut root = reg.0;
+
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+
+        let mut current = reg.0;
+
+        while current != root {
+            current = mem::replace(&mut self.parent[current], root);
+        }
+
+        VReg(root)
+    }
+
+    // Intervals that only meet at one instruction, which reads one and writes
+    // the other, can share a register. Each member of the smaller class only
+    // has to be checked against the member of the larger one that starts
+    // last before it ends.
+    fn interferes(&self, a: VReg, b: VReg) -> bool {
+        let (small, large) = if self.members[a.0].len() <= self.members[b.0].len() {
+            (&self.members[a.0], &self.members[b.0])
+        } else {
+            (&self.members[b.0], &self.members[a.0])
+        };
+
+        small.iter().any(|&(start, end)| {
+            large.range(..(end, 0)).next_back().is_some_and(|&(_, other_end)| other_end > start)
+        })
+    }
+
+    // Moves the smaller set of members into the larger, but keeps `root`
+    fn merge(&mut self, root: VReg, merged: VReg) {
+        self.parent[merged.0] = root.0;
+
+        let mut members = mem::take(&mut self.members[merged.0]);
+
+        if members.len() > self.members[root.0].len() {
+            mem::swap(&mut members, &mut self.members[root.0]);
+        }
+
+        self.members[root.0].extend(members);
+    }
+}
+
+/// Merges the two sides of copies whose intervals do not overlap into one
+/// register, and drops the copies that become no-ops.
+pub fn coalesce(function: &mut Function) {
+    let (intervals, _) = intervals(function);
+
+    let mut classes = Classes::new(&intervals);
+    let mut merged_any = false;
+    let params: HashSet<VReg> = function.params.iter().map(|&(reg, _)| reg).collect();
+
+    for block in &function.blocks {
+        for instr in &block.instrs {
+            let (dst, src) = match *instr {
+                Instr::Copy { dst, src: Operand::Reg(src) } => (dst, src),
+                _ => continue
+            };
+
+            let (dst, src) = (classes.find(dst), classes.find(src));
+
+            // Parameters keep their own registers so the function signature stays put
+            if dst == src || (params.contains(&dst) && params.contains(&src)) {
+                continue;
+            }
+
+            if !classes.interferes(dst, src) {
+                let (root, merged) = if params.contains(&dst) { (dst, src) } else { (src, dst) };
+
+                classes.merge(root, merged);
+                merged_any = true;
+            }
+        }
+    }
+
+    if !merged_any {
+        return;
+    }
+
+    let mut rename = |reg: &mut VReg| *reg = classes.find(*reg);
+
+    for block in &mut function.blocks {
+        for instr in &mut block.instrs {
+            if let Some(dst) = instr.dst_mut() {
+                rename(dst);
+            }
+
+            for operand in instr.operands_mut() {
+                if let Operand::Reg(ref mut reg) = *operand {
+                    rename(reg);
+                }
+            }
+        }
+
+        for operand in block.terminator.operands_mut() {
+            if let Operand::Reg(ref mut reg) = *operand {
+                rename(reg);
+            }
+        }
+
+        block.instrs.retain(|instr| match *instr {
+            Instr::Copy { dst, src } => src != Operand::Reg(dst),
+            _ => true
+        });
+    }
+}
+
+/// Assigns every register of a function to one of the given machine
+/// registers or a spill slot. The function should already be coalesced.
+pub fn allocate<R: Copy + Eq>(function: &Function, caller_saved: &[R], callee_saved: &[R]) -> Allocation<R> {
+    let (intervals, calls) = intervals(function);
+
+    let mut order: Vec<(VReg, Interval)> = intervals.iter().enumerate()
+        .filter_map(|(index, interval)| interval.map(|interval| (VReg(index), interval)))
+        .collect();
+    order.sort_by_key(|&(reg, interval)| (interval.start, reg));
+
+    // A copy's destination prefers its source's register, so the copy can go
+    let mut hints: HashMap<VReg, VReg> = HashMap::new();
+
+    for block in &function.blocks {
+        for instr in &block.instrs {
+            if let Instr::Copy { dst, src: Operand::Reg(src) } = *instr {
+                hints.entry(dst).or_insert(src);
+            }
+        }
+    }
+
+    // Calls are numbered in order, so only the first after an interval starts can be inside it
+    let crosses_call = |interval: &Interval| {
+        let next = calls.partition_point(|call| call.position <= interval.start);
+        calls.get(next).is_some_and(|call| call.position < interval.end)
+    };
+
+    let mut locations: HashMap<VReg, Location<R>> = HashMap::new();
+    let mut active: Vec<(VReg, Interval, R)> = Vec::new();
+    let mut spill_slots = 0;
+
+    for &(reg, interval) in &order {
+        active.retain(|&(_, active_interval, _)| active_interval.end > interval.start);
+
+        let is_free = |register: &R, active: &[(VReg, Interval, R)]| !active.iter().any(|&(_, _, used)| used == *register);
+
+        let preferred: Vec<R> = if crosses_call(&interval) {
+            callee_saved.iter().chain(caller_saved.iter()).cloned().collect()
+        } else {
+            caller_saved.iter().chain(callee_saved.iter()).cloned().collect()
+        };
+
+        let hinted = hints.get(&reg)
+            .and_then(|src| match locations.get(src) {
+                Some(&Location::Reg(register)) => Some(register),
+                _ => None
+            })
+            .filter(|register| is_free(register, &active));
+
+        let free = hinted.or_else(|| preferred.into_iter().find(|register| is_free(register, &active)));
+
+        match free {
+            Some(register) => {
+                locations.insert(reg, Location::Reg(register));
+                active.push((reg, interval, register));
+            },
+            None => {
+                // Spill whichever of this and the active intervals ends last
+                let (index, &(victim, victim_interval, register)) = active.iter().enumerate()
+                    .max_by_key(|&(_, &(_, active_interval, _))| active_interval.end)
+                    .expect("no registers to allocate");
+
+                if victim_interval.end > interval.end {
+                    locations.insert(victim, Location::Stack(spill_slots));
+                    locations.insert(reg, Location::Reg(register));
+                    active[index] = (reg, interval, register);
+                } else {
+                    locations.insert(reg, Location::Stack(spill_slots));
+                }
+
+                spill_slots += 1;
+            }
+        }
+    }
+
+    let callee_saved_used = callee_saved.iter().cloned()
+        .filter(|register| locations.values().any(|&location| location == Location::Reg(*register)))
+        .collect();
+
+    // Sweeps the calls and the intervals in caller-saved registers together,
+    // keeping those that have started and not yet ended. Registers are never
+    // shared by overlapping intervals, so few are kept at once.
+    let in_caller_saved: Vec<(Interval, R)> = order.iter()
+        .filter_map(|&(reg, interval)| match locations.get(&reg) {
+            Some(&Location::Reg(register)) if caller_saved.contains(&register) => Some((interval, register)),
+            _ => None
+        })
+        .collect();
+
+    let mut saved_at_calls = HashMap::new();
+    let mut live: Vec<(Interval, R)> = Vec::new();
+    let mut next = 0;
+
+    for call in &calls {
+        while next < in_caller_saved.len() && in_caller_saved[next].0.start < call.position {
+            live.push(in_caller_saved[next]);
+            next += 1;
+        }
+
+        live.retain(|&(interval, _)| interval.end > call.position);
+
+        let mut saved: Vec<R> = Vec::new();
+
+        for &(_, register) in &live {
+            if !saved.contains(&register) {
+                saved.push(register);
+            }
+        }
+
+        // Keep the output stable regardless of allocation order
+        saved.sort_by_key(|register| caller_saved.iter().position(|other| other == register));
+        saved_at_calls.insert((call.block, call.index), saved);
+    }
+
+    Allocation {
+        locations,
+        spill_slots,
+        callee_saved_used,
+        saved_at_calls
+    }
+}
@@ -0,0 +1,213 @@
+//! RISC-V 64 instruction selection for RV64IM, following the standard
+//! calling convention.
+//!
+//! The argument registers are not allocated. `s0` is the frame pointer, `t4`
+//! forms addresses too far from the stack pointer for an offset, and `t5`
+//! and `t6` hold spilled values and constants while an instruction needs
+//! them.
+
+use std::fmt;
+
+use backend::{self, Abi, Assembly, Base, Isa, Source};
+use ir::{BinaryOp, Module, UnaryOp};
+use Type;
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Hash)]
+pub struct Reg(u8);
+
+const NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2",
+    "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5",
+    "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7",
+    "s8", "s9", "s10", "s11", "t3", "t4", "t5", "t6"
+];
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", NAMES[self.0 as usize])
+    }
+}
+
+const T4: Reg = Reg(29);
+const T5: Reg = Reg(30);
+
+// Whether a value fits the signed 12-bit immediate of `addi` and memory offsets
+fn fits_imm12(value: i64) -> bool {
+    (-2048..2048).contains(&value)
+}
+
+fn base(base: Base) -> &'static str {
+    match base {
+        Base::StackPointer => "sp",
+        Base::FramePointer => "s0"
+    }
+}
+
+// The address of a stack location, as `offset(register)`
+fn address(asm: &mut Assembly, from: Base, offset: i64) -> String {
+    if fits_imm12(offset) {
+        return format!("{}({})", offset, base(from));
+    }
+
+    asm.emit(&format!("li {}, {}", T4, offset));
+    asm.emit(&format!("add {}, {}, {}", T4, T4, base(from)));
+
+    format!("0({})", T4)
+}
+
+pub struct Riscv64;
+
+impl Isa for Riscv64 {
+    type Reg = Reg;
+
+    const ABI: Abi<Reg> = Abi {
+        argument_registers: &[Reg(10), Reg(11), Reg(12), Reg(13), Reg(14), Reg(15), Reg(16), Reg(17)],
+        return_register: Reg(10),
+        caller_saved: &[Reg(5), Reg(6), Reg(7), Reg(28)],
+        callee_saved: &[Reg(9), Reg(18), Reg(19), Reg(20), Reg(21), Reg(22), Reg(23), Reg(24), Reg(25), Reg(26), Reg(27)],
+        scratch: [Reg(30), Reg(31)]
+    };
+
+    fn prologue(asm: &mut Assembly, frame_size: usize) {
+        asm.emit("addi sp, sp, -16");
+        asm.emit("sd ra, 8(sp)");
+        asm.emit("sd s0, 0(sp)");
+        asm.emit("addi s0, sp, 16");
+
+        if fits_imm12(-(frame_size as i64)) {
+            asm.emit(&format!("addi sp, sp, -{}", frame_size));
+        } else {
+            Riscv64::load_immediate(asm, T5, frame_size as i64);
+            asm.emit(&format!("sub sp, sp, {}", T5));
+        }
+    }
+
+    fn epilogue(asm: &mut Assembly) {
+        asm.emit("addi sp, s0, -16");
+        asm.emit("ld ra, 8(sp)");
+        asm.emit("ld s0, 0(sp)");
+        asm.emit("addi sp, sp, 16");
+        asm.emit("ret");
+    }
+
+    // The frame pointer is the stack pointer at the call
+    fn incoming_argument(index: usize) -> i64 {
+        index as i64 * 8
+    }
+
+    // The assembler expands `li` to as many instructions as the value needs
+    fn load_immediate(asm: &mut Assembly, dst: Reg, value: i64) {
+        asm.emit(&format!("li {}, {}", dst, value));
+    }
+
+    fn load(asm: &mut Assembly, dst: Reg, from: Base, offset: i64) {
+        let address = address(asm, from, offset);
+        asm.emit(&format!("ld {}, {}", dst, address));
+    }
+
+    fn store(asm: &mut Assembly, src: Reg, to: Base, offset: i64) {
+        let address = address(asm, to, offset);
+        asm.emit(&format!("sd {}, {}", src, address));
+    }
+
+    fn mov(asm: &mut Assembly, dst: Reg, src: Reg) {
+        asm.emit(&format!("mv {}, {}", dst, src));
+    }
+
+    // Subtraction of a constant is done by adding its negation
+    fn accepts_immediate(op: BinaryOp, value: i64) -> bool {
+        match op {
+            BinaryOp::Add => fits_imm12(value),
+            BinaryOp::Sub => fits_imm12(-value),
+            _ => false
+        }
+    }
+
+    fn unary(asm: &mut Assembly, op: UnaryOp, dst: Reg, src: Reg) {
+        asm.emit(&format!("{} {}, {}", op.name(), dst, src));
+    }
+
+    fn binary(asm: &mut Assembly, op: BinaryOp, dst: Reg, lhs: Reg, rhs: Source<Reg>) {
+        let rhs = match rhs {
+            Source::Reg(rhs) => rhs,
+            Source::Imm(value) => {
+                let (mnemonic, value) = match op {
+                    BinaryOp::Add => ("addi", value),
+                    BinaryOp::Sub => ("addi", -value),
+                    BinaryOp::Shl => ("slli", value),
+                    BinaryOp::Sar => ("srai", value),
+                    BinaryOp::Shr => ("srli", value),
+                    _ => unreachable!("{} does not take an immediate", op.name())
+                };
+
+                return asm.emit(&format!("{} {}, {}, {}", mnemonic, dst, lhs, value));
+            }
+        };
+
+        // Only less than has an instruction, the other comparisons swap its
+        // operands or invert its result
+        let (mnemonic, lhs, rhs, invert) = match op {
+            BinaryOp::Eq | BinaryOp::Ne => {
+                asm.emit(&format!("sub {}, {}, {}", dst, lhs, rhs));
+                let mnemonic = if op == BinaryOp::Eq { "seqz" } else { "snez" };
+                return asm.emit(&format!("{} {}, {}", mnemonic, dst, dst));
+            },
+            BinaryOp::Lt => ("slt", lhs, rhs, false),
+            BinaryOp::Gt => ("slt", rhs, lhs, false),
+            BinaryOp::Le => ("slt", rhs, lhs, true),
+            BinaryOp::Ge => ("slt", lhs, rhs, true),
+            BinaryOp::ULt => ("sltu", lhs, rhs, false),
+            BinaryOp::UGt => ("sltu", rhs, lhs, false),
+            BinaryOp::ULe => ("sltu", rhs, lhs, true),
+            BinaryOp::UGe => ("sltu", lhs, rhs, true),
+            BinaryOp::Add => ("add", lhs, rhs, false),
+            BinaryOp::Sub => ("sub", lhs, rhs, false),
+            BinaryOp::Mul => ("mul", lhs, rhs, false),
+            BinaryOp::Div => ("div", lhs, rhs, false),
+            BinaryOp::UDiv => ("divu", lhs, rhs, false),
+            // Register shift amounts are taken modulo 64 too
+            BinaryOp::Shl => ("sll", lhs, rhs, false),
+            BinaryOp::Sar => ("sra", lhs, rhs, false),
+            BinaryOp::Shr => ("srl", lhs, rhs, false)
+        };
+
+        asm.emit(&format!("{} {}, {}, {}", mnemonic, dst, lhs, rhs));
+
+        if invert {
+            asm.emit(&format!("xori {}, {}, 1", dst, dst));
+        }
+    }
+
+    fn extend(asm: &mut Assembly, ty: Type, dst: Reg, src: Reg) {
+        match ty {
+            Type::Char => {
+                asm.emit(&format!("slli {}, {}, 56", dst, src));
+                asm.emit(&format!("srai {}, {}, 56", dst, dst));
+            },
+            Type::Int => asm.emit(&format!("sext.w {}, {}", dst, src)),
+            _ => {
+                asm.emit(&format!("slli {}, {}, 32", dst, src));
+                asm.emit(&format!("srli {}, {}, 32", dst, dst));
+            }
+        }
+    }
+
+    fn branch(asm: &mut Assembly, condition: Reg, if_zero: bool, label: &str) {
+        let mnemonic = if if_zero { "beqz" } else { "bnez" };
+
+        asm.emit(&format!("{} {}, {}", mnemonic, condition, label));
+    }
+
+    fn jump(asm: &mut Assembly, label: &str) {
+        asm.emit(&format!("j {}", label));
+    }
+
+    fn call(asm: &mut Assembly, function: &str) {
+        asm.emit(&format!("call {}", function));
+    }
+}
+
+/// Generates RISC-V 64 assembly for a module that is out of SSA form.
+pub fn generate(module: &Module) -> String {
+    backend::generate::<Riscv64>(module)
+}
@@ -0,0 +1,558 @@
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+use diagnostics::{Diagnostic, Warning};
+use constant;
+use TranslationUnit;
+use FunctionDef;
+use BlockItem;
+use Decl;
+use DeclId;
+use Stmt;
+use StmtKind;
+use Expr;
+use ExprKind;
+use UnaryOperator;
+use BinaryOperator;
+use Type;
+use Span;
+
+const INT_MAX: usize = i32::MAX as usize;
+const LONG_MAX: usize = i64::MAX as usize;
+
+#[derive(Debug,Clone)]
+struct FunctionSignature {
+    return_type: Type,
+    params: Vec<Type>,
+    defined: bool
+}
+
+#[derive(Debug,Clone,Copy)]
+struct Variable {
+    id: DeclId,
+    ty: Type,
+    span: Span,
+    is_param: bool
+}
+
+struct Analyzer {
+    functions: HashMap<String, FunctionSignature>,
+    scopes: Vec<HashMap<String, Variable>>,
+    used: HashSet<DeclId>,
+    diagnostics: Vec<Diagnostic>,
+    function_name: String,
+    return_type: Type
+}
+
+impl Analyzer {
+    fn new() -> Analyzer {
+        Analyzer {
+            functions: HashMap::new(),
+            scopes: Vec::new(),
+            used: HashSet::new(),
+            diagnostics: Vec::new(),
+            function_name: String::new(),
+            return_type: Type::Int
+        }
+    }
+
+    fn error<S: Into<String>>(&mut self, message: S, span: Span) {
+        self.diagnostics.push(Diagnostic::error(message, span));
+    }
+
+    fn warning<S: Into<String>>(&mut self, warning: Warning, message: S, span: Span) {
+        self.diagnostics.push(Diagnostic::warning(warning, message, span));
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().expect("no open scope");
+
+        let mut unused: Vec<(&String, &Variable)> = scope.iter()
+            .filter(|&(_, variable)| !self.used.contains(&variable.id))
+            .collect();
+
+        unused.sort_by_key(|&(_, variable)| variable.span.start);
+
+        for (name, variable) in unused {
+            let (warning, kind) = if variable.is_param {
+                (Warning::UnusedParameter, "parameter")
+            } else {
+                (Warning::UnusedVariable, "variable")
+            };
+
+            self.diagnostics.push(Diagnostic::warning(warning, format!("unused {} '{}'", kind, name), variable.span));
+        }
+    }
+
+    fn lookup_variable(&self, name: &str) -> Option<Variable> {
+        self.scopes.iter().rev().filter_map(|scope| scope.get(name)).next().cloned()
+    }
+
+    fn declare_variable(&mut self, name: &str, variable: Variable, span: Span) {
+        let scope = self.scopes.last_mut().expect("no open scope");
+
+        if scope.contains_key(name) {
+            self.diagnostics.push(Diagnostic::error(format!("redefinition of '{}'", name), span));
+            return;
+        }
+
+        scope.insert(name.to_string(), variable);
+    }
+
+    fn analyze_translation_unit(&mut self, translation_unit: &mut TranslationUnit) {
+        for function in &mut translation_unit.functions {
+            self.declare_function(function);
+
+            if function.is_definition() {
+                self.analyze_function(function);
+            }
+        }
+    }
+
+    fn declare_function(&mut self, function: &FunctionDef) {
+        let signature = FunctionSignature {
+            return_type: function.return_type,
+            params: function.param_types(),
+            defined: function.is_definition()
+        };
+
+        for param in &function.params {
+            if param.ty == Type::Void {
+                self.error("parameter has incomplete type 'void'", param.span);
+            }
+        }
+
+        let previous = self.functions.get(&function.name).cloned();
+
+        match previous {
+            Some(previous) => {
+                if previous.return_type != signature.return_type || previous.params != signature.params {
+                    self.error(format!("conflicting types for '{}'", function.name), function.span);
+                } else if previous.defined && signature.defined {
+                    self.error(format!("redefinition of '{}'", function.name), function.span);
+                } else if signature.defined {
+                    self.functions.insert(function.name.clone(), signature);
+                }
+            },
+            None => {
+                self.functions.insert(function.name.clone(), signature);
+            }
+        }
+    }
+
+    fn analyze_function(&mut self, function: &mut FunctionDef) {
+        self.function_name = function.name.clone();
+        self.return_type = function.return_type;
+        self.push_scope();
+
+        for param in &function.params {
+            match param.name {
+                Some(ref name) => {
+                    let variable = Variable {
+                        id: param.id,
+                        ty: param.ty,
+                        span: param.span,
+                        is_param: true
+                    };
+
+                    self.declare_variable(name, variable, param.span);
+                },
+                None => self.error("parameter name omitted in function definition", param.span)
+            }
+        }
+
+        let body = function.body.as_mut().expect("prototype has no body");
+
+        // The parameters and the outermost block share one scope
+        self.analyze_block_items(body);
+        self.pop_scope();
+
+        if function.return_type != Type::Void && function.name != "main" && block_falls_through(body) {
+            let message = format!("non-void function '{}' does not return a value in all control paths", function.name);
+            self.warning(Warning::ReturnType, message, function.span);
+        }
+    }
+
+    fn analyze_block_items(&mut self, items: &mut [BlockItem]) {
+        let mut reachable = true;
+
+        for item in items.iter_mut() {
+            self.analyze_block_item(item);
+
+            // Checked after analysis so that constant conditions have been folded
+            if let BlockItem::Stmt(ref stmt) = *item {
+                if !reachable {
+                    self.warning(Warning::UnreachableCode, "code will never be executed", stmt.span);
+                    // Only the first unreachable statement is reported
+                    reachable = true;
+                } else if !stmt_falls_through(stmt) {
+                    reachable = false;
+                }
+            }
+        }
+    }
+
+    fn analyze_block_item(&mut self, item: &mut BlockItem) {
+        match *item {
+            BlockItem::Decl(ref mut decl) => self.analyze_decl(decl),
+            BlockItem::Stmt(ref mut stmt) => self.analyze_stmt(stmt)
+        }
+    }
+
+    fn analyze_decl(&mut self, decl: &mut Decl) {
+        if decl.ty == Type::Void {
+            self.error(format!("variable '{}' has incomplete type 'void'", decl.name), decl.span);
+        }
+
+        let variable = Variable {
+            id: decl.id,
+            ty: decl.ty,
+            span: decl.span,
+            is_param: false
+        };
+
+        // A declaration is in scope from the end of its declarator, which includes its own initializer
+        self.declare_variable(&decl.name, variable, decl.span);
+
+        if let Some(ref mut init) = decl.init {
+            self.analyze_value(init);
+            self.convert_checked(init, decl.ty);
+        }
+    }
+
+    fn analyze_stmt(&mut self, stmt: &mut Stmt) {
+        match stmt.kind {
+            StmtKind::Return(Some(ref mut expr)) => {
+                self.analyze_value(expr);
+
+                if self.return_type == Type::Void {
+                    let message = format!("void function '{}' should not return a value", self.function_name);
+                    self.error(message, stmt.span);
+                } else {
+                    let return_type = self.return_type;
+                    self.convert_checked(expr, return_type);
+                }
+            },
+            StmtKind::Return(None) => {
+                if self.return_type != Type::Void {
+                    let message = format!("non-void function '{}' should return a value", self.function_name);
+                    self.error(message, stmt.span);
+                }
+            },
+            StmtKind::Expr(ref mut expr) => {
+                self.analyze_expr(expr);
+            },
+            StmtKind::Compound(ref mut items) => {
+                self.push_scope();
+                self.analyze_block_items(items);
+                self.pop_scope();
+            },
+            StmtKind::If(ref mut condition, ref mut then_branch, ref mut else_branch) => {
+                self.analyze_condition(condition);
+                self.analyze_stmt(then_branch);
+
+                if let Some(ref mut else_branch) = *else_branch {
+                    self.analyze_stmt(else_branch);
+                }
+            },
+            StmtKind::While(ref mut condition, ref mut body) => {
+                self.analyze_condition(condition);
+                self.analyze_stmt(body);
+            }
+        }
+    }
+
+    fn analyze_condition(&mut self, condition: &mut Expr) {
+        self.analyze_value(condition);
+
+        // An assignment wrapped in its own parentheses starts before its target
+        if let ExprKind::Assignment(ref target, _) = condition.kind {
+            if target.span.start == condition.span.start {
+                self.warning(Warning::Parentheses, "using the result of an assignment as a condition without parentheses", condition.span);
+            }
+        }
+    }
+
+    // Converts an assigned, passed or returned value, warning when it may not fit
+    fn convert_checked(&mut self, expr: &mut Expr, ty: Type) {
+        let from = expr.ty();
+
+        if from.size() > ty.size() && !constant_fits(expr, ty) {
+            let message = format!("implicit conversion loses integer precision: '{}' to '{}'", from, ty);
+            self.warning(Warning::Conversion, message, expr.span);
+        }
+
+        convert(expr, ty);
+    }
+
+    // Analyzes an expression whose value is used, which rules out void
+    fn analyze_value(&mut self, expr: &mut Expr) -> Type {
+        let ty = self.analyze_expr(expr);
+
+        if ty == Type::Void {
+            self.error("void value not ignored as it ought to be", expr.span);
+            expr.ty = Some(Type::Int);
+
+            return Type::Int;
+        }
+
+        ty
+    }
+
+    fn analyze_expr(&mut self, expr: &mut Expr) -> Type {
+        let span = expr.span;
+
+        let ty = match expr.kind {
+            ExprKind::Constant(value) => {
+                if value <= INT_MAX {
+                    Type::Int
+                } else {
+                    if value > LONG_MAX {
+                        self.error("integer literal is too large to be represented in any integer type", span);
+                    }
+
+                    Type::Long
+                }
+            },
+            ExprKind::Variable(ref name, ref mut decl_id) => {
+                match self.lookup_variable(name) {
+                    Some(variable) => {
+                        *decl_id = Some(variable.id);
+                        self.used.insert(variable.id);
+                        variable.ty
+                    },
+                    None if self.functions.contains_key(name) => {
+                        self.error(format!("function '{}' cannot be used as a value", name), span);
+                        Type::Int
+                    },
+                    None => {
+                        self.error(format!("use of undeclared identifier '{}'", name), span);
+                        Type::Int
+                    }
+                }
+            },
+            ExprKind::Assignment(ref mut target, ref mut value) => {
+                let target_type = self.analyze_value(target);
+
+                if !is_lvalue(target) {
+                    self.error("expression is not assignable", target.span);
+                }
+
+                self.analyze_value(value);
+                self.convert_checked(value, target_type);
+
+                target_type
+            },
+            ExprKind::Unary(operator, ref mut operand) => {
+                let operand_type = self.analyze_value(operand);
+
+                match operator {
+                    UnaryOperator::Negation | UnaryOperator::BitwiseComplement => {
+                        let promoted = promote(operand_type);
+                        convert(operand, promoted);
+
+                        promoted
+                    },
+                    UnaryOperator::LogicalNegation => Type::Int
+                }
+            },
+            ExprKind::Binary(operator, ref mut lhs, ref mut rhs) => {
+                let lhs_type = self.analyze_value(lhs);
+                let rhs_type = self.analyze_value(rhs);
+
+                match operator {
+                    BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr => Type::Int,
+                    // The operands of a shift are promoted separately and the left one decides the type
+                    BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => {
+                        let (lhs_type, rhs_type) = (promote(lhs_type), promote(rhs_type));
+
+                        if let Some(count) = constant::literal_value(rhs) {
+                            if count >= lhs_type.size() as i64 * 8 {
+                                self.warning(Warning::ShiftCountOverflow, "shift count >= width of type", rhs.span);
+                            }
+                        }
+
+                        convert(lhs, lhs_type);
+                        convert(rhs, rhs_type);
+
+                        lhs_type
+                    },
+                    _ => {
+                        let common = common_type(lhs_type, rhs_type);
+
+                        if operator == BinaryOperator::Division && constant::literal_value(rhs) == Some(0) {
+                            self.warning(Warning::DivisionByZero, "division by zero is undefined", rhs.span);
+                        }
+
+                        if is_comparison(operator) && common == Type::UnsignedInt && promote(lhs_type).is_signed() != promote(rhs_type).is_signed() {
+                            let signed = if promote(lhs_type).is_signed() { &**lhs } else { &**rhs };
+
+                            // Comparing with a non-negative constant is always fine
+                            if constant::literal_value(signed).is_none_or(|value| value < 0) {
+                                let message = format!("comparison of integers of different signs: '{}' and '{}'", lhs_type, rhs_type);
+                                self.warning(Warning::SignCompare, message, span);
+                            }
+                        }
+
+                        convert(lhs, common);
+                        convert(rhs, common);
+
+                        if is_comparison(operator) {
+                            Type::Int
+                        } else {
+                            common
+                        }
+                    }
+                }
+            },
+            ExprKind::Call(ref name, ref mut args) => {
+                for arg in args.iter_mut() {
+                    self.analyze_value(arg);
+                }
+
+                if self.lookup_variable(name).is_some() {
+                    self.error(format!("called object '{}' is not a function", name), span);
+                    Type::Int
+                } else {
+                    match self.functions.get(name).cloned() {
+                        Some(signature) => {
+                            if signature.params.len() != args.len() {
+                                let quantity = if args.len() > signature.params.len() { "many" } else { "few" };
+                                self.error(format!("too {} arguments to function call to '{}', expected {}, have {}", quantity, name, signature.params.len(), args.len()), span);
+                            }
+
+                            for (arg, &param_type) in args.iter_mut().zip(signature.params.iter()) {
+                                self.convert_checked(arg, param_type);
+                            }
+
+                            signature.return_type
+                        },
+                        None => {
+                            self.error(format!("call to undeclared function '{}'", name), span);
+                            Type::Int
+                        }
+                    }
+                }
+            },
+            ExprKind::ImplicitCast(ty, ref mut operand) => {
+                self.analyze_value(operand);
+                ty
+            }
+        };
+
+        expr.ty = Some(ty);
+
+        if let Some(folded) = fold(expr) {
+            if folded.overflow {
+                let message = format!("overflow in expression; result is {} with type '{}'", folded.value, ty);
+                self.warning(Warning::IntegerOverflow, message, span);
+            }
+        }
+
+        ty
+    }
+}
+
+fn is_lvalue(expr: &Expr) -> bool {
+    matches!(expr.kind, ExprKind::Variable(_, Some(_)))
+}
+
+fn is_comparison(operator: BinaryOperator) -> bool {
+    matches!(operator, BinaryOperator::LessThan | BinaryOperator::LessThanOrEqual |
+        BinaryOperator::GreaterThan | BinaryOperator::GreaterThanOrEqual |
+        BinaryOperator::Equal | BinaryOperator::NotEqual)
+}
+
+/// The integer promotion: anything narrower than int is computed as int.
+pub fn promote(ty: Type) -> Type {
+    match ty {
+        Type::Char => Type::Int,
+        ty => ty
+    }
+}
+
+/// The usual arithmetic conversions for a pair of operands.
+pub fn common_type(lhs: Type, rhs: Type) -> Type {
+    let (lhs, rhs) = (promote(lhs), promote(rhs));
+
+    if lhs == rhs {
+        lhs
+    } else if lhs == Type::Long || rhs == Type::Long {
+        // long can represent every unsigned int value, so it wins
+        Type::Long
+    } else {
+        Type::UnsignedInt
+    }
+}
+
+// Wraps `expr` in an implicit conversion to `ty` unless it already has that type
+fn convert(expr: &mut Expr, ty: Type) {
+    if expr.ty == Some(ty) {
+        return;
+    }
+
+    let span = expr.span;
+    let inner = mem::replace(expr, Expr::new(ExprKind::Constant(0), span));
+
+    *expr = Expr {
+        kind: ExprKind::ImplicitCast(ty, Box::new(inner)),
+        span,
+        ty: Some(ty)
+    };
+
+    fold(expr);
+}
+
+// Replaces a type checked expression whose operands are all literals with
+// the literal it evaluates to, so constants fold bottom-up as they are checked
+fn fold(expr: &mut Expr) -> Option<constant::Folded> {
+    if constant::literal_value(expr).is_some() {
+        return None;
+    }
+
+    let folded = constant::fold_node(expr)?;
+    *expr = constant::literal(folded.value, expr.ty(), expr.span);
+
+    Some(folded)
+}
+
+fn constant_fits(expr: &Expr, ty: Type) -> bool {
+    constant::literal_value(expr).is_some_and(|value| constant::fits(value as i128, ty))
+}
+
+/// Whether control can reach the end of a block without hitting a return.
+pub fn block_falls_through(items: &[BlockItem]) -> bool {
+    items.iter().filter_map(BlockItem::as_stmt).all(stmt_falls_through)
+}
+
+fn stmt_falls_through(stmt: &Stmt) -> bool {
+    match stmt.kind {
+        StmtKind::Return(_) => false,
+        StmtKind::Expr(_) => true,
+        StmtKind::Compound(ref items) => block_falls_through(items),
+        StmtKind::If(_, ref then_branch, Some(ref else_branch)) => stmt_falls_through(then_branch) || stmt_falls_through(else_branch),
+        StmtKind::If(_, _, None) => true,
+        // There is no break, so only a loop that can end can be left
+        StmtKind::While(ref condition, _) => constant::literal_value(condition).is_none_or(|value| value == 0)
+    }
+}
+
+/// Resolves names, checks types and annotates every expression with its type,
+/// inserting implicit conversions where C requires them. The tree is only
+/// fit for code generation when no error diagnostics are returned.
+///
+/// Every warning is reported; `WarningOptions::apply` picks the enabled ones.
+pub fn analyze(translation_unit: &mut TranslationUnit) -> Vec<Diagnostic> {
+    let mut analyzer = Analyzer::new();
+
+    analyzer.analyze_translation_unit(translation_unit);
+
+    let mut diagnostics = analyzer.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+
+    diagnostics
+}
@@ -0,0 +1,459 @@
+//! Stable JSON and S-expression dumps of the AST for external tools.
+//!
+//! Every node carries its byte span in the source. The JSON layout is
+//! versioned through the top level `version` field and only changes when
+//! that number is bumped:
+//!
+//! 1. Functions without parameters, declarations and return and
+//!    expression statements.
+//! 2. Types, parameters, prototypes, calls and declaration ids.
+//! 3. If, else and while statements and shifts.
+
+use TranslationUnit;
+use FunctionDef;
+use Param;
+use BlockItem;
+use Decl;
+use Stmt;
+use StmtKind;
+use Expr;
+use ExprKind;
+use Span;
+use Type;
+
+pub const FORMAT_VERSION: u64 = 3;
+
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum Format {
+    Json,
+    Sexpr
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "json" => Some(Format::Json),
+            "sexpr" | "sexp" => Some(Format::Sexpr),
+            _ => None
+        }
+    }
+}
+
+pub fn dump(translation_unit: &TranslationUnit, format: Format) -> String {
+    match format {
+        Format::Json => to_json(translation_unit),
+        Format::Sexpr => to_sexpr(translation_unit)
+    }
+}
+
+/// Checks that a JSON dump is in the layout this version writes, for tools
+/// that read dumps back, so that one in another layout is not misread.
+pub fn check_json_version(json: &str) -> Result<(), String> {
+    let version = json.trim_start()
+        .strip_prefix('{')
+        .map(str::trim_start)
+        .and_then(|rest| rest.strip_prefix("\"version\""))
+        .map(str::trim_start)
+        .and_then(|rest| rest.strip_prefix(':'))
+        .map(|rest| rest.trim_start().chars().take_while(char::is_ascii_digit).collect::<String>())
+        .and_then(|digits| digits.parse::<u64>().ok())
+        .ok_or_else(|| "the dump does not start with a version".to_string())?;
+
+    if version != FORMAT_VERSION {
+        return Err(format!("the dump is in version {} of the layout, but only version {} can be read", version, FORMAT_VERSION));
+    }
+
+    Ok(())
+}
+
+enum Json {
+    Null,
+    Number(u64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>)
+}
+
+pub fn to_json(translation_unit: &TranslationUnit) -> String {
+    let value = Json::Object(vec![
+        ("version", Json::Number(FORMAT_VERSION)),
+        ("node", node_name("TranslationUnit")),
+        ("functions", Json::Array(translation_unit.functions.iter().map(function_json).collect()))
+    ]);
+
+    let mut buf = String::new();
+    write_json(&value, 0, &mut buf);
+    buf.push('\n');
+
+    buf
+}
+
+fn node_name(name: &str) -> Json {
+    Json::String(name.to_string())
+}
+
+fn span_json(span: Span) -> Json {
+    Json::Object(vec![
+        ("start", Json::Number(span.start as u64)),
+        ("end", Json::Number(span.end as u64))
+    ])
+}
+
+fn type_json(ty: Type) -> Json {
+    Json::String(ty.name().to_string())
+}
+
+fn function_json(function: &FunctionDef) -> Json {
+    let body = match function.body {
+        Some(ref body) => Json::Array(body.iter().map(block_item_json).collect()),
+        None => Json::Null
+    };
+
+    Json::Object(vec![
+        ("node", node_name("FunctionDef")),
+        ("span", span_json(function.span)),
+        ("name", Json::String(function.name.clone())),
+        ("return_type", type_json(function.return_type)),
+        ("params", Json::Array(function.params.iter().map(param_json).collect())),
+        ("body", body)
+    ])
+}
+
+fn param_json(param: &Param) -> Json {
+    Json::Object(vec![
+        ("node", node_name("Param")),
+        ("span", span_json(param.span)),
+        ("id", Json::Number(param.id.0 as u64)),
+        ("name", param.name.as_ref().map_or(Json::Null, |name| Json::String(name.clone()))),
+        ("type", type_json(param.ty))
+    ])
+}
+
+fn block_item_json(item: &BlockItem) -> Json {
+    match *item {
+        BlockItem::Decl(ref decl) => decl_json(decl),
+        BlockItem::Stmt(ref stmt) => stmt_json(stmt)
+    }
+}
+
+fn decl_json(decl: &Decl) -> Json {
+    Json::Object(vec![
+        ("node", node_name("Decl")),
+        ("span", span_json(decl.span)),
+        ("id", Json::Number(decl.id.0 as u64)),
+        ("name", Json::String(decl.name.clone())),
+        ("type", type_json(decl.ty)),
+        ("init", decl.init.as_ref().map_or(Json::Null, expr_json))
+    ])
+}
+
+fn stmt_json(stmt: &Stmt) -> Json {
+    let (name, mut fields) = match stmt.kind {
+        StmtKind::Return(ref expr) => ("Return", vec![("expr", expr.as_ref().map_or(Json::Null, expr_json))]),
+        StmtKind::Expr(ref expr) => ("ExprStmt", vec![("expr", expr_json(expr))]),
+        StmtKind::Compound(ref items) => ("Compound", vec![("items", Json::Array(items.iter().map(block_item_json).collect()))]),
+        StmtKind::If(ref condition, ref then_branch, ref else_branch) => ("If", vec![
+            ("condition", expr_json(condition)),
+            ("then", stmt_json(then_branch)),
+            ("else", else_branch.as_ref().map_or(Json::Null, |else_branch| stmt_json(else_branch)))
+        ]),
+        StmtKind::While(ref condition, ref body) => ("While", vec![
+            ("condition", expr_json(condition)),
+            ("body", stmt_json(body))
+        ])
+    };
+
+    fields.insert(0, ("node", node_name(name)));
+    fields.insert(1, ("span", span_json(stmt.span)));
+
+    Json::Object(fields)
+}
+
+fn expr_json(expr: &Expr) -> Json {
+    let mut fields = Vec::new();
+
+    match expr.kind {
+        ExprKind::Constant(value) => {
+            fields.push(("node", node_name("Constant")));
+            fields.push(("value", Json::Number(value as u64)));
+        },
+        ExprKind::Variable(ref name, decl_id) => {
+            fields.push(("node", node_name("Variable")));
+            fields.push(("name", Json::String(name.clone())));
+            fields.push(("decl", decl_id.map_or(Json::Null, |id| Json::Number(id.0 as u64))));
+        },
+        ExprKind::Call(ref name, ref args) => {
+            fields.push(("node", node_name("Call")));
+            fields.push(("function", Json::String(name.clone())));
+            fields.push(("args", Json::Array(args.iter().map(expr_json).collect())));
+        },
+        ExprKind::ImplicitCast(ty, ref operand) => {
+            fields.push(("node", node_name("ImplicitCast")));
+            fields.push(("to", type_json(ty)));
+            fields.push(("operand", expr_json(operand)));
+        },
+        ExprKind::Assignment(ref target, ref value) => {
+            fields.push(("node", node_name("Assignment")));
+            fields.push(("target", expr_json(target)));
+            fields.push(("value", expr_json(value)));
+        },
+        ExprKind::Unary(operator, ref operand) => {
+            fields.push(("node", node_name("Unary")));
+            fields.push(("operator", Json::String(operator.symbol().to_string())));
+            fields.push(("operand", expr_json(operand)));
+        },
+        ExprKind::Binary(operator, ref lhs, ref rhs) => {
+            fields.push(("node", node_name("Binary")));
+            fields.push(("operator", Json::String(operator.symbol().to_string())));
+            fields.push(("lhs", expr_json(lhs)));
+            fields.push(("rhs", expr_json(rhs)));
+        }
+    }
+
+    fields.insert(1, ("span", span_json(expr.span)));
+
+    if let Some(ty) = expr.ty {
+        fields.insert(2, ("type", type_json(ty)));
+    }
+
+    Json::Object(fields)
+}
+
+fn write_json(value: &Json, depth: usize, buf: &mut String) {
+    match *value {
+        Json::Null => buf.push_str("null"),
+        Json::Number(number) => buf.push_str(&number.to_string()),
+        Json::String(ref string) => write_json_string(string, buf),
+        Json::Array(ref elements) => {
+            if elements.is_empty() {
+                buf.push_str("[]");
+                return;
+            }
+
+            buf.push_str("[\n");
+
+            for (index, element) in elements.iter().enumerate() {
+                indent(depth + 1, buf);
+                write_json(element, depth + 1, buf);
+
+                if index + 1 < elements.len() {
+                    buf.push(',');
+                }
+
+                buf.push('\n');
+            }
+
+            indent(depth, buf);
+            buf.push(']');
+        },
+        Json::Object(ref fields) => {
+            buf.push_str("{\n");
+
+            for (index, &(key, ref field)) in fields.iter().enumerate() {
+                indent(depth + 1, buf);
+                write_json_string(key, buf);
+                buf.push_str(": ");
+                write_json(field, depth + 1, buf);
+
+                if index + 1 < fields.len() {
+                    buf.push(',');
+                }
+
+                buf.push('\n');
+            }
+
+            indent(depth, buf);
+            buf.push('}');
+        }
+    }
+}
+
+fn write_json_string(string: &str, buf: &mut String) {
+    buf.push('"');
+
+    for c in string.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            c if (c as u32) < 0x20 => buf.push_str(&format!("\\u{:04x}", c as u32)),
+            c => buf.push(c)
+        }
+    }
+
+    buf.push('"');
+}
+
+fn indent(depth: usize, buf: &mut String) {
+    for _ in 0..depth {
+        buf.push_str("  ");
+    }
+}
+
+enum Sexpr {
+    Atom(String),
+    List(Vec<Sexpr>)
+}
+
+fn atom<T: ToString>(value: T) -> Sexpr {
+    Sexpr::Atom(value.to_string())
+}
+
+pub fn to_sexpr(translation_unit: &TranslationUnit) -> String {
+    let mut elements = vec![atom("translation-unit")];
+    elements.extend(translation_unit.functions.iter().map(function_sexpr));
+
+    let mut buf = String::new();
+    write_sexpr(&Sexpr::List(elements), 0, &mut buf);
+    buf.push('\n');
+
+    buf
+}
+
+fn span_sexpr(span: Span) -> Sexpr {
+    Sexpr::List(vec![atom("span"), atom(span.start), atom(span.end)])
+}
+
+fn type_sexpr(ty: Type) -> Sexpr {
+    atom(ty.name().replace(' ', "-"))
+}
+
+fn function_sexpr(function: &FunctionDef) -> Sexpr {
+    let head = if function.is_definition() { "function-def" } else { "function-decl" };
+
+    let mut params = vec![atom("params")];
+    params.extend(function.params.iter().map(param_sexpr));
+
+    let mut elements = vec![atom(head), atom(&function.name), type_sexpr(function.return_type), span_sexpr(function.span), Sexpr::List(params)];
+    elements.extend(function.body.iter().flatten().map(block_item_sexpr));
+
+    Sexpr::List(elements)
+}
+
+fn param_sexpr(param: &Param) -> Sexpr {
+    let mut elements = vec![atom("param")];
+
+    if let Some(ref name) = param.name {
+        elements.push(atom(name));
+    }
+
+    elements.push(type_sexpr(param.ty));
+    elements.push(atom(format!("#{}", param.id.0)));
+
+    Sexpr::List(elements)
+}
+
+fn block_item_sexpr(item: &BlockItem) -> Sexpr {
+    match *item {
+        BlockItem::Decl(ref decl) => decl_sexpr(decl),
+        BlockItem::Stmt(ref stmt) => stmt_sexpr(stmt)
+    }
+}
+
+fn decl_sexpr(decl: &Decl) -> Sexpr {
+    let mut elements = vec![atom("decl"), atom(&decl.name), type_sexpr(decl.ty), atom(format!("#{}", decl.id.0)), span_sexpr(decl.span)];
+
+    if let Some(ref init) = decl.init {
+        elements.push(expr_sexpr(init));
+    }
+
+    Sexpr::List(elements)
+}
+
+fn stmt_sexpr(stmt: &Stmt) -> Sexpr {
+    let elements = match stmt.kind {
+        StmtKind::Return(ref expr) => {
+            let mut elements = vec![atom("return"), span_sexpr(stmt.span)];
+            elements.extend(expr.iter().map(expr_sexpr));
+            elements
+        },
+        StmtKind::Expr(ref expr) => vec![atom("expr-stmt"), span_sexpr(stmt.span), expr_sexpr(expr)],
+        StmtKind::Compound(ref items) => {
+            let mut elements = vec![atom("compound"), span_sexpr(stmt.span)];
+            elements.extend(items.iter().map(block_item_sexpr));
+            elements
+        },
+        StmtKind::If(ref condition, ref then_branch, ref else_branch) => {
+            let mut elements = vec![atom("if"), span_sexpr(stmt.span), expr_sexpr(condition), stmt_sexpr(then_branch)];
+            elements.extend(else_branch.iter().map(|else_branch| stmt_sexpr(else_branch)));
+            elements
+        },
+        StmtKind::While(ref condition, ref body) => {
+            vec![atom("while"), span_sexpr(stmt.span), expr_sexpr(condition), stmt_sexpr(body)]
+        }
+    };
+
+    Sexpr::List(elements)
+}
+
+fn expr_sexpr(expr: &Expr) -> Sexpr {
+    let elements = match expr.kind {
+        ExprKind::Constant(value) => vec![atom("constant"), atom(value), span_sexpr(expr.span)],
+        ExprKind::Variable(ref name, decl_id) => {
+            let mut elements = vec![atom("variable"), atom(name)];
+            elements.extend(decl_id.map(|id| atom(format!("#{}", id.0))));
+            elements.push(span_sexpr(expr.span));
+            elements
+        },
+        ExprKind::Call(ref name, ref args) => {
+            let mut elements = vec![atom("call"), atom(name), span_sexpr(expr.span)];
+            elements.extend(args.iter().map(expr_sexpr));
+            elements
+        },
+        ExprKind::ImplicitCast(ty, ref operand) => {
+            vec![atom("implicit-cast"), type_sexpr(ty), span_sexpr(expr.span), expr_sexpr(operand)]
+        },
+        ExprKind::Assignment(ref target, ref value) => {
+            vec![atom("assignment"), span_sexpr(expr.span), expr_sexpr(target), expr_sexpr(value)]
+        },
+        ExprKind::Unary(operator, ref operand) => {
+            vec![atom("unary"), atom(operator.symbol()), span_sexpr(expr.span), expr_sexpr(operand)]
+        },
+        ExprKind::Binary(operator, ref lhs, ref rhs) => {
+            vec![atom("binary"), atom(operator.symbol()), span_sexpr(expr.span), expr_sexpr(lhs), expr_sexpr(rhs)]
+        }
+    };
+
+    Sexpr::List(elements)
+}
+
+// Lists whose elements are all atoms or spans stay on one line; anything
+// with nested nodes puts each node on its own indented line.
+fn write_sexpr(sexpr: &Sexpr, depth: usize, buf: &mut String) {
+    let elements = match *sexpr {
+        Sexpr::Atom(ref value) => {
+            buf.push_str(value);
+            return;
+        },
+        Sexpr::List(ref elements) => elements
+    };
+
+    buf.push('(');
+
+    let mut first = true;
+
+    for element in elements {
+        if is_flat(element) {
+            if !first {
+                buf.push(' ');
+            }
+
+            write_sexpr(element, depth + 1, buf);
+        } else {
+            buf.push('\n');
+            indent(depth + 1, buf);
+            write_sexpr(element, depth + 1, buf);
+        }
+
+        first = false;
+    }
+
+    buf.push(')');
+}
+
+fn is_flat(sexpr: &Sexpr) -> bool {
+    match *sexpr {
+        Sexpr::Atom(_) => true,
+        Sexpr::List(ref elements) => elements.iter().all(|element| matches!(*element, Sexpr::Atom(_)))
+    }
+}
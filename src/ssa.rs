@@ -0,0 +1,463 @@
+//! Conversion of IR functions into and out of static single assignment form.
+//!
+//! Construction follows Cytron et al.: phis go on the iterated dominance
+//! frontier of every block assigning a register that is live across blocks,
+//! then a walk of the dominator tree renames each assignment to a fresh
+//! register. Dominators are computed with the iterative algorithm of Cooper,
+//! Harvey and Kennedy.
+
+use std::collections::{HashMap, HashSet};
+
+use ir::{Block, BlockId, Function, Instr, Operand, Terminator, VReg};
+
+/// The dominator tree of a function's reachable blocks.
+#[derive(Debug,Clone)]
+pub struct Dominators {
+    idom: Vec<Option<BlockId>>,
+    children: Vec<Vec<BlockId>>,
+    // Position of each block in reverse postorder, to answer queries quickly
+    order: Vec<usize>
+}
+
+impl Dominators {
+    pub fn compute(function: &Function) -> Dominators {
+        let rpo = function.reverse_postorder();
+        let predecessors = function.predecessors();
+
+        let mut order = vec![usize::MAX; function.blocks.len()];
+
+        for (index, id) in rpo.iter().enumerate() {
+            order[id.0] = index;
+        }
+
+        let mut idom: Vec<Option<BlockId>> = vec![None; function.blocks.len()];
+        idom[Function::ENTRY.0] = Some(Function::ENTRY);
+
+        let intersect = |idom: &[Option<BlockId>], mut a: BlockId, mut b: BlockId| {
+            while a != b {
+                while order[a.0] > order[b.0] {
+                    a = idom[a.0].unwrap();
+                }
+
+                while order[b.0] > order[a.0] {
+                    b = idom[b.0].unwrap();
+                }
+            }
+
+            a
+        };
+
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for &id in rpo.iter().skip(1) {
+                let mut new_idom = None;
+
+                for &predecessor in &predecessors[id.0] {
+                    if idom[predecessor.0].is_none() {
+                        continue;
+                    }
+
+                    new_idom = Some(match new_idom {
+                        None => predecessor,
+                        Some(current) => intersect(&idom, predecessor, current)
+                    });
+                }
+
+                if new_idom != idom[id.0] {
+                    idom[id.0] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut children = vec![Vec::new(); function.blocks.len()];
+
+        for &id in rpo.iter().skip(1) {
+            children[idom[id.0].unwrap().0].push(id);
+        }
+
+        Dominators {
+            idom,
+            children,
+            order
+        }
+    }
+
+    /// The immediate dominator of a block, or `None` for the entry.
+    pub fn idom(&self, id: BlockId) -> Option<BlockId> {
+        match self.idom[id.0] {
+            Some(idom) if idom != id => Some(idom),
+            _ => None
+        }
+    }
+
+    /// Blocks immediately dominated by `id`, in reverse postorder.
+    pub fn children(&self, id: BlockId) -> &[BlockId] {
+        &self.children[id.0]
+    }
+
+    pub fn dominates(&self, a: BlockId, mut b: BlockId) -> bool {
+        loop {
+            if a == b {
+                return true;
+            }
+
+            if self.order[b.0] < self.order[a.0] {
+                return false;
+            }
+
+            match self.idom(b) {
+                Some(idom) => b = idom,
+                None => return false
+            }
+        }
+    }
+
+    /// The dominance frontier of every block: where its dominance ends.
+    pub fn frontiers(&self, function: &Function) -> Vec<HashSet<BlockId>> {
+        let predecessors = function.predecessors();
+        let mut frontiers = vec![HashSet::new(); function.blocks.len()];
+
+        for id in function.block_ids() {
+            if predecessors[id.0].len() < 2 || self.idom[id.0].is_none() {
+                continue;
+            }
+
+            for &predecessor in &predecessors[id.0] {
+                let mut runner = predecessor;
+
+                while Some(runner) != self.idom(id) && self.idom[runner.0].is_some() {
+                    frontiers[runner.0].insert(id);
+
+                    match self.idom(runner) {
+                        Some(idom) => runner = idom,
+                        None => break
+                    }
+                }
+            }
+        }
+
+        frontiers
+    }
+}
+
+// Registers read in some block before being assigned there, which are the
+// only ones that can need a phi
+fn global_registers(function: &Function) -> HashSet<VReg> {
+    let mut globals = HashSet::new();
+
+    for block in &function.blocks {
+        let mut assigned = HashSet::new();
+
+        for instr in &block.instrs {
+            globals.extend(instr.uses().into_iter().filter(|reg| !assigned.contains(reg)));
+            assigned.extend(instr.dst());
+        }
+
+        let uses = block.terminator.operands().into_iter().filter_map(Operand::as_reg);
+        globals.extend(uses.filter(|reg| !assigned.contains(reg)));
+    }
+
+    globals
+}
+
+struct Renamer<'a> {
+    function: &'a mut Function,
+    dominators: &'a Dominators,
+    // The current name of each original register
+    stacks: HashMap<VReg, Vec<VReg>>,
+    // The original register each phi at the start of a block stands for
+    phi_registers: Vec<Vec<VReg>>
+}
+
+impl<'a> Renamer<'a> {
+    // A register read before any assignment reaches it is undefined, and any
+    // value will do
+    fn current(&self, reg: VReg) -> Operand {
+        match self.stacks.get(&reg).and_then(|stack| stack.last()) {
+            Some(&name) => Operand::Reg(name),
+            None => Operand::Imm(0)
+        }
+    }
+
+    fn rename_operand(&self, operand: &mut Operand) {
+        if let Operand::Reg(reg) = *operand {
+            *operand = self.current(reg);
+        }
+    }
+
+    fn rename_block(&mut self, id: BlockId) {
+        let mut pushed = Vec::new();
+        let mut instrs = ::std::mem::take(&mut self.function.blocks[id.0].instrs);
+
+        for (index, instr) in instrs.iter_mut().enumerate() {
+            if !instr.is_phi() {
+                for operand in instr.operands_mut() {
+                    self.rename_operand(operand);
+                }
+            }
+
+            let phi_register = self.phi_registers[id.0].get(index).cloned();
+
+            if let Some(dst) = instr.dst_mut() {
+                let original = phi_register.unwrap_or(*dst);
+                let name = self.function.new_vreg();
+
+                self.stacks.entry(original).or_default().push(name);
+                pushed.push(original);
+                *dst = name;
+            }
+        }
+
+        self.function.blocks[id.0].instrs = instrs;
+
+        let mut terminator = self.function.blocks[id.0].terminator.clone();
+
+        for operand in terminator.operands_mut() {
+            self.rename_operand(operand);
+        }
+
+        for successor in terminator.successors() {
+            let mut successor_instrs = ::std::mem::take(&mut self.function.blocks[successor.0].instrs);
+
+            for (index, instr) in successor_instrs.iter_mut().enumerate() {
+                if let Instr::Phi { ref mut sources, .. } = *instr {
+                    let original = self.phi_registers[successor.0][index];
+
+                    if !sources.iter().any(|&(block, _)| block == id) {
+                        sources.push((id, self.current(original)));
+                    }
+                }
+            }
+
+            self.function.blocks[successor.0].instrs = successor_instrs;
+        }
+
+        self.function.blocks[id.0].terminator = terminator;
+
+        for &child in self.dominators.children(id).to_vec().iter() {
+            self.rename_block(child);
+        }
+
+        for original in pushed {
+            self.stacks.get_mut(&original).unwrap().pop();
+        }
+    }
+}
+
+/// Puts a function into SSA form, so that every register is assigned once
+/// and every use is dominated by its assignment.
+pub fn construct(function: &mut Function) {
+    function.remove_unreachable_blocks();
+
+    let dominators = Dominators::compute(function);
+    let frontiers = dominators.frontiers(function);
+    let globals = global_registers(function);
+
+    let mut assignments: HashMap<VReg, Vec<BlockId>> = HashMap::new();
+
+    for &(param, _) in &function.params {
+        assignments.entry(param).or_default().push(Function::ENTRY);
+    }
+
+    for id in function.block_ids() {
+        for dst in function.block(id).instrs.iter().filter_map(Instr::dst) {
+            assignments.entry(dst).or_default().push(id);
+        }
+    }
+
+    let mut registers: Vec<VReg> = globals.into_iter().filter(|reg| assignments.contains_key(reg)).collect();
+    registers.sort();
+
+    let mut phi_registers = vec![Vec::new(); function.blocks.len()];
+
+    for reg in registers {
+        let mut has_phi = HashSet::new();
+        let mut worklist = assignments[&reg].clone();
+
+        while let Some(id) = worklist.pop() {
+            let mut frontier: Vec<BlockId> = frontiers[id.0].iter().cloned().collect();
+            frontier.sort();
+
+            for target in frontier {
+                if has_phi.insert(target) {
+                    phi_registers[target.0].push(reg);
+                    worklist.push(target);
+                }
+            }
+        }
+    }
+
+    // Phis start out assigning the register they stand for, and get renamed
+    // along with everything else
+    for id in function.block_ids().collect::<Vec<_>>() {
+        let phis = phi_registers[id.0].iter().map(|&dst| Instr::Phi { dst, sources: Vec::new() });
+        function.blocks[id.0].instrs.splice(0..0, phis.collect::<Vec<_>>());
+    }
+
+    let mut stacks = HashMap::new();
+
+    for &(param, _) in &function.params {
+        stacks.insert(param, vec![param]);
+    }
+
+    let mut renamer = Renamer {
+        function,
+        dominators: &dominators,
+        stacks,
+        phi_registers
+    };
+
+    renamer.rename_block(Function::ENTRY);
+}
+
+/// Orders a set of copies that happen all at once, as the phis at the start
+/// of a block do, so that none overwrites a register a later one reads.
+/// Cycles are broken with a fresh register.
+fn sequentialize(function: &mut Function, mut copies: Vec<(VReg, Operand)>) -> Vec<Instr> {
+    let mut instrs = Vec::new();
+
+    copies.retain(|&(dst, src)| src != Operand::Reg(dst));
+
+    while !copies.is_empty() {
+        // A copy is safe to do once nothing left to do reads its destination
+        let ready = copies.iter().position(|&(dst, _)| !copies.iter().any(|&(_, src)| src == Operand::Reg(dst)));
+
+        match ready {
+            Some(index) => {
+                let (dst, src) = copies.remove(index);
+                instrs.push(Instr::Copy { dst, src });
+            },
+            None => {
+                // Every remaining destination is read by another copy, so they
+                // form cycles; save one destination and read the saved copy
+                let saved = copies[0].0;
+                let temporary = function.new_vreg();
+                instrs.push(Instr::Copy { dst: temporary, src: Operand::Reg(saved) });
+
+                for &mut (_, ref mut src) in copies.iter_mut() {
+                    if *src == Operand::Reg(saved) {
+                        *src = Operand::Reg(temporary);
+                    }
+                }
+            }
+        }
+    }
+
+    instrs
+}
+
+/// Takes a function out of SSA form by replacing phis with copies at the end
+/// of each predecessor. Edges from a block with several successors into one
+/// with phis are split first, so the copies run only on that edge.
+pub fn destruct(function: &mut Function) {
+    let predecessors = function.predecessors();
+
+    for id in function.block_ids().collect::<Vec<_>>() {
+        let phis: Vec<(VReg, Vec<(BlockId, Operand)>)> = function.blocks[id.0].instrs.iter().filter_map(|instr| match *instr {
+            Instr::Phi { dst, ref sources } => Some((dst, sources.clone())),
+            _ => None
+        }).collect();
+
+        if phis.is_empty() {
+            continue;
+        }
+
+        function.blocks[id.0].instrs.retain(|instr| !instr.is_phi());
+
+        let mut incoming = predecessors[id.0].clone();
+        incoming.dedup();
+
+        for predecessor in incoming {
+            let copies = phis.iter().map(|&(dst, ref sources)| {
+                let &(_, src) = sources.iter().find(|&&(block, _)| block == predecessor).expect("phi is missing a source");
+
+                (dst, src)
+            }).collect();
+
+            let copies = sequentialize(function, copies);
+
+            if function.successors(predecessor).len() == 1 {
+                function.blocks[predecessor.0].instrs.extend(copies);
+                continue;
+            }
+
+            let split = BlockId(function.blocks.len());
+            function.blocks.push(Block {
+                instrs: copies,
+                terminator: Terminator::Jump(id)
+            });
+
+            match function.blocks[predecessor.0].terminator {
+                Terminator::Branch { ref mut then_block, ref mut else_block, .. } => {
+                    if *then_block == id {
+                        *then_block = split;
+                    }
+
+                    if *else_block == id {
+                        *else_block = split;
+                    }
+                },
+                _ => unreachable!("only branches have several successors")
+            }
+        }
+    }
+
+    function.remove_unreachable_blocks();
+}
+
+/// Checks that a function in SSA form assigns each register once, and that
+/// each use is dominated by the assignment, with a phi's uses counting as
+/// being at the end of the matching predecessor.
+pub fn verify(function: &Function) -> Result<(), String> {
+    let dominators = Dominators::compute(function);
+    let mut definitions: HashMap<VReg, (BlockId, usize)> = HashMap::new();
+
+    for &(param, _) in &function.params {
+        definitions.insert(param, (Function::ENTRY, 0));
+    }
+
+    for id in function.block_ids() {
+        for (index, instr) in function.block(id).instrs.iter().enumerate() {
+            if let Some(dst) = instr.dst() {
+                if definitions.insert(dst, (id, index + 1)).is_some() {
+                    return Err(format!("{} is assigned more than once", dst));
+                }
+            }
+        }
+    }
+
+    let check = |reg: VReg, block: BlockId, position: usize| match definitions.get(&reg) {
+        Some(&(defined_in, defined_at)) if defined_in == block => defined_at <= position,
+        Some(&(defined_in, _)) => dominators.dominates(defined_in, block),
+        None => false
+    };
+
+    for id in function.block_ids() {
+        let block = function.block(id);
+
+        for (index, instr) in block.instrs.iter().enumerate() {
+            let valid = match *instr {
+                Instr::Phi { ref sources, .. } => sources.iter().all(|&(source, operand)| {
+                    operand.as_reg().is_none_or(|reg| check(reg, source, usize::MAX))
+                }),
+                _ => instr.uses().into_iter().all(|reg| check(reg, id, index))
+            };
+
+            if !valid {
+                return Err(format!("{}: '{}' uses a register its assignment does not dominate", id, instr));
+            }
+        }
+
+        let terminator = block.terminator.operands().into_iter().filter_map(Operand::as_reg);
+
+        if !terminator.into_iter().all(|reg| check(reg, id, usize::MAX)) {
+            return Err(format!("{}: '{}' uses a register its assignment does not dominate", id, block.terminator));
+        }
+    }
+
+    Ok(())
+}
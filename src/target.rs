@@ -0,0 +1,109 @@
+//! The machines code can be generated for, and the tools that build and run
+//! programs for each of them.
+
+use Type;
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum Target {
+    X86_64,
+    Aarch64,
+    Riscv64,
+    I386,
+    Wasm32
+}
+
+/// The sizes a target's ABI gives the machine word and the C types that
+/// vary between targets.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub struct DataLayout {
+    /// The size in bytes of registers, pointers and stack slots.
+    pub word_size: usize,
+    pub long_size: usize,
+    /// What the stack pointer is a multiple of at calls.
+    pub stack_alignment: usize
+}
+
+impl DataLayout {
+    pub const LP64: DataLayout = DataLayout { word_size: 8, long_size: 8, stack_alignment: 16 };
+    pub const ILP32: DataLayout = DataLayout { word_size: 4, long_size: 4, stack_alignment: 16 };
+
+    /// The type whose operations compute `ty` in the IR. Where `long` is 32
+    /// bits it is computed as `int`, so no value is wider than the target's.
+    pub fn representation(&self, ty: Type) -> Type {
+        match ty {
+            Type::Long if self.long_size == 4 => Type::Int,
+            ty => ty
+        }
+    }
+}
+
+impl Target {
+    /// The target a `--target=` option names, either as a triple or just the
+    /// architecture.
+    pub fn from_name(name: &str) -> Option<Target> {
+        match name {
+            "x86_64" | "x86_64-linux-gnu" | "x86_64-unknown-linux-gnu" => Some(Target::X86_64),
+            "aarch64" | "aarch64-linux-gnu" | "aarch64-unknown-linux-gnu" => Some(Target::Aarch64),
+            "riscv64" | "riscv64-linux-gnu" | "riscv64-unknown-linux-gnu" => Some(Target::Riscv64),
+            "i386" | "i386-linux-gnu" | "i686-linux-gnu" | "i686-pc-linux-gnu" => Some(Target::I386),
+            "wasm32" | "wasm32-unknown-unknown" => Some(Target::Wasm32),
+            _ => None
+        }
+    }
+
+    pub fn triple(self) -> &'static str {
+        match self {
+            Target::X86_64 => "x86_64-linux-gnu",
+            Target::Aarch64 => "aarch64-linux-gnu",
+            Target::Riscv64 => "riscv64-linux-gnu",
+            Target::I386 => "i386-linux-gnu",
+            Target::Wasm32 => "wasm32-unknown-unknown"
+        }
+    }
+
+    pub fn data_layout(self) -> DataLayout {
+        match self {
+            Target::I386 | Target::Wasm32 => DataLayout::ILP32,
+            _ => DataLayout::LP64
+        }
+    }
+
+    /// Whether the target's programs run on this machine without an emulator.
+    pub fn is_native(self) -> bool {
+        self == Target::X86_64 || self == Target::I386
+    }
+
+    /// The extension of the files code is generated into.
+    pub fn assembly_extension(self) -> &'static str {
+        match self {
+            Target::Wasm32 => "wat",
+            _ => "s"
+        }
+    }
+
+    /// The C compiler driver that assembles and links for the target.
+    pub fn compiler(self) -> String {
+        if self.is_native() {
+            "gcc".to_string()
+        } else {
+            format!("{}-gcc", self.triple())
+        }
+    }
+
+    /// Options the compiler driver needs to build for the target.
+    pub fn compiler_flags(self) -> &'static [&'static str] {
+        match self {
+            Target::I386 => &["-m32"],
+            _ => &[]
+        }
+    }
+
+    /// The user mode emulator that runs the target's programs on this machine.
+    pub fn emulator(self) -> Option<&'static str> {
+        match self {
+            Target::Aarch64 => Some("qemu-aarch64"),
+            Target::Riscv64 => Some("qemu-riscv64"),
+            Target::X86_64 | Target::I386 | Target::Wasm32 => None
+        }
+    }
+}
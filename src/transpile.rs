@@ -0,0 +1,395 @@
+//! Translation of a type checked AST back to C that behaves the same on any
+//! host, for checking the other backends against a C compiler.
+//!
+//! Each type becomes the fixed width type the target's data layout gives
+//! it, and every operation whose result C leaves undefined or up to the
+//! implementation goes through a helper that spells it out. Conversions to
+//! signed types and left shifts wrap, the way GCC defines them, right shifts
+//! of negative values are arithmetic, and signed overflow, division by zero,
+//! out of range shift counts and running off the end of a function that
+//! returns a value report their source location and abort. A program that
+//! aborts has undefined behavior, so how the compiler handled it says
+//! nothing. Operands are still evaluated in whatever order the C
+//! compiler picks, as they are in the original, and the host's `int` is
+//! assumed to be 32 bits, so `uint32_t` arithmetic is not promoted.
+
+use constant;
+use sema;
+use target::{DataLayout, Target};
+use visit::{self, Visitor};
+use TranslationUnit;
+use FunctionDef;
+use Decl;
+use Stmt;
+use StmtKind;
+use Expr;
+use ExprKind;
+use UnaryOperator;
+use BinaryOperator;
+use Type;
+use Span;
+
+const INDENT: &str = "    ";
+
+// Only `<stdio.h>` is included, for `stderr`, and `abort` is declared, so
+// that few library prototypes can clash with the program's own functions
+const PRELUDE: &str = "#include <stdint.h>
+#include <stdio.h>
+
+void abort(void);
+
+static void cc_undefined(const char *at, const char *what) {
+    fprintf(stderr, \"%s: undefined behavior: %s\\n\", at, what);
+    abort();
+}
+
+static inline int8_t cc_to_i8(int64_t v) {
+    uint8_t u = (uint8_t)v;
+    return u > INT8_MAX ? -(int8_t)(UINT8_MAX - u) - 1 : (int8_t)u;
+}
+
+static inline int32_t cc_to_i32(int64_t v) {
+    uint32_t u = (uint32_t)v;
+    return u > INT32_MAX ? -(int32_t)(UINT32_MAX - u) - 1 : (int32_t)u;
+}
+
+static inline uint32_t cc_div_u32(uint32_t a, uint32_t b, const char *at) {
+    if (b == 0)
+        cc_undefined(at, \"division by zero\");
+    return a / b;
+}
+
+static inline uint32_t cc_shl_u32(uint32_t a, int64_t n, const char *at) {
+    if (n < 0 || n >= 32)
+        cc_undefined(at, \"shift count out of range\");
+    return a << n;
+}
+
+static inline uint32_t cc_shr_u32(uint32_t a, int64_t n, const char *at) {
+    if (n < 0 || n >= 32)
+        cc_undefined(at, \"shift count out of range\");
+    return a >> n;
+}
+";
+
+// The checked operations of a signed type, for `$S`, `$T`, `$MIN`, `$MAX`
+// and `$BITS` to be replaced
+const SIGNED: &str = "
+static inline $T cc_add_$S($T a, $T b, const char *at) {
+    if (b > 0 ? a > $MAX - b : a < $MIN - b)
+        cc_undefined(at, \"signed overflow in '+'\");
+    return a + b;
+}
+
+static inline $T cc_sub_$S($T a, $T b, const char *at) {
+    if (b < 0 ? a > $MAX + b : a < $MIN + b)
+        cc_undefined(at, \"signed overflow in '-'\");
+    return a - b;
+}
+
+static inline $T cc_mul_$S($T a, $T b, const char *at) {
+    if (a > 0 ? (b > 0 ? a > $MAX / b : b < $MIN / a) : (b > 0 ? a < $MIN / b : a != 0 && b < $MAX / a))
+        cc_undefined(at, \"signed overflow in '*'\");
+    return a * b;
+}
+
+static inline $T cc_div_$S($T a, $T b, const char *at) {
+    if (b == 0)
+        cc_undefined(at, \"division by zero\");
+    if (a == $MIN && b == -1)
+        cc_undefined(at, \"signed overflow in '/'\");
+    return a / b;
+}
+
+static inline $T cc_neg_$S($T a, const char *at) {
+    if (a == $MIN)
+        cc_undefined(at, \"signed overflow in '-'\");
+    return -a;
+}
+
+static inline $T cc_shl_$S($T a, int64_t n, const char *at) {
+    uint$BITS_t u;
+    if (n < 0 || n >= $BITS)
+        cc_undefined(at, \"shift count out of range\");
+    u = (uint$BITS_t)a << n;
+    return u > $MAX ? -($T)(UINT$BITS_MAX - u) - 1 : ($T)u;
+}
+
+static inline $T cc_shr_$S($T a, int64_t n, const char *at) {
+    if (n < 0 || n >= $BITS)
+        cc_undefined(at, \"shift count out of range\");
+    return a < 0 ? ~(~a >> n) : a >> n;
+}
+";
+
+fn signed_helpers(suffix: &str, ty: &str, bits: u32) -> String {
+    SIGNED.replace("$S", suffix)
+        .replace("$T", ty)
+        .replace("$MIN", &format!("INT{}_MIN", bits))
+        .replace("$MAX", &format!("INT{}_MAX", bits))
+        .replace("$BITS", &bits.to_string())
+}
+
+struct Translator<'a> {
+    layout: DataLayout,
+    // The offset each line of the source starts at
+    line_starts: Vec<usize>,
+    source: &'a str,
+    buf: String,
+    depth: usize
+}
+
+impl<'a> Translator<'a> {
+    fn new(source: &'a str, layout: DataLayout) -> Translator<'a> {
+        let mut line_starts = vec![0];
+        line_starts.extend(source.match_indices('\n').map(|(index, _)| index + 1));
+
+        Translator {
+            layout,
+            line_starts,
+            source,
+            buf: String::new(),
+            depth: 0
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.buf.push_str(INDENT);
+        }
+
+        self.buf.push_str(text);
+        self.buf.push('\n');
+    }
+
+    fn ty(&self, ty: Type) -> &'static str {
+        match self.layout.representation(ty) {
+            Type::Void => "void",
+            Type::Char => "int8_t",
+            Type::Int => "int32_t",
+            Type::UnsignedInt => "uint32_t",
+            Type::Long => "int64_t"
+        }
+    }
+
+    // The suffix of the helpers for a type
+    fn suffix(&self, ty: Type) -> &'static str {
+        match self.layout.representation(ty) {
+            Type::Char => "i8",
+            Type::Int => "i32",
+            Type::UnsignedInt => "u32",
+            Type::Long => "i64",
+            Type::Void => unreachable!("void has no operations")
+        }
+    }
+
+    // Where an expression starts, as a string literal of `line:column`
+    fn location(&self, span: Span) -> String {
+        let line = self.line_starts.partition_point(|&start| start <= span.start);
+        let column = self.source[self.line_starts[line - 1]..span.start].chars().count() + 1;
+
+        format!("\"{}:{}\"", line, column)
+    }
+
+    fn signature(&self, function: &FunctionDef) -> String {
+        let params = if function.params.is_empty() {
+            "void".to_string()
+        } else {
+            function.params.iter().map(|param| match param.name {
+                Some(ref name) => format!("{} {}", self.ty(param.ty), name),
+                None => self.ty(param.ty).to_string()
+            }).collect::<Vec<_>>().join(", ")
+        };
+
+        format!("{} {}({})", self.ty(function.return_type), function.name, params)
+    }
+
+    fn literal(&self, value: i64, ty: Type) -> String {
+        let ty = self.layout.representation(ty);
+        let value = constant::wrap(value as i128, ty);
+
+        match ty {
+            Type::Char => format!("((int8_t){})", value),
+            Type::UnsignedInt => format!("UINT32_C({})", value),
+            Type::Int if value == i32::MIN as i64 => "INT32_MIN".to_string(),
+            Type::Long if value == i64::MIN => "INT64_MIN".to_string(),
+            Type::Long => format!("INT64_C({})", value),
+            _ if value < 0 => format!("({})", value),
+            _ => value.to_string()
+        }
+    }
+
+    // Converts a value the way an implicit cast from `from` to `to` does
+    fn convert(&self, value: String, from: Type, to: Type) -> String {
+        let (from, to) = (self.layout.representation(from), self.layout.representation(to));
+
+        if from == to {
+            value
+        } else if to.is_signed() && (to.size() < from.size() || to.size() == from.size() && !from.is_signed()) {
+            // Out of range values are converted however the implementation likes
+            format!("cc_to_{}({})", self.suffix(to), value)
+        } else {
+            format!("(({}){})", self.ty(to), value)
+        }
+    }
+
+    fn expression(&self, expr: &Expr) -> String {
+        if let Some(value) = constant::literal_value(expr) {
+            return self.literal(value, expr.ty());
+        }
+
+        match expr.kind {
+            ExprKind::Constant(_) => unreachable!("constants are literals"),
+            ExprKind::Variable(ref name, _) => name.clone(),
+            ExprKind::Assignment(ref target, ref value) => format!("({} = {})", self.expression(target), self.expression(value)),
+            ExprKind::ImplicitCast(ty, ref operand) => self.convert(self.expression(operand), operand.ty(), ty),
+            ExprKind::Call(ref name, ref args) => {
+                let args: Vec<String> = args.iter().map(|arg| self.expression(arg)).collect();
+
+                format!("{}({})", name, args.join(", "))
+            },
+            ExprKind::Unary(operator, ref operand) => {
+                let value = self.expression(operand);
+
+                match operator {
+                    UnaryOperator::Negation if expr.ty().is_signed() => {
+                        format!("cc_neg_{}({}, {})", self.suffix(expr.ty()), value, self.location(expr.span))
+                    },
+                    UnaryOperator::Negation => format!("(({})-{})", self.ty(expr.ty()), value),
+                    UnaryOperator::BitwiseComplement => format!("(~{})", value),
+                    UnaryOperator::LogicalNegation => format!("((int32_t)!{})", value)
+                }
+            },
+            ExprKind::Binary(operator, ref lhs, ref rhs) => {
+                let ty = lhs.ty();
+                let (lhs, rhs) = (self.expression(lhs), self.expression(rhs));
+
+                let helper = match operator {
+                    BinaryOperator::Addition if ty.is_signed() => "add",
+                    BinaryOperator::Subtraction if ty.is_signed() => "sub",
+                    BinaryOperator::Multiplication if ty.is_signed() => "mul",
+                    BinaryOperator::Division => "div",
+                    BinaryOperator::ShiftLeft => "shl",
+                    BinaryOperator::ShiftRight => "shr",
+                    // Unsigned arithmetic wraps
+                    BinaryOperator::Addition | BinaryOperator::Subtraction | BinaryOperator::Multiplication => {
+                        return format!("(({})({} {} {}))", self.ty(ty), lhs, operator.symbol(), rhs);
+                    },
+                    _ => return format!("((int32_t)({} {} {}))", lhs, operator.symbol(), rhs)
+                };
+
+                format!("cc_{}_{}({}, {}, {})", helper, self.suffix(ty), lhs, rhs, self.location(expr.span))
+            }
+        }
+    }
+
+    // Translates the body of an if, else or while, always in braces
+    fn nested(&mut self, header: &str, stmt: &Stmt) {
+        self.line(&format!("{} {{", header));
+        self.depth += 1;
+
+        match stmt.kind {
+            StmtKind::Compound(ref items) => {
+                for item in items {
+                    self.visit_block_item(item);
+                }
+            },
+            _ => self.visit_stmt(stmt)
+        }
+
+        self.depth -= 1;
+    }
+}
+
+impl<'a> Visitor for Translator<'a> {
+    fn visit_function_def(&mut self, function: &FunctionDef) {
+        let signature = self.signature(function);
+
+        self.buf.push('\n');
+
+        if !function.is_definition() {
+            self.line(&format!("{};", signature));
+            return;
+        }
+
+        self.line(&format!("{} {{", signature));
+
+        self.depth += 1;
+        visit::walk_function_def(self, function);
+
+        // Using the value of a call that ran off the end is undefined, but
+        // main returns 0 instead
+        let body = function.body.as_ref().unwrap();
+
+        if function.return_type != Type::Void && function.name != "main" && sema::block_falls_through(body) {
+            let brace = self.location(Span::new(function.span.end - 1, function.span.end));
+            self.line(&format!("cc_undefined({}, \"missing return\");", brace));
+        }
+
+        self.depth -= 1;
+
+        self.line("}");
+    }
+
+    fn visit_decl(&mut self, decl: &Decl) {
+        let line = match decl.init {
+            Some(ref init) => format!("{} {} = {};", self.ty(decl.ty), decl.name, self.expression(init)),
+            None => format!("{} {};", self.ty(decl.ty), decl.name)
+        };
+
+        self.line(&line);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt.kind {
+            StmtKind::Return(Some(ref expr)) => {
+                let line = format!("return {};", self.expression(expr));
+                self.line(&line);
+            },
+            StmtKind::Return(None) => self.line("return;"),
+            StmtKind::Expr(ref expr) => {
+                let line = format!("{};", self.expression(expr));
+                self.line(&line);
+            },
+            StmtKind::If(ref condition, ref then_branch, ref else_branch) => {
+                let header = format!("if ({})", self.expression(condition));
+                self.nested(&header, then_branch);
+
+                if let Some(ref else_branch) = *else_branch {
+                    self.nested("} else", else_branch);
+                }
+
+                self.line("}");
+            },
+            StmtKind::While(ref condition, ref body) => {
+                let header = format!("while ({})", self.expression(condition));
+                self.nested(&header, body);
+                self.line("}");
+            },
+            StmtKind::Compound(_) => {
+                self.line("{");
+
+                self.depth += 1;
+                visit::walk_stmt(self, stmt);
+                self.depth -= 1;
+
+                self.line("}");
+            }
+        }
+    }
+}
+
+/// Translates a translation unit that has passed semantic analysis without
+/// errors to C with the integer types of the target's data layout. `source`
+/// is the program the spans point into, for the locations of undefined
+/// behavior.
+pub fn generate(translation_unit: &TranslationUnit, source: &str, target: Target) -> String {
+    let mut translator = Translator::new(source, target.data_layout());
+
+    translator.buf.push_str(PRELUDE);
+    translator.buf.push_str(&signed_helpers("i32", "int32_t", 32));
+    translator.buf.push_str(&signed_helpers("i64", "int64_t", 64));
+    translator.visit_translation_unit(translation_unit);
+
+    translator.buf
+}
@@ -0,0 +1,325 @@
+use TranslationUnit;
+use FunctionDef;
+use Param;
+use BlockItem;
+use Decl;
+use Stmt;
+use StmtKind;
+use Expr;
+use ExprKind;
+
+/// Read-only traversal of the AST. Every method defaults to walking the
+/// node's children, so implementors only override the nodes they care about
+/// and call the matching `walk_*` function to keep descending.
+pub trait Visitor {
+    fn visit_translation_unit(&mut self, translation_unit: &TranslationUnit) {
+        walk_translation_unit(self, translation_unit);
+    }
+
+    fn visit_function_def(&mut self, function: &FunctionDef) {
+        walk_function_def(self, function);
+    }
+
+    fn visit_param(&mut self, _param: &Param) {}
+
+    fn visit_block_item(&mut self, item: &BlockItem) {
+        walk_block_item(self, item);
+    }
+
+    fn visit_decl(&mut self, decl: &Decl) {
+        walk_decl(self, decl);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+}
+
+pub fn walk_translation_unit<V: Visitor + ?Sized>(visitor: &mut V, translation_unit: &TranslationUnit) {
+    for function in &translation_unit.functions {
+        visitor.visit_function_def(function);
+    }
+}
+
+pub fn walk_function_def<V: Visitor + ?Sized>(visitor: &mut V, function: &FunctionDef) {
+    for param in &function.params {
+        visitor.visit_param(param);
+    }
+
+    for item in function.body.iter().flatten() {
+        visitor.visit_block_item(item);
+    }
+}
+
+pub fn walk_block_item<V: Visitor + ?Sized>(visitor: &mut V, item: &BlockItem) {
+    match *item {
+        BlockItem::Decl(ref decl) => visitor.visit_decl(decl),
+        BlockItem::Stmt(ref stmt) => visitor.visit_stmt(stmt)
+    }
+}
+
+pub fn walk_decl<V: Visitor + ?Sized>(visitor: &mut V, decl: &Decl) {
+    if let Some(ref init) = decl.init {
+        visitor.visit_expr(init);
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match stmt.kind {
+        StmtKind::Return(ref expr) => {
+            if let Some(ref expr) = *expr {
+                visitor.visit_expr(expr);
+            }
+        },
+        StmtKind::Expr(ref expr) => visitor.visit_expr(expr),
+        StmtKind::Compound(ref items) => {
+            for item in items {
+                visitor.visit_block_item(item);
+            }
+        },
+        StmtKind::If(ref condition, ref then_branch, ref else_branch) => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(then_branch);
+
+            if let Some(ref else_branch) = *else_branch {
+                visitor.visit_stmt(else_branch);
+            }
+        },
+        StmtKind::While(ref condition, ref body) => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(body);
+        }
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr.kind {
+        ExprKind::Constant(_) | ExprKind::Variable(_, _) => {},
+        ExprKind::Unary(_, ref operand) | ExprKind::ImplicitCast(_, ref operand) => visitor.visit_expr(operand),
+        ExprKind::Assignment(ref lhs, ref rhs) | ExprKind::Binary(_, ref lhs, ref rhs) => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        },
+        ExprKind::Call(_, ref args) => {
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+    }
+}
+
+/// In-place traversal of the AST, for passes that rewrite nodes without
+/// changing their shape.
+pub trait VisitorMut {
+    fn visit_translation_unit_mut(&mut self, translation_unit: &mut TranslationUnit) {
+        walk_translation_unit_mut(self, translation_unit);
+    }
+
+    fn visit_function_def_mut(&mut self, function: &mut FunctionDef) {
+        walk_function_def_mut(self, function);
+    }
+
+    fn visit_param_mut(&mut self, _param: &mut Param) {}
+
+    fn visit_block_item_mut(&mut self, item: &mut BlockItem) {
+        walk_block_item_mut(self, item);
+    }
+
+    fn visit_decl_mut(&mut self, decl: &mut Decl) {
+        walk_decl_mut(self, decl);
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+    }
+}
+
+pub fn walk_translation_unit_mut<V: VisitorMut + ?Sized>(visitor: &mut V, translation_unit: &mut TranslationUnit) {
+    for function in &mut translation_unit.functions {
+        visitor.visit_function_def_mut(function);
+    }
+}
+
+pub fn walk_function_def_mut<V: VisitorMut + ?Sized>(visitor: &mut V, function: &mut FunctionDef) {
+    for param in &mut function.params {
+        visitor.visit_param_mut(param);
+    }
+
+    for item in function.body.iter_mut().flatten() {
+        visitor.visit_block_item_mut(item);
+    }
+}
+
+pub fn walk_block_item_mut<V: VisitorMut + ?Sized>(visitor: &mut V, item: &mut BlockItem) {
+    match *item {
+        BlockItem::Decl(ref mut decl) => visitor.visit_decl_mut(decl),
+        BlockItem::Stmt(ref mut stmt) => visitor.visit_stmt_mut(stmt)
+    }
+}
+
+pub fn walk_decl_mut<V: VisitorMut + ?Sized>(visitor: &mut V, decl: &mut Decl) {
+    if let Some(ref mut init) = decl.init {
+        visitor.visit_expr_mut(init);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Stmt) {
+    match stmt.kind {
+        StmtKind::Return(ref mut expr) => {
+            if let Some(ref mut expr) = *expr {
+                visitor.visit_expr_mut(expr);
+            }
+        },
+        StmtKind::Expr(ref mut expr) => visitor.visit_expr_mut(expr),
+        StmtKind::Compound(ref mut items) => {
+            for item in items {
+                visitor.visit_block_item_mut(item);
+            }
+        },
+        StmtKind::If(ref mut condition, ref mut then_branch, ref mut else_branch) => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_stmt_mut(then_branch);
+
+            if let Some(ref mut else_branch) = *else_branch {
+                visitor.visit_stmt_mut(else_branch);
+            }
+        },
+        StmtKind::While(ref mut condition, ref mut body) => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_stmt_mut(body);
+        }
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    match expr.kind {
+        ExprKind::Constant(_) | ExprKind::Variable(_, _) => {},
+        ExprKind::Unary(_, ref mut operand) | ExprKind::ImplicitCast(_, ref mut operand) => visitor.visit_expr_mut(operand),
+        ExprKind::Assignment(ref mut lhs, ref mut rhs) | ExprKind::Binary(_, ref mut lhs, ref mut rhs) => {
+            visitor.visit_expr_mut(lhs);
+            visitor.visit_expr_mut(rhs);
+        },
+        ExprKind::Call(_, ref mut args) => {
+            for arg in args {
+                visitor.visit_expr_mut(arg);
+            }
+        }
+    }
+}
+
+/// Owning traversal that rebuilds the AST bottom-up, for source-to-source
+/// rewrites that replace nodes with differently shaped ones.
+pub trait Fold {
+    fn fold_translation_unit(&mut self, translation_unit: TranslationUnit) -> TranslationUnit {
+        noop_fold_translation_unit(self, translation_unit)
+    }
+
+    fn fold_function_def(&mut self, function: FunctionDef) -> FunctionDef {
+        noop_fold_function_def(self, function)
+    }
+
+    fn fold_param(&mut self, param: Param) -> Param {
+        param
+    }
+
+    fn fold_block_item(&mut self, item: BlockItem) -> BlockItem {
+        noop_fold_block_item(self, item)
+    }
+
+    fn fold_decl(&mut self, decl: Decl) -> Decl {
+        noop_fold_decl(self, decl)
+    }
+
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        noop_fold_stmt(self, stmt)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        noop_fold_expr(self, expr)
+    }
+}
+
+pub fn noop_fold_translation_unit<F: Fold + ?Sized>(folder: &mut F, translation_unit: TranslationUnit) -> TranslationUnit {
+    TranslationUnit {
+        functions: translation_unit.functions.into_iter().map(|function| folder.fold_function_def(function)).collect()
+    }
+}
+
+pub fn noop_fold_function_def<F: Fold + ?Sized>(folder: &mut F, function: FunctionDef) -> FunctionDef {
+    FunctionDef {
+        params: function.params.into_iter().map(|param| folder.fold_param(param)).collect(),
+        body: function.body.map(|body| body.into_iter().map(|item| folder.fold_block_item(item)).collect()),
+        ..function
+    }
+}
+
+pub fn noop_fold_block_item<F: Fold + ?Sized>(folder: &mut F, item: BlockItem) -> BlockItem {
+    match item {
+        BlockItem::Decl(decl) => BlockItem::Decl(folder.fold_decl(decl)),
+        BlockItem::Stmt(stmt) => BlockItem::Stmt(folder.fold_stmt(stmt))
+    }
+}
+
+pub fn noop_fold_decl<F: Fold + ?Sized>(folder: &mut F, decl: Decl) -> Decl {
+    Decl {
+        init: decl.init.map(|init| folder.fold_expr(init)),
+        ..decl
+    }
+}
+
+pub fn noop_fold_stmt<F: Fold + ?Sized>(folder: &mut F, stmt: Stmt) -> Stmt {
+    let kind = match stmt.kind {
+        StmtKind::Return(expr) => StmtKind::Return(expr.map(|expr| folder.fold_expr(expr))),
+        StmtKind::Expr(expr) => StmtKind::Expr(folder.fold_expr(expr)),
+        StmtKind::Compound(items) => StmtKind::Compound(items.into_iter().map(|item| folder.fold_block_item(item)).collect()),
+        StmtKind::If(condition, then_branch, else_branch) => {
+            let condition = folder.fold_expr(condition);
+            let then_branch = Box::new(folder.fold_stmt(*then_branch));
+            let else_branch = else_branch.map(|else_branch| Box::new(folder.fold_stmt(*else_branch)));
+
+            StmtKind::If(condition, then_branch, else_branch)
+        },
+        StmtKind::While(condition, body) => {
+            let condition = folder.fold_expr(condition);
+
+            StmtKind::While(condition, Box::new(folder.fold_stmt(*body)))
+        }
+    };
+
+    Stmt::new(kind, stmt.span)
+}
+
+pub fn noop_fold_expr<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    let kind = match expr.kind {
+        ExprKind::Constant(value) => ExprKind::Constant(value),
+        ExprKind::Variable(name, decl_id) => ExprKind::Variable(name, decl_id),
+        ExprKind::Assignment(lhs, rhs) => {
+            ExprKind::Assignment(Box::new(folder.fold_expr(*lhs)), Box::new(folder.fold_expr(*rhs)))
+        },
+        ExprKind::Unary(operator, operand) => {
+            ExprKind::Unary(operator, Box::new(folder.fold_expr(*operand)))
+        },
+        ExprKind::Binary(operator, lhs, rhs) => {
+            ExprKind::Binary(operator, Box::new(folder.fold_expr(*lhs)), Box::new(folder.fold_expr(*rhs)))
+        },
+        ExprKind::Call(name, args) => {
+            ExprKind::Call(name, args.into_iter().map(|arg| folder.fold_expr(arg)).collect())
+        },
+        ExprKind::ImplicitCast(ty, operand) => {
+            ExprKind::ImplicitCast(ty, Box::new(folder.fold_expr(*operand)))
+        }
+    };
+
+    Expr {
+        kind,
+        ..expr
+    }
+}
@@ -0,0 +1,357 @@
+//! WebAssembly text generation from IR, for the 32-bit `wasm32` target.
+//!
+//! WebAssembly has no jumps, only nested `block`s and `loop`s to break out
+//! of, so the control flow graph is rebuilt into that structure following
+//! Ramsey's "Beyond Relooper": a block is placed inside whichever block
+//! dominates it, loop headers open a `loop`, and a block that control
+//! reaches from more than one place is placed after a `block` that the
+//! branches to it break out of. That works for any reducible graph, which
+//! is every graph `if` and `while` can make.
+//!
+//! Each virtual register is a WebAssembly local, and since the language has
+//! no address-of operator none of them needs a place in memory. The module
+//! still exports a memory with `__stack_pointer` at its top, which is where
+//! the C ABI for WebAssembly keeps locals whose address is taken.
+
+use std::collections::HashMap;
+
+use ir::{self, BinaryOp, BlockId, Function, Module, Operand, Terminator, UnaryOp, VReg};
+use ssa::Dominators;
+use Type;
+
+// How a label in scope was opened, innermost last
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+enum Context {
+    IfThenElse,
+    LoopHeadedBy(BlockId),
+    BlockFollowedBy(BlockId)
+}
+
+fn binary(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "i32.add",
+        BinaryOp::Sub => "i32.sub",
+        BinaryOp::Mul => "i32.mul",
+        BinaryOp::Div => "i32.div_s",
+        BinaryOp::UDiv => "i32.div_u",
+        BinaryOp::Shl => "i32.shl",
+        BinaryOp::Sar => "i32.shr_s",
+        BinaryOp::Shr => "i32.shr_u",
+        BinaryOp::Eq => "i32.eq",
+        BinaryOp::Ne => "i32.ne",
+        BinaryOp::Lt => "i32.lt_s",
+        BinaryOp::Le => "i32.le_s",
+        BinaryOp::Gt => "i32.gt_s",
+        BinaryOp::Ge => "i32.ge_s",
+        BinaryOp::ULt => "i32.lt_u",
+        BinaryOp::ULe => "i32.le_u",
+        BinaryOp::UGt => "i32.gt_u",
+        BinaryOp::UGe => "i32.ge_u"
+    }
+}
+
+fn local(reg: VReg) -> String {
+    format!("$r{}", reg.0)
+}
+
+// A function's parameters and result
+fn signature(params: &[Type], return_type: Type) -> String {
+    let mut signature = String::new();
+
+    for _ in params {
+        signature.push_str(" (param i32)");
+    }
+
+    if return_type != Type::Void {
+        signature.push_str(" (result i32)");
+    }
+
+    signature
+}
+
+struct FunctionGenerator<'a> {
+    function: &'a Function,
+    // Whether each function called returns a value to drop if unused
+    returns_value: &'a HashMap<String, bool>,
+    dominators: Dominators,
+    // The position of each block in reverse postorder
+    order: Vec<usize>,
+    predecessors: Vec<Vec<BlockId>>,
+    context: Vec<Context>,
+    buf: String
+}
+
+impl<'a> FunctionGenerator<'a> {
+    fn emit(&mut self, line: &str) {
+        for _ in 0..self.context.len() + 2 {
+            self.buf.push_str("  ");
+        }
+
+        self.buf.push_str(line);
+        self.buf.push('\n');
+    }
+
+    fn push(&mut self, operand: Operand) {
+        match operand {
+            Operand::Reg(reg) => self.emit(&format!("local.get {}", local(reg))),
+            // Values are normalized to 32 bits, either signed or unsigned
+            Operand::Imm(value) => self.emit(&format!("i32.const {}", value as i32))
+        }
+    }
+
+    fn is_backward(&self, from: BlockId, to: BlockId) -> bool {
+        self.order[to.0] <= self.order[from.0]
+    }
+
+    fn is_loop_header(&self, id: BlockId) -> bool {
+        self.predecessors[id.0].iter().any(|&predecessor| self.is_backward(predecessor, id))
+    }
+
+    // Whether control reaches a block from more than one place, not counting
+    // the back edges of a loop
+    fn is_merge(&self, id: BlockId) -> bool {
+        self.predecessors[id.0].iter().filter(|&&predecessor| !self.is_backward(predecessor, id)).count() > 1
+    }
+
+    fn generate(mut self) -> String {
+        let function = self.function;
+
+        self.buf.push_str(&format!("  (func ${} (export \"{}\")", function.name, function.name));
+
+        for &(reg, _) in &function.params {
+            self.buf.push_str(&format!(" (param {} i32)", local(reg)));
+        }
+
+        if function.return_type != Type::Void {
+            self.buf.push_str(" (result i32)");
+        }
+
+        self.buf.push('\n');
+
+        let mut locals: Vec<VReg> = function.blocks.iter()
+            .flat_map(|block| block.instrs.iter().flat_map(|instr| instr.dst().into_iter().chain(instr.uses())))
+            .filter(|&reg| !function.params.iter().any(|&(param, _)| param == reg))
+            .collect();
+
+        locals.sort_by_key(|reg| reg.0);
+        locals.dedup();
+
+        for reg in locals {
+            let line = format!("(local {} i32)", local(reg));
+            self.emit(&line);
+        }
+
+        self.tree(Function::ENTRY);
+
+        // Control never reaches the end, but validation cannot tell after an
+        // `end` whose contents all branch away
+        if !self.buf.ends_with("return\n") {
+            self.emit("unreachable");
+        }
+
+        self.buf.push_str("  )\n");
+        self.buf
+    }
+
+    // Places a block and everything it dominates
+    fn tree(&mut self, id: BlockId) {
+        let mut merges: Vec<BlockId> = self.dominators.children(id).iter().cloned()
+            .filter(|&child| self.is_merge(child))
+            .collect();
+
+        // The merge that comes last in the graph gets the outermost `block`,
+        // so it is placed last
+        merges.sort_by_key(|&merge| std::cmp::Reverse(self.order[merge.0]));
+
+        if self.is_loop_header(id) {
+            self.emit(&format!("loop ${}.loop", id));
+            self.context.push(Context::LoopHeadedBy(id));
+            self.within(id, &merges);
+            self.context.pop();
+            self.emit("end");
+        } else {
+            self.within(id, &merges);
+        }
+    }
+
+    // Places a block's code inside a `block` for each merge it dominates,
+    // each merge going after its `end`
+    fn within(&mut self, id: BlockId, merges: &[BlockId]) {
+        match merges.split_first() {
+            Some((&merge, inner)) => {
+                self.emit(&format!("block ${}", merge));
+                self.context.push(Context::BlockFollowedBy(merge));
+                self.within(id, inner);
+                self.context.pop();
+                self.emit("end");
+
+                self.tree(merge);
+            },
+            None => {
+                for instr in &self.function.block(id).instrs {
+                    self.generate_instr(instr);
+                }
+
+                self.generate_terminator(id, &self.function.block(id).terminator);
+            }
+        }
+    }
+
+    // The label a branch to `to` breaks to, if it does not fall into it
+    fn label(&self, from: BlockId, to: BlockId) -> Option<String> {
+        let context = if self.is_backward(from, to) {
+            Context::LoopHeadedBy(to)
+        } else if self.is_merge(to) {
+            Context::BlockFollowedBy(to)
+        } else {
+            return None;
+        };
+
+        assert!(self.context.contains(&context), "{} in function {} is not structured", to, self.function.name);
+
+        Some(match context {
+            Context::LoopHeadedBy(_) => format!("${}.loop", to),
+            _ => format!("${}", to)
+        })
+    }
+
+    fn branch(&mut self, from: BlockId, to: BlockId) {
+        match self.label(from, to) {
+            Some(label) => self.emit(&format!("br {}", label)),
+            None => self.tree(to)
+        }
+    }
+
+    fn generate_instr(&mut self, instr: &ir::Instr) {
+        match *instr {
+            ir::Instr::Copy { dst, src } => {
+                self.push(src);
+                self.emit(&format!("local.set {}", local(dst)));
+            },
+            ir::Instr::Unary { op: UnaryOp::Neg, dst, src } => {
+                self.emit("i32.const 0");
+                self.push(src);
+                self.emit("i32.sub");
+                self.emit(&format!("local.set {}", local(dst)));
+            },
+            ir::Instr::Unary { op: UnaryOp::Not, dst, src } => {
+                self.push(src);
+                self.emit("i32.const -1");
+                self.emit("i32.xor");
+                self.emit(&format!("local.set {}", local(dst)));
+            },
+            ir::Instr::Binary { op, dst, lhs, rhs } => {
+                self.push(lhs);
+                self.push(rhs);
+                self.emit(binary(op));
+                self.emit(&format!("local.set {}", local(dst)));
+            },
+            ir::Instr::Cast { dst, ty, src } => {
+                self.push(src);
+
+                // Every other type is a whole word
+                if ty == Type::Char {
+                    self.emit("i32.extend8_s");
+                }
+
+                self.emit(&format!("local.set {}", local(dst)));
+            },
+            ir::Instr::Call { dst, ref function, ref args } => {
+                for &arg in args {
+                    self.push(arg);
+                }
+
+                self.emit(&format!("call ${}", function));
+
+                match dst {
+                    Some(dst) => self.emit(&format!("local.set {}", local(dst))),
+                    None if self.returns_value[function] => self.emit("drop"),
+                    None => {}
+                }
+            },
+            ir::Instr::Phi { .. } => panic!("phi in function {} reached code generation", self.function.name)
+        }
+    }
+
+    fn generate_terminator(&mut self, id: BlockId, terminator: &Terminator) {
+        match *terminator {
+            Terminator::Return(value) => {
+                if let Some(value) = value {
+                    self.push(value);
+                }
+
+                self.emit("return");
+            },
+            Terminator::Jump(target) => self.branch(id, target),
+            Terminator::Branch { condition: Operand::Imm(0), else_block, .. } => self.branch(id, else_block),
+            Terminator::Branch { condition: Operand::Imm(_), then_block, .. } => self.branch(id, then_block),
+            Terminator::Branch { condition, then_block, else_block } => {
+                // A side that only breaks out needs no `if`
+                if let Some(label) = self.label(id, then_block) {
+                    self.push(condition);
+                    self.emit(&format!("br_if {}", label));
+                    self.branch(id, else_block);
+                } else if let Some(label) = self.label(id, else_block) {
+                    self.push(condition);
+                    self.emit("i32.eqz");
+                    self.emit(&format!("br_if {}", label));
+                    self.branch(id, then_block);
+                } else {
+                    self.push(condition);
+                    self.emit("if");
+                    self.context.push(Context::IfThenElse);
+                    self.branch(id, then_block);
+                    self.context.pop();
+                    self.emit("else");
+                    self.context.push(Context::IfThenElse);
+                    self.branch(id, else_block);
+                    self.context.pop();
+                    self.emit("end");
+                }
+            }
+        }
+    }
+}
+
+/// Generates a WebAssembly text module for a module that is out of SSA form
+/// and was lowered for a 32-bit data layout. Functions that are declared but
+/// not defined are imported from `env`.
+pub fn generate(module: &Module) -> String {
+    let mut returns_value = HashMap::new();
+    let mut buf = "(module\n".to_string();
+
+    for declaration in &module.declarations {
+        returns_value.insert(declaration.name.clone(), declaration.return_type != Type::Void);
+
+        buf.push_str(&format!("  (import \"env\" \"{}\" (func ${}{}))\n",
+            declaration.name, declaration.name, signature(&declaration.params, declaration.return_type)));
+    }
+
+    for function in &module.functions {
+        returns_value.insert(function.name.clone(), function.return_type != Type::Void);
+    }
+
+    buf.push_str("  (memory (export \"memory\") 1)\n");
+    buf.push_str("  (global $__stack_pointer (mut i32) (i32.const 65536))\n");
+
+    for function in &module.functions {
+        let mut order = vec![usize::MAX; function.blocks.len()];
+
+        for (index, id) in function.reverse_postorder().into_iter().enumerate() {
+            order[id.0] = index;
+        }
+
+        buf.push_str(&FunctionGenerator {
+            function,
+            returns_value: &returns_value,
+            dominators: Dominators::compute(function),
+            order,
+            predecessors: function.predecessors(),
+            context: Vec::new(),
+            buf: String::new()
+        }.generate());
+    }
+
+    buf.push_str(")\n");
+    buf
+}
@@ -0,0 +1,512 @@
+//! A structured form of x86 assembly, so generated code can be inspected
+//! and rewritten before it is printed in AT&T or Intel syntax. Registers are
+//! named by their 64-bit forms, and printed as wide as the target's word.
+
+use std::fmt;
+
+use target::DataLayout;
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Hash)]
+pub enum Reg {
+    Rax,
+    Rbx,
+    Rcx,
+    Rdx,
+    Rsi,
+    Rdi,
+    Rbp,
+    Rsp,
+    R8,
+    R9,
+    R10,
+    R11,
+    R12,
+    R13,
+    R14,
+    R15
+}
+
+impl Reg {
+    pub fn name(self) -> &'static str {
+        match self {
+            Reg::Rax => "rax",
+            Reg::Rbx => "rbx",
+            Reg::Rcx => "rcx",
+            Reg::Rdx => "rdx",
+            Reg::Rsi => "rsi",
+            Reg::Rdi => "rdi",
+            Reg::Rbp => "rbp",
+            Reg::Rsp => "rsp",
+            Reg::R8 => "r8",
+            Reg::R9 => "r9",
+            Reg::R10 => "r10",
+            Reg::R11 => "r11",
+            Reg::R12 => "r12",
+            Reg::R13 => "r13",
+            Reg::R14 => "r14",
+            Reg::R15 => "r15"
+        }
+    }
+
+    /// The name of the low 32 bits.
+    pub fn name32(self) -> String {
+        match self {
+            Reg::Rax | Reg::Rbx | Reg::Rcx | Reg::Rdx | Reg::Rsi | Reg::Rdi | Reg::Rbp | Reg::Rsp => {
+                format!("e{}", &self.name()[1..])
+            },
+            _ => format!("{}d", self.name())
+        }
+    }
+
+    /// The name of the low 8 bits.
+    pub fn name8(self) -> String {
+        match self {
+            Reg::Rax | Reg::Rbx | Reg::Rcx | Reg::Rdx => format!("{}l", &self.name()[1..2]),
+            Reg::Rsi | Reg::Rdi | Reg::Rbp | Reg::Rsp => format!("{}l", &self.name()[1..]),
+            _ => format!("{}b", self.name())
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Hash)]
+pub enum Operand {
+    Reg(Reg),
+    Imm(i64),
+    /// A word at a fixed offset from a base register.
+    Mem { base: Reg, offset: i64 }
+}
+
+impl Operand {
+    pub fn is_mem(self) -> bool {
+        matches!(self, Operand::Mem { .. })
+    }
+
+    /// Whether the operand fits the sign-extended 32-bit immediate field most
+    /// instructions have. Only `mov` to a register takes a full 64 bits.
+    pub fn fits_imm32(self) -> bool {
+        match self {
+            Operand::Imm(value) => value as i32 as i64 == value,
+            _ => true
+        }
+    }
+
+    /// Registers read to find the operand, or to read it if it is one.
+    pub fn registers(self) -> Vec<Reg> {
+        match self {
+            Operand::Reg(reg) | Operand::Mem { base: reg, .. } => vec![reg],
+            Operand::Imm(_) => Vec::new()
+        }
+    }
+}
+
+/// A name for a position in the code, either a function or a local label.
+#[derive(Debug,PartialEq,Eq,Clone,Hash)]
+pub struct Label(pub String);
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Hash)]
+pub enum Condition {
+    E,
+    Ne,
+    L,
+    Le,
+    G,
+    Ge,
+    B,
+    Be,
+    A,
+    Ae
+}
+
+impl Condition {
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Condition::E => "e",
+            Condition::Ne => "ne",
+            Condition::L => "l",
+            Condition::Le => "le",
+            Condition::G => "g",
+            Condition::Ge => "ge",
+            Condition::B => "b",
+            Condition::Be => "be",
+            Condition::A => "a",
+            Condition::Ae => "ae"
+        }
+    }
+
+    pub fn negate(self) -> Condition {
+        match self {
+            Condition::E => Condition::Ne,
+            Condition::Ne => Condition::E,
+            Condition::L => Condition::Ge,
+            Condition::Le => Condition::G,
+            Condition::G => Condition::Le,
+            Condition::Ge => Condition::L,
+            Condition::B => Condition::Ae,
+            Condition::Be => Condition::A,
+            Condition::A => Condition::Be,
+            Condition::Ae => Condition::B
+        }
+    }
+}
+
+/// An instruction or a line of assembly that matters to control flow. Unless
+/// noted, operations are on quadwords and operands are in AT&T order.
+#[derive(Debug,PartialEq,Eq,Clone)]
+pub enum Instr {
+    Global(String),
+    Label(Label),
+    Mov { src: Operand, dst: Operand },
+    /// Sign extends the low byte of `src`.
+    Movsx8 { src: Operand, dst: Reg },
+    /// Sign extends the low 32 bits of `src`.
+    Movsx32 { src: Operand, dst: Reg },
+    /// Zero extends the low byte of `src`.
+    Movzx8 { src: Reg, dst: Reg },
+    /// Zero extends the low 32 bits of `src`, as any 32-bit write does.
+    Movzx32 { src: Operand, dst: Reg },
+    /// Zeroes a register with the idiomatic 32-bit `xor`.
+    Zero(Reg),
+    Add { src: Operand, dst: Operand },
+    Sub { src: Operand, dst: Operand },
+    And { src: Operand, dst: Operand },
+    Imul { src: Operand, dst: Reg },
+    Neg(Operand),
+    Not(Operand),
+    /// Shifts take an immediate count or `%rcx`, of which only `%cl` is read.
+    Shl { count: Operand, dst: Operand },
+    Sar { count: Operand, dst: Operand },
+    Shr { count: Operand, dst: Operand },
+    /// Sign extends `%rax` into `%rdx`.
+    Cqo,
+    /// Divides `%rdx:%rax`, leaving the quotient in `%rax`.
+    Idiv(Operand),
+    Div(Operand),
+    Cmp { src: Operand, dst: Operand },
+    Test { src: Operand, dst: Operand },
+    /// Sets the low byte of a register to whether the condition holds.
+    Set(Condition, Reg),
+    Jmp(Label),
+    Jcc(Condition, Label),
+    Call(String),
+    Ret,
+    Push(Operand),
+    Pop(Operand)
+}
+
+impl Instr {
+    /// Whether control can reach the next instruction without a jump to it.
+    pub fn falls_through(&self) -> bool {
+        !matches!(*self, Instr::Jmp(_) | Instr::Ret)
+    }
+
+    /// Whether control can continue somewhere other than the next instruction.
+    pub fn is_branch(&self) -> bool {
+        matches!(*self, Instr::Jmp(_) | Instr::Jcc(_, _) | Instr::Ret)
+    }
+
+    pub fn jump_target(&self) -> Option<&Label> {
+        match *self {
+            Instr::Jmp(ref label) | Instr::Jcc(_, ref label) => Some(label),
+            _ => None
+        }
+    }
+
+    pub fn reads_flags(&self) -> bool {
+        matches!(*self, Instr::Jcc(_, _) | Instr::Set(_, _))
+    }
+
+    /// Whether the instruction overwrites all the flags `reads_flags` ones use.
+    /// Shifts are left out, since a zero count leaves the flags alone.
+    pub fn writes_flags(&self) -> bool {
+        matches!(*self, Instr::Zero(_) | Instr::Add { .. } | Instr::Sub { .. } | Instr::And { .. } | Instr::Neg(_) |
+            Instr::Cmp { .. } | Instr::Test { .. } | Instr::Call(_))
+    }
+
+    /// Registers the instruction reads, including those used for addressing.
+    /// A call reads the argument registers.
+    pub fn reads(&self) -> Vec<Reg> {
+        let mut registers = match *self {
+            Instr::Global(_) | Instr::Label(_) | Instr::Zero(_) | Instr::Jmp(_) | Instr::Jcc(_, _) => Vec::new(),
+            Instr::Mov { src, dst } => {
+                let mut registers = src.registers();
+
+                if dst.is_mem() {
+                    registers.extend(dst.registers());
+                }
+
+                registers
+            },
+            Instr::Movsx8 { src, .. } | Instr::Movsx32 { src, .. } | Instr::Movzx32 { src, .. } => src.registers(),
+            Instr::Movzx8 { src, .. } => vec![src],
+            Instr::Add { src, dst } | Instr::Sub { src, dst } | Instr::And { src, dst } |
+            Instr::Cmp { src, dst } | Instr::Test { src, dst } => {
+                let mut registers = src.registers();
+                registers.extend(dst.registers());
+                registers
+            },
+            Instr::Imul { src, dst } => {
+                let mut registers = src.registers();
+                registers.push(dst);
+                registers
+            },
+            Instr::Neg(operand) | Instr::Not(operand) | Instr::Push(operand) => operand.registers(),
+            Instr::Shl { count, dst } | Instr::Sar { count, dst } | Instr::Shr { count, dst } => {
+                let mut registers = count.registers();
+                registers.extend(dst.registers());
+                registers
+            },
+            Instr::Cqo => vec![Reg::Rax],
+            Instr::Idiv(operand) | Instr::Div(operand) => {
+                let mut registers = operand.registers();
+                registers.extend(vec![Reg::Rax, Reg::Rdx]);
+                registers
+            },
+            // Only the low byte is written, so the rest is kept
+            Instr::Set(_, reg) => vec![reg],
+            Instr::Call(_) => vec![Reg::Rdi, Reg::Rsi, Reg::Rdx, Reg::Rcx, Reg::R8, Reg::R9],
+            Instr::Ret => vec![Reg::Rax],
+            Instr::Pop(operand) => if operand.is_mem() { operand.registers() } else { Vec::new() }
+        };
+
+        if let Instr::Push(_) | Instr::Pop(_) | Instr::Call(_) | Instr::Ret = *self {
+            registers.push(Reg::Rsp);
+        }
+
+        registers
+    }
+
+    /// Registers the instruction writes. A call clobbers every caller-saved
+    /// register.
+    pub fn writes(&self) -> Vec<Reg> {
+        let destination = |operand: Operand| match operand {
+            Operand::Reg(reg) => vec![reg],
+            _ => Vec::new()
+        };
+
+        let mut registers = match *self {
+            Instr::Mov { dst, .. } | Instr::Add { dst, .. } | Instr::Sub { dst, .. } | Instr::And { dst, .. } |
+            Instr::Shl { dst, .. } | Instr::Sar { dst, .. } | Instr::Shr { dst, .. } => destination(dst),
+            Instr::Neg(operand) | Instr::Not(operand) | Instr::Pop(operand) => destination(operand),
+            Instr::Movsx8 { dst, .. } | Instr::Movsx32 { dst, .. } | Instr::Movzx8 { dst, .. } |
+            Instr::Movzx32 { dst, .. } | Instr::Imul { dst, .. } | Instr::Set(_, dst) | Instr::Zero(dst) => vec![dst],
+            Instr::Cqo => vec![Reg::Rdx],
+            Instr::Idiv(_) | Instr::Div(_) => vec![Reg::Rax, Reg::Rdx],
+            Instr::Call(_) => vec![Reg::Rax, Reg::Rcx, Reg::Rdx, Reg::Rsi, Reg::Rdi, Reg::R8, Reg::R9, Reg::R10, Reg::R11],
+            Instr::Global(_) | Instr::Label(_) | Instr::Cmp { .. } | Instr::Test { .. } |
+            Instr::Jmp(_) | Instr::Jcc(_, _) | Instr::Ret | Instr::Push(_) => Vec::new()
+        };
+
+        if let Instr::Push(_) | Instr::Pop(_) | Instr::Call(_) | Instr::Ret = *self {
+            registers.push(Reg::Rsp);
+        }
+
+        registers
+    }
+
+    /// Whether the instruction reads or writes memory.
+    pub fn accesses_memory(&self) -> bool {
+        match *self {
+            Instr::Mov { src, dst } | Instr::Add { src, dst } | Instr::Sub { src, dst } | Instr::And { src, dst } |
+            Instr::Cmp { src, dst } | Instr::Test { src, dst } => src.is_mem() || dst.is_mem(),
+            Instr::Shl { count, dst } | Instr::Sar { count, dst } | Instr::Shr { count, dst } => count.is_mem() || dst.is_mem(),
+            Instr::Movsx8 { src, .. } | Instr::Movsx32 { src, .. } | Instr::Movzx32 { src, .. } | Instr::Imul { src, .. } => src.is_mem(),
+            Instr::Neg(operand) | Instr::Not(operand) | Instr::Idiv(operand) | Instr::Div(operand) => operand.is_mem(),
+            Instr::Push(_) | Instr::Pop(_) | Instr::Call(_) | Instr::Ret => true,
+            _ => false
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum Syntax {
+    Att,
+    Intel
+}
+
+impl Syntax {
+    /// The syntax a `-masm=` option names.
+    pub fn from_name(name: &str) -> Option<Syntax> {
+        match name {
+            "att" => Some(Syntax::Att),
+            "intel" => Some(Syntax::Intel),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "%{}", self.name())
+    }
+}
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operand::Reg(reg) => write!(f, "{}", reg),
+            Operand::Imm(value) => write!(f, "${}", value),
+            Operand::Mem { base, offset: 0 } => write!(f, "({})", base),
+            Operand::Mem { base, offset } => write!(f, "{}({})", offset, base)
+        }
+    }
+}
+
+// The name of the low `size` bytes of a register
+fn register(reg: Reg, size: usize) -> String {
+    match size {
+        8 => reg.name().to_string(),
+        4 => reg.name32(),
+        _ => reg.name8()
+    }
+}
+
+// An operand `size` bytes wide, on a machine whose addresses are `word` bytes
+fn att_operand(operand: Operand, size: usize, word: usize) -> String {
+    match operand {
+        Operand::Reg(reg) => format!("%{}", register(reg, size)),
+        Operand::Imm(value) => format!("${}", value),
+        Operand::Mem { base, offset: 0 } => format!("(%{})", register(base, word)),
+        Operand::Mem { base, offset } => format!("{}(%{})", offset, register(base, word))
+    }
+}
+
+fn att(instr: &Instr, word: usize) -> String {
+    let w = |operand| att_operand(operand, word, word);
+    let suffix = if word == 8 { 'q' } else { 'l' };
+
+    // Shift counts in a register are always %cl
+    let count = |operand| match operand {
+        Operand::Reg(Reg::Rcx) => "%cl".to_string(),
+        _ => w(operand)
+    };
+
+    match *instr {
+        Instr::Global(ref name) => format!(".globl {}", name),
+        Instr::Label(ref label) => format!("{}:", label),
+        Instr::Mov { src, dst } => format!("mov{} {}, {}", suffix, w(src), w(dst)),
+        Instr::Movsx8 { src, dst } => format!("movsb{} {}, %{}", suffix, att_operand(src, 1, word), register(dst, word)),
+        Instr::Movsx32 { src, dst } if word == 4 => format!("movl {}, %{}", w(src), dst.name32()),
+        Instr::Movsx32 { src, dst } => format!("movslq {}, {}", att_operand(src, 4, word), dst),
+        Instr::Movzx8 { src, dst } => format!("movzb{} %{}, %{}", suffix, src.name8(), register(dst, word)),
+        Instr::Movzx32 { src, dst } => format!("movl {}, %{}", att_operand(src, 4, word), dst.name32()),
+        Instr::Zero(reg) => format!("xorl %{}, %{}", reg.name32(), reg.name32()),
+        Instr::Add { src, dst } => format!("add{} {}, {}", suffix, w(src), w(dst)),
+        Instr::Sub { src, dst } => format!("sub{} {}, {}", suffix, w(src), w(dst)),
+        Instr::And { src, dst } => format!("and{} {}, {}", suffix, w(src), w(dst)),
+        Instr::Imul { src, dst } => format!("imul{} {}, %{}", suffix, w(src), register(dst, word)),
+        Instr::Neg(operand) => format!("neg{} {}", suffix, w(operand)),
+        Instr::Not(operand) => format!("not{} {}", suffix, w(operand)),
+        Instr::Shl { count: shift, dst } => format!("shl{} {}, {}", suffix, count(shift), w(dst)),
+        Instr::Sar { count: shift, dst } => format!("sar{} {}, {}", suffix, count(shift), w(dst)),
+        Instr::Shr { count: shift, dst } => format!("shr{} {}, {}", suffix, count(shift), w(dst)),
+        Instr::Cqo if word == 4 => "cltd".to_string(),
+        Instr::Cqo => "cqo".to_string(),
+        Instr::Idiv(operand) => format!("idiv{} {}", suffix, w(operand)),
+        Instr::Div(operand) => format!("div{} {}", suffix, w(operand)),
+        Instr::Cmp { src, dst } => format!("cmp{} {}, {}", suffix, w(src), w(dst)),
+        Instr::Test { src, dst } => format!("test{} {}, {}", suffix, w(src), w(dst)),
+        Instr::Set(condition, reg) => format!("set{} %{}", condition.suffix(), reg.name8()),
+        Instr::Jmp(ref label) => format!("jmp {}", label),
+        Instr::Jcc(condition, ref label) => format!("j{} {}", condition.suffix(), label),
+        Instr::Call(ref name) => format!("call {}", name),
+        Instr::Ret => "ret".to_string(),
+        Instr::Push(operand) => format!("push{} {}", suffix, w(operand)),
+        Instr::Pop(operand) => format!("pop{} {}", suffix, w(operand))
+    }
+}
+
+// An operand in Intel syntax, where memory is spelled with its size since
+// the other operand does not always give it
+fn intel_operand(operand: Operand, size: usize, word: usize) -> String {
+    let size_name = match size {
+        8 => "QWORD",
+        4 => "DWORD",
+        _ => "BYTE"
+    };
+
+    match operand {
+        Operand::Reg(reg) => register(reg, size),
+        Operand::Imm(value) => value.to_string(),
+        Operand::Mem { base, offset: 0 } => format!("{} PTR [{}]", size_name, register(base, word)),
+        Operand::Mem { base, offset } if offset < 0 => format!("{} PTR [{}-{}]", size_name, register(base, word), -offset),
+        Operand::Mem { base, offset } => format!("{} PTR [{}+{}]", size_name, register(base, word), offset)
+    }
+}
+
+fn intel(instr: &Instr, word: usize) -> String {
+    let w = |operand| intel_operand(operand, word, word);
+    let shift = |operand| match operand {
+        Operand::Reg(Reg::Rcx) => "cl".to_string(),
+        _ => w(operand)
+    };
+
+    match *instr {
+        Instr::Global(ref name) => format!(".globl {}", name),
+        Instr::Label(ref label) => format!("{}:", label),
+        Instr::Mov { src, dst } => format!("mov {}, {}", w(dst), w(src)),
+        Instr::Movsx8 { src, dst } => format!("movsx {}, {}", register(dst, word), intel_operand(src, 1, word)),
+        Instr::Movsx32 { src, dst } if word == 4 => format!("mov {}, {}", dst.name32(), w(src)),
+        Instr::Movsx32 { src, dst } => format!("movsxd {}, {}", dst.name(), intel_operand(src, 4, word)),
+        Instr::Movzx8 { src, dst } => format!("movzx {}, {}", register(dst, word), src.name8()),
+        Instr::Movzx32 { src, dst } => format!("mov {}, {}", dst.name32(), intel_operand(src, 4, word)),
+        Instr::Zero(reg) => format!("xor {}, {}", reg.name32(), reg.name32()),
+        Instr::Add { src, dst } => format!("add {}, {}", w(dst), w(src)),
+        Instr::Sub { src, dst } => format!("sub {}, {}", w(dst), w(src)),
+        Instr::And { src, dst } => format!("and {}, {}", w(dst), w(src)),
+        // The two operand form with an immediate is shorthand for three
+        Instr::Imul { src: Operand::Imm(value), dst } => {
+            format!("imul {}, {}, {}", register(dst, word), register(dst, word), value)
+        },
+        Instr::Imul { src, dst } => format!("imul {}, {}", register(dst, word), w(src)),
+        Instr::Neg(operand) => format!("neg {}", w(operand)),
+        Instr::Not(operand) => format!("not {}", w(operand)),
+        Instr::Shl { count, dst } => format!("shl {}, {}", w(dst), shift(count)),
+        Instr::Sar { count, dst } => format!("sar {}, {}", w(dst), shift(count)),
+        Instr::Shr { count, dst } => format!("shr {}, {}", w(dst), shift(count)),
+        Instr::Cqo if word == 4 => "cdq".to_string(),
+        Instr::Cqo => "cqo".to_string(),
+        Instr::Idiv(operand) => format!("idiv {}", w(operand)),
+        Instr::Div(operand) => format!("div {}", w(operand)),
+        Instr::Cmp { src, dst } => format!("cmp {}, {}", w(dst), w(src)),
+        Instr::Test { src, dst } => format!("test {}, {}", w(dst), w(src)),
+        Instr::Set(condition, reg) => format!("set{} {}", condition.suffix(), reg.name8()),
+        Instr::Jmp(ref label) => format!("jmp {}", label),
+        Instr::Jcc(condition, ref label) => format!("j{} {}", condition.suffix(), label),
+        Instr::Call(ref name) => format!("call {}", name),
+        Instr::Ret => "ret".to_string(),
+        Instr::Push(operand) => format!("push {}", w(operand)),
+        Instr::Pop(operand) => format!("pop {}", w(operand))
+    }
+}
+
+impl Instr {
+    /// Prints the instruction with operands as wide as the layout's word.
+    pub fn print(&self, syntax: Syntax, layout: &DataLayout) -> String {
+        match syntax {
+            Syntax::Att => att(self, layout.word_size),
+            Syntax::Intel => intel(self, layout.word_size)
+        }
+    }
+}
+
+/// Prints instructions one per line, starting with the directive that
+/// selects the syntax.
+pub fn print(instrs: &[Instr], syntax: Syntax, layout: &DataLayout) -> String {
+    let mut buf = match syntax {
+        Syntax::Att => String::new(),
+        Syntax::Intel => ".intel_syntax noprefix\n".to_string()
+    };
+
+    for instr in instrs {
+        buf.push_str(&instr.print(syntax, layout));
+        buf.push('\n');
+    }
+
+    buf
+}
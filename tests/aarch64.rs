@@ -0,0 +1,32 @@
+//! Checks the AArch64 backend against golden assembly, and runs the programs
+//! under qemu when a cross toolchain and emulator are installed.
+
+extern crate cc;
+
+mod common;
+
+use cc::aarch64;
+
+use common::Cross;
+
+const TARGET: Cross = Cross {
+    name: "aarch64",
+    title: "AArch64",
+    llvm_mc: &["--triple=aarch64-linux-gnu"],
+    generate: aarch64::generate
+};
+
+#[test]
+fn matches_golden_assembly() {
+    TARGET.check_golden_assembly();
+}
+
+#[test]
+fn assembles() {
+    TARGET.check_assembles();
+}
+
+#[test]
+fn runs_under_qemu() {
+    TARGET.check_runs_under_qemu();
+}
@@ -0,0 +1,261 @@
+//! Programs and helpers shared by the integration tests.
+
+// Each test binary uses only some of these
+#![allow(dead_code)]
+
+use cc::{diagnostics, ir, lower, opt, parser, printer, sema};
+use cc::target::DataLayout;
+use cc::TranslationUnit;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Calls with arguments on the stack, and recursion that rotates them.
+pub const CALLS: &str = "
+int putchar(int c);
+
+int perm(int a, int b, int c, int d, int e, int f, int g, int h, int i, int j) {
+    return a * 1 + b * 2 + c * 3 + d * 4 + e * 5 + f * 6 + g * 7 + h * 8 + i * 9 - j;
+}
+
+int rotate(int a, int b, int c, int d, int e, int f, int g, int h, int i, int j) {
+    if (a > 100)
+        return perm(a, b, c, d, e, f, g, h, i, j);
+    return rotate(b + 100, c, d, e, f, g, h, i, j, a);
+}
+
+int main() {
+    putchar(72);
+    return rotate(1, 2, 3, 4, 5, 6, 7, 8, 9, 10) - perm(10, 9, 8, 7, 6, 5, 4, 3, 2, 1);
+}
+";
+
+/// Every operator, on every type, with constants of every width.
+pub const ARITHMETIC: &str = "
+long wide(long x) {
+    return x * 5000000000 + 4000000000 + (x < 3000000000);
+}
+
+unsigned int ucmp(unsigned int a, unsigned int b) {
+    return (a < b) + (a <= b) * 2 + (a > b) * 4 + (a >= b) * 8 + (a >> 28) * 16 + a / b;
+}
+
+int shifts(int x, int n) {
+    return (x << n) + (x >> n) + (x << 3) - (x >> 1) + -x + ~n + !x;
+}
+
+char narrow(int x) {
+    char c = x;
+    c = c + 100;
+    return c;
+}
+
+int logic(int x, int y) {
+    int r = 0;
+    while (x > 0 && y != 0 || x == 7) {
+        r = r + x / y;
+        x = x - 1;
+    }
+    return r;
+}
+
+int main() {
+    return wide(7) / 1000000000 + ucmp(1, 4000000000) + shifts(-77, 2) + narrow(100) + logic(9, 2);
+}
+";
+
+/// The example programs in `cfiles`, with the status each exits with.
+pub const EXAMPLES: [(&str, i32); 8] = [
+    ("return_2", 2),
+    ("simple_add", 3),
+    ("simple_bitwise", 253),
+    ("simple_div", 2),
+    ("simple_equal", 1),
+    ("simple_less", 1),
+    ("simple_mul", 2),
+    ("simple_sub", 1)
+];
+
+/// The levels the backends are tested at: unoptimized, and with every pass.
+pub const LEVELS: [(&str, opt::Level); 2] = [("O0", opt::Level::O0), ("O2", opt::Level::O2)];
+
+/// Every program the backends are tested on, with its exit status.
+pub fn programs() -> Vec<(String, String, i32)> {
+    let mut programs = vec![
+        ("calls".to_string(), CALLS.to_string(), 220),
+        ("arithmetic".to_string(), ARITHMETIC.to_string(), 199)
+    ];
+
+    for &(name, status) in &EXAMPLES {
+        programs.push((name.to_string(), example(name), status));
+    }
+
+    programs
+}
+
+/// The programs with the status each exits with when compiled for `layout`.
+pub fn programs_for(layout: &DataLayout) -> Vec<(String, String, i32)> {
+    let mut programs = programs();
+
+    // Where `long` is 32 bits, the large constants in `wide` wrap
+    if layout.word_size == 4 {
+        for &mut (ref name, _, ref mut status) in &mut programs {
+            if name == "arithmetic" {
+                *status = 160;
+            }
+        }
+    }
+
+    programs
+}
+
+pub fn example(name: &str) -> String {
+    let mut source = String::new();
+    File::open(format!("cfiles/{}.c", name)).unwrap().read_to_string(&mut source).unwrap();
+
+    source
+}
+
+pub fn analyze(source: &str) -> TranslationUnit {
+    let mut ast = parser::parse(source);
+    assert!(!diagnostics::has_errors(&sema::analyze(&mut ast)));
+
+    ast
+}
+
+/// Parses without spans, for comparing with trees built by hand or parsed
+/// from other text.
+pub fn parse_without_spans(source: &str) -> TranslationUnit {
+    let mut ast = parser::parse(source);
+    printer::erase_spans(&mut ast);
+
+    ast
+}
+
+pub fn lower(source: &str, level: opt::Level) -> ir::Module {
+    lower_for(source, level, &DataLayout::LP64)
+}
+
+pub fn lower_for(source: &str, level: opt::Level, layout: &DataLayout) -> ir::Module {
+    let mut module = lower::lower(&analyze(source), layout);
+    opt::optimize(&mut module, level);
+
+    module
+}
+
+/// Whether a program can be run, judged by asking for its version.
+pub fn installed(program: &str) -> bool {
+    Command::new(program).arg("--version").output().is_ok()
+}
+
+/// A directory of this test run's own for files to assemble and link.
+pub fn scratch_directory(name: &str) -> PathBuf {
+    let directory = env::temp_dir().join(format!("cc-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    directory
+}
+
+pub fn write(path: &Path, contents: &str) {
+    File::create(path).unwrap().write_all(contents.as_bytes()).unwrap();
+}
+
+/// Compares output with a file under `tests/golden`, or rewrites the file
+/// when `CC_UPDATE_GOLDEN` is set.
+pub fn check_golden(name: &str, actual: &str) {
+    let path = Path::new("tests/golden").join(name);
+
+    if env::var_os("CC_UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        return write(&path, actual);
+    }
+
+    let mut expected = String::new();
+    File::open(&path)
+        .unwrap_or_else(|_| panic!("{} is missing, run with CC_UPDATE_GOLDEN=1 to create it", path.display()))
+        .read_to_string(&mut expected)
+        .unwrap();
+
+    assert!(expected == actual, "{} differs from the generated code:\n{}", path.display(), actual);
+}
+
+/// A 64-bit Linux target with a GNU cross toolchain named after it, whose
+/// assembly is checked against goldens and run under qemu.
+pub struct Cross {
+    /// The name used for the toolchain, qemu and the golden directory.
+    pub name: &'static str,
+    /// The name in messages.
+    pub title: &'static str,
+    /// What to give llvm-mc to assemble for the target, when there is no
+    /// GNU assembler.
+    pub llvm_mc: &'static [&'static str],
+    pub generate: fn(&ir::Module) -> String
+}
+
+impl Cross {
+    fn generate(&self, source: &str, level: opt::Level) -> String {
+        (self.generate)(&lower(source, level))
+    }
+
+    pub fn check_golden_assembly(&self) {
+        for (name, source, _) in programs() {
+            for &(level_name, level) in &LEVELS {
+                check_golden(&format!("{}/{}-{}.s", self.name, name, level_name), &self.generate(&source, level));
+            }
+        }
+    }
+
+    pub fn check_assembles(&self) {
+        let gnu = format!("{}-linux-gnu-as", self.name);
+
+        let assembler: Vec<&str> = if installed(&gnu) {
+            vec![&gnu]
+        } else if installed("llvm-mc") {
+            std::iter::once("llvm-mc").chain(self.llvm_mc.iter().cloned()).chain(std::iter::once("-filetype=obj")).collect()
+        } else {
+            return eprintln!("no {} assembler is installed, skipping", self.title);
+        };
+
+        let directory = scratch_directory(&format!("{}-as", self.name));
+
+        for (name, source, _) in programs() {
+            for &(level_name, level) in &LEVELS {
+                let path = directory.join(format!("{}-{}.s", name, level_name));
+                write(&path, &self.generate(&source, level));
+
+                let output = Command::new(assembler[0]).args(&assembler[1..])
+                    .arg(&path).arg("-o").arg(path.with_extension("o"))
+                    .output().unwrap();
+
+                assert!(output.status.success(), "{} does not assemble:\n{}", path.display(), String::from_utf8_lossy(&output.stderr));
+            }
+        }
+    }
+
+    pub fn check_runs_under_qemu(&self) {
+        let (gcc, qemu) = (format!("{}-linux-gnu-gcc", self.name), format!("qemu-{}", self.name));
+
+        if !installed(&gcc) || !installed(&qemu) {
+            return eprintln!("no {} toolchain and emulator are installed, skipping", self.title);
+        }
+
+        let directory = scratch_directory(&format!("{}-run", self.name));
+
+        for (name, source, status) in programs() {
+            for &(level_name, level) in &LEVELS {
+                let path = directory.join(format!("{}-{}.s", name, level_name));
+                let program = path.with_extension("");
+                write(&path, &self.generate(&source, level));
+
+                let output = Command::new(&gcc).arg("-static").arg(&path).arg("-o").arg(&program).output().unwrap();
+                assert!(output.status.success(), "{} does not link:\n{}", path.display(), String::from_utf8_lossy(&output.stderr));
+
+                let output = Command::new(&qemu).arg(&program).output().unwrap();
+                assert_eq!(output.status.code(), Some(status), "{} exits with the wrong status", program.display());
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Checks constant folding where C arithmetic is subtle: overflow, wrapping,
+//! and operations whose result is undefined and so must not be folded.
+
+extern crate cc;
+
+use cc::constant::{self, Folded};
+use cc::diagnostics::Warning;
+use cc::{parser, sema, BinaryOperator, Expr, ExprKind, Span, Type, UnaryOperator};
+
+// Analyzes `ty x = init;`, returning the initializer and the warnings about it
+fn initializer(ty: &str, init: &str) -> (Expr, Vec<(Warning, String)>) {
+    let source = format!("int main() {{ {} x = {}; return x != 0; }}", ty, init);
+    let mut ast = parser::parse(&source);
+    let diagnostics = sema::analyze(&mut ast);
+
+    let warnings = diagnostics.into_iter()
+        .map(|diagnostic| (diagnostic.warning.expect("not a warning"), diagnostic.message))
+        .collect();
+    let init = ast.functions[0].decls()[0].init.clone().unwrap();
+
+    (init, warnings)
+}
+
+fn folded(ty: &str, init: &str) -> Option<i64> {
+    constant::literal_value(&initializer(ty, init).0)
+}
+
+fn typed(kind: ExprKind, ty: Type) -> Expr {
+    Expr {
+        kind,
+        span: Span::default(),
+        ty: Some(ty)
+    }
+}
+
+fn binary(operator: BinaryOperator, lhs: i64, rhs: i64, ty: Type) -> Expr {
+    let (lhs, rhs) = (constant::literal(lhs, ty, Span::default()), constant::literal(rhs, ty, Span::default()));
+
+    typed(ExprKind::Binary(operator, Box::new(lhs), Box::new(rhs)), ty)
+}
+
+#[test]
+fn folds_nested_expressions_bottom_up() {
+    assert_eq!(folded("int", "(1 + 2) * 3 - 4 / 2"), Some(7));
+    assert_eq!(folded("int", "-(2 << 3) >> 1"), Some(-8));
+    assert_eq!(folded("int", "!0 + ~0 + (3 < 4) + (5 == 6)"), Some(1));
+    assert_eq!(folded("long", "4294967296 * 4"), Some(17179869184));
+}
+
+#[test]
+fn folds_logical_operators_that_a_constant_decides() {
+    let source = "int main() { int y = 1; int a = 0 && y; int b = 1 || y; int c = 1 && y; return a + b + c; }";
+    let mut ast = parser::parse(source);
+    sema::analyze(&mut ast);
+
+    let decls = ast.functions[0].decls();
+    let value = |index: usize| constant::evaluate(decls[index].init.as_ref().unwrap());
+
+    assert_eq!((value(1), value(2), value(3)), (Some(0), Some(1), None));
+}
+
+#[test]
+fn wraps_and_reports_signed_overflow() {
+    let (init, warnings) = initializer("int", "2147483647 + 1");
+    assert_eq!(constant::literal_value(&init), Some(-2147483648));
+    assert_eq!(warnings, [(Warning::IntegerOverflow, "overflow in expression; result is -2147483648 with type 'int'".to_string())]);
+
+    // INT_MIN / -1 is the one division that overflows
+    let (init, warnings) = initializer("int", "(-2147483647 - 1) / -1");
+    assert_eq!(constant::literal_value(&init), Some(-2147483648));
+    assert_eq!(warnings, [(Warning::IntegerOverflow, "overflow in expression; result is -2147483648 with type 'int'".to_string())]);
+
+    let (init, warnings) = initializer("long", "-(-9223372036854775807 - 1)");
+    assert_eq!(constant::literal_value(&init), Some(i64::MIN));
+    assert_eq!(warnings.len(), 1);
+
+    // In range results, including INT_MIN itself, are quiet
+    assert_eq!(initializer("int", "-2147483647 - 1").1, []);
+    assert_eq!(initializer("int", "(-2147483647 - 1) / 1").1, []);
+}
+
+#[test]
+fn wraps_unsigned_arithmetic_silently() {
+    let max = u32::MAX as i64;
+
+    assert_eq!(constant::fold_node(&binary(BinaryOperator::Addition, max, 1, Type::UnsignedInt)),
+               Some(Folded { value: 0, overflow: false }));
+    assert_eq!(constant::fold_node(&binary(BinaryOperator::Subtraction, 0, 1, Type::UnsignedInt)),
+               Some(Folded { value: max, overflow: false }));
+    assert_eq!(constant::fold_node(&binary(BinaryOperator::Multiplication, 65536, 65536, Type::UnsignedInt)),
+               Some(Folded { value: 0, overflow: false }));
+
+    // Unsigned values are zero extended, so a right shift brings in zeroes
+    assert_eq!(constant::fold_node(&binary(BinaryOperator::ShiftRight, max, 28, Type::UnsignedInt)),
+               Some(Folded { value: 15, overflow: false }));
+
+    let complement = typed(ExprKind::Unary(UnaryOperator::BitwiseComplement, Box::new(constant::literal(0, Type::UnsignedInt, Span::default()))), Type::UnsignedInt);
+    assert_eq!(constant::fold_node(&complement), Some(Folded { value: max, overflow: false }));
+
+    // Conversions wrap too, without an overflow warning
+    assert_eq!(folded("unsigned int", "-1"), Some(max));
+    assert_eq!(folded("char", "200 + 100"), Some(44));
+}
+
+#[test]
+fn leaves_shifts_out_of_range_unfolded() {
+    let (init, warnings) = initializer("int", "1 << 32");
+    assert_eq!(constant::literal_value(&init), None);
+    assert_eq!(warnings, [(Warning::ShiftCountOverflow, "shift count >= width of type".to_string())]);
+
+    assert_eq!(folded("int", "1 >> 40"), None);
+    assert_eq!(folded("int", "1 << -1"), None);
+    assert_eq!(constant::fold_node(&binary(BinaryOperator::ShiftLeft, 1, 64, Type::Long)), None);
+
+    // The width is the left operand's, so a long can shift further
+    assert_eq!(folded("long", "4294967296 << 31"), Some(1 << 63));
+    assert_eq!(folded("int", "1 << 31"), Some(-2147483648));
+}
+
+#[test]
+fn leaves_division_by_zero_unfolded() {
+    let (init, warnings) = initializer("int", "1 / 0");
+    assert_eq!(constant::literal_value(&init), None);
+    assert!(matches!(init.kind, ExprKind::Binary(BinaryOperator::Division, _, _)));
+    assert_eq!(warnings, [(Warning::DivisionByZero, "division by zero is undefined".to_string())]);
+
+    // Operands fold first, so a divisor that folds to zero is caught too
+    let (init, warnings) = initializer("int", "1 / (2 - 2)");
+    assert_eq!(constant::literal_value(&init), None);
+    assert_eq!(warnings.len(), 1);
+
+    assert_eq!(constant::evaluate(&init), None);
+}
@@ -0,0 +1,219 @@
+.globl wide
+wide:
+stp x29, x30, [sp, #-16]!
+mov x29, sp
+sub sp, sp, #64
+mov x9, x0
+.Lwide.bb0:
+movz x17, #61952, lsl #0
+movk x17, #10757, lsl #16
+movk x17, #1, lsl #32
+mul x10, x9, x17
+movz x17, #10240, lsl #0
+movk x17, #61035, lsl #16
+add x10, x10, x17
+movz x17, #24064, lsl #0
+movk x17, #45776, lsl #16
+cmp x9, x17
+cset x9, lt
+add x9, x10, x9
+mov x0, x9
+mov sp, x29
+ldp x29, x30, [sp], #16
+ret
+.globl ucmp
+ucmp:
+stp x29, x30, [sp, #-16]!
+mov x29, sp
+sub sp, sp, #64
+mov x9, x0
+mov w9, w9
+mov x10, x1
+mov w10, w10
+.Lucmp.bb0:
+cmp x9, x10
+cset x11, lo
+cmp x9, x10
+cset x12, ls
+mov x17, #2
+mul x12, x12, x17
+sxtw x12, w12
+add x11, x11, x12
+sxtw x11, w11
+cmp x9, x10
+cset x12, hi
+mov x17, #4
+mul x12, x12, x17
+sxtw x12, w12
+add x11, x11, x12
+sxtw x11, w11
+cmp x9, x10
+cset x12, hs
+mov x17, #8
+mul x12, x12, x17
+sxtw x12, w12
+add x11, x11, x12
+sxtw x11, w11
+mov w11, w11
+lsr x12, x9, #28
+mov x17, #16
+mul x12, x12, x17
+mov w12, w12
+add x11, x11, x12
+mov w11, w11
+udiv x9, x9, x10
+add x9, x11, x9
+mov w9, w9
+mov x0, x9
+mov sp, x29
+ldp x29, x30, [sp], #16
+ret
+.globl shifts
+shifts:
+stp x29, x30, [sp, #-16]!
+mov x29, sp
+sub sp, sp, #64
+mov x9, x0
+sxtw x9, w9
+mov x10, x1
+sxtw x10, w10
+.Lshifts.bb0:
+lsl x11, x9, x10
+sxtw x11, w11
+asr x12, x9, x10
+add x11, x11, x12
+sxtw x11, w11
+lsl x12, x9, #3
+sxtw x12, w12
+add x11, x11, x12
+sxtw x11, w11
+asr x12, x9, #1
+sub x11, x11, x12
+sxtw x11, w11
+neg x12, x9
+sxtw x12, w12
+add x11, x11, x12
+sxtw x11, w11
+mvn x10, x10
+sxtw x10, w10
+add x10, x11, x10
+sxtw x10, w10
+cmp x9, #0
+cset x9, eq
+add x9, x10, x9
+sxtw x9, w9
+mov x0, x9
+mov sp, x29
+ldp x29, x30, [sp], #16
+ret
+.globl narrow
+narrow:
+stp x29, x30, [sp, #-16]!
+mov x29, sp
+sub sp, sp, #64
+mov x9, x0
+sxtw x9, w9
+.Lnarrow.bb0:
+sxtb x9, w9
+sxtw x9, w9
+add x9, x9, #100
+sxtw x9, w9
+sxtb x9, w9
+mov x0, x9
+mov sp, x29
+ldp x29, x30, [sp], #16
+ret
+.globl logic
+logic:
+stp x29, x30, [sp, #-16]!
+mov x29, sp
+sub sp, sp, #64
+mov x9, x0
+sxtw x9, w9
+mov x10, x1
+sxtw x10, w10
+.Llogic.bb0:
+cmp x10, #0
+cset x11, ne
+cmp x11, #0
+cset x11, ne
+mov x12, #0
+.Llogic.bb1:
+cmp x9, #0
+cset x13, gt
+cbz x13, .Llogic.bb3
+.Llogic.bb2:
+mov x13, x11
+b .Llogic.bb4
+.Llogic.bb3:
+mov x13, #0
+.Llogic.bb4:
+cbz x13, .Llogic.bb6
+.Llogic.bb5:
+mov x13, #1
+b .Llogic.bb7
+.Llogic.bb6:
+cmp x9, #7
+cset x14, eq
+cmp x14, #0
+cset x14, ne
+mov x13, x14
+.Llogic.bb7:
+cbz x13, .Llogic.bb9
+.Llogic.bb8:
+sdiv x13, x9, x10
+add x13, x12, x13
+sxtw x13, w13
+sub x14, x9, #1
+sxtw x14, w14
+mov x9, x14
+mov x12, x13
+b .Llogic.bb1
+.Llogic.bb9:
+mov x0, x12
+mov sp, x29
+ldp x29, x30, [sp], #16
+ret
+.globl main
+main:
+stp x29, x30, [sp, #-16]!
+mov x29, sp
+sub sp, sp, #64
+str x19, [sp, #56]
+.Lmain.bb0:
+mov x0, #7
+bl wide
+mov x9, x0
+movz x17, #51712, lsl #0
+movk x17, #15258, lsl #16
+sdiv x19, x9, x17
+mov x0, #1
+movz x1, #10240, lsl #0
+movk x1, #61035, lsl #16
+bl ucmp
+mov x9, x0
+mov w9, w9
+add x19, x19, x9
+mov x0, #-77
+mov x1, #2
+bl shifts
+mov x9, x0
+sxtw x9, w9
+add x19, x19, x9
+mov x0, #100
+bl narrow
+mov x9, x0
+sxtb x9, w9
+add x19, x19, x9
+mov x0, #9
+mov x1, #2
+bl logic
+mov x9, x0
+sxtw x9, w9
+add x9, x19, x9
+sxtw x9, w9
+mov x0, x9
+ldr x19, [sp, #56]
+mov sp, x29
+ldp x29, x30, [sp], #16
+ret
@@ -0,0 +1,225 @@
+.globl perm
+perm:
+stp x29, x30, [sp, #-16]!
+mov x29, sp
+sub sp, sp, #80
+str x19, [sp, #56]
+str x20, [sp, #64]
+str x21, [sp, #72]
+mov x9, x0
+sxtw x9, w9
+mov x10, x1
+sxtw x10, w10
+mov x11, x2
+sxtw x11, w11
+mov x12, x3
+sxtw x12, w12
+mov x13, x4
+sxtw x13, w13
+mov x14, x5
+sxtw x14, w14
+mov x15, x6
+sxtw x15, w15
+mov x19, x7
+sxtw x19, w19
+ldr x20, [x29, #16]
+sxtw x20, w20
+ldr x21, [x29, #24]
+sxtw x21, w21
+.Lperm.bb0:
+mov x17, #1
+mul x9, x9, x17
+sxtw x9, w9
+mov x17, #2
+mul x10, x10, x17
+sxtw x10, w10
+add x9, x9, x10
+sxtw x9, w9
+mov x17, #3
+mul x10, x11, x17
+sxtw x10, w10
+add x9, x9, x10
+sxtw x9, w9
+mov x17, #4
+mul x10, x12, x17
+sxtw x10, w10
+add x9, x9, x10
+sxtw x9, w9
+mov x17, #5
+mul x10, x13, x17
+sxtw x10, w10
+add x9, x9, x10
+sxtw x9, w9
+mov x17, #6
+mul x10, x14, x17
+sxtw x10, w10
+add x9, x9, x10
+sxtw x9, w9
+mov x17, #7
+mul x10, x15, x17
+sxtw x10, w10
+add x9, x9, x10
+sxtw x9, w9
+mov x17, #8
+mul x10, x19, x17
+sxtw x10, w10
+add x9, x9, x10
+sxtw x9, w9
+mov x17, #9
+mul x10, x20, x17
+sxtw x10, w10
+add x9, x9, x10
+sxtw x9, w9
+sub x9, x9, x21
+sxtw x9, w9
+mov x0, x9
+ldr x19, [sp, #56]
+ldr x20, [sp, #64]
+ldr x21, [sp, #72]
+mov sp, x29
+ldp x29, x30, [sp], #16
+ret
+.globl rotate
+rotate:
+stp x29, x30, [sp, #-16]!
+mov x29, sp
+sub sp, sp, #160
+str x19, [sp, #72]
+str x20, [sp, #80]
+str x21, [sp, #88]
+str x22, [sp, #96]
+str x23, [sp, #104]
+str x24, [sp, #112]
+str x25, [sp, #120]
+str x26, [sp, #128]
+str x27, [sp, #136]
+str x28, [sp, #144]
+mov x19, x0
+sxtw x19, w19
+mov x20, x1
+sxtw x20, w20
+mov x21, x2
+sxtw x21, w21
+mov x22, x3
+sxtw x22, w22
+mov x23, x4
+sxtw x23, w23
+mov x24, x5
+sxtw x24, w24
+mov x25, x6
+sxtw x25, w25
+mov x26, x7
+sxtw x26, w26
+ldr x27, [x29, #16]
+sxtw x27, w27
+ldr x28, [x29, #24]
+sxtw x28, w28
+.Lrotate.bb0:
+cmp x19, #100
+cset x9, gt
+cbz x9, .Lrotate.bb2
+.Lrotate.bb1:
+str x27, [sp, #0]
+str x28, [sp, #8]
+mov x0, x19
+mov x1, x20
+mov x2, x21
+mov x3, x22
+mov x4, x23
+mov x5, x24
+mov x6, x25
+mov x7, x26
+bl perm
+mov x9, x0
+sxtw x9, w9
+mov x0, x9
+ldr x19, [sp, #72]
+ldr x20, [sp, #80]
+ldr x21, [sp, #88]
+ldr x22, [sp, #96]
+ldr x23, [sp, #104]
+ldr x24, [sp, #112]
+ldr x25, [sp, #120]
+ldr x26, [sp, #128]
+ldr x27, [sp, #136]
+ldr x28, [sp, #144]
+mov sp, x29
+ldp x29, x30, [sp], #16
+ret
+.Lrotate.bb2:
+add x9, x20, #100
+sxtw x9, w9
+str x28, [sp, #0]
+str x19, [sp, #8]
+mov x0, x9
+mov x1, x21
+mov x2, x22
+mov x3, x23
+mov x4, x24
+mov x5, x25
+mov x6, x26
+mov x7, x27
+bl rotate
+mov x9, x0
+sxtw x9, w9
+mov x0, x9
+ldr x19, [sp, #72]
+ldr x20, [sp, #80]
+ldr x21, [sp, #88]
+ldr x22, [sp, #96]
+ldr x23, [sp, #104]
+ldr x24, [sp, #112]
+ldr x25, [sp, #120]
+ldr x26, [sp, #128]
+ldr x27, [sp, #136]
+ldr x28, [sp, #144]
+mov sp, x29
+ldp x29, x30, [sp], #16
+ret
+.globl main
+main:
+stp x29, x30, [sp, #-16]!
+mov x29, sp
+sub sp, sp, #80
+str x19, [sp, #72]
+.Lmain.bb0:
+mov x0, #72
+bl putchar
+mov x9, x0
+mov x16, #9
+str x16, [sp, #0]
+mov x16, #10
+str x16, [sp, #8]
+mov x0, #1
+mov x1, #2
+mov x2, #3
+mov x3, #4
+mov x4, #5
+mov x5, #6
+mov x6, #7
+mov x7, #8
+bl rotate
+mov x9, x0
+sxtw x19, w9
+mov x16, #2
+str x16, [sp, #0]
+mov x16, #1
+str x16, [sp, #8]
+mov x0, #10
+mov x1, #9
+mov x2, #8
+mov x3, #7
+mov x4, #6
+mov x5, #5
+mov x6, #4
+mov x7, #3
+bl perm
+mov x9, x0
+sxtw x9, w9
+sub x9, x19, x9
+sxtw x9, w9
+mov x0, x9
+ldr x19, [sp, #72]
+mov sp, x29
+ldp x29, x30, [sp], #16
+ret
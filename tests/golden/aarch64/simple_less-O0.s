@@ -0,0 +1,10 @@
+.globl main
+main:
+stp x29, x30, [sp, #-16]!
+mov x29, sp
+sub sp, sp, #64
+.Lmain.bb0:
+mov x0, #1
+mov sp, x29
+ldp x29, x30, [sp], #16
+ret
@@ -0,0 +1,244 @@
+.globl wide
+wide:
+pushl %ebp
+movl %esp, %ebp
+subl $8, %esp
+movl %ebx, -4(%ebp)
+movl %esi, -8(%ebp)
+movl 8(%ebp), %ebx
+movl %ebx, %esi
+imull $705032704, %esi
+addl $-294967296, %esi
+cmpl $-1294967296, %ebx
+setl %al
+movzbl %al, %ebx
+movl %esi, %eax
+addl %ebx, %eax
+movl %eax, %ebx
+movl -4(%ebp), %ebx
+movl -8(%ebp), %esi
+movl %ebp, %esp
+popl %ebp
+ret
+.globl ucmp
+ucmp:
+pushl %ebp
+movl %esp, %ebp
+subl $24, %esp
+movl %ebx, -8(%ebp)
+movl %esi, -12(%ebp)
+movl %edi, -16(%ebp)
+movl 8(%ebp), %ebx
+movl 12(%ebp), %eax
+movl %eax, -4(%ebp)
+cmpl -4(%ebp), %ebx
+setb %al
+movzbl %al, %edi
+cmpl -4(%ebp), %ebx
+setbe %al
+movzbl %al, %esi
+shll $1, %esi
+movl %edi, %eax
+addl %esi, %eax
+movl %eax, %esi
+cmpl -4(%ebp), %ebx
+seta %al
+movzbl %al, %edi
+shll $2, %edi
+addl %edi, %esi
+cmpl -4(%ebp), %ebx
+setae %al
+movzbl %al, %edi
+shll $3, %edi
+addl %edi, %esi
+movl %ebx, %edi
+shrl $28, %edi
+shll $4, %edi
+addl %edi, %esi
+movl %ebx, %eax
+xorl %edx, %edx
+divl -4(%ebp)
+movl %eax, %ebx
+movl %esi, %eax
+addl %ebx, %eax
+movl %eax, %ebx
+movl -8(%ebp), %ebx
+movl -12(%ebp), %esi
+movl -16(%ebp), %edi
+movl %ebp, %esp
+popl %ebp
+ret
+.globl shifts
+shifts:
+pushl %ebp
+movl %esp, %ebp
+subl $24, %esp
+movl %ebx, -8(%ebp)
+movl %esi, -12(%ebp)
+movl %edi, -16(%ebp)
+movl 8(%ebp), %eax
+movl %eax, -4(%ebp)
+movl 12(%ebp), %esi
+movl %esi, %ecx
+movl -4(%ebp), %edi
+shll %cl, %edi
+movl %esi, %ecx
+movl -4(%ebp), %ebx
+sarl %cl, %ebx
+movl %edi, %eax
+addl %ebx, %eax
+movl %eax, %ebx
+movl -4(%ebp), %edi
+shll $3, %edi
+addl %edi, %ebx
+movl -4(%ebp), %edi
+sarl $1, %edi
+subl %edi, %ebx
+movl -4(%ebp), %edi
+negl %edi
+addl %edi, %ebx
+notl %esi
+addl %esi, %ebx
+movl -4(%ebp), %eax
+cmpl $0, %eax
+sete %al
+movzbl %al, %esi
+addl %esi, %ebx
+movl %ebx, %eax
+movl -8(%ebp), %ebx
+movl -12(%ebp), %esi
+movl -16(%ebp), %edi
+movl %ebp, %esp
+popl %ebp
+ret
+.globl narrow
+narrow:
+pushl %ebp
+movl %esp, %ebp
+subl $8, %esp
+movl %ebx, -4(%ebp)
+movl %esi, -8(%ebp)
+movl 8(%ebp), %ebx
+movsbl %bl, %ebx
+movl %ebx, %esi
+addl $100, %esi
+movl %esi, %eax
+movsbl %al, %esi
+movl %esi, %ebx
+movl %ebx, %eax
+movl -4(%ebp), %ebx
+movl -8(%ebp), %esi
+movl %ebp, %esp
+popl %ebp
+ret
+.globl logic
+logic:
+pushl %ebp
+movl %esp, %ebp
+subl $24, %esp
+movl %ebx, -12(%ebp)
+movl %esi, -16(%ebp)
+movl %edi, -20(%ebp)
+movl 8(%ebp), %ebx
+movl 12(%ebp), %eax
+movl %eax, -8(%ebp)
+movl $0, -4(%ebp)
+.Llogic.bb1:
+cmpl $0, %ebx
+jle .Llogic.bb3
+movl -8(%ebp), %eax
+cmpl $0, %eax
+setne %al
+movzbl %al, %edi
+cmpl $0, %edi
+setne %al
+movzbl %al, %edi
+jmp .Llogic.bb4
+.Llogic.bb3:
+movl $0, %edi
+.Llogic.bb4:
+testl %edi, %edi
+je .Llogic.bb6
+movl $1, %edi
+jmp .Llogic.bb7
+.Llogic.bb6:
+cmpl $7, %ebx
+sete %al
+movzbl %al, %esi
+cmpl $0, %esi
+setne %al
+movzbl %al, %edi
+.Llogic.bb7:
+testl %edi, %edi
+je .Llogic.bb9
+movl %ebx, %eax
+cltd
+idivl -8(%ebp)
+movl %eax, %esi
+movl -4(%ebp), %eax
+addl %esi, %eax
+movl %eax, %esi
+movl %esi, -4(%ebp)
+movl %ebx, %esi
+subl $1, %esi
+movl %esi, %ebx
+jmp .Llogic.bb1
+.Llogic.bb9:
+movl -4(%ebp), %eax
+movl -12(%ebp), %ebx
+movl -16(%ebp), %esi
+movl -20(%ebp), %edi
+movl %ebp, %esp
+popl %ebp
+ret
+.globl main
+main:
+pushl %ebp
+movl %esp, %ebp
+subl $8, %esp
+movl %ebx, -4(%ebp)
+movl %esi, -8(%ebp)
+subl $12, %esp
+pushl $7
+call wide
+addl $16, %esp
+movl %eax, %ebx
+movl $1000000000, %ecx
+movl %ebx, %eax
+cltd
+idivl %ecx
+movl %eax, %ebx
+subl $8, %esp
+pushl $-294967296
+pushl $1
+call ucmp
+addl $16, %esp
+movl %eax, %esi
+addl %esi, %ebx
+subl $8, %esp
+pushl $2
+pushl $-77
+call shifts
+addl $16, %esp
+movl %eax, %esi
+addl %esi, %ebx
+subl $12, %esp
+pushl $100
+call narrow
+addl $16, %esp
+movl %eax, %esi
+movsbl %al, %esi
+addl %esi, %ebx
+subl $8, %esp
+pushl $2
+pushl $9
+call logic
+addl $16, %esp
+movl %eax, %esi
+addl %esi, %ebx
+movl %ebx, %eax
+movl -4(%ebp), %ebx
+movl -8(%ebp), %esi
+movl %ebp, %esp
+popl %ebp
+ret
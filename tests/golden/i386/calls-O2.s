@@ -0,0 +1,176 @@
+.globl perm
+perm:
+pushl %ebp
+movl %esp, %ebp
+subl $40, %esp
+movl %ebx, -32(%ebp)
+movl %esi, -36(%ebp)
+movl %edi, -40(%ebp)
+movl 8(%ebp), %ebx
+movl 12(%ebp), %esi
+movl 16(%ebp), %edi
+movl 20(%ebp), %eax
+movl %eax, -4(%ebp)
+movl 24(%ebp), %eax
+movl %eax, -8(%ebp)
+movl 28(%ebp), %eax
+movl %eax, -12(%ebp)
+movl 32(%ebp), %eax
+movl %eax, -16(%ebp)
+movl 36(%ebp), %eax
+movl %eax, -20(%ebp)
+movl 40(%ebp), %eax
+movl %eax, -24(%ebp)
+movl 44(%ebp), %eax
+movl %eax, -28(%ebp)
+shll $1, %esi
+addl %esi, %ebx
+movl %edi, %esi
+imull $3, %esi
+addl %esi, %ebx
+movl -4(%ebp), %esi
+shll $2, %esi
+addl %esi, %ebx
+movl -8(%ebp), %esi
+imull $5, %esi
+addl %esi, %ebx
+movl -12(%ebp), %esi
+imull $6, %esi
+addl %esi, %ebx
+movl -16(%ebp), %esi
+imull $7, %esi
+addl %esi, %ebx
+movl -20(%ebp), %esi
+shll $3, %esi
+addl %esi, %ebx
+movl -24(%ebp), %esi
+imull $9, %esi
+addl %esi, %ebx
+subl -28(%ebp), %ebx
+movl %ebx, %eax
+movl -32(%ebp), %ebx
+movl -36(%ebp), %esi
+movl -40(%ebp), %edi
+movl %ebp, %esp
+popl %ebp
+ret
+.globl rotate
+rotate:
+pushl %ebp
+movl %esp, %ebp
+subl $56, %esp
+movl %ebx, -36(%ebp)
+movl %esi, -40(%ebp)
+movl %edi, -44(%ebp)
+movl 8(%ebp), %ebx
+movl 12(%ebp), %esi
+movl 16(%ebp), %eax
+movl %eax, -32(%ebp)
+movl 20(%ebp), %eax
+movl %eax, -4(%ebp)
+movl 24(%ebp), %eax
+movl %eax, -8(%ebp)
+movl 28(%ebp), %eax
+movl %eax, -12(%ebp)
+movl 32(%ebp), %eax
+movl %eax, -16(%ebp)
+movl 36(%ebp), %eax
+movl %eax, -20(%ebp)
+movl 40(%ebp), %eax
+movl %eax, -24(%ebp)
+movl 44(%ebp), %eax
+movl %eax, -28(%ebp)
+cmpl $100, %ebx
+setg %al
+movzbl %al, %edi
+testl %edi, %edi
+je .Lrotate.bb2
+subl $8, %esp
+pushl -28(%ebp)
+pushl -24(%ebp)
+pushl -20(%ebp)
+pushl -16(%ebp)
+pushl -12(%ebp)
+pushl -8(%ebp)
+pushl -4(%ebp)
+pushl -32(%ebp)
+pushl %esi
+pushl %ebx
+call perm
+addl $48, %esp
+movl %eax, %edi
+movl -36(%ebp), %ebx
+movl -40(%ebp), %esi
+movl -44(%ebp), %edi
+movl %ebp, %esp
+popl %ebp
+ret
+.Lrotate.bb2:
+addl $100, %esi
+subl $8, %esp
+pushl %ebx
+pushl -28(%ebp)
+pushl -24(%ebp)
+pushl -20(%ebp)
+pushl -16(%ebp)
+pushl -12(%ebp)
+pushl -8(%ebp)
+pushl -4(%ebp)
+pushl -32(%ebp)
+pushl %esi
+call rotate
+addl $48, %esp
+movl %eax, %ebx
+movl -36(%ebp), %ebx
+movl -40(%ebp), %esi
+movl -44(%ebp), %edi
+movl %ebp, %esp
+popl %ebp
+ret
+.globl main
+main:
+pushl %ebp
+movl %esp, %ebp
+subl $8, %esp
+movl %ebx, -4(%ebp)
+movl %esi, -8(%ebp)
+subl $12, %esp
+pushl $72
+call putchar
+addl $16, %esp
+movl %eax, %ebx
+subl $8, %esp
+pushl $10
+pushl $9
+pushl $8
+pushl $7
+pushl $6
+pushl $5
+pushl $4
+pushl $3
+pushl $2
+pushl $1
+call rotate
+addl $48, %esp
+movl %eax, %ebx
+subl $8, %esp
+pushl $1
+pushl $2
+pushl $3
+pushl $4
+pushl $5
+pushl $6
+pushl $7
+pushl $8
+pushl $9
+pushl $10
+call perm
+addl $48, %esp
+movl %eax, %esi
+subl %esi, %ebx
+movl %ebx, %eax
+movl -4(%ebp), %ebx
+movl -8(%ebp), %esi
+movl %ebp, %esp
+popl %ebp
+ret
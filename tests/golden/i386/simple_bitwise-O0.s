@@ -0,0 +1,9 @@
+.globl main
+main:
+pushl %ebp
+movl %esp, %ebp
+subl $8, %esp
+movl $-3, %eax
+movl %ebp, %esp
+popl %ebp
+ret
@@ -0,0 +1,177 @@
+target triple = "x86_64-linux-gnu"
+
+define i64 @wide(i64 %arg0) {
+entry:
+  %x.0 = alloca i64
+  store i64 %arg0, i64* %x.0
+  %t1 = load i64, i64* %x.0
+  %t2 = mul i64 %t1, 5000000000
+  %t3 = add i64 %t2, 4000000000
+  %t4 = load i64, i64* %x.0
+  %t5 = icmp slt i64 %t4, 3000000000
+  %t6 = zext i1 %t5 to i32
+  %t7 = sext i32 %t6 to i64
+  %t8 = add i64 %t3, %t7
+  ret i64 %t8
+}
+
+define i32 @ucmp(i32 %arg0, i32 %arg1) {
+entry:
+  %a.1 = alloca i32
+  store i32 %arg0, i32* %a.1
+  %b.2 = alloca i32
+  store i32 %arg1, i32* %b.2
+  %t1 = load i32, i32* %a.1
+  %t2 = load i32, i32* %b.2
+  %t3 = icmp ult i32 %t1, %t2
+  %t4 = zext i1 %t3 to i32
+  %t5 = load i32, i32* %a.1
+  %t6 = load i32, i32* %b.2
+  %t7 = icmp ule i32 %t5, %t6
+  %t8 = zext i1 %t7 to i32
+  %t9 = mul i32 %t8, 2
+  %t10 = add i32 %t4, %t9
+  %t11 = load i32, i32* %a.1
+  %t12 = load i32, i32* %b.2
+  %t13 = icmp ugt i32 %t11, %t12
+  %t14 = zext i1 %t13 to i32
+  %t15 = mul i32 %t14, 4
+  %t16 = add i32 %t10, %t15
+  %t17 = load i32, i32* %a.1
+  %t18 = load i32, i32* %b.2
+  %t19 = icmp uge i32 %t17, %t18
+  %t20 = zext i1 %t19 to i32
+  %t21 = mul i32 %t20, 8
+  %t22 = add i32 %t16, %t21
+  %t23 = load i32, i32* %a.1
+  %t24 = lshr i32 %t23, 28
+  %t25 = mul i32 %t24, 16
+  %t26 = add i32 %t22, %t25
+  %t27 = load i32, i32* %a.1
+  %t28 = load i32, i32* %b.2
+  %t29 = udiv i32 %t27, %t28
+  %t30 = add i32 %t26, %t29
+  ret i32 %t30
+}
+
+define i32 @shifts(i32 %arg0, i32 %arg1) {
+entry:
+  %x.3 = alloca i32
+  store i32 %arg0, i32* %x.3
+  %n.4 = alloca i32
+  store i32 %arg1, i32* %n.4
+  %t1 = load i32, i32* %x.3
+  %t2 = load i32, i32* %n.4
+  %t3 = shl i32 %t1, %t2
+  %t4 = load i32, i32* %x.3
+  %t5 = load i32, i32* %n.4
+  %t6 = ashr i32 %t4, %t5
+  %t7 = add i32 %t3, %t6
+  %t8 = load i32, i32* %x.3
+  %t9 = shl i32 %t8, 3
+  %t10 = add i32 %t7, %t9
+  %t11 = load i32, i32* %x.3
+  %t12 = ashr i32 %t11, 1
+  %t13 = sub i32 %t10, %t12
+  %t14 = load i32, i32* %x.3
+  %t15 = sub i32 0, %t14
+  %t16 = add i32 %t13, %t15
+  %t17 = load i32, i32* %n.4
+  %t18 = xor i32 %t17, -1
+  %t19 = add i32 %t16, %t18
+  %t20 = load i32, i32* %x.3
+  %t21 = icmp eq i32 %t20, 0
+  %t22 = zext i1 %t21 to i32
+  %t23 = add i32 %t19, %t22
+  ret i32 %t23
+}
+
+define signext i8 @narrow(i32 %arg0) {
+entry:
+  %x.5 = alloca i32
+  store i32 %arg0, i32* %x.5
+  %c.6 = alloca i8
+  %t1 = load i32, i32* %x.5
+  %t2 = trunc i32 %t1 to i8
+  store i8 %t2, i8* %c.6
+  %t3 = load i8, i8* %c.6
+  %t4 = sext i8 %t3 to i32
+  %t5 = add i32 %t4, 100
+  %t6 = trunc i32 %t5 to i8
+  store i8 %t6, i8* %c.6
+  %t7 = load i8, i8* %c.6
+  ret i8 %t7
+}
+
+define i32 @logic(i32 %arg0, i32 %arg1) {
+entry:
+  %x.7 = alloca i32
+  store i32 %arg0, i32* %x.7
+  %y.8 = alloca i32
+  store i32 %arg1, i32* %y.8
+  %r.9 = alloca i32
+  store i32 0, i32* %r.9
+  br label %L1
+L1:
+  %t1 = load i32, i32* %x.7
+  %t2 = icmp sgt i32 %t1, 0
+  %t3 = zext i1 %t2 to i32
+  %t4 = icmp ne i32 %t3, 0
+  br i1 %t4, label %L6, label %L7
+L6:
+  %t5 = load i32, i32* %y.8
+  %t6 = icmp ne i32 %t5, 0
+  %t7 = zext i1 %t6 to i32
+  %t8 = icmp ne i32 %t7, 0
+  br label %L7
+L7:
+  %t9 = phi i1 [ false, %L1 ], [ %t8, %L6 ]
+  %t10 = zext i1 %t9 to i32
+  %t11 = icmp ne i32 %t10, 0
+  br i1 %t11, label %L5, label %L4
+L4:
+  %t12 = load i32, i32* %x.7
+  %t13 = icmp eq i32 %t12, 7
+  %t14 = zext i1 %t13 to i32
+  %t15 = icmp ne i32 %t14, 0
+  br label %L5
+L5:
+  %t16 = phi i1 [ true, %L7 ], [ %t15, %L4 ]
+  %t17 = zext i1 %t16 to i32
+  %t18 = icmp ne i32 %t17, 0
+  br i1 %t18, label %L2, label %L3
+L2:
+  %t19 = load i32, i32* %r.9
+  %t20 = load i32, i32* %x.7
+  %t21 = load i32, i32* %y.8
+  %t22 = sdiv i32 %t20, %t21
+  %t23 = add i32 %t19, %t22
+  store i32 %t23, i32* %r.9
+  %t24 = load i32, i32* %x.7
+  %t25 = sub i32 %t24, 1
+  store i32 %t25, i32* %x.7
+  br label %L1
+L3:
+  %t26 = load i32, i32* %r.9
+  ret i32 %t26
+}
+
+define i32 @main() {
+entry:
+  %t1 = call i64 @wide(i64 7)
+  %t2 = sdiv i64 %t1, 1000000000
+  %t3 = call i32 @ucmp(i32 1, i32 -294967296)
+  %t4 = zext i32 %t3 to i64
+  %t5 = add i64 %t2, %t4
+  %t6 = call i32 @shifts(i32 -77, i32 2)
+  %t7 = sext i32 %t6 to i64
+  %t8 = add i64 %t5, %t7
+  %t9 = call signext i8 @narrow(i32 100)
+  %t10 = sext i8 %t9 to i64
+  %t11 = add i64 %t8, %t10
+  %t12 = call i32 @logic(i32 9, i32 2)
+  %t13 = sext i32 %t12 to i64
+  %t14 = add i64 %t11, %t13
+  %t15 = trunc i64 %t14 to i32
+  ret i32 %t15
+}
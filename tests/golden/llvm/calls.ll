@@ -0,0 +1,121 @@
+target triple = "x86_64-linux-gnu"
+
+declare i32 @putchar(i32)
+
+define i32 @perm(i32 %arg0, i32 %arg1, i32 %arg2, i32 %arg3, i32 %arg4, i32 %arg5, i32 %arg6, i32 %arg7, i32 %arg8, i32 %arg9) {
+entry:
+  %a.1 = alloca i32
+  store i32 %arg0, i32* %a.1
+  %b.2 = alloca i32
+  store i32 %arg1, i32* %b.2
+  %c.3 = alloca i32
+  store i32 %arg2, i32* %c.3
+  %d.4 = alloca i32
+  store i32 %arg3, i32* %d.4
+  %e.5 = alloca i32
+  store i32 %arg4, i32* %e.5
+  %f.6 = alloca i32
+  store i32 %arg5, i32* %f.6
+  %g.7 = alloca i32
+  store i32 %arg6, i32* %g.7
+  %h.8 = alloca i32
+  store i32 %arg7, i32* %h.8
+  %i.9 = alloca i32
+  store i32 %arg8, i32* %i.9
+  %j.10 = alloca i32
+  store i32 %arg9, i32* %j.10
+  %t1 = load i32, i32* %a.1
+  %t2 = mul i32 %t1, 1
+  %t3 = load i32, i32* %b.2
+  %t4 = mul i32 %t3, 2
+  %t5 = add i32 %t2, %t4
+  %t6 = load i32, i32* %c.3
+  %t7 = mul i32 %t6, 3
+  %t8 = add i32 %t5, %t7
+  %t9 = load i32, i32* %d.4
+  %t10 = mul i32 %t9, 4
+  %t11 = add i32 %t8, %t10
+  %t12 = load i32, i32* %e.5
+  %t13 = mul i32 %t12, 5
+  %t14 = add i32 %t11, %t13
+  %t15 = load i32, i32* %f.6
+  %t16 = mul i32 %t15, 6
+  %t17 = add i32 %t14, %t16
+  %t18 = load i32, i32* %g.7
+  %t19 = mul i32 %t18, 7
+  %t20 = add i32 %t17, %t19
+  %t21 = load i32, i32* %h.8
+  %t22 = mul i32 %t21, 8
+  %t23 = add i32 %t20, %t22
+  %t24 = load i32, i32* %i.9
+  %t25 = mul i32 %t24, 9
+  %t26 = add i32 %t23, %t25
+  %t27 = load i32, i32* %j.10
+  %t28 = sub i32 %t26, %t27
+  ret i32 %t28
+}
+
+define i32 @rotate(i32 %arg0, i32 %arg1, i32 %arg2, i32 %arg3, i32 %arg4, i32 %arg5, i32 %arg6, i32 %arg7, i32 %arg8, i32 %arg9) {
+entry:
+  %a.11 = alloca i32
+  store i32 %arg0, i32* %a.11
+  %b.12 = alloca i32
+  store i32 %arg1, i32* %b.12
+  %c.13 = alloca i32
+  store i32 %arg2, i32* %c.13
+  %d.14 = alloca i32
+  store i32 %arg3, i32* %d.14
+  %e.15 = alloca i32
+  store i32 %arg4, i32* %e.15
+  %f.16 = alloca i32
+  store i32 %arg5, i32* %f.16
+  %g.17 = alloca i32
+  store i32 %arg6, i32* %g.17
+  %h.18 = alloca i32
+  store i32 %arg7, i32* %h.18
+  %i.19 = alloca i32
+  store i32 %arg8, i32* %i.19
+  %j.20 = alloca i32
+  store i32 %arg9, i32* %j.20
+  %t1 = load i32, i32* %a.11
+  %t2 = icmp sgt i32 %t1, 100
+  %t3 = zext i1 %t2 to i32
+  %t4 = icmp ne i32 %t3, 0
+  br i1 %t4, label %L1, label %L2
+L1:
+  %t5 = load i32, i32* %a.11
+  %t6 = load i32, i32* %b.12
+  %t7 = load i32, i32* %c.13
+  %t8 = load i32, i32* %d.14
+  %t9 = load i32, i32* %e.15
+  %t10 = load i32, i32* %f.16
+  %t11 = load i32, i32* %g.17
+  %t12 = load i32, i32* %h.18
+  %t13 = load i32, i32* %i.19
+  %t14 = load i32, i32* %j.20
+  %t15 = call i32 @perm(i32 %t5, i32 %t6, i32 %t7, i32 %t8, i32 %t9, i32 %t10, i32 %t11, i32 %t12, i32 %t13, i32 %t14)
+  ret i32 %t15
+L2:
+  %t16 = load i32, i32* %b.12
+  %t17 = add i32 %t16, 100
+  %t18 = load i32, i32* %c.13
+  %t19 = load i32, i32* %d.14
+  %t20 = load i32, i32* %e.15
+  %t21 = load i32, i32* %f.16
+  %t22 = load i32, i32* %g.17
+  %t23 = load i32, i32* %h.18
+  %t24 = load i32, i32* %i.19
+  %t25 = load i32, i32* %j.20
+  %t26 = load i32, i32* %a.11
+  %t27 = call i32 @rotate(i32 %t17, i32 %t18, i32 %t19, i32 %t20, i32 %t21, i32 %t22, i32 %t23, i32 %t24, i32 %t25, i32 %t26)
+  ret i32 %t27
+}
+
+define i32 @main() {
+entry:
+  %t1 = call i32 @putchar(i32 72)
+  %t2 = call i32 @rotate(i32 1, i32 2, i32 3, i32 4, i32 5, i32 6, i32 7, i32 8, i32 9, i32 10)
+  %t3 = call i32 @perm(i32 10, i32 9, i32 8, i32 7, i32 6, i32 5, i32 4, i32 3, i32 2, i32 1)
+  %t4 = sub i32 %t2, %t3
+  ret i32 %t4
+}
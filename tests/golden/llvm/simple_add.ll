@@ -0,0 +1,6 @@
+target triple = "x86_64-linux-gnu"
+
+define i32 @main() {
+entry:
+  ret i32 3
+}
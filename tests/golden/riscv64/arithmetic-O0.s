@@ -0,0 +1,250 @@
+.globl wide
+wide:
+addi sp, sp, -16
+sd ra, 8(sp)
+sd s0, 0(sp)
+addi s0, sp, 16
+addi sp, sp, -32
+mv t0, a0
+.Lwide.bb0:
+li t6, 5000000000
+mul t1, t0, t6
+li t6, 4000000000
+add t1, t1, t6
+li t6, 3000000000
+slt t0, t0, t6
+add t0, t1, t0
+mv a0, t0
+addi sp, s0, -16
+ld ra, 8(sp)
+ld s0, 0(sp)
+addi sp, sp, 16
+ret
+.globl ucmp
+ucmp:
+addi sp, sp, -16
+sd ra, 8(sp)
+sd s0, 0(sp)
+addi s0, sp, 16
+addi sp, sp, -32
+mv t0, a0
+slli t0, t0, 32
+srli t0, t0, 32
+mv t1, a1
+slli t1, t1, 32
+srli t1, t1, 32
+.Lucmp.bb0:
+sltu t2, t0, t1
+sltu t3, t1, t0
+xori t3, t3, 1
+li t6, 2
+mul t3, t3, t6
+sext.w t3, t3
+add t2, t2, t3
+sext.w t2, t2
+sltu t3, t1, t0
+li t6, 4
+mul t3, t3, t6
+sext.w t3, t3
+add t2, t2, t3
+sext.w t2, t2
+sltu t3, t0, t1
+xori t3, t3, 1
+li t6, 8
+mul t3, t3, t6
+sext.w t3, t3
+add t2, t2, t3
+sext.w t2, t2
+slli t2, t2, 32
+srli t2, t2, 32
+srli t3, t0, 28
+li t6, 16
+mul t3, t3, t6
+slli t3, t3, 32
+srli t3, t3, 32
+add t2, t2, t3
+slli t2, t2, 32
+srli t2, t2, 32
+divu t0, t0, t1
+add t0, t2, t0
+slli t0, t0, 32
+srli t0, t0, 32
+mv a0, t0
+addi sp, s0, -16
+ld ra, 8(sp)
+ld s0, 0(sp)
+addi sp, sp, 16
+ret
+.globl shifts
+shifts:
+addi sp, sp, -16
+sd ra, 8(sp)
+sd s0, 0(sp)
+addi s0, sp, 16
+addi sp, sp, -32
+mv t0, a0
+sext.w t0, t0
+mv t1, a1
+sext.w t1, t1
+.Lshifts.bb0:
+sll t2, t0, t1
+sext.w t2, t2
+sra t3, t0, t1
+add t2, t2, t3
+sext.w t2, t2
+slli t3, t0, 3
+sext.w t3, t3
+add t2, t2, t3
+sext.w t2, t2
+srai t3, t0, 1
+sub t2, t2, t3
+sext.w t2, t2
+neg t3, t0
+sext.w t3, t3
+add t2, t2, t3
+sext.w t2, t2
+not t1, t1
+sext.w t1, t1
+add t1, t2, t1
+sext.w t1, t1
+li t6, 0
+sub t0, t0, t6
+seqz t0, t0
+add t0, t1, t0
+sext.w t0, t0
+mv a0, t0
+addi sp, s0, -16
+ld ra, 8(sp)
+ld s0, 0(sp)
+addi sp, sp, 16
+ret
+.globl narrow
+narrow:
+addi sp, sp, -16
+sd ra, 8(sp)
+sd s0, 0(sp)
+addi s0, sp, 16
+addi sp, sp, -32
+mv t0, a0
+sext.w t0, t0
+.Lnarrow.bb0:
+slli t0, t0, 56
+srai t0, t0, 56
+sext.w t1, t0
+addi t1, t1, 100
+sext.w t1, t1
+slli t1, t1, 56
+srai t1, t1, 56
+mv t0, t1
+mv a0, t0
+addi sp, s0, -16
+ld ra, 8(sp)
+ld s0, 0(sp)
+addi sp, sp, 16
+ret
+.globl logic
+logic:
+addi sp, sp, -16
+sd ra, 8(sp)
+sd s0, 0(sp)
+addi s0, sp, 16
+addi sp, sp, -48
+sd s1, 32(sp)
+mv t0, a0
+sext.w t0, t0
+mv t1, a1
+sext.w t1, t1
+.Llogic.bb0:
+li t2, 0
+.Llogic.bb1:
+li t6, 0
+slt t3, t6, t0
+beqz t3, .Llogic.bb3
+.Llogic.bb2:
+li t6, 0
+sub t3, t1, t6
+snez t3, t3
+li t6, 0
+sub t3, t3, t6
+snez t3, t3
+j .Llogic.bb4
+.Llogic.bb3:
+li t3, 0
+.Llogic.bb4:
+beqz t3, .Llogic.bb6
+.Llogic.bb5:
+li t3, 1
+j .Llogic.bb7
+.Llogic.bb6:
+li t6, 7
+sub s1, t0, t6
+seqz s1, s1
+li t6, 0
+sub t3, s1, t6
+snez t3, t3
+.Llogic.bb7:
+beqz t3, .Llogic.bb9
+.Llogic.bb8:
+div t3, t0, t1
+add t3, t2, t3
+sext.w t3, t3
+mv t2, t3
+addi t3, t0, -1
+sext.w t3, t3
+mv t0, t3
+j .Llogic.bb1
+.Llogic.bb9:
+mv a0, t2
+ld s1, 32(sp)
+addi sp, s0, -16
+ld ra, 8(sp)
+ld s0, 0(sp)
+addi sp, sp, 16
+ret
+.globl main
+main:
+addi sp, sp, -16
+sd ra, 8(sp)
+sd s0, 0(sp)
+addi s0, sp, 16
+addi sp, sp, -48
+sd s1, 32(sp)
+.Lmain.bb0:
+li a0, 7
+call wide
+mv t0, a0
+li t6, 1000000000
+div s1, t0, t6
+li a0, 1
+li a1, 4000000000
+call ucmp
+mv t0, a0
+slli t0, t0, 32
+srli t0, t0, 32
+add s1, s1, t0
+li a0, -77
+li a1, 2
+call shifts
+mv t0, a0
+sext.w t0, t0
+add s1, s1, t0
+li a0, 100
+call narrow
+mv t0, a0
+slli t0, t0, 56
+srai t0, t0, 56
+add s1, s1, t0
+li a0, 9
+li a1, 2
+call logic
+mv t0, a0
+sext.w t0, t0
+add t0, s1, t0
+sext.w t0, t0
+mv a0, t0
+ld s1, 32(sp)
+addi sp, s0, -16
+ld ra, 8(sp)
+ld s0, 0(sp)
+addi sp, sp, 16
+ret
@@ -0,0 +1,245 @@
+.globl perm
+perm:
+addi sp, sp, -16
+sd ra, 8(sp)
+sd s0, 0(sp)
+addi s0, sp, 16
+addi sp, sp, -80
+sd s1, 32(sp)
+sd s2, 40(sp)
+sd s3, 48(sp)
+sd s4, 56(sp)
+sd s5, 64(sp)
+sd s6, 72(sp)
+mv t0, a0
+sext.w t0, t0
+mv t1, a1
+sext.w t1, t1
+mv t2, a2
+sext.w t2, t2
+mv t3, a3
+sext.w t3, t3
+mv s1, a4
+sext.w s1, s1
+mv s2, a5
+sext.w s2, s2
+mv s3, a6
+sext.w s3, s3
+mv s4, a7
+sext.w s4, s4
+ld s5, 0(s0)
+sext.w s5, s5
+ld s6, 8(s0)
+sext.w s6, s6
+.Lperm.bb0:
+li t6, 1
+mul t0, t0, t6
+sext.w t0, t0
+li t6, 2
+mul t1, t1, t6
+sext.w t1, t1
+add t0, t0, t1
+sext.w t0, t0
+li t6, 3
+mul t1, t2, t6
+sext.w t1, t1
+add t0, t0, t1
+sext.w t0, t0
+li t6, 4
+mul t1, t3, t6
+sext.w t1, t1
+add t0, t0, t1
+sext.w t0, t0
+li t6, 5
+mul t1, s1, t6
+sext.w t1, t1
+add t0, t0, t1
+sext.w t0, t0
+li t6, 6
+mul t1, s2, t6
+sext.w t1, t1
+add t0, t0, t1
+sext.w t0, t0
+li t6, 7
+mul t1, s3, t6
+sext.w t1, t1
+add t0, t0, t1
+sext.w t0, t0
+li t6, 8
+mul t1, s4, t6
+sext.w t1, t1
+add t0, t0, t1
+sext.w t0, t0
+li t6, 9
+mul t1, s5, t6
+sext.w t1, t1
+add t0, t0, t1
+sext.w t0, t0
+sub t0, t0, s6
+sext.w t0, t0
+mv a0, t0
+ld s1, 32(sp)
+ld s2, 40(sp)
+ld s3, 48(sp)
+ld s4, 56(sp)
+ld s5, 64(sp)
+ld s6, 72(sp)
+addi sp, s0, -16
+ld ra, 8(sp)
+ld s0, 0(sp)
+addi sp, sp, 16
+ret
+.globl rotate
+rotate:
+addi sp, sp, -16
+sd ra, 8(sp)
+sd s0, 0(sp)
+addi s0, sp, 16
+addi sp, sp, -128
+sd s1, 48(sp)
+sd s2, 56(sp)
+sd s3, 64(sp)
+sd s4, 72(sp)
+sd s5, 80(sp)
+sd s6, 88(sp)
+sd s7, 96(sp)
+sd s8, 104(sp)
+sd s9, 112(sp)
+sd s10, 120(sp)
+mv s1, a0
+sext.w s1, s1
+mv s2, a1
+sext.w s2, s2
+mv s3, a2
+sext.w s3, s3
+mv s4, a3
+sext.w s4, s4
+mv s5, a4
+sext.w s5, s5
+mv s6, a5
+sext.w s6, s6
+mv s7, a6
+sext.w s7, s7
+mv s8, a7
+sext.w s8, s8
+ld s9, 0(s0)
+sext.w s9, s9
+ld s10, 8(s0)
+sext.w s10, s10
+.Lrotate.bb0:
+li t6, 100
+slt t0, t6, s1
+beqz t0, .Lrotate.bb2
+.Lrotate.bb1:
+sd s9, 0(sp)
+sd s10, 8(sp)
+mv a0, s1
+mv a1, s2
+mv a2, s3
+mv a3, s4
+mv a4, s5
+mv a5, s6
+mv a6, s7
+mv a7, s8
+call perm
+mv t0, a0
+sext.w t0, t0
+mv a0, t0
+ld s1, 48(sp)
+ld s2, 56(sp)
+ld s3, 64(sp)
+ld s4, 72(sp)
+ld s5, 80(sp)
+ld s6, 88(sp)
+ld s7, 96(sp)
+ld s8, 104(sp)
+ld s9, 112(sp)
+ld s10, 120(sp)
+addi sp, s0, -16
+ld ra, 8(sp)
+ld s0, 0(sp)
+addi sp, sp, 16
+ret
+.Lrotate.bb2:
+addi t0, s2, 100
+sext.w t0, t0
+sd s10, 0(sp)
+sd s1, 8(sp)
+mv a0, t0
+mv a1, s3
+mv a2, s4
+mv a3, s5
+mv a4, s6
+mv a5, s7
+mv a6, s8
+mv a7, s9
+call rotate
+mv t0, a0
+sext.w t0, t0
+mv a0, t0
+ld s1, 48(sp)
+ld s2, 56(sp)
+ld s3, 64(sp)
+ld s4, 72(sp)
+ld s5, 80(sp)
+ld s6, 88(sp)
+ld s7, 96(sp)
+ld s8, 104(sp)
+ld s9, 112(sp)
+ld s10, 120(sp)
+addi sp, s0, -16
+ld ra, 8(sp)
+ld s0, 0(sp)
+addi sp, sp, 16
+ret
+.globl main
+main:
+addi sp, sp, -16
+sd ra, 8(sp)
+sd s0, 0(sp)
+addi s0, sp, 16
+addi sp, sp, -64
+sd s1, 48(sp)
+.Lmain.bb0:
+li a0, 72
+call putchar
+mv t0, a0
+li t5, 9
+sd t5, 0(sp)
+li t5, 10
+sd t5, 8(sp)
+li a0, 1
+li a1, 2
+li a2, 3
+li a3, 4
+li a4, 5
+li a5, 6
+li a6, 7
+li a7, 8
+call rotate
+mv t0, a0
+sext.w s1, t0
+li t5, 2
+sd t5, 0(sp)
+li t5, 1
+sd t5, 8(sp)
+li a0, 10
+li a1, 9
+li a2, 8
+li a3, 7
+li a4, 6
+li a5, 5
+li a6, 4
+li a7, 3
+call perm
+mv t0, a0
+sext.w t0, t0
+sub t0, s1, t0
+sext.w t0, t0
+mv a0, t0
+ld s1, 48(sp)
+addi sp, s0, -16
+ld ra, 8(sp)
+ld s0, 0(sp)
+addi sp, sp, 16
+ret
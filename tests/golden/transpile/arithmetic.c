@@ -0,0 +1,160 @@
+#include <stdint.h>
+#include <stdio.h>
+
+void abort(void);
+
+static void cc_undefined(const char *at, const char *what) {
+    fprintf(stderr, "%s: undefined behavior: %s\n", at, what);
+    abort();
+}
+
+static inline int8_t cc_to_i8(int64_t v) {
+    uint8_t u = (uint8_t)v;
+    return u > INT8_MAX ? -(int8_t)(UINT8_MAX - u) - 1 : (int8_t)u;
+}
+
+static inline int32_t cc_to_i32(int64_t v) {
+    uint32_t u = (uint32_t)v;
+    return u > INT32_MAX ? -(int32_t)(UINT32_MAX - u) - 1 : (int32_t)u;
+}
+
+static inline uint32_t cc_div_u32(uint32_t a, uint32_t b, const char *at) {
+    if (b == 0)
+        cc_undefined(at, "division by zero");
+    return a / b;
+}
+
+static inline uint32_t cc_shl_u32(uint32_t a, int64_t n, const char *at) {
+    if (n < 0 || n >= 32)
+        cc_undefined(at, "shift count out of range");
+    return a << n;
+}
+
+static inline uint32_t cc_shr_u32(uint32_t a, int64_t n, const char *at) {
+    if (n < 0 || n >= 32)
+        cc_undefined(at, "shift count out of range");
+    return a >> n;
+}
+
+static inline int32_t cc_add_i32(int32_t a, int32_t b, const char *at) {
+    if (b > 0 ? a > INT32_MAX - b : a < INT32_MIN - b)
+        cc_undefined(at, "signed overflow in '+'");
+    return a + b;
+}
+
+static inline int32_t cc_sub_i32(int32_t a, int32_t b, const char *at) {
+    if (b < 0 ? a > INT32_MAX + b : a < INT32_MIN + b)
+        cc_undefined(at, "signed overflow in '-'");
+    return a - b;
+}
+
+static inline int32_t cc_mul_i32(int32_t a, int32_t b, const char *at) {
+    if (a > 0 ? (b > 0 ? a > INT32_MAX / b : b < INT32_MIN / a) : (b > 0 ? a < INT32_MIN / b : a != 0 && b < INT32_MAX / a))
+        cc_undefined(at, "signed overflow in '*'");
+    return a * b;
+}
+
+static inline int32_t cc_div_i32(int32_t a, int32_t b, const char *at) {
+    if (b == 0)
+        cc_undefined(at, "division by zero");
+    if (a == INT32_MIN && b == -1)
+        cc_undefined(at, "signed overflow in '/'");
+    return a / b;
+}
+
+static inline int32_t cc_neg_i32(int32_t a, const char *at) {
+    if (a == INT32_MIN)
+        cc_undefined(at, "signed overflow in '-'");
+    return -a;
+}
+
+static inline int32_t cc_shl_i32(int32_t a, int64_t n, const char *at) {
+    uint32_t u;
+    if (n < 0 || n >= 32)
+        cc_undefined(at, "shift count out of range");
+    u = (uint32_t)a << n;
+    return u > INT32_MAX ? -(int32_t)(UINT32_MAX - u) - 1 : (int32_t)u;
+}
+
+static inline int32_t cc_shr_i32(int32_t a, int64_t n, const char *at) {
+    if (n < 0 || n >= 32)
+        cc_undefined(at, "shift count out of range");
+    return a < 0 ? ~(~a >> n) : a >> n;
+}
+
+static inline int64_t cc_add_i64(int64_t a, int64_t b, const char *at) {
+    if (b > 0 ? a > INT64_MAX - b : a < INT64_MIN - b)
+        cc_undefined(at, "signed overflow in '+'");
+    return a + b;
+}
+
+static inline int64_t cc_sub_i64(int64_t a, int64_t b, const char *at) {
+    if (b < 0 ? a > INT64_MAX + b : a < INT64_MIN + b)
+        cc_undefined(at, "signed overflow in '-'");
+    return a - b;
+}
+
+static inline int64_t cc_mul_i64(int64_t a, int64_t b, const char *at) {
+    if (a > 0 ? (b > 0 ? a > INT64_MAX / b : b < INT64_MIN / a) : (b > 0 ? a < INT64_MIN / b : a != 0 && b < INT64_MAX / a))
+        cc_undefined(at, "signed overflow in '*'");
+    return a * b;
+}
+
+static inline int64_t cc_div_i64(int64_t a, int64_t b, const char *at) {
+    if (b == 0)
+        cc_undefined(at, "division by zero");
+    if (a == INT64_MIN && b == -1)
+        cc_undefined(at, "signed overflow in '/'");
+    return a / b;
+}
+
+static inline int64_t cc_neg_i64(int64_t a, const char *at) {
+    if (a == INT64_MIN)
+        cc_undefined(at, "signed overflow in '-'");
+    return -a;
+}
+
+static inline int64_t cc_shl_i64(int64_t a, int64_t n, const char *at) {
+    uint64_t u;
+    if (n < 0 || n >= 64)
+        cc_undefined(at, "shift count out of range");
+    u = (uint64_t)a << n;
+    return u > INT64_MAX ? -(int64_t)(UINT64_MAX - u) - 1 : (int64_t)u;
+}
+
+static inline int64_t cc_shr_i64(int64_t a, int64_t n, const char *at) {
+    if (n < 0 || n >= 64)
+        cc_undefined(at, "shift count out of range");
+    return a < 0 ? ~(~a >> n) : a >> n;
+}
+
+int64_t wide(int64_t x) {
+    return cc_add_i64(cc_add_i64(cc_mul_i64(x, INT64_C(5000000000), "3:12"), INT64_C(4000000000), "3:12"), ((int64_t)((int32_t)(x < INT64_C(3000000000)))), "3:12");
+}
+
+uint32_t ucmp(uint32_t a, uint32_t b) {
+    return ((uint32_t)(((uint32_t)(((uint32_t)cc_add_i32(cc_add_i32(cc_add_i32(((int32_t)(a < b)), cc_mul_i32(((int32_t)(a <= b)), 2, "7:22"), "7:12"), cc_mul_i32(((int32_t)(a > b)), 4, "7:37"), "7:12"), cc_mul_i32(((int32_t)(a >= b)), 8, "7:51"), "7:12")) + ((uint32_t)(cc_shr_u32(a, 28, "7:66") * UINT32_C(16))))) + cc_div_u32(a, b, "7:83")));
+}
+
+int32_t shifts(int32_t x, int32_t n) {
+    return cc_add_i32(cc_add_i32(cc_add_i32(cc_sub_i32(cc_add_i32(cc_add_i32(cc_shl_i32(x, n, "11:12"), cc_shr_i32(x, n, "11:23"), "11:12"), cc_shl_i32(x, 3, "11:34"), "11:12"), cc_shr_i32(x, 1, "11:45"), "11:12"), cc_neg_i32(x, "11:56"), "11:12"), (~n), "11:12"), ((int32_t)!x), "11:12");
+}
+
+int8_t narrow(int32_t x) {
+    int8_t c = cc_to_i8(x);
+    (c = cc_to_i8(cc_add_i32(((int32_t)c), 100, "16:9")));
+    return c;
+}
+
+int32_t logic(int32_t x, int32_t y) {
+    int32_t r = 0;
+    while (((int32_t)(((int32_t)(((int32_t)(x > 0)) && ((int32_t)(y != 0)))) || ((int32_t)(x == 7))))) {
+        (r = cc_add_i32(r, cc_div_i32(x, y, "23:17"), "23:13"));
+        (x = cc_sub_i32(x, 1, "24:13"));
+    }
+    return r;
+}
+
+int32_t main(void) {
+    return cc_to_i32(cc_add_i64(cc_add_i64(cc_add_i64(cc_add_i64(cc_div_i64(wide(INT64_C(7)), INT64_C(1000000000), "30:12"), ((int64_t)ucmp(UINT32_C(1), UINT32_C(4000000000))), "30:12"), ((int64_t)shifts((-77), 2)), "30:12"), ((int64_t)narrow(100)), "30:12"), ((int64_t)logic(9, 2)), "30:12"));
+}
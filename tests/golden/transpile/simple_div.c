@@ -0,0 +1,133 @@
+#include <stdint.h>
+#include <stdio.h>
+
+void abort(void);
+
+static void cc_undefined(const char *at, const char *what) {
+    fprintf(stderr, "%s: undefined behavior: %s\n", at, what);
+    abort();
+}
+
+static inline int8_t cc_to_i8(int64_t v) {
+    uint8_t u = (uint8_t)v;
+    return u > INT8_MAX ? -(int8_t)(UINT8_MAX - u) - 1 : (int8_t)u;
+}
+
+static inline int32_t cc_to_i32(int64_t v) {
+    uint32_t u = (uint32_t)v;
+    return u > INT32_MAX ? -(int32_t)(UINT32_MAX - u) - 1 : (int32_t)u;
+}
+
+static inline uint32_t cc_div_u32(uint32_t a, uint32_t b, const char *at) {
+    if (b == 0)
+        cc_undefined(at, "division by zero");
+    return a / b;
+}
+
+static inline uint32_t cc_shl_u32(uint32_t a, int64_t n, const char *at) {
+    if (n < 0 || n >= 32)
+        cc_undefined(at, "shift count out of range");
+    return a << n;
+}
+
+static inline uint32_t cc_shr_u32(uint32_t a, int64_t n, const char *at) {
+    if (n < 0 || n >= 32)
+        cc_undefined(at, "shift count out of range");
+    return a >> n;
+}
+
+static inline int32_t cc_add_i32(int32_t a, int32_t b, const char *at) {
+    if (b > 0 ? a > INT32_MAX - b : a < INT32_MIN - b)
+        cc_undefined(at, "signed overflow in '+'");
+    return a + b;
+}
+
+static inline int32_t cc_sub_i32(int32_t a, int32_t b, const char *at) {
+    if (b < 0 ? a > INT32_MAX + b : a < INT32_MIN + b)
+        cc_undefined(at, "signed overflow in '-'");
+    return a - b;
+}
+
+static inline int32_t cc_mul_i32(int32_t a, int32_t b, const char *at) {
+    if (a > 0 ? (b > 0 ? a > INT32_MAX / b : b < INT32_MIN / a) : (b > 0 ? a < INT32_MIN / b : a != 0 && b < INT32_MAX / a))
+        cc_undefined(at, "signed overflow in '*'");
+    return a * b;
+}
+
+static inline int32_t cc_div_i32(int32_t a, int32_t b, const char *at) {
+    if (b == 0)
+        cc_undefined(at, "division by zero");
+    if (a == INT32_MIN && b == -1)
+        cc_undefined(at, "signed overflow in '/'");
+    return a / b;
+}
+
+static inline int32_t cc_neg_i32(int32_t a, const char *at) {
+    if (a == INT32_MIN)
+        cc_undefined(at, "signed overflow in '-'");
+    return -a;
+}
+
+static inline int32_t cc_shl_i32(int32_t a, int64_t n, const char *at) {
+    uint32_t u;
+    if (n < 0 || n >= 32)
+        cc_undefined(at, "shift count out of range");
+    u = (uint32_t)a << n;
+    return u > INT32_MAX ? -(int32_t)(UINT32_MAX - u) - 1 : (int32_t)u;
+}
+
+static inline int32_t cc_shr_i32(int32_t a, int64_t n, const char *at) {
+    if (n < 0 || n >= 32)
+        cc_undefined(at, "shift count out of range");
+    return a < 0 ? ~(~a >> n) : a >> n;
+}
+
+static inline int64_t cc_add_i64(int64_t a, int64_t b, const char *at) {
+    if (b > 0 ? a > INT64_MAX - b : a < INT64_MIN - b)
+        cc_undefined(at, "signed overflow in '+'");
+    return a + b;
+}
+
+static inline int64_t cc_sub_i64(int64_t a, int64_t b, const char *at) {
+    if (b < 0 ? a > INT64_MAX + b : a < INT64_MIN + b)
+        cc_undefined(at, "signed overflow in '-'");
+    return a - b;
+}
+
+static inline int64_t cc_mul_i64(int64_t a, int64_t b, const char *at) {
+    if (a > 0 ? (b > 0 ? a > INT64_MAX / b : b < INT64_MIN / a) : (b > 0 ? a < INT64_MIN / b : a != 0 && b < INT64_MAX / a))
+        cc_undefined(at, "signed overflow in '*'");
+    return a * b;
+}
+
+static inline int64_t cc_div_i64(int64_t a, int64_t b, const char *at) {
+    if (b == 0)
+        cc_undefined(at, "division by zero");
+    if (a == INT64_MIN && b == -1)
+        cc_undefined(at, "signed overflow in '/'");
+    return a / b;
+}
+
+static inline int64_t cc_neg_i64(int64_t a, const char *at) {
+    if (a == INT64_MIN)
+        cc_undefined(at, "signed overflow in '-'");
+    return -a;
+}
+
+static inline int64_t cc_shl_i64(int64_t a, int64_t n, const char *at) {
+    uint64_t u;
+    if (n < 0 || n >= 64)
+        cc_undefined(at, "shift count out of range");
+    u = (uint64_t)a << n;
+    return u > INT64_MAX ? -(int64_t)(UINT64_MAX - u) - 1 : (int64_t)u;
+}
+
+static inline int64_t cc_shr_i64(int64_t a, int64_t n, const char *at) {
+    if (n < 0 || n >= 64)
+        cc_undefined(at, "shift count out of range");
+    return a < 0 ? ~(~a >> n) : a >> n;
+}
+
+int32_t main(void) {
+    return 2;
+}
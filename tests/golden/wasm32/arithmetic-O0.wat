@@ -0,0 +1,456 @@
+(module
+  (memory (export "memory") 1)
+  (global $__stack_pointer (mut i32) (i32.const 65536))
+  (func $wide (export "wide") (param $r0 i32) (result i32)
+    (local $r1 i32)
+    (local $r2 i32)
+    (local $r3 i32)
+    (local $r4 i32)
+    (local $r5 i32)
+    (local $r6 i32)
+    (local $r7 i32)
+    (local $r8 i32)
+    local.get $r0
+    i32.const 705032704
+    i32.mul
+    local.set $r1
+    local.get $r1
+    local.set $r2
+    local.get $r2
+    i32.const -294967296
+    i32.add
+    local.set $r3
+    local.get $r3
+    local.set $r4
+    local.get $r0
+    i32.const -1294967296
+    i32.lt_s
+    local.set $r5
+    local.get $r5
+    local.set $r6
+    local.get $r4
+    local.get $r6
+    i32.add
+    local.set $r7
+    local.get $r7
+    local.set $r8
+    local.get $r8
+    return
+  )
+  (func $ucmp (export "ucmp") (param $r0 i32) (param $r1 i32) (result i32)
+    (local $r2 i32)
+    (local $r3 i32)
+    (local $r4 i32)
+    (local $r5 i32)
+    (local $r6 i32)
+    (local $r7 i32)
+    (local $r8 i32)
+    (local $r9 i32)
+    (local $r10 i32)
+    (local $r11 i32)
+    (local $r12 i32)
+    (local $r13 i32)
+    (local $r14 i32)
+    (local $r15 i32)
+    (local $r16 i32)
+    (local $r17 i32)
+    (local $r18 i32)
+    (local $r19 i32)
+    (local $r20 i32)
+    (local $r21 i32)
+    (local $r22 i32)
+    (local $r23 i32)
+    (local $r24 i32)
+    (local $r25 i32)
+    (local $r26 i32)
+    local.get $r0
+    local.get $r1
+    i32.lt_u
+    local.set $r2
+    local.get $r0
+    local.get $r1
+    i32.le_u
+    local.set $r3
+    local.get $r3
+    i32.const 2
+    i32.mul
+    local.set $r4
+    local.get $r4
+    local.set $r5
+    local.get $r2
+    local.get $r5
+    i32.add
+    local.set $r6
+    local.get $r6
+    local.set $r7
+    local.get $r0
+    local.get $r1
+    i32.gt_u
+    local.set $r8
+    local.get $r8
+    i32.const 4
+    i32.mul
+    local.set $r9
+    local.get $r9
+    local.set $r10
+    local.get $r7
+    local.get $r10
+    i32.add
+    local.set $r11
+    local.get $r11
+    local.set $r12
+    local.get $r0
+    local.get $r1
+    i32.ge_u
+    local.set $r13
+    local.get $r13
+    i32.const 8
+    i32.mul
+    local.set $r14
+    local.get $r14
+    local.set $r15
+    local.get $r12
+    local.get $r15
+    i32.add
+    local.set $r16
+    local.get $r16
+    local.set $r17
+    local.get $r17
+    local.set $r18
+    local.get $r0
+    i32.const 28
+    i32.shr_u
+    local.set $r19
+    local.get $r19
+    i32.const 16
+    i32.mul
+    local.set $r20
+    local.get $r20
+    local.set $r21
+    local.get $r18
+    local.get $r21
+    i32.add
+    local.set $r22
+    local.get $r22
+    local.set $r23
+    local.get $r0
+    local.get $r1
+    i32.div_u
+    local.set $r24
+    local.get $r23
+    local.get $r24
+    i32.add
+    local.set $r25
+    local.get $r25
+    local.set $r26
+    local.get $r26
+    return
+  )
+  (func $shifts (export "shifts") (param $r0 i32) (param $r1 i32) (result i32)
+    (local $r2 i32)
+    (local $r3 i32)
+    (local $r4 i32)
+    (local $r5 i32)
+    (local $r6 i32)
+    (local $r7 i32)
+    (local $r8 i32)
+    (local $r9 i32)
+    (local $r10 i32)
+    (local $r11 i32)
+    (local $r12 i32)
+    (local $r13 i32)
+    (local $r14 i32)
+    (local $r15 i32)
+    (local $r16 i32)
+    (local $r17 i32)
+    (local $r18 i32)
+    (local $r19 i32)
+    (local $r20 i32)
+    (local $r21 i32)
+    (local $r22 i32)
+    (local $r23 i32)
+    (local $r24 i32)
+    local.get $r0
+    local.get $r1
+    i32.shl
+    local.set $r2
+    local.get $r2
+    local.set $r3
+    local.get $r0
+    local.get $r1
+    i32.shr_s
+    local.set $r4
+    local.get $r3
+    local.get $r4
+    i32.add
+    local.set $r5
+    local.get $r5
+    local.set $r6
+    local.get $r0
+    i32.const 3
+    i32.shl
+    local.set $r7
+    local.get $r7
+    local.set $r8
+    local.get $r6
+    local.get $r8
+    i32.add
+    local.set $r9
+    local.get $r9
+    local.set $r10
+    local.get $r0
+    i32.const 1
+    i32.shr_s
+    local.set $r11
+    local.get $r10
+    local.get $r11
+    i32.sub
+    local.set $r12
+    local.get $r12
+    local.set $r13
+    i32.const 0
+    local.get $r0
+    i32.sub
+    local.set $r14
+    local.get $r14
+    local.set $r15
+    local.get $r13
+    local.get $r15
+    i32.add
+    local.set $r16
+    local.get $r16
+    local.set $r17
+    local.get $r1
+    i32.const -1
+    i32.xor
+    local.set $r18
+    local.get $r18
+    local.set $r19
+    local.get $r17
+    local.get $r19
+    i32.add
+    local.set $r20
+    local.get $r20
+    local.set $r21
+    local.get $r0
+    i32.const 0
+    i32.eq
+    local.set $r22
+    local.get $r21
+    local.get $r22
+    i32.add
+    local.set $r23
+    local.get $r23
+    local.set $r24
+    local.get $r24
+    return
+  )
+  (func $narrow (export "narrow") (param $r0 i32) (result i32)
+    (local $r1 i32)
+    (local $r2 i32)
+    (local $r3 i32)
+    (local $r4 i32)
+    (local $r5 i32)
+    (local $r6 i32)
+    local.get $r0
+    i32.extend8_s
+    local.set $r2
+    local.get $r2
+    local.set $r1
+    local.get $r1
+    local.set $r3
+    local.get $r3
+    i32.const 100
+    i32.add
+    local.set $r4
+    local.get $r4
+    local.set $r5
+    local.get $r5
+    i32.extend8_s
+    local.set $r6
+    local.get $r6
+    local.set $r1
+    local.get $r1
+    return
+  )
+  (func $logic (export "logic") (param $r0 i32) (param $r1 i32) (result i32)
+    (local $r2 i32)
+    (local $r3 i32)
+    (local $r4 i32)
+    (local $r5 i32)
+    (local $r6 i32)
+    (local $r7 i32)
+    (local $r8 i32)
+    (local $r9 i32)
+    (local $r10 i32)
+    (local $r11 i32)
+    (local $r12 i32)
+    i32.const 0
+    local.set $r2
+    loop $bb1.loop
+      block $bb4
+        local.get $r0
+        i32.const 0
+        i32.gt_s
+        local.set $r5
+        local.get $r5
+        if
+          local.get $r1
+          i32.const 0
+          i32.ne
+          local.set $r6
+          local.get $r6
+          i32.const 0
+          i32.ne
+          local.set $r4
+          br $bb4
+        else
+          i32.const 0
+          local.set $r4
+          br $bb4
+        end
+      end
+      block $bb7
+        local.get $r4
+        if
+          i32.const 1
+          local.set $r3
+          br $bb7
+        else
+          local.get $r0
+          i32.const 7
+          i32.eq
+          local.set $r7
+          local.get $r7
+          i32.const 0
+          i32.ne
+          local.set $r3
+          br $bb7
+        end
+      end
+      local.get $r3
+      if
+        local.get $r0
+        local.get $r1
+        i32.div_s
+        local.set $r8
+        local.get $r2
+        local.get $r8
+        i32.add
+        local.set $r9
+        local.get $r9
+        local.set $r10
+        local.get $r10
+        local.set $r2
+        local.get $r0
+        i32.const 1
+        i32.sub
+        local.set $r11
+        local.get $r11
+        local.set $r12
+        local.get $r12
+        local.set $r0
+        br $bb1.loop
+      else
+        local.get $r2
+        return
+      end
+    end
+    unreachable
+  )
+  (func $main (export "main") (result i32)
+    (local $r0 i32)
+    (local $r1 i32)
+    (local $r2 i32)
+    (local $r3 i32)
+    (local $r4 i32)
+    (local $r5 i32)
+    (local $r6 i32)
+    (local $r7 i32)
+    (local $r8 i32)
+    (local $r9 i32)
+    (local $r10 i32)
+    (local $r11 i32)
+    (local $r12 i32)
+    (local $r13 i32)
+    (local $r14 i32)
+    (local $r15 i32)
+    (local $r16 i32)
+    (local $r17 i32)
+    (local $r18 i32)
+    (local $r19 i32)
+    (local $r20 i32)
+    (local $r21 i32)
+    (local $r22 i32)
+    (local $r23 i32)
+    i32.const 7
+    call $wide
+    local.set $r0
+    local.get $r0
+    local.set $r1
+    local.get $r1
+    i32.const 1000000000
+    i32.div_s
+    local.set $r2
+    i32.const 1
+    i32.const -294967296
+    call $ucmp
+    local.set $r3
+    local.get $r3
+    local.set $r4
+    local.get $r4
+    local.set $r5
+    local.get $r2
+    local.get $r5
+    i32.add
+    local.set $r6
+    local.get $r6
+    local.set $r7
+    i32.const -77
+    i32.const 2
+    call $shifts
+    local.set $r8
+    local.get $r8
+    local.set $r9
+    local.get $r9
+    local.set $r10
+    local.get $r7
+    local.get $r10
+    i32.add
+    local.set $r11
+    local.get $r11
+    local.set $r12
+    i32.const 100
+    call $narrow
+    local.set $r13
+    local.get $r13
+    i32.extend8_s
+    local.set $r14
+    local.get $r14
+    local.set $r15
+    local.get $r12
+    local.get $r15
+    i32.add
+    local.set $r16
+    local.get $r16
+    local.set $r17
+    i32.const 9
+    i32.const 2
+    call $logic
+    local.set $r18
+    local.get $r18
+    local.set $r19
+    local.get $r19
+    local.set $r20
+    local.get $r17
+    local.get $r20
+    i32.add
+    local.set $r21
+    local.get $r21
+    local.set $r22
+    local.get $r22
+    local.set $r23
+    local.get $r23
+    return
+  )
+)
@@ -0,0 +1,460 @@
+(module
+  (memory (export "memory") 1)
+  (global $__stack_pointer (mut i32) (i32.const 65536))
+  (func $wide (export "wide") (param $r0 i32) (result i32)
+    (local $r9 i32)
+    (local $r10 i32)
+    (local $r11 i32)
+    (local $r12 i32)
+    (local $r13 i32)
+    (local $r14 i32)
+    (local $r15 i32)
+    (local $r16 i32)
+    local.get $r0
+    i32.const 705032704
+    i32.mul
+    local.set $r9
+    local.get $r9
+    local.set $r10
+    local.get $r10
+    i32.const -294967296
+    i32.add
+    local.set $r11
+    local.get $r11
+    local.set $r12
+    local.get $r0
+    i32.const -1294967296
+    i32.lt_s
+    local.set $r13
+    local.get $r13
+    local.set $r14
+    local.get $r12
+    local.get $r14
+    i32.add
+    local.set $r15
+    local.get $r15
+    local.set $r16
+    local.get $r16
+    return
+  )
+  (func $ucmp (export "ucmp") (param $r0 i32) (param $r1 i32) (result i32)
+    (local $r27 i32)
+    (local $r28 i32)
+    (local $r29 i32)
+    (local $r30 i32)
+    (local $r31 i32)
+    (local $r32 i32)
+    (local $r33 i32)
+    (local $r34 i32)
+    (local $r35 i32)
+    (local $r36 i32)
+    (local $r37 i32)
+    (local $r38 i32)
+    (local $r39 i32)
+    (local $r40 i32)
+    (local $r41 i32)
+    (local $r42 i32)
+    (local $r43 i32)
+    (local $r44 i32)
+    (local $r45 i32)
+    (local $r46 i32)
+    (local $r47 i32)
+    (local $r48 i32)
+    (local $r49 i32)
+    (local $r50 i32)
+    (local $r51 i32)
+    local.get $r0
+    local.get $r1
+    i32.lt_u
+    local.set $r27
+    local.get $r0
+    local.get $r1
+    i32.le_u
+    local.set $r28
+    local.get $r28
+    i32.const 2
+    i32.mul
+    local.set $r29
+    local.get $r29
+    local.set $r30
+    local.get $r27
+    local.get $r30
+    i32.add
+    local.set $r31
+    local.get $r31
+    local.set $r32
+    local.get $r0
+    local.get $r1
+    i32.gt_u
+    local.set $r33
+    local.get $r33
+    i32.const 4
+    i32.mul
+    local.set $r34
+    local.get $r34
+    local.set $r35
+    local.get $r32
+    local.get $r35
+    i32.add
+    local.set $r36
+    local.get $r36
+    local.set $r37
+    local.get $r0
+    local.get $r1
+    i32.ge_u
+    local.set $r38
+    local.get $r38
+    i32.const 8
+    i32.mul
+    local.set $r39
+    local.get $r39
+    local.set $r40
+    local.get $r37
+    local.get $r40
+    i32.add
+    local.set $r41
+    local.get $r41
+    local.set $r42
+    local.get $r42
+    local.set $r43
+    local.get $r0
+    i32.const 28
+    i32.shr_u
+    local.set $r44
+    local.get $r44
+    i32.const 16
+    i32.mul
+    local.set $r45
+    local.get $r45
+    local.set $r46
+    local.get $r43
+    local.get $r46
+    i32.add
+    local.set $r47
+    local.get $r47
+    local.set $r48
+    local.get $r0
+    local.get $r1
+    i32.div_u
+    local.set $r49
+    local.get $r48
+    local.get $r49
+    i32.add
+    local.set $r50
+    local.get $r50
+    local.set $r51
+    local.get $r51
+    return
+  )
+  (func $shifts (export "shifts") (param $r0 i32) (param $r1 i32) (result i32)
+    (local $r25 i32)
+    (local $r26 i32)
+    (local $r27 i32)
+    (local $r28 i32)
+    (local $r29 i32)
+    (local $r30 i32)
+    (local $r31 i32)
+    (local $r32 i32)
+    (local $r33 i32)
+    (local $r34 i32)
+    (local $r35 i32)
+    (local $r36 i32)
+    (local $r37 i32)
+    (local $r38 i32)
+    (local $r39 i32)
+    (local $r40 i32)
+    (local $r41 i32)
+    (local $r42 i32)
+    (local $r43 i32)
+    (local $r44 i32)
+    (local $r45 i32)
+    (local $r46 i32)
+    (local $r47 i32)
+    local.get $r0
+    local.get $r1
+    i32.shl
+    local.set $r25
+    local.get $r25
+    local.set $r26
+    local.get $r0
+    local.get $r1
+    i32.shr_s
+    local.set $r27
+    local.get $r26
+    local.get $r27
+    i32.add
+    local.set $r28
+    local.get $r28
+    local.set $r29
+    local.get $r0
+    i32.const 3
+    i32.shl
+    local.set $r30
+    local.get $r30
+    local.set $r31
+    local.get $r29
+    local.get $r31
+    i32.add
+    local.set $r32
+    local.get $r32
+    local.set $r33
+    local.get $r0
+    i32.const 1
+    i32.shr_s
+    local.set $r34
+    local.get $r33
+    local.get $r34
+    i32.sub
+    local.set $r35
+    local.get $r35
+    local.set $r36
+    i32.const 0
+    local.get $r0
+    i32.sub
+    local.set $r37
+    local.get $r37
+    local.set $r38
+    local.get $r36
+    local.get $r38
+    i32.add
+    local.set $r39
+    local.get $r39
+    local.set $r40
+    local.get $r1
+    i32.const -1
+    i32.xor
+    local.set $r41
+    local.get $r41
+    local.set $r42
+    local.get $r40
+    local.get $r42
+    i32.add
+    local.set $r43
+    local.get $r43
+    local.set $r44
+    local.get $r0
+    i32.const 0
+    i32.eq
+    local.set $r45
+    local.get $r44
+    local.get $r45
+    i32.add
+    local.set $r46
+    local.get $r46
+    local.set $r47
+    local.get $r47
+    return
+  )
+  (func $narrow (export "narrow") (param $r0 i32) (result i32)
+    (local $r7 i32)
+    (local $r9 i32)
+    (local $r10 i32)
+    (local $r11 i32)
+    (local $r12 i32)
+    local.get $r0
+    i32.extend8_s
+    local.set $r7
+    local.get $r7
+    local.set $r9
+    local.get $r9
+    i32.const 100
+    i32.add
+    local.set $r10
+    local.get $r10
+    local.set $r11
+    local.get $r11
+    i32.extend8_s
+    local.set $r12
+    local.get $r12
+    return
+  )
+  (func $logic (export "logic") (param $r0 i32) (param $r1 i32) (result i32)
+    (local $r14 i32)
+    (local $r15 i32)
+    (local $r18 i32)
+    (local $r19 i32)
+    (local $r20 i32)
+    (local $r22 i32)
+    (local $r24 i32)
+    (local $r25 i32)
+    (local $r26 i32)
+    (local $r27 i32)
+    (local $r28 i32)
+    (local $r29 i32)
+    (local $r31 i32)
+    (local $r32 i32)
+    local.get $r1
+    i32.const 0
+    i32.ne
+    local.set $r19
+    local.get $r19
+    i32.const 0
+    i32.ne
+    local.set $r20
+    local.get $r0
+    local.set $r14
+    i32.const 0
+    local.set $r15
+    loop $bb1.loop
+      block $bb4
+        local.get $r14
+        i32.const 0
+        i32.gt_s
+        local.set $r18
+        local.get $r18
+        if
+          local.get $r20
+          local.set $r22
+          br $bb4
+        else
+          i32.const 0
+          local.set $r22
+          br $bb4
+        end
+      end
+      block $bb7
+        local.get $r22
+        if
+          i32.const 1
+          local.set $r26
+          br $bb7
+        else
+          local.get $r14
+          i32.const 7
+          i32.eq
+          local.set $r24
+          local.get $r24
+          i32.const 0
+          i32.ne
+          local.set $r25
+          local.get $r25
+          local.set $r26
+          br $bb7
+        end
+      end
+      local.get $r26
+      if
+        local.get $r14
+        local.get $r1
+        i32.div_s
+        local.set $r27
+        local.get $r15
+        local.get $r27
+        i32.add
+        local.set $r28
+        local.get $r28
+        local.set $r29
+        local.get $r14
+        i32.const 1
+        i32.sub
+        local.set $r31
+        local.get $r31
+        local.set $r32
+        local.get $r32
+        local.set $r14
+        local.get $r29
+        local.set $r15
+        br $bb1.loop
+      else
+        local.get $r15
+        return
+      end
+    end
+    unreachable
+  )
+  (func $main (export "main") (result i32)
+    (local $r24 i32)
+    (local $r25 i32)
+    (local $r26 i32)
+    (local $r27 i32)
+    (local $r28 i32)
+    (local $r29 i32)
+    (local $r30 i32)
+    (local $r31 i32)
+    (local $r32 i32)
+    (local $r33 i32)
+    (local $r34 i32)
+    (local $r35 i32)
+    (local $r36 i32)
+    (local $r37 i32)
+    (local $r38 i32)
+    (local $r39 i32)
+    (local $r40 i32)
+    (local $r41 i32)
+    (local $r42 i32)
+    (local $r43 i32)
+    (local $r44 i32)
+    (local $r45 i32)
+    (local $r46 i32)
+    (local $r47 i32)
+    i32.const 7
+    call $wide
+    local.set $r24
+    local.get $r24
+    local.set $r25
+    local.get $r25
+    i32.const 1000000000
+    i32.div_s
+    local.set $r26
+    i32.const 1
+    i32.const -294967296
+    call $ucmp
+    local.set $r27
+    local.get $r27
+    local.set $r28
+    local.get $r28
+    local.set $r29
+    local.get $r26
+    local.get $r29
+    i32.add
+    local.set $r30
+    local.get $r30
+    local.set $r31
+    i32.const -77
+    i32.const 2
+    call $shifts
+    local.set $r32
+    local.get $r32
+    local.set $r33
+    local.get $r33
+    local.set $r34
+    local.get $r31
+    local.get $r34
+    i32.add
+    local.set $r35
+    local.get $r35
+    local.set $r36
+    i32.const 100
+    call $narrow
+    local.set $r37
+    local.get $r37
+    i32.extend8_s
+    local.set $r38
+    local.get $r38
+    local.set $r39
+    local.get $r36
+    local.get $r39
+    i32.add
+    local.set $r40
+    local.get $r40
+    local.set $r41
+    i32.const 9
+    i32.const 2
+    call $logic
+    local.set $r42
+    local.get $r42
+    local.set $r43
+    local.get $r43
+    local.set $r44
+    local.get $r41
+    local.get $r44
+    i32.add
+    local.set $r45
+    local.get $r45
+    local.set $r46
+    local.get $r46
+    local.set $r47
+    local.get $r47
+    return
+  )
+)
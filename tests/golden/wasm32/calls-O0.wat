@@ -0,0 +1,260 @@
+(module
+  (import "env" "putchar" (func $putchar (param i32) (result i32)))
+  (memory (export "memory") 1)
+  (global $__stack_pointer (mut i32) (i32.const 65536))
+  (func $perm (export "perm") (param $r0 i32) (param $r1 i32) (param $r2 i32) (param $r3 i32) (param $r4 i32) (param $r5 i32) (param $r6 i32) (param $r7 i32) (param $r8 i32) (param $r9 i32) (result i32)
+    (local $r10 i32)
+    (local $r11 i32)
+    (local $r12 i32)
+    (local $r13 i32)
+    (local $r14 i32)
+    (local $r15 i32)
+    (local $r16 i32)
+    (local $r17 i32)
+    (local $r18 i32)
+    (local $r19 i32)
+    (local $r20 i32)
+    (local $r21 i32)
+    (local $r22 i32)
+    (local $r23 i32)
+    (local $r24 i32)
+    (local $r25 i32)
+    (local $r26 i32)
+    (local $r27 i32)
+    (local $r28 i32)
+    (local $r29 i32)
+    (local $r30 i32)
+    (local $r31 i32)
+    (local $r32 i32)
+    (local $r33 i32)
+    (local $r34 i32)
+    (local $r35 i32)
+    (local $r36 i32)
+    (local $r37 i32)
+    (local $r38 i32)
+    (local $r39 i32)
+    (local $r40 i32)
+    (local $r41 i32)
+    (local $r42 i32)
+    (local $r43 i32)
+    (local $r44 i32)
+    (local $r45 i32)
+    local.get $r0
+    i32.const 1
+    i32.mul
+    local.set $r10
+    local.get $r10
+    local.set $r11
+    local.get $r1
+    i32.const 2
+    i32.mul
+    local.set $r12
+    local.get $r12
+    local.set $r13
+    local.get $r11
+    local.get $r13
+    i32.add
+    local.set $r14
+    local.get $r14
+    local.set $r15
+    local.get $r2
+    i32.const 3
+    i32.mul
+    local.set $r16
+    local.get $r16
+    local.set $r17
+    local.get $r15
+    local.get $r17
+    i32.add
+    local.set $r18
+    local.get $r18
+    local.set $r19
+    local.get $r3
+    i32.const 4
+    i32.mul
+    local.set $r20
+    local.get $r20
+    local.set $r21
+    local.get $r19
+    local.get $r21
+    i32.add
+    local.set $r22
+    local.get $r22
+    local.set $r23
+    local.get $r4
+    i32.const 5
+    i32.mul
+    local.set $r24
+    local.get $r24
+    local.set $r25
+    local.get $r23
+    local.get $r25
+    i32.add
+    local.set $r26
+    local.get $r26
+    local.set $r27
+    local.get $r5
+    i32.const 6
+    i32.mul
+    local.set $r28
+    local.get $r28
+    local.set $r29
+    local.get $r27
+    local.get $r29
+    i32.add
+    local.set $r30
+    local.get $r30
+    local.set $r31
+    local.get $r6
+    i32.const 7
+    i32.mul
+    local.set $r32
+    local.get $r32
+    local.set $r33
+    local.get $r31
+    local.get $r33
+    i32.add
+    local.set $r34
+    local.get $r34
+    local.set $r35
+    local.get $r7
+    i32.const 8
+    i32.mul
+    local.set $r36
+    local.get $r36
+    local.set $r37
+    local.get $r35
+    local.get $r37
+    i32.add
+    local.set $r38
+    local.get $r38
+    local.set $r39
+    local.get $r8
+    i32.const 9
+    i32.mul
+    local.set $r40
+    local.get $r40
+    local.set $r41
+    local.get $r39
+    local.get $r41
+    i32.add
+    local.set $r42
+    local.get $r42
+    local.set $r43
+    local.get $r43
+    local.get $r9
+    i32.sub
+    local.set $r44
+    local.get $r44
+    local.set $r45
+    local.get $r45
+    return
+  )
+  (func $rotate (export "rotate") (param $r0 i32) (param $r1 i32) (param $r2 i32) (param $r3 i32) (param $r4 i32) (param $r5 i32) (param $r6 i32) (param $r7 i32) (param $r8 i32) (param $r9 i32) (result i32)
+    (local $r10 i32)
+    (local $r11 i32)
+    (local $r12 i32)
+    (local $r13 i32)
+    (local $r14 i32)
+    (local $r15 i32)
+    (local $r16 i32)
+    local.get $r0
+    i32.const 100
+    i32.gt_s
+    local.set $r10
+    local.get $r10
+    if
+      local.get $r0
+      local.get $r1
+      local.get $r2
+      local.get $r3
+      local.get $r4
+      local.get $r5
+      local.get $r6
+      local.get $r7
+      local.get $r8
+      local.get $r9
+      call $perm
+      local.set $r11
+      local.get $r11
+      local.set $r12
+      local.get $r12
+      return
+    else
+      local.get $r1
+      i32.const 100
+      i32.add
+      local.set $r13
+      local.get $r13
+      local.set $r14
+      local.get $r14
+      local.get $r2
+      local.get $r3
+      local.get $r4
+      local.get $r5
+      local.get $r6
+      local.get $r7
+      local.get $r8
+      local.get $r9
+      local.get $r0
+      call $rotate
+      local.set $r15
+      local.get $r15
+      local.set $r16
+      local.get $r16
+      return
+    end
+    unreachable
+  )
+  (func $main (export "main") (result i32)
+    (local $r0 i32)
+    (local $r1 i32)
+    (local $r2 i32)
+    (local $r3 i32)
+    (local $r4 i32)
+    (local $r5 i32)
+    (local $r6 i32)
+    (local $r7 i32)
+    i32.const 72
+    call $putchar
+    local.set $r0
+    local.get $r0
+    local.set $r1
+    i32.const 1
+    i32.const 2
+    i32.const 3
+    i32.const 4
+    i32.const 5
+    i32.const 6
+    i32.const 7
+    i32.const 8
+    i32.const 9
+    i32.const 10
+    call $rotate
+    local.set $r2
+    local.get $r2
+    local.set $r3
+    i32.const 10
+    i32.const 9
+    i32.const 8
+    i32.const 7
+    i32.const 6
+    i32.const 5
+    i32.const 4
+    i32.const 3
+    i32.const 2
+    i32.const 1
+    call $perm
+    local.set $r4
+    local.get $r4
+    local.set $r5
+    local.get $r3
+    local.get $r5
+    i32.sub
+    local.set $r6
+    local.get $r6
+    local.set $r7
+    local.get $r7
+    return
+  )
+)
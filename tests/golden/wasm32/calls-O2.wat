@@ -0,0 +1,257 @@
+(module
+  (import "env" "putchar" (func $putchar (param i32) (result i32)))
+  (memory (export "memory") 1)
+  (global $__stack_pointer (mut i32) (i32.const 65536))
+  (func $perm (export "perm") (param $r0 i32) (param $r1 i32) (param $r2 i32) (param $r3 i32) (param $r4 i32) (param $r5 i32) (param $r6 i32) (param $r7 i32) (param $r8 i32) (param $r9 i32) (result i32)
+    (local $r46 i32)
+    (local $r47 i32)
+    (local $r48 i32)
+    (local $r49 i32)
+    (local $r50 i32)
+    (local $r51 i32)
+    (local $r52 i32)
+    (local $r53 i32)
+    (local $r54 i32)
+    (local $r55 i32)
+    (local $r56 i32)
+    (local $r57 i32)
+    (local $r58 i32)
+    (local $r59 i32)
+    (local $r60 i32)
+    (local $r61 i32)
+    (local $r62 i32)
+    (local $r63 i32)
+    (local $r64 i32)
+    (local $r65 i32)
+    (local $r66 i32)
+    (local $r67 i32)
+    (local $r68 i32)
+    (local $r69 i32)
+    (local $r70 i32)
+    (local $r71 i32)
+    (local $r72 i32)
+    (local $r73 i32)
+    (local $r74 i32)
+    (local $r75 i32)
+    (local $r76 i32)
+    (local $r77 i32)
+    (local $r78 i32)
+    (local $r79 i32)
+    (local $r80 i32)
+    (local $r81 i32)
+    local.get $r0
+    i32.const 1
+    i32.mul
+    local.set $r46
+    local.get $r46
+    local.set $r47
+    local.get $r1
+    i32.const 2
+    i32.mul
+    local.set $r48
+    local.get $r48
+    local.set $r49
+    local.get $r47
+    local.get $r49
+    i32.add
+    local.set $r50
+    local.get $r50
+    local.set $r51
+    local.get $r2
+    i32.const 3
+    i32.mul
+    local.set $r52
+    local.get $r52
+    local.set $r53
+    local.get $r51
+    local.get $r53
+    i32.add
+    local.set $r54
+    local.get $r54
+    local.set $r55
+    local.get $r3
+    i32.const 4
+    i32.mul
+    local.set $r56
+    local.get $r56
+    local.set $r57
+    local.get $r55
+    local.get $r57
+    i32.add
+    local.set $r58
+    local.get $r58
+    local.set $r59
+    local.get $r4
+    i32.const 5
+    i32.mul
+    local.set $r60
+    local.get $r60
+    local.set $r61
+    local.get $r59
+    local.get $r61
+    i32.add
+    local.set $r62
+    local.get $r62
+    local.set $r63
+    local.get $r5
+    i32.const 6
+    i32.mul
+    local.set $r64
+    local.get $r64
+    local.set $r65
+    local.get $r63
+    local.get $r65
+    i32.add
+    local.set $r66
+    local.get $r66
+    local.set $r67
+    local.get $r6
+    i32.const 7
+    i32.mul
+    local.set $r68
+    local.get $r68
+    local.set $r69
+    local.get $r67
+    local.get $r69
+    i32.add
+    local.set $r70
+    local.get $r70
+    local.set $r71
+    local.get $r7
+    i32.const 8
+    i32.mul
+    local.set $r72
+    local.get $r72
+    local.set $r73
+    local.get $r71
+    local.get $r73
+    i32.add
+    local.set $r74
+    local.get $r74
+    local.set $r75
+    local.get $r8
+    i32.const 9
+    i32.mul
+    local.set $r76
+    local.get $r76
+    local.set $r77
+    local.get $r75
+    local.get $r77
+    i32.add
+    local.set $r78
+    local.get $r78
+    local.set $r79
+    local.get $r79
+    local.get $r9
+    i32.sub
+    local.set $r80
+    local.get $r80
+    local.set $r81
+    local.get $r81
+    return
+  )
+  (func $rotate (export "rotate") (param $r0 i32) (param $r1 i32) (param $r2 i32) (param $r3 i32) (param $r4 i32) (param $r5 i32) (param $r6 i32) (param $r7 i32) (param $r8 i32) (param $r9 i32) (result i32)
+    (local $r17 i32)
+    (local $r18 i32)
+    (local $r19 i32)
+    (local $r20 i32)
+    (local $r21 i32)
+    (local $r22 i32)
+    (local $r23 i32)
+    local.get $r0
+    i32.const 100
+    i32.gt_s
+    local.set $r17
+    local.get $r17
+    if
+      local.get $r0
+      local.get $r1
+      local.get $r2
+      local.get $r3
+      local.get $r4
+      local.get $r5
+      local.get $r6
+      local.get $r7
+      local.get $r8
+      local.get $r9
+      call $perm
+      local.set $r18
+      local.get $r18
+      local.set $r19
+      local.get $r19
+      return
+    else
+      local.get $r1
+      i32.const 100
+      i32.add
+      local.set $r20
+      local.get $r20
+      local.set $r21
+      local.get $r21
+      local.get $r2
+      local.get $r3
+      local.get $r4
+      local.get $r5
+      local.get $r6
+      local.get $r7
+      local.get $r8
+      local.get $r9
+      local.get $r0
+      call $rotate
+      local.set $r22
+      local.get $r22
+      local.set $r23
+      local.get $r23
+      return
+    end
+    unreachable
+  )
+  (func $main (export "main") (result i32)
+    (local $r8 i32)
+    (local $r10 i32)
+    (local $r11 i32)
+    (local $r12 i32)
+    (local $r13 i32)
+    (local $r14 i32)
+    (local $r15 i32)
+    i32.const 72
+    call $putchar
+    local.set $r8
+    i32.const 1
+    i32.const 2
+    i32.const 3
+    i32.const 4
+    i32.const 5
+    i32.const 6
+    i32.const 7
+    i32.const 8
+    i32.const 9
+    i32.const 10
+    call $rotate
+    local.set $r10
+    local.get $r10
+    local.set $r11
+    i32.const 10
+    i32.const 9
+    i32.const 8
+    i32.const 7
+    i32.const 6
+    i32.const 5
+    i32.const 4
+    i32.const 3
+    i32.const 2
+    i32.const 1
+    call $perm
+    local.set $r12
+    local.get $r12
+    local.set $r13
+    local.get $r11
+    local.get $r13
+    i32.sub
+    local.set $r14
+    local.get $r14
+    local.set $r15
+    local.get $r15
+    return
+  )
+)
@@ -0,0 +1,8 @@
+(module
+  (memory (export "memory") 1)
+  (global $__stack_pointer (mut i32) (i32.const 65536))
+  (func $main (export "main") (result i32)
+    i32.const 1
+    return
+  )
+)
@@ -0,0 +1,93 @@
+//! Checks the 32-bit x86 output against golden assembly, that it assembles
+//! the same in both syntaxes, and runs the programs when gcc has multilib
+//! support.
+
+extern crate cc;
+
+mod common;
+
+use cc::{codegen, opt, peephole};
+use cc::target::DataLayout;
+use cc::x86::{self, Syntax};
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+use std::process::Command;
+
+fn generate(source: &str, level: opt::Level, syntax: Syntax) -> String {
+    let layout = DataLayout::ILP32;
+    let instrs = peephole::optimize(codegen::generate(&common::lower_for(source, level, &layout), &layout));
+
+    x86::print(&instrs, syntax, &layout)
+}
+
+fn assemble(path: &Path) -> Vec<u8> {
+    let object = path.with_extension("o");
+    let output = Command::new("as").arg("--32").arg(path).arg("-o").arg(&object).output().unwrap();
+    assert!(output.status.success(), "{} does not assemble:\n{}", path.display(), String::from_utf8_lossy(&output.stderr));
+
+    let mut bytes = Vec::new();
+    File::open(&object).unwrap().read_to_end(&mut bytes).unwrap();
+
+    bytes
+}
+
+#[test]
+fn matches_golden_assembly() {
+    for (name, source, _) in common::programs() {
+        for &(level_name, level) in &common::LEVELS {
+            common::check_golden(&format!("i386/{}-{}.s", name, level_name), &generate(&source, level, Syntax::Att));
+        }
+    }
+}
+
+#[test]
+fn intel_assembles_like_att() {
+    if !common::installed("as") {
+        return eprintln!("as is not installed, skipping");
+    }
+
+    let directory = common::scratch_directory("i386-as");
+
+    for (name, source, _) in common::programs() {
+        for &(level_name, level) in &common::LEVELS {
+            let att = directory.join(format!("{}-{}-att.s", name, level_name));
+            let intel = directory.join(format!("{}-{}-intel.s", name, level_name));
+            common::write(&att, &generate(&source, level, Syntax::Att));
+            common::write(&intel, &generate(&source, level, Syntax::Intel));
+
+            assert!(assemble(&att) == assemble(&intel), "{} assembles differently in Intel syntax", intel.display());
+        }
+    }
+}
+
+#[test]
+fn runs_with_multilib() {
+    let directory = common::scratch_directory("i386-run");
+
+    // gcc is installed without 32-bit libraries more often than not
+    let probe = directory.join("probe.c");
+    common::write(&probe, "int main() { return 0; }\n");
+
+    let linked = Command::new("gcc").arg("-m32").arg(&probe).arg("-o").arg(probe.with_extension(""))
+        .output().is_ok_and(|output| output.status.success());
+
+    if !linked {
+        return eprintln!("gcc cannot link 32-bit programs, skipping");
+    }
+
+    for (name, source, expected) in common::programs_for(&DataLayout::ILP32) {
+        for &(level_name, level) in &common::LEVELS {
+            let path = directory.join(format!("{}-{}.s", name, level_name));
+            let program = path.with_extension("");
+            common::write(&path, &generate(&source, level, Syntax::Att));
+
+            let output = Command::new("gcc").arg("-m32").arg(&path).arg("-o").arg(&program).output().unwrap();
+            assert!(output.status.success(), "{} does not link:\n{}", path.display(), String::from_utf8_lossy(&output.stderr));
+
+            let output = Command::new(&program).output().unwrap();
+            assert_eq!(output.status.code(), Some(expected), "{} exits with the wrong status", program.display());
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Checks the integrated assembler against GNU as, instruction by
+//! instruction, and that its objects link with gcc and run.
+
+extern crate cc;
+
+mod common;
+
+use cc::{codegen, elf, encoder, generator, opt, peephole};
+use cc::target::DataLayout;
+use cc::x86::{self, Condition, Instr, Label, Operand, Reg, Syntax};
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::Path;
+use std::process::Command;
+
+fn generate(source: &str, level: opt::Level) -> Vec<Instr> {
+    let instrs = match level {
+        opt::Level::O0 => generator::generate(common::analyze(source)),
+        level => codegen::generate(&common::lower(source, level), &DataLayout::LP64)
+    };
+
+    peephole::optimize(instrs)
+}
+
+fn write_object(path: &Path, instrs: &[Instr]) {
+    File::create(path).unwrap().write_all(&elf::write(&encoder::encode(instrs), "test.c")).unwrap();
+}
+
+fn assemble(path: &Path, instrs: &[Instr]) {
+    common::write(&path.with_extension("s"), &x86::print(instrs, Syntax::Att, &DataLayout::LP64));
+
+    let output = Command::new("as").arg(path.with_extension("s")).arg("-o").arg(path).output().unwrap();
+    assert!(output.status.success(), "{} does not assemble:\n{}", path.display(), String::from_utf8_lossy(&output.stderr));
+}
+
+// The instructions of an object, without addresses or branch targets,
+// which differ where as picks the short form of a jump
+fn disassemble(path: &Path) -> Vec<String> {
+    let output = Command::new("objdump").arg("-d").arg("--no-show-raw-insn").arg(path).output().unwrap();
+    assert!(output.status.success(), "{} does not disassemble", path.display());
+
+    String::from_utf8(output.stdout).unwrap().lines()
+        .filter_map(|line| line.split_once(":\t"))
+        .map(|(_, instr)| {
+            let words: Vec<&str> = instr.split_whitespace().collect();
+
+            match words[0].trim_end_matches('q') {
+                mnemonic if mnemonic.starts_with('j') || mnemonic == "call" => mnemonic.to_string(),
+                _ => words.join(" ")
+            }
+        })
+        .collect()
+}
+
+fn text(path: &Path) -> Vec<u8> {
+    let binary = path.with_extension("bin");
+    let output = Command::new("objcopy").arg("-O").arg("binary").arg("--only-section=.text").arg(path).arg(&binary).output().unwrap();
+    assert!(output.status.success(), "could not copy the code out of {}", path.display());
+
+    fs::read(binary).unwrap()
+}
+
+#[test]
+fn encodes_like_the_assembler() {
+    if !common::installed("as") || !common::installed("objdump") {
+        return eprintln!("as or objdump is not installed, skipping");
+    }
+
+    let directory = common::scratch_directory("integrated-as");
+
+    for (name, source, _) in common::programs() {
+        for &(level_name, level) in &common::LEVELS {
+            let instrs = generate(&source, level);
+            let expected = directory.join(format!("{}-{}-as.o", name, level_name));
+            let actual = directory.join(format!("{}-{}.o", name, level_name));
+            assemble(&expected, &instrs);
+            write_object(&actual, &instrs);
+
+            assert_eq!(disassemble(&actual), disassemble(&expected), "{} encodes differently from as", actual.display());
+        }
+    }
+}
+
+// The registers and addressing modes with special encodings, which the
+// generated programs do not all reach
+#[test]
+fn encodes_special_registers_like_the_assembler() {
+    if !common::installed("as") || !common::installed("objcopy") {
+        return eprintln!("as or objcopy is not installed, skipping");
+    }
+
+    let mut instrs = Vec::new();
+
+    for &base in &[Reg::Rsp, Reg::Rbp, Reg::R12, Reg::R13, Reg::Rax] {
+        for &offset in &[0, -8, 1000] {
+            let mem = Operand::Mem { base, offset };
+
+            instrs.push(Instr::Mov { src: Operand::Reg(Reg::R9), dst: mem });
+            instrs.push(Instr::Mov { src: mem, dst: Operand::Reg(Reg::Rsi) });
+            instrs.push(Instr::Add { src: Operand::Imm(300), dst: mem });
+            instrs.push(Instr::Movsx8 { src: mem, dst: Reg::R14 });
+            instrs.push(Instr::Movzx32 { src: mem, dst: Reg::Rdi });
+            instrs.push(Instr::Push(mem));
+            instrs.push(Instr::Pop(mem));
+        }
+    }
+
+    for &reg in &[Reg::Rsp, Reg::Rsi, Reg::Rdi, Reg::R8, Reg::R15] {
+        instrs.push(Instr::Set(Condition::Le, reg));
+        instrs.push(Instr::Movzx8 { src: reg, dst: Reg::Rax });
+        instrs.push(Instr::Movsx8 { src: Operand::Reg(reg), dst: Reg::R10 });
+        instrs.push(Instr::Mov { src: Operand::Imm(-5000000000), dst: Operand::Reg(reg) });
+        instrs.push(Instr::Movzx32 { src: Operand::Imm(4000000000), dst: reg });
+        instrs.push(Instr::Imul { src: Operand::Imm(100000), dst: reg });
+        instrs.push(Instr::Shl { count: Operand::Imm(1), dst: Operand::Reg(reg) });
+        instrs.push(Instr::Sar { count: Operand::Reg(Reg::Rcx), dst: Operand::Reg(reg) });
+        instrs.push(Instr::Test { src: Operand::Imm(255), dst: Operand::Reg(reg) });
+        instrs.push(Instr::Zero(reg));
+        instrs.push(Instr::Push(Operand::Reg(reg)));
+        instrs.push(Instr::Pop(Operand::Reg(reg)));
+    }
+
+    instrs.push(Instr::Label(Label("end".to_string())));
+    instrs.push(Instr::Ret);
+
+    let directory = common::scratch_directory("integrated-as-registers");
+    let expected = directory.join("as.o");
+    let actual = directory.join("integrated.o");
+    assemble(&expected, &instrs);
+    write_object(&actual, &instrs);
+
+    assert!(text(&actual) == text(&expected), "{} encodes differently from as", actual.display());
+}
+
+#[test]
+fn links_with_gcc() {
+    if !common::installed("gcc") {
+        return eprintln!("gcc is not installed, skipping");
+    }
+
+    let directory = common::scratch_directory("integrated-as-link");
+
+    for (name, source, expected) in common::programs() {
+        for &(level_name, level) in &common::LEVELS {
+            let object = directory.join(format!("{}-{}.o", name, level_name));
+            let program = object.with_extension("");
+            write_object(&object, &generate(&source, level));
+
+            let output = Command::new("gcc").arg(&object).arg("-o").arg(&program).output().unwrap();
+            assert!(output.status.success(), "{} does not link:\n{}", object.display(), String::from_utf8_lossy(&output.stderr));
+            assert!(output.stderr.is_empty(), "{} links with warnings:\n{}", object.display(), String::from_utf8_lossy(&output.stderr));
+
+            let output = Command::new(&program).output().unwrap();
+            assert_eq!(output.status.code(), Some(expected), "{} exits with the wrong status", program.display());
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Runs the programs in the interpreter, and checks that it stops at
+//! undefined behavior with an error at the expression responsible.
+
+extern crate cc;
+
+mod common;
+
+use cc::interp;
+use cc::target::DataLayout;
+
+// Each reaches its undefined behavior in the expression given
+const UNDEFINED: [(&str, &str, &str); 7] = [
+    ("int main() { int x = 2147483647; return x + 1; }", "x + 1", "signed overflow: 2147483648 does not fit in 'int'"),
+    ("int main() { int x = 0; return 7 / x; }", "7 / x", "division by zero"),
+    ("int main() { long x = 1; int n = 64; return x << n; }", "x << n", "shift count 64 is out of range for 'long'"),
+    ("int main() { int x = 0 - 2147483647 - 1; return -x; }", "-x", "signed overflow: 2147483648 does not fit in 'int'"),
+    ("int main() { int x; return x; }", "x", "variable 'x' is used uninitialized"),
+    ("int getchar(); int main() { return getchar(); }", "getchar()", "'getchar' is not defined in this file, so it cannot be interpreted"),
+    ("int f(int a) { if (a > 5) return 1; } int main() { return f(2) + 3; }", "f(2)", "'f' reached the end of its body without returning a value")
+];
+
+#[test]
+fn runs_the_programs() {
+    for (name, source, expected) in common::programs() {
+        let mut output = Vec::new();
+        let status = interp::run(&common::analyze(&source), &DataLayout::LP64, &mut output).unwrap();
+
+        assert_eq!(status & 255, expected as i64, "{} returns the wrong status", name);
+
+        if name == "calls" {
+            assert_eq!(output, b"H");
+        }
+    }
+}
+
+#[test]
+fn stops_at_undefined_behavior() {
+    for &(source, expression, message) in &UNDEFINED {
+        let error = interp::run(&common::analyze(source), &DataLayout::LP64, Vec::new()).unwrap_err();
+
+        assert_eq!((&source[error.span.start..error.span.end], &error.message[..]), (expression, message), "{} stops in the wrong place", source);
+    }
+}
+
+#[test]
+fn overflows_where_long_is_32_bits() {
+    let error = interp::run(&common::analyze(common::ARITHMETIC), &DataLayout::ILP32, Vec::new()).unwrap_err();
+
+    assert_eq!(error.message, "signed overflow: 4935228928 does not fit in 'long'");
+}
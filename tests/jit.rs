@@ -0,0 +1,45 @@
+//! Runs the programs in this process through the JIT.
+
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+extern crate cc;
+
+mod common;
+
+use cc::{codegen, encoder, generator, jit, opt, peephole};
+use cc::target::DataLayout;
+
+fn compile(source: &str, level: opt::Level) -> encoder::Code {
+    let instrs = match level {
+        opt::Level::O0 => generator::generate(common::analyze(source)),
+        level => codegen::generate(&common::lower(source, level), &DataLayout::LP64)
+    };
+
+    encoder::encode(&peephole::optimize(instrs))
+}
+
+#[test]
+fn runs_the_programs() {
+    for (name, source, expected) in common::programs() {
+        for &(level_name, level) in &common::LEVELS {
+            // Exit statuses are the low byte of what main returns
+            let status = jit::run(&compile(&source, level)).unwrap() & 255;
+
+            assert_eq!(status, expected, "{} at {} returns the wrong status", name, level_name);
+        }
+    }
+}
+
+#[test]
+fn calls_into_libc() {
+    let code = compile("int abs(int x);\nint main() { return abs(-42) + abs(7); }\n", opt::Level::O0);
+
+    assert_eq!(jit::run(&code), Ok(49));
+}
+
+#[test]
+fn reports_undefined_symbols() {
+    let code = compile("int missing(int x);\nint main() { return missing(1); }\n", opt::Level::O2);
+
+    assert_eq!(jit::run(&code), Err("undefined reference to 'missing'".to_string()));
+}
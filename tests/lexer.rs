@@ -0,0 +1,151 @@
+//! Checks how the lexer splits identifiers and keywords, the spans it gives
+//! tokens, and that it yields them one at a time.
+
+extern crate cc;
+
+use cc::lexer::{self, LexErrorKind, Lexer, SpannedToken};
+use cc::{Keyword, Span, Token};
+
+const KEYWORDS: [(&str, Keyword); 44] = [
+    ("auto", Keyword::Auto),
+    ("break", Keyword::Break),
+    ("case", Keyword::Case),
+    ("char", Keyword::Char),
+    ("const", Keyword::Const),
+    ("continue", Keyword::Continue),
+    ("default", Keyword::Default),
+    ("do", Keyword::Do),
+    ("double", Keyword::Double),
+    ("else", Keyword::Else),
+    ("enum", Keyword::Enum),
+    ("extern", Keyword::Extern),
+    ("float", Keyword::Float),
+    ("for", Keyword::For),
+    ("goto", Keyword::Goto),
+    ("if", Keyword::If),
+    ("inline", Keyword::Inline),
+    ("int", Keyword::Int),
+    ("long", Keyword::Long),
+    ("register", Keyword::Register),
+    ("restrict", Keyword::Restrict),
+    ("return", Keyword::Return),
+    ("short", Keyword::Short),
+    ("signed", Keyword::Signed),
+    ("sizeof", Keyword::Sizeof),
+    ("static", Keyword::Static),
+    ("struct", Keyword::Struct),
+    ("switch", Keyword::Switch),
+    ("typedef", Keyword::Typedef),
+    ("union", Keyword::Union),
+    ("unsigned", Keyword::Unsigned),
+    ("void", Keyword::Void),
+    ("volatile", Keyword::Volatile),
+    ("while", Keyword::While),
+    ("_Alignas", Keyword::Alignas),
+    ("_Alignof", Keyword::Alignof),
+    ("_Atomic", Keyword::Atomic),
+    ("_Bool", Keyword::Bool),
+    ("_Complex", Keyword::Complex),
+    ("_Generic", Keyword::Generic),
+    ("_Imaginary", Keyword::Imaginary),
+    ("_Noreturn", Keyword::Noreturn),
+    ("_Static_assert", Keyword::StaticAssert),
+    ("_Thread_local", Keyword::ThreadLocal)
+];
+
+#[test]
+fn lexes_every_c11_keyword() {
+    for &(spelling, keyword) in &KEYWORDS {
+        assert_eq!(lexer::lex(spelling), Ok(vec![Token::Keyword(keyword)]), "{} is not lexed as a keyword", spelling);
+
+        // Keywords are whole identifiers, so a longer identifier is not one
+        let longer = format!("{}_", spelling);
+        assert_eq!(lexer::lex(&longer), Ok(vec![Token::Identifier(&longer)]));
+    }
+}
+
+#[test]
+fn lexes_identifiers_with_digits_and_underscores() {
+    assert_eq!(lexer::lex("x1"), Ok(vec![Token::Identifier("x1")]));
+    assert_eq!(lexer::lex("_start"), Ok(vec![Token::Identifier("_start")]));
+    assert_eq!(lexer::lex("__x_2_"), Ok(vec![Token::Identifier("__x_2_")]));
+
+    // An identifier cannot start with a digit
+    assert_eq!(lexer::lex("1x"), Ok(vec![Token::IntegerLiteral(1), Token::Identifier("x")]));
+}
+
+#[test]
+fn rejects_letters_outside_ascii() {
+    let error = Lexer::new("int é;").find_map(Result::err).unwrap();
+
+    assert_eq!(error.kind, LexErrorKind::UnexpectedCharacter('é'));
+    assert_eq!(error.span, Span { start: 4, end: 6 });
+
+    // Nor can one continue an identifier
+    let error = lexer::lex("xé").unwrap_err();
+
+    assert_eq!(error.kind, LexErrorKind::UnexpectedCharacter('é'));
+    assert_eq!(error.span, Span { start: 1, end: 3 });
+}
+
+fn spanned(token: Token, start: usize, end: usize) -> SpannedToken {
+    SpannedToken { token, span: Span::new(start, end) }
+}
+
+#[test]
+fn spans_tokens_by_byte_offset_across_lines() {
+    let source = "int main() {\n    return 42;\r\n}\n";
+    let tokens: Vec<SpannedToken> = Lexer::new(source).map(Result::unwrap)
+        .filter(|spanned| spanned.token != Token::Space)
+        .collect();
+
+    assert_eq!(tokens, [
+        spanned(Token::Keyword(Keyword::Int), 0, 3),
+        spanned(Token::Identifier("main"), 4, 8),
+        spanned(Token::OpenParen, 8, 9),
+        spanned(Token::CloseParen, 9, 10),
+        spanned(Token::OpenBrace, 11, 12),
+        spanned(Token::NewLine, 12, 13),
+        spanned(Token::Keyword(Keyword::Return), 17, 23),
+        spanned(Token::IntegerLiteral(42), 24, 26),
+        spanned(Token::Semicolon, 26, 27),
+        spanned(Token::NewLine, 27, 28),
+        spanned(Token::NewLine, 28, 29),
+        spanned(Token::CloseBrace, 29, 30),
+        spanned(Token::NewLine, 30, 31)
+    ]);
+
+    for token in &tokens {
+        assert!(source.is_char_boundary(token.span.start) && source.is_char_boundary(token.span.end));
+    }
+}
+
+#[test]
+fn yields_tokens_before_an_error_and_goes_on_after_it() {
+    let mut lexer = Lexer::new("x = 1;\ny = 99999999999999999999999;\nz @ 2");
+
+    // The tokens before the error come out as they are lexed
+    let before: Vec<Token> = lexer.by_ref().take(6).map(|result| result.unwrap().token).collect();
+    assert_eq!(before, [Token::Identifier("x"), Token::Space, Token::Assignment, Token::Space, Token::IntegerLiteral(1), Token::Semicolon]);
+
+    let rest: Vec<Result<Token, LexErrorKind>> = lexer
+        .map(|result| result.map(|spanned| spanned.token).map_err(|error| error.kind))
+        .filter(|result| *result != Ok(Token::Space))
+        .collect();
+
+    assert_eq!(rest, [
+        Ok(Token::NewLine),
+        Ok(Token::Identifier("y")),
+        Ok(Token::Assignment),
+        Err(LexErrorKind::IntegerLiteralTooLarge),
+        Ok(Token::Semicolon),
+        Ok(Token::NewLine),
+        Ok(Token::Identifier("z")),
+        Err(LexErrorKind::UnexpectedCharacter('@')),
+        Ok(Token::IntegerLiteral(2))
+    ]);
+
+    // The error's span is where it is in the source
+    let error = Lexer::new("x = 1;\nz @ 2").find_map(Result::err).unwrap();
+    assert_eq!(error.span, Span::new(9, 10));
+}
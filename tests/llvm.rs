@@ -0,0 +1,36 @@
+//! Checks the LLVM IR generator against golden text, and runs the programs
+//! with lli when it is installed.
+
+extern crate cc;
+
+mod common;
+
+use cc::llvm;
+use cc::target::Target;
+
+use std::process::Command;
+
+#[test]
+fn matches_golden_text() {
+    for (name, source, _) in common::programs() {
+        common::check_golden(&format!("llvm/{}.ll", name), &llvm::generate(&common::analyze(&source), Target::X86_64));
+    }
+}
+
+#[test]
+fn runs_under_lli() {
+    if !common::installed("lli") {
+        return eprintln!("lli is not installed, skipping");
+    }
+
+    let directory = common::scratch_directory("llvm-run");
+
+    for (name, source, expected) in common::programs() {
+        let path = directory.join(format!("{}.ll", name));
+        common::write(&path, &llvm::generate(&common::analyze(&source), Target::X86_64));
+
+        let output = Command::new("lli").arg(&path).output().unwrap();
+        assert!(output.stderr.is_empty(), "{} is not valid:\n{}", path.display(), String::from_utf8_lossy(&output.stderr));
+        assert_eq!(output.status.code(), Some(expected), "{} exits with the wrong status", path.display());
+    }
+}
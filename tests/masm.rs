@@ -0,0 +1,74 @@
+//! Checks that AT&T and Intel syntax output assemble to the same object code.
+
+extern crate cc;
+
+mod common;
+
+use cc::{codegen, generator, opt, peephole};
+use cc::target::DataLayout;
+use cc::x86::{self, Instr, Syntax};
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::process::Command;
+
+fn compile(source: &str, level: opt::Level, optimize_peephole: bool) -> Vec<Instr> {
+    let instrs = match level {
+        opt::Level::O0 => generator::generate(common::analyze(source)),
+        level => codegen::generate(&common::lower(source, level), &DataLayout::LP64)
+    };
+
+    if optimize_peephole {
+        peephole::optimize(instrs)
+    } else {
+        instrs
+    }
+}
+
+// Assembles with the system assembler, or returns `None` if there is none
+fn assemble(assembly: &str, name: &str) -> Option<Vec<u8>> {
+    let directory = common::scratch_directory("masm");
+    let source = directory.join(format!("{}.s", name));
+    let object = directory.join(format!("{}.o", name));
+    common::write(&source, assembly);
+
+    let output = Command::new("as").arg(&source).arg("-o").arg(&object).output().ok()?;
+    assert!(output.status.success(), "as rejected {}:\n{}\n{}", name, String::from_utf8_lossy(&output.stderr), assembly);
+
+    let mut bytes = Vec::new();
+    File::open(&object).unwrap().read_to_end(&mut bytes).unwrap();
+
+    Some(bytes)
+}
+
+#[test]
+fn intel_assembles_like_att() {
+    let levels = [("O0", opt::Level::O0), ("O1", opt::Level::O1), ("O2", opt::Level::O2)];
+
+    for (name, source, _) in common::programs() {
+        for &(level_name, level) in &levels {
+            for &optimize_peephole in &[false, true] {
+                let instrs = compile(&source, level, optimize_peephole);
+                let name = format!("{}-{}-{}", name, level_name, optimize_peephole);
+
+                let att = match assemble(&x86::print(&instrs, Syntax::Att, &DataLayout::LP64), &format!("{}-att", name)) {
+                    Some(bytes) => bytes,
+                    None => return eprintln!("as is not installed, skipping")
+                };
+                let intel = assemble(&x86::print(&instrs, Syntax::Intel, &DataLayout::LP64), &format!("{}-intel", name)).unwrap();
+
+                assert!(att == intel, "{} assembles differently in Intel syntax", name);
+            }
+        }
+    }
+}
+
+#[test]
+fn intel_output_selects_syntax() {
+    let instrs = compile("int main() { return 2; }", opt::Level::O0, true);
+    let intel = x86::print(&instrs, Syntax::Intel, &DataLayout::LP64);
+
+    assert!(intel.starts_with(".intel_syntax noprefix\n"));
+    assert!(intel.contains("mov rax, 2"));
+    assert!(x86::print(&instrs, Syntax::Att, &DataLayout::LP64).contains("movq $2, %rax"));
+}
@@ -0,0 +1,222 @@
+//! Runs each SSA pass on its own over lowered functions, checking what it
+//! changes and that the function is still in valid SSA form afterwards.
+
+extern crate cc;
+
+mod common;
+
+use cc::ir::{self, BinaryOp, BlockId, Function, Instr, Operand, Terminator, VReg};
+use cc::{opt, ssa};
+
+// The function `f` in `source`, lowered without optimization and put into SSA form
+fn in_ssa(source: &str) -> Function {
+    let module = common::lower(source, opt::Level::O0);
+    let mut function = module.functions.into_iter().find(|function| function.name == "f").unwrap();
+
+    ssa::construct(&mut function);
+    check(&function);
+
+    function
+}
+
+fn check(function: &Function) {
+    assert_eq!(ir::verify_function(function), Ok(()), "invalid IR:\n{}", function);
+    assert_eq!(ssa::verify(function), Ok(()), "invalid SSA:\n{}", function);
+}
+
+// Every instruction, with the block it is in
+fn instrs(function: &Function) -> Vec<(BlockId, &Instr)> {
+    function.block_ids().flat_map(|id| function.block(id).instrs.iter().map(move |instr| (id, instr))).collect()
+}
+
+fn count<F: Fn(&Instr) -> bool>(function: &Function, predicate: F) -> usize {
+    instrs(function).into_iter().filter(|&(_, instr)| predicate(instr)).count()
+}
+
+fn is_binary(op: BinaryOp) -> impl Fn(&Instr) -> bool {
+    move |instr| matches!(*instr, Instr::Binary { op: instr_op, .. } if instr_op == op)
+}
+
+// The blocks that return, with the value each returns
+fn returns(function: &Function) -> Vec<Option<Operand>> {
+    function.blocks.iter().filter_map(|block| match block.terminator {
+        Terminator::Return(value) => Some(value),
+        _ => None
+    }).collect()
+}
+
+const LOOP: &str = "
+int f(int a, int b, int n) {
+    int x = 0;
+    int y = 0;
+    while (n > 0) {
+        x = a / b;
+        y = a * 4 + b;
+        n = n - 1;
+    }
+    return x + y;
+}
+";
+
+#[test]
+fn construction_assigns_each_register_once() {
+    let function = in_ssa(LOOP);
+
+    // The loop header merges x, y and n, which the body assigns; a and b need no phi
+    let header = function.block(BlockId(1));
+    let phis: Vec<&Instr> = header.instrs.iter().take_while(|instr| instr.is_phi()).collect();
+    assert_eq!(phis.len(), 3, "wrong phis:\n{}", function);
+    assert_eq!(count(&function, Instr::is_phi), 3);
+
+    for phi in phis {
+        match *phi {
+            Instr::Phi { ref sources, .. } => {
+                let mut blocks: Vec<BlockId> = sources.iter().map(|&(block, _)| block).collect();
+                blocks.sort();
+                assert_eq!(blocks, [BlockId(0), BlockId(2)]);
+            },
+            _ => unreachable!()
+        }
+    }
+
+    // Parameters keep their registers, since nothing assigns them before use
+    assert!(instrs(&function).iter().any(|&(_, instr)| {
+        matches!(*instr, Instr::Binary { op: BinaryOp::Div, lhs: Operand::Reg(VReg(0)), rhs: Operand::Reg(VReg(1)), .. })
+    }));
+}
+
+#[test]
+fn destruction_replaces_phis_with_copies() {
+    let mut function = in_ssa(LOOP);
+    ssa::destruct(&mut function);
+
+    assert_eq!(count(&function, Instr::is_phi), 0);
+    assert_eq!(ir::verify_function(&function), Ok(()));
+
+    // A phi whose value comes from one side of a branch needs the edge split
+    let mut function = in_ssa("int f(int a) { int x = 1; if (a) x = 2; return x; }");
+    let blocks = function.blocks.len();
+    ssa::destruct(&mut function);
+
+    assert_eq!(count(&function, Instr::is_phi), 0);
+    assert_eq!(function.blocks.len(), blocks + 1, "the edge is not split:\n{}", function);
+}
+
+#[test]
+fn sccp_folds_constants_and_drops_branches_never_taken() {
+    let mut function = in_ssa("int f(int a) { int x = 2; int y = x * 3; if (y > 5) return a + y; return a - 1; }");
+    opt::sccp(&mut function);
+    opt::propagate_copies(&mut function);
+    opt::eliminate_dead_code(&mut function);
+    check(&function);
+
+    assert_eq!(count(&function, is_binary(BinaryOp::Mul)), 0);
+    assert_eq!(count(&function, is_binary(BinaryOp::Sub)), 0, "the untaken return is kept:\n{}", function);
+    assert!(!function.blocks.iter().any(|block| matches!(block.terminator, Terminator::Branch { .. })));
+    assert!(instrs(&function).iter().any(|&(_, instr)| matches!(*instr, Instr::Binary { op: BinaryOp::Add, rhs: Operand::Imm(6), .. })));
+}
+
+#[test]
+fn sccp_merges_equal_constants_through_phis() {
+    let mut function = in_ssa("int f(int a) { int x; if (a) x = 4; else x = 4; return x + 1; }");
+    opt::sccp(&mut function);
+    check(&function);
+
+    assert_eq!(returns(&function), [Some(Operand::Imm(5))]);
+
+    // Different constants on each side are not a constant
+    let mut function = in_ssa("int f(int a) { int x; if (a) x = 4; else x = 5; return x + 1; }");
+    opt::sccp(&mut function);
+    check(&function);
+
+    assert!(returns(&function).iter().all(|value| value.is_some_and(|value| value.as_imm().is_none())));
+}
+
+#[test]
+fn copy_propagation_removes_copies_and_single_valued_phis() {
+    let mut function = in_ssa("int f(int a) { int x = a; int y = x; if (a) y = x; return y + 1; }");
+    opt::propagate_copies(&mut function);
+    check(&function);
+
+    assert_eq!(count(&function, |instr| matches!(*instr, Instr::Copy { .. })), 0, "copies are left:\n{}", function);
+    assert!(instrs(&function).iter().any(|&(_, instr)| matches!(*instr, Instr::Binary { op: BinaryOp::Add, lhs: Operand::Reg(VReg(0)), .. })));
+}
+
+#[test]
+fn dce_removes_unused_results_but_keeps_calls() {
+    let mut function = in_ssa("int g(int a); int f(int a) { int x = a * 2; int y = g(a) + 3; return a; }");
+    opt::eliminate_dead_code(&mut function);
+    check(&function);
+
+    assert_eq!(count(&function, is_binary(BinaryOp::Mul)), 0);
+    assert_eq!(count(&function, is_binary(BinaryOp::Add)), 0);
+    assert_eq!(count(&function, |instr| matches!(*instr, Instr::Call { .. })), 1);
+}
+
+#[test]
+fn dce_removes_phis_that_only_feed_each_other() {
+    let mut function = in_ssa("int f(int n) { int unused = 0; while (n > 0) { unused = unused + 1; n = n - 1; } return n; }");
+    opt::eliminate_dead_code(&mut function);
+    check(&function);
+
+    // Only n's phi is left, and the increment of the unused counter is gone
+    assert_eq!(count(&function, Instr::is_phi), 1, "dead phis are left:\n{}", function);
+    assert_eq!(count(&function, is_binary(BinaryOp::Add)), 0);
+}
+
+#[test]
+fn cse_reuses_dominating_expressions() {
+    let mut function = in_ssa("int f(int a, int b) { int x = (a + b) * (b + a); if (a) x = x + (a + b); return x; }");
+    opt::eliminate_common_subexpressions(&mut function);
+    opt::eliminate_dead_code(&mut function);
+    check(&function);
+
+    // Both orders of the commutative add, and the one in the dominated branch, are one add
+    assert_eq!(count(&function, is_binary(BinaryOp::Add)), 2, "the sums are not shared:\n{}", function);
+
+    // An expression in one branch does not dominate the other
+    let mut function = in_ssa("int f(int a, int b) { int x; if (a) x = a - b; else x = a - b + 1; return x; }");
+    opt::eliminate_common_subexpressions(&mut function);
+    check(&function);
+
+    assert_eq!(count(&function, is_binary(BinaryOp::Sub)), 2);
+}
+
+#[test]
+fn licm_hoists_invariants_into_the_preheader() {
+    let mut function = in_ssa(LOOP);
+    opt::hoist_loop_invariants(&mut function);
+    check(&function);
+
+    let block_of = |op: BinaryOp| instrs(&function).into_iter().find(|&(_, instr)| is_binary(op)(instr)).unwrap().0;
+
+    assert_eq!(block_of(BinaryOp::Mul), Function::ENTRY, "a * 4 is not hoisted:\n{}", function);
+    assert_eq!(block_of(BinaryOp::Add), Function::ENTRY, "a * 4 + b is not hoisted:\n{}", function);
+
+    // The decrement depends on n, which the loop assigns
+    assert_ne!(block_of(BinaryOp::Sub), Function::ENTRY);
+}
+
+#[test]
+fn licm_does_not_hoist_a_division_that_may_trap() {
+    // With n = 0 the loop never runs, so hoisting a / b would divide by zero
+    let mut function = in_ssa(LOOP);
+    opt::hoist_loop_invariants(&mut function);
+    check(&function);
+
+    let (block, _) = instrs(&function).into_iter().find(|&(_, instr)| is_binary(BinaryOp::Div)(instr)).unwrap();
+    assert_eq!(block, BlockId(2), "the division left the loop body:\n{}", function);
+
+    // Dividing by a constant other than 0 or -1 cannot trap, so it can move
+    let divide = |divisor: &str| {
+        let mut function = in_ssa(&LOOP.replace("a / b", &format!("a / {}", divisor)));
+        opt::hoist_loop_invariants(&mut function);
+        check(&function);
+
+        instrs(&function).into_iter().find(|&(_, instr)| is_binary(BinaryOp::Div)(instr)).unwrap().0
+    };
+
+    assert_eq!(divide("2"), Function::ENTRY);
+    assert_eq!(divide("-1"), BlockId(2));
+    assert_eq!(divide("0"), BlockId(2));
+}
@@ -0,0 +1,232 @@
+//! Checks the token cursor's lookahead, and the trees the parser builds for
+//! each construct of the language.
+
+extern crate cc;
+
+mod common;
+
+use cc::lexer::{LexErrorKind, Lexer, SpannedToken};
+use cc::parser::TokenCursor;
+use cc::{BinaryOperator, BlockItem, Decl, DeclId, Expr, ExprKind, FunctionDef, Keyword, Param, Span, Stmt, StmtKind, Token, TranslationUnit, Type};
+
+fn expr(kind: ExprKind) -> Expr {
+    Expr::new(kind, Span::default())
+}
+
+fn constant(value: usize) -> Expr {
+    expr(ExprKind::Constant(value))
+}
+
+fn variable(name: &str) -> Expr {
+    expr(ExprKind::Variable(name.to_string(), None))
+}
+
+fn assignment(target: Expr, value: Expr) -> Expr {
+    expr(ExprKind::Assignment(Box::new(target), Box::new(value)))
+}
+
+fn binary(operator: BinaryOperator, lhs: Expr, rhs: Expr) -> Expr {
+    expr(ExprKind::Binary(operator, Box::new(lhs), Box::new(rhs)))
+}
+
+fn call(name: &str, args: Vec<Expr>) -> Expr {
+    expr(ExprKind::Call(name.to_string(), args))
+}
+
+fn stmt(kind: StmtKind) -> BlockItem {
+    BlockItem::Stmt(Stmt::new(kind, Span::default()))
+}
+
+fn decl(id: usize, name: &str, ty: Type, init: Option<Expr>) -> BlockItem {
+    BlockItem::Decl(Decl {
+        id: DeclId(id),
+        name: name.to_string(),
+        ty,
+        init,
+        span: Span::default()
+    })
+}
+
+fn param(id: usize, name: Option<&str>, ty: Type) -> Param {
+    Param {
+        id: DeclId(id),
+        name: name.map(str::to_string),
+        ty,
+        span: Span::default()
+    }
+}
+
+fn function(name: &str, return_type: Type, params: Vec<Param>, body: Option<Vec<BlockItem>>) -> FunctionDef {
+    FunctionDef {
+        name: name.to_string(),
+        return_type,
+        params,
+        body,
+        span: Span::default()
+    }
+}
+
+fn main_function(body: Vec<BlockItem>) -> TranslationUnit {
+    TranslationUnit {
+        functions: vec![function("main", Type::Int, Vec::new(), Some(body))]
+    }
+}
+
+#[test]
+fn parses_locals_assignment_and_blocks() {
+    let ast = common::parse_without_spans("int main() { int a = 1; int b; b = a = 2; { int a; a = b; } return a; }");
+
+    // Assignment is right associative, and a block's declarations get ids of their own
+    assert_eq!(ast, main_function(vec![
+        decl(0, "a", Type::Int, Some(constant(1))),
+        decl(1, "b", Type::Int, None),
+        stmt(StmtKind::Expr(assignment(variable("b"), assignment(variable("a"), constant(2))))),
+        stmt(StmtKind::Compound(vec![
+            decl(2, "a", Type::Int, None),
+            stmt(StmtKind::Expr(assignment(variable("a"), variable("b"))))
+        ])),
+        stmt(StmtKind::Return(Some(variable("a"))))
+    ]));
+}
+
+#[test]
+fn parses_each_type_name() {
+    let ast = common::parse_without_spans("int main() { char c; long l; long int li; unsigned u; unsigned int ui; return 0; }");
+
+    // The type names that mean the same type are merged
+    assert_eq!(ast, main_function(vec![
+        decl(0, "c", Type::Char, None),
+        decl(1, "l", Type::Long, None),
+        decl(2, "li", Type::Long, None),
+        decl(3, "u", Type::UnsignedInt, None),
+        decl(4, "ui", Type::UnsignedInt, None),
+        stmt(StmtKind::Return(Some(constant(0))))
+    ]));
+}
+
+#[test]
+fn parses_prototypes_parameters_and_calls() {
+    let ast = common::parse_without_spans("int f(int, char c); void g(void); long f(int a, char c) { return f(a, c + 1); }");
+
+    // Parameters take ids in order, even in prototypes, and void alone means none
+    assert_eq!(ast, TranslationUnit {
+        functions: vec![
+            function("f", Type::Int, vec![param(0, None, Type::Int), param(1, Some("c"), Type::Char)], None),
+            function("g", Type::Void, Vec::new(), None),
+            function("f", Type::Long, vec![param(2, Some("a"), Type::Int), param(3, Some("c"), Type::Char)], Some(vec![
+                stmt(StmtKind::Return(Some(call("f", vec![
+                    variable("a"),
+                    binary(BinaryOperator::Addition, variable("c"), constant(1))
+                ]))))
+            ]))
+        ]
+    });
+}
+
+#[test]
+fn parses_if_else_and_while() {
+    let ast = common::parse_without_spans("int main() { int a = 0; while (a) if (a) a = 1; else if (a) a = 2; else a = 3; return a; }");
+
+    // Each else belongs to the nearest if
+    let inner = Stmt::new(StmtKind::If(
+        variable("a"),
+        Box::new(Stmt::new(StmtKind::Expr(assignment(variable("a"), constant(2))), Span::default())),
+        Some(Box::new(Stmt::new(StmtKind::Expr(assignment(variable("a"), constant(3))), Span::default())))
+    ), Span::default());
+    let outer = Stmt::new(StmtKind::If(
+        variable("a"),
+        Box::new(Stmt::new(StmtKind::Expr(assignment(variable("a"), constant(1))), Span::default())),
+        Some(Box::new(inner))
+    ), Span::default());
+
+    assert_eq!(ast, main_function(vec![
+        decl(0, "a", Type::Int, Some(constant(0))),
+        stmt(StmtKind::While(variable("a"), Box::new(outer))),
+        stmt(StmtKind::Return(Some(variable("a"))))
+    ]));
+}
+
+#[test]
+fn parses_shifts_between_additive_and_relational_operators() {
+    let ast = common::parse_without_spans("int main() { return 1 << 2 + 3 < 4 >> 5 >> 6; }");
+
+    let lhs = binary(BinaryOperator::ShiftLeft, constant(1), binary(BinaryOperator::Addition, constant(2), constant(3)));
+    let rhs = binary(BinaryOperator::ShiftRight, binary(BinaryOperator::ShiftRight, constant(4), constant(5)), constant(6));
+
+    assert_eq!(ast, main_function(vec![
+        stmt(StmtKind::Return(Some(binary(BinaryOperator::LessThan, lhs, rhs))))
+    ]));
+}
+
+fn spanned(token: Token, start: usize, end: usize) -> SpannedToken {
+    SpannedToken { token, span: Span::new(start, end) }
+}
+
+fn tokens(mut cursor: TokenCursor) -> Vec<SpannedToken> {
+    let mut tokens = Vec::new();
+
+    while let Some(token) = cursor.next_token().unwrap() {
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+#[test]
+fn keeps_whitespace_with_its_spans_only_in_trivia_mode() {
+    let source = "int\tx;\r\n  return";
+
+    assert_eq!(tokens(TokenCursor::with_trivia(Lexer::new(source))), [
+        spanned(Token::Keyword(Keyword::Int), 0, 3),
+        spanned(Token::Space, 3, 4),
+        spanned(Token::Identifier("x"), 4, 5),
+        spanned(Token::Semicolon, 5, 6),
+        spanned(Token::NewLine, 6, 7),
+        spanned(Token::NewLine, 7, 8),
+        spanned(Token::Space, 8, 9),
+        spanned(Token::Space, 9, 10),
+        spanned(Token::Keyword(Keyword::Return), 10, 16)
+    ]);
+
+    assert_eq!(tokens(TokenCursor::new(Lexer::new(source))), [
+        spanned(Token::Keyword(Keyword::Int), 0, 3),
+        spanned(Token::Identifier("x"), 4, 5),
+        spanned(Token::Semicolon, 5, 6),
+        spanned(Token::Keyword(Keyword::Return), 10, 16)
+    ]);
+}
+
+#[test]
+fn peeks_past_the_buffered_tokens_and_at_the_end() {
+    let mut cursor = TokenCursor::new(Lexer::new("a b  c"));
+
+    assert_eq!(cursor.peek_nth(0).unwrap(), Some(&spanned(Token::Identifier("a"), 0, 1)));
+
+    // Only `a` is buffered, so this lexes on past `b`
+    assert_eq!(cursor.peek_nth(2).unwrap(), Some(&spanned(Token::Identifier("c"), 5, 6)));
+    assert_eq!(cursor.peek_nth(1).unwrap(), Some(&spanned(Token::Identifier("b"), 2, 3)));
+    assert_eq!(cursor.peek_nth(3).unwrap(), None);
+
+    // Peeking consumes nothing
+    assert_eq!(cursor.next_token().unwrap(), Some(spanned(Token::Identifier("a"), 0, 1)));
+    assert_eq!(cursor.peek_nth(1).unwrap(), Some(&spanned(Token::Identifier("c"), 5, 6)));
+    assert_eq!(cursor.next_token().unwrap(), Some(spanned(Token::Identifier("b"), 2, 3)));
+    assert_eq!(cursor.next_token().unwrap(), Some(spanned(Token::Identifier("c"), 5, 6)));
+
+    assert_eq!(cursor.peek_nth(0).unwrap(), None);
+    assert_eq!(cursor.peek_nth(5).unwrap(), None);
+    assert_eq!(cursor.next_token().unwrap(), None);
+}
+
+#[test]
+fn reports_a_lexing_error_once_lookahead_reaches_it() {
+    let mut cursor = TokenCursor::new(Lexer::new("a $ b"));
+
+    assert_eq!(cursor.peek_nth(0).unwrap(), Some(&spanned(Token::Identifier("a"), 0, 1)));
+
+    let error = cursor.peek_nth(1).unwrap_err();
+    assert_eq!((error.kind, error.span), (LexErrorKind::UnexpectedCharacter('$'), Span::new(2, 3)));
+
+    // What was buffered before the error is still there
+    assert_eq!(cursor.next_token().unwrap(), Some(spanned(Token::Identifier("a"), 0, 1)));
+}
@@ -0,0 +1,229 @@
+//! Checks each peephole rewrite on short hand-written sequences, and that
+//! each leaves alone the code where it would change what the program does.
+
+extern crate cc;
+
+use cc::peephole;
+use cc::x86::{Condition, Instr, Label, Operand, Reg};
+
+fn reg(register: Reg) -> Operand {
+    Operand::Reg(register)
+}
+
+fn mov(src: Operand, dst: Reg) -> Instr {
+    Instr::Mov { src, dst: reg(dst) }
+}
+
+fn label(name: &str) -> Label {
+    Label(name.to_string())
+}
+
+// A function named f around `body`, since the label after `.globl` is kept
+fn function(body: Vec<Instr>) -> Vec<Instr> {
+    let mut instrs = vec![Instr::Global("f".to_string()), Instr::Label(label("f"))];
+    instrs.extend(body);
+
+    instrs
+}
+
+fn check(body: Vec<Instr>, expected: Vec<Instr>) {
+    assert_eq!(peephole::optimize(function(body)), function(expected));
+}
+
+fn unchanged(body: Vec<Instr>) {
+    check(body.clone(), body);
+}
+
+#[test]
+fn turns_push_and_pop_into_a_move() {
+    check(vec![Instr::Push(reg(Reg::Rcx)), Instr::Pop(reg(Reg::Rax)), Instr::Ret], vec![mov(reg(Reg::Rcx), Reg::Rax), Instr::Ret]);
+
+    // Instructions in between may stay if they leave the stack and the register alone
+    check(
+        vec![Instr::Push(reg(Reg::Rcx)), Instr::Add { src: Operand::Imm(1), dst: reg(Reg::Rcx) }, Instr::Pop(reg(Reg::Rax)), Instr::Ret],
+        vec![mov(reg(Reg::Rcx), Reg::Rax), Instr::Add { src: Operand::Imm(1), dst: reg(Reg::Rcx) }, Instr::Ret]
+    );
+
+    // Popping what was pushed does nothing at all
+    check(vec![Instr::Push(reg(Reg::Rax)), Instr::Pop(reg(Reg::Rax)), Instr::Ret], vec![Instr::Ret]);
+
+    // Nested pairs go from the inside out, each move taking its push's place
+    check(
+        vec![Instr::Push(reg(Reg::Rcx)), Instr::Push(reg(Reg::Rdx)), Instr::Pop(reg(Reg::Rsi)), Instr::Pop(reg(Reg::Rax)), Instr::Ret],
+        vec![mov(reg(Reg::Rcx), Reg::Rax), mov(reg(Reg::Rdx), Reg::Rsi), Instr::Ret]
+    );
+}
+
+#[test]
+fn keeps_push_and_pop_around_what_needs_them() {
+    unchanged(vec![Instr::Push(reg(Reg::Rcx)), mov(Operand::Imm(2), Reg::Rax), Instr::Pop(reg(Reg::Rax)), Instr::Ret]);
+    unchanged(vec![Instr::Push(reg(Reg::Rcx)), Instr::Call("g".to_string()), Instr::Pop(reg(Reg::Rcx)), Instr::Ret]);
+    unchanged(vec![
+        Instr::Push(reg(Reg::Rcx)),
+        mov(Operand::Mem { base: Reg::Rsp, offset: 0 }, Reg::Rdx),
+        Instr::Pop(reg(Reg::Rax)),
+        Instr::Ret
+    ]);
+}
+
+#[test]
+fn zeroes_registers_with_xor_when_the_flags_are_dead() {
+    check(vec![mov(Operand::Imm(0), Reg::Rax), Instr::Ret], vec![Instr::Zero(Reg::Rax), Instr::Ret]);
+
+    // xor would clobber the flags the set reads
+    unchanged(vec![
+        Instr::Cmp { src: reg(Reg::Rcx), dst: reg(Reg::Rdx) },
+        mov(Operand::Imm(0), Reg::Rax),
+        Instr::Set(Condition::L, Reg::Rax),
+        Instr::Ret
+    ]);
+}
+
+#[test]
+fn multiplies_by_powers_of_two_with_shifts() {
+    check(
+        vec![Instr::Imul { src: Operand::Imm(8), dst: Reg::Rax }, Instr::Ret],
+        vec![Instr::Shl { count: Operand::Imm(3), dst: reg(Reg::Rax) }, Instr::Ret]
+    );
+    check(vec![Instr::Imul { src: Operand::Imm(1), dst: Reg::Rax }, Instr::Ret], vec![Instr::Ret]);
+    unchanged(vec![Instr::Imul { src: Operand::Imm(6), dst: Reg::Rax }, Instr::Ret]);
+    unchanged(vec![Instr::Imul { src: Operand::Imm(-8), dst: Reg::Rax }, Instr::Ret]);
+
+    // A loaded constant becomes an immediate, which can then be a shift
+    check(
+        vec![mov(Operand::Imm(4), Reg::Rax), Instr::Imul { src: reg(Reg::Rcx), dst: Reg::Rax }, Instr::Ret],
+        vec![mov(reg(Reg::Rcx), Reg::Rax), Instr::Shl { count: Operand::Imm(2), dst: reg(Reg::Rax) }, Instr::Ret]
+    );
+    check(
+        vec![mov(Operand::Imm(5), Reg::Rax), Instr::Add { src: reg(Reg::Rcx), dst: reg(Reg::Rax) }, Instr::Ret],
+        vec![mov(reg(Reg::Rcx), Reg::Rax), Instr::Add { src: Operand::Imm(5), dst: reg(Reg::Rax) }, Instr::Ret]
+    );
+
+    // The flags of the shift differ from the multiply's, so one that is read stays
+    unchanged(vec![
+        Instr::Imul { src: Operand::Imm(8), dst: Reg::Rax },
+        Instr::Set(Condition::E, Reg::Rcx),
+        Instr::Ret
+    ]);
+}
+
+#[test]
+fn drops_moves_back_to_where_a_value_came_from() {
+    check(
+        vec![mov(reg(Reg::Rax), Reg::Rcx), mov(reg(Reg::Rcx), Reg::Rax), Instr::Ret],
+        vec![mov(reg(Reg::Rax), Reg::Rcx), Instr::Ret]
+    );
+}
+
+// if (rcx < rdx) return 1; return 2; with the comparison materialized
+fn compare_and_branch(then_body: Vec<Instr>) -> Vec<Instr> {
+    let mut instrs = vec![
+        Instr::Cmp { src: reg(Reg::Rdx), dst: reg(Reg::Rcx) },
+        mov(Operand::Imm(0), Reg::Rax),
+        Instr::Set(Condition::L, Reg::Rax),
+        Instr::Movzx8 { src: Reg::Rax, dst: Reg::Rax },
+        Instr::Cmp { src: Operand::Imm(0), dst: reg(Reg::Rax) },
+        Instr::Jcc(Condition::E, label("else")),
+        mov(Operand::Imm(1), Reg::Rax),
+        Instr::Ret,
+        Instr::Label(label("else"))
+    ];
+    instrs.extend(then_body);
+
+    instrs
+}
+
+#[test]
+fn fuses_a_materialized_comparison_into_the_branch() {
+    let else_body = vec![mov(Operand::Imm(2), Reg::Rax), Instr::Ret];
+
+    check(compare_and_branch(else_body.clone()), vec![
+        Instr::Cmp { src: reg(Reg::Rdx), dst: reg(Reg::Rcx) },
+        Instr::Jcc(Condition::Ge, label("else")),
+        mov(Operand::Imm(1), Reg::Rax),
+        Instr::Ret,
+        Instr::Label(label("else")),
+        mov(Operand::Imm(2), Reg::Rax),
+        Instr::Ret
+    ]);
+
+    // The result is still read where the branch goes, so it has to be made
+    let kept = compare_and_branch(vec![Instr::Ret]);
+    let optimized = peephole::optimize(function(kept));
+
+    assert!(optimized.contains(&Instr::Set(Condition::L, Reg::Rax)), "the comparison result is gone:\n{:?}", optimized);
+    assert!(optimized.contains(&Instr::Jcc(Condition::E, label("else"))));
+}
+
+#[test]
+fn threads_jumps_to_jumps() {
+    check(vec![
+        Instr::Jcc(Condition::E, label("a")),
+        Instr::Call("g".to_string()),
+        Instr::Ret,
+        Instr::Label(label("a")),
+        Instr::Jmp(label("b")),
+        Instr::Label(label("c")),
+        Instr::Call("h".to_string()),
+        Instr::Label(label("b")),
+        Instr::Ret
+    ], vec![
+        Instr::Jcc(Condition::E, label("b")),
+        Instr::Call("g".to_string()),
+        Instr::Ret,
+        Instr::Label(label("b")),
+        Instr::Ret
+    ]);
+}
+
+#[test]
+fn drops_jumps_to_the_next_instruction() {
+    check(vec![
+        Instr::Jmp(label("a")),
+        Instr::Label(label("a")),
+        Instr::Jcc(Condition::Ne, label("b")),
+        Instr::Label(label("b")),
+        Instr::Ret
+    ], vec![Instr::Ret]);
+}
+
+#[test]
+fn inverts_a_branch_over_a_jump() {
+    check(vec![
+        Instr::Cmp { src: Operand::Imm(0), dst: reg(Reg::Rdi) },
+        Instr::Jcc(Condition::Ne, label("skip")),
+        Instr::Jmp(label("out")),
+        Instr::Label(label("skip")),
+        Instr::Call("g".to_string()),
+        Instr::Label(label("out")),
+        Instr::Ret
+    ], vec![
+        Instr::Cmp { src: Operand::Imm(0), dst: reg(Reg::Rdi) },
+        Instr::Jcc(Condition::E, label("out")),
+        Instr::Call("g".to_string()),
+        Instr::Label(label("out")),
+        Instr::Ret
+    ]);
+}
+
+#[test]
+fn optimizes_long_runs_quickly() {
+    // Each of these used to be removed with a shift of everything after it
+    let count = 100000;
+    let mut body = Vec::new();
+
+    for _ in 0..count {
+        body.push(Instr::Push(reg(Reg::Rax)));
+        body.push(Instr::Pop(reg(Reg::Rax)));
+        body.push(Instr::Imul { src: Operand::Imm(1), dst: Reg::Rcx });
+        body.push(Instr::Add { src: Operand::Imm(1), dst: reg(Reg::Rdx) });
+        body.push(mov(reg(Reg::Rax), Reg::Rcx));
+        body.push(mov(reg(Reg::Rcx), Reg::Rax));
+    }
+
+    body.push(Instr::Ret);
+
+    let optimized = peephole::optimize(function(body));
+
+    assert_eq!(optimized.len(), 2 * count + 3);
+}
@@ -0,0 +1,138 @@
+//! Checks that printing a tree and parsing the result gives the same tree,
+//! which is what makes `--emit=c` safe to feed back into the compiler.
+
+extern crate cc;
+
+mod common;
+
+use cc::{printer, BlockItem, StmtKind};
+
+use std::fs;
+
+// Parentheses the printer has to keep, and ones it can drop
+const PRECEDENCE: &str = "
+int main() {
+    int a = 1;
+    int b = 2;
+    int c = 3;
+    a = a - (b - c);
+    a = a - b - c;
+    a = (a + b) * c;
+    a = a + b * c;
+    a = a / (b * c);
+    a = -(-a);
+    a = -~!a;
+    a = a << b + c;
+    a = (a << b) + c;
+    a = (a < b) << c;
+    a = (a == b) == (b != c);
+    a = a < b == b > c;
+    a = !(a && b) || c;
+    a = (a || b) && c;
+    a = a || b && c;
+    a = b = c;
+    a = (b = c) + 1;
+    return a;
+}
+";
+
+// An else binds to the nearest if, so an inner if without one needs braces
+const DANGLING_ELSE: &str = "
+int main() {
+    int x = 0;
+    if (x)
+        if (x > 1)
+            x = 1;
+        else
+            x = 2;
+    if (x) {
+        if (x > 1)
+            x = 3;
+    } else
+        x = 4;
+    while (x)
+        if (x)
+            x = 5;
+        else
+            x = 6;
+    return x;
+}
+";
+
+// Example files that are not whole programs
+const FRAGMENTS: [&str; 1] = ["fuck.c"];
+
+fn check_round_trip(name: &str, source: &str) {
+    let printed = printer::print(&cc::parser::parse(source));
+
+    assert!(common::parse_without_spans(&printed) == common::parse_without_spans(source),
+            "{} parses differently once printed:\n{}", name, printed);
+
+    // Printing is also stable, so printing twice changes nothing
+    assert_eq!(printer::print(&cc::parser::parse(&printed)), printed, "{} prints differently the second time", name);
+}
+
+#[test]
+fn round_trips_the_example_files() {
+    let mut checked = 0;
+
+    for entry in fs::read_dir("cfiles").unwrap() {
+        let path = entry.unwrap().path();
+        let fragment = FRAGMENTS.iter().any(|&name| path.file_name().is_some_and(|file_name| file_name == name));
+
+        if path.extension().is_some_and(|extension| extension == "c") && !fragment {
+            check_round_trip(&path.display().to_string(), &fs::read_to_string(&path).unwrap());
+            checked += 1;
+        }
+    }
+
+    assert!(checked > 0, "there are no example files");
+}
+
+#[test]
+fn round_trips_the_test_programs() {
+    for (name, source, _) in common::programs() {
+        check_round_trip(&name, &source);
+    }
+}
+
+#[test]
+fn keeps_the_parentheses_precedence_needs() {
+    check_round_trip("precedence", PRECEDENCE);
+}
+
+#[test]
+fn keeps_each_else_with_its_if() {
+    check_round_trip("dangling else", DANGLING_ELSE);
+}
+
+#[test]
+fn braces_an_inner_if_that_would_take_the_else() {
+    let mut ast = cc::parser::parse("int main() { int x = 0; if (x) { if (x > 1) x = 1; } else x = 2; return x; }");
+
+    // Without the braces no source gives this tree, but a transformation can
+    if let BlockItem::Stmt(ref mut stmt) = ast.functions[0].body.as_mut().unwrap()[1] {
+        if let StmtKind::If(_, ref mut then_branch, _) = stmt.kind {
+            let inner = match then_branch.kind {
+                StmtKind::Compound(ref items) => items[0].as_stmt().unwrap().clone(),
+                _ => unreachable!()
+            };
+
+            **then_branch = inner;
+        }
+    }
+
+    // The printer has to add braces, so the else stays with the outer if
+    let printed = printer::print(&ast);
+    let reparsed = cc::parser::parse(&printed);
+
+    let kept = match reparsed.functions[0].body.as_ref().unwrap()[1].as_stmt().unwrap().kind {
+        StmtKind::If(_, ref then_branch, Some(_)) => match then_branch.kind {
+            StmtKind::Compound(ref items) => matches!(items[0].as_stmt().unwrap().kind, StmtKind::If(_, _, None)),
+            _ => false
+        },
+        _ => false
+    };
+
+    assert!(kept, "the else moved to the inner if:\n{}", printed);
+}
@@ -0,0 +1,202 @@
+//! Checks coalescing and register allocation on small hand-built functions.
+
+extern crate cc;
+
+use cc::ir::{BinaryOp, Block, BlockId, Function, Instr, Operand, Terminator, VReg};
+use cc::regalloc::{self, Location};
+use cc::Type;
+
+const CALLER_SAVED: [&str; 2] = ["r0", "r1"];
+const CALLEE_SAVED: [&str; 1] = ["s0"];
+
+fn reg(index: usize) -> Operand {
+    Operand::Reg(VReg(index))
+}
+
+fn add(dst: usize, lhs: Operand, rhs: Operand) -> Instr {
+    Instr::Binary { op: BinaryOp::Add, dst: VReg(dst), lhs, rhs }
+}
+
+fn copy(dst: usize, src: usize) -> Instr {
+    Instr::Copy { dst: VReg(dst), src: reg(src) }
+}
+
+fn call(dst: Option<usize>, args: Vec<Operand>) -> Instr {
+    Instr::Call { dst: dst.map(VReg), function: "g".to_string(), args }
+}
+
+// A function of one block taking `params` parameters, returning `result`
+fn function(params: usize, instrs: Vec<Instr>, result: Operand) -> Function {
+    let vreg_count = instrs.iter().filter_map(Instr::dst).map(|reg| reg.0 + 1).chain(Some(params)).max().unwrap();
+
+    Function {
+        name: "f".to_string(),
+        return_type: Type::Int,
+        params: (0..params).map(|index| (VReg(index), Type::Int)).collect(),
+        blocks: vec![Block { instrs, terminator: Terminator::Return(Some(result)) }],
+        vreg_count
+    }
+}
+
+fn register(location: Option<Location<&'static str>>) -> &'static str {
+    match location {
+        Some(Location::Reg(register)) => register,
+        location => panic!("not in a register: {:?}", location)
+    }
+}
+
+#[test]
+fn shares_a_register_between_intervals_that_only_touch() {
+    // Each value dies where the next is made, so one register does for all
+    let function = function(1, vec![add(1, reg(0), Operand::Imm(1)), add(2, reg(1), Operand::Imm(2)), add(3, reg(2), Operand::Imm(3))], reg(3));
+    let allocation = regalloc::allocate(&function, &CALLER_SAVED[..1], &[]);
+
+    assert_eq!(allocation.spill_slots, 0);
+
+    for index in 0..4 {
+        assert_eq!(allocation.location(VReg(index)), Some(Location::Reg("r0")));
+    }
+
+    // A register that never appears has nowhere to live
+    assert_eq!(allocation.location(VReg(4)), None);
+}
+
+#[test]
+fn spills_the_interval_that_ends_last() {
+    // %0, %1 and %2 are all live at the first add, with two registers to go round
+    let function = function(3, vec![
+        add(3, reg(0), reg(1)),
+        add(4, reg(3), reg(2))
+    ], reg(4));
+    let allocation = regalloc::allocate(&function, &CALLER_SAVED, &[]);
+
+    assert_eq!(allocation.spill_slots, 1);
+    assert_eq!(allocation.location(VReg(2)), Some(Location::Stack(0)));
+    assert_ne!(register(allocation.location(VReg(0))), register(allocation.location(VReg(1))));
+
+    // Once %0 and %1 die their registers are free again
+    assert!(CALLER_SAVED.contains(&register(allocation.location(VReg(3)))));
+}
+
+#[test]
+fn spills_into_a_new_slot_each_time() {
+    let params = 6;
+    let function = function(params, vec![
+        add(6, reg(0), reg(1)),
+        add(7, reg(6), reg(2)),
+        add(8, reg(7), reg(3)),
+        add(9, reg(8), reg(4)),
+        add(10, reg(9), reg(5))
+    ], reg(10));
+    let allocation = regalloc::allocate(&function, &CALLER_SAVED, &[]);
+
+    // Two of the six parameters fit, and each other one gets its own slot
+    assert_eq!(allocation.spill_slots, 4);
+
+    let mut slots: Vec<usize> = (0..params).filter_map(|index| match allocation.location(VReg(index)) {
+        Some(Location::Stack(slot)) => Some(slot),
+        _ => None
+    }).collect();
+    slots.sort();
+    assert_eq!(slots, [0, 1, 2, 3]);
+}
+
+#[test]
+fn keeps_unused_parameters_apart_from_used_ones() {
+    // %0 and %2 are never read, but they still arrive in registers of their own
+    let function = function(4, vec![add(4, reg(1), reg(3))], reg(4));
+    let allocation = regalloc::allocate(&function, &["r0", "r1", "r2", "r3"], &[]);
+
+    let mut registers: Vec<&str> = (0..4).map(|index| register(allocation.location(VReg(index)))).collect();
+    registers.sort();
+    registers.dedup();
+    assert_eq!(registers.len(), 4);
+}
+
+#[test]
+fn coalesces_copies_whose_sides_do_not_interfere() {
+    let mut function = function(1, vec![add(1, reg(0), Operand::Imm(1)), copy(2, 1), add(3, reg(2), Operand::Imm(2))], reg(3));
+    regalloc::coalesce(&mut function);
+
+    // The copy goes, and its destination is renamed to its source
+    assert_eq!(function.blocks[0].instrs, [add(1, reg(0), Operand::Imm(1)), add(3, reg(1), Operand::Imm(2))]);
+}
+
+#[test]
+fn keeps_copies_whose_sides_interfere() {
+    // %0 is still needed after it is copied, so the two need different registers
+    let instrs = vec![copy(1, 0), add(1, reg(1), Operand::Imm(1)), add(2, reg(0), reg(1))];
+    let mut coalesced = function(1, instrs.clone(), reg(2));
+    regalloc::coalesce(&mut coalesced);
+
+    assert_eq!(coalesced.blocks[0].instrs, instrs);
+
+    // Nor are two parameters merged, even when they could be
+    let mut coalesced = function(2, vec![copy(0, 1)], reg(0));
+    regalloc::coalesce(&mut coalesced);
+
+    assert_eq!(coalesced.blocks[0].instrs, [copy(0, 1)]);
+}
+
+#[test]
+fn coalesces_chains_of_copies_into_a_parameter() {
+    let mut function = function(1, vec![copy(1, 0), copy(2, 1), copy(3, 2), add(4, reg(3), Operand::Imm(1))], reg(4));
+    regalloc::coalesce(&mut function);
+
+    assert_eq!(function.blocks[0].instrs, [add(4, reg(0), Operand::Imm(1))]);
+}
+
+#[test]
+fn keeps_values_live_across_calls_in_callee_saved_registers() {
+    let function = function(1, vec![add(1, reg(0), Operand::Imm(1)), call(Some(2), vec![reg(0)]), add(3, reg(1), reg(2))], reg(3));
+    let allocation = regalloc::allocate(&function, &CALLER_SAVED, &CALLEE_SAVED);
+
+    assert_eq!(register(allocation.location(VReg(1))), "s0");
+    assert_eq!(allocation.callee_saved_used, ["s0"]);
+
+    // The argument dies at the call, so it needs no saving
+    assert!(CALLER_SAVED.contains(&register(allocation.location(VReg(0)))));
+    assert_eq!(allocation.saved_at_call(BlockId(0), 1), &[] as &[&str]);
+}
+
+#[test]
+fn saves_caller_saved_registers_live_across_calls() {
+    // With no callee-saved registers left, both values live across the call are saved around it
+    let function = function(2, vec![
+        call(None, Vec::new()),
+        call(Some(2), vec![reg(0)]),
+        add(3, reg(0), reg(1)),
+        add(4, reg(3), reg(2))
+    ], reg(4));
+    let allocation = regalloc::allocate(&function, &CALLER_SAVED, &[]);
+
+    assert_eq!(allocation.saved_at_call(BlockId(0), 0), ["r0", "r1"]);
+    assert_eq!(allocation.saved_at_call(BlockId(0), 1), ["r0", "r1"]);
+    assert!(allocation.callee_saved_used.is_empty());
+
+    // A callee-saved register takes one of them, and only the other is saved
+    let allocation = regalloc::allocate(&function, &CALLER_SAVED, &CALLEE_SAVED);
+
+    assert_eq!(allocation.callee_saved_used, ["s0"]);
+    assert_eq!(allocation.saved_at_call(BlockId(0), 0).len(), 1);
+    assert_eq!(allocation.saved_at_call(BlockId(0), 1).len(), 1);
+}
+
+#[test]
+fn allocates_large_functions_quickly() {
+    // Thousands of calls, each with a value live across it, would take
+    // minutes if each call were checked against each interval
+    let count = 20000;
+    let mut instrs = Vec::new();
+
+    for index in 0..count {
+        instrs.push(call(Some(index + 1), vec![reg(index)]));
+    }
+
+    let mut function = function(1, instrs, reg(count));
+    regalloc::coalesce(&mut function);
+    let allocation = regalloc::allocate(&function, &CALLER_SAVED, &CALLEE_SAVED);
+
+    assert_eq!(allocation.spill_slots, 0);
+    assert_eq!(allocation.saved_at_call(BlockId(0), count - 1), &[] as &[&str]);
+}
@@ -0,0 +1,32 @@
+//! Checks the RISC-V 64 backend against golden assembly, and runs the programs
+//! under qemu when a cross toolchain and emulator are installed.
+
+extern crate cc;
+
+mod common;
+
+use cc::riscv64;
+
+use common::Cross;
+
+const TARGET: Cross = Cross {
+    name: "riscv64",
+    title: "RISC-V 64",
+    llvm_mc: &["--triple=riscv64-linux-gnu", "-mattr=+m"],
+    generate: riscv64::generate
+};
+
+#[test]
+fn matches_golden_assembly() {
+    TARGET.check_golden_assembly();
+}
+
+#[test]
+fn assembles() {
+    TARGET.check_assembles();
+}
+
+#[test]
+fn runs_under_qemu() {
+    TARGET.check_runs_under_qemu();
+}
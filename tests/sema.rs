@@ -0,0 +1,105 @@
+//! Checks the errors semantic analysis reports, and the source each points at.
+
+extern crate cc;
+
+use cc::diagnostics::Severity;
+use cc::{parser, sema, ExprKind, Type};
+
+// Each error's message, with the source its span covers
+fn errors(source: &str) -> Vec<(String, &str)> {
+    sema::analyze(&mut parser::parse(source)).into_iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .map(|diagnostic| (diagnostic.message, &source[diagnostic.span.start..diagnostic.span.end]))
+        .collect()
+}
+
+fn check(source: &str, expected: &[(&str, &str)]) {
+    let actual = errors(source);
+    let actual: Vec<(&str, &str)> = actual.iter().map(|&(ref message, text)| (&message[..], text)).collect();
+
+    assert_eq!(actual, expected, "wrong errors for:\n{}", source);
+}
+
+#[test]
+fn reports_undeclared_and_redefined_names() {
+    check("int main() { return x; }", &[("use of undeclared identifier 'x'", "x")]);
+    check("int main() { int a; int a; return 0; }", &[("redefinition of 'a'", "int a;")]);
+    check("int f() { return 1; } int f() { return 2; } int main() { return f(); }", &[("redefinition of 'f'", "int f() { return 2; }")]);
+
+    // A prototype may be repeated, and a block may shadow an outer name
+    check("int f(); int f(); int f() { return 1; } int main() { int a = f(); { int a = 2; return a; } }", &[]);
+}
+
+#[test]
+fn reports_declarations_that_disagree() {
+    check("int f(int a, char b); int f(int a, int b) { return a + b; } int main() { return 0; }",
+          &[("conflicting types for 'f'", "int f(int a, int b) { return a + b; }")]);
+    check("int f(); long f() { return 1; } int main() { return 0; }", &[("conflicting types for 'f'", "long f() { return 1; }")]);
+}
+
+#[test]
+fn reports_void_where_a_value_is_needed() {
+    check("int main() { void v; return 0; }", &[("variable 'v' has incomplete type 'void'", "void v;")]);
+    check("int f(void x) { return 0; } int main() { return 0; }", &[("parameter has incomplete type 'void'", "void x")]);
+    check("void f() { } int main() { return f(); }", &[("void value not ignored as it ought to be", "f()")]);
+    check("void f() { } int main() { int x = f() + 1; return x; }", &[("void value not ignored as it ought to be", "f()")]);
+}
+
+#[test]
+fn reports_returns_that_do_not_match_the_function() {
+    check("void f() { return 1; } int main() { f(); return 0; }", &[("void function 'f' should not return a value", "return 1;")]);
+    check("int f() { return; } int main() { return f(); }", &[("non-void function 'f' should return a value", "return;")]);
+}
+
+#[test]
+fn reports_unnamed_parameters_only_in_definitions() {
+    check("int f(int) { return 0; } int main() { return 0; }", &[("parameter name omitted in function definition", "int")]);
+    check("int f(int, char); int main() { return f(1, 2); }", &[]);
+}
+
+#[test]
+fn reports_literals_too_large_for_long() {
+    check("int main() { long x = 9223372036854775807; return x != 0; }", &[]);
+    check("int main() { return 9223372036854775808 != 0; }",
+          &[("integer literal is too large to be represented in any integer type", "9223372036854775808")]);
+}
+
+#[test]
+fn reports_misused_names() {
+    check("int f(); int main() { return f; }", &[("function 'f' cannot be used as a value", "f")]);
+    check("int main() { int g = 0; return g(); }", &[("called object 'g' is not a function", "g()")]);
+    check("int main() { 1 = 2; return 0; }", &[("expression is not assignable", "1")]);
+}
+
+#[test]
+fn reports_calls_that_do_not_match_the_prototype() {
+    check("int f(int a); int main() { return f(1, 2) + f(); }", &[
+        ("too many arguments to function call to 'f', expected 1, have 2", "f(1, 2)"),
+        ("too few arguments to function call to 'f', expected 1, have 0", "f()")
+    ]);
+    check("int main() { return g(); }", &[("call to undeclared function 'g'", "g()")]);
+}
+
+#[test]
+fn converts_operands_with_the_usual_arithmetic_conversions() {
+    assert_eq!(sema::promote(Type::Char), Type::Int);
+    assert_eq!(sema::common_type(Type::Char, Type::Char), Type::Int);
+    assert_eq!(sema::common_type(Type::Int, Type::UnsignedInt), Type::UnsignedInt);
+    assert_eq!(sema::common_type(Type::UnsignedInt, Type::Long), Type::Long);
+
+    // The operands are cast to the common type, and the comparison is an int
+    let mut ast = parser::parse("int main() { char c = 1; unsigned int u = 2; long l = 3; return c + u < l; }");
+    assert!(sema::analyze(&mut ast).is_empty());
+
+    let stmt = ast.functions[0].stmts().last().unwrap();
+    let comparison = stmt.expr().unwrap().without_implicit_casts();
+    assert_eq!(comparison.ty(), Type::Int);
+
+    match comparison.kind {
+        ExprKind::Binary(_, ref lhs, ref rhs) => {
+            assert_eq!((lhs.ty(), rhs.ty()), (Type::Long, Type::Long));
+            assert_eq!(lhs.without_implicit_casts().ty(), Type::UnsignedInt);
+        },
+        _ => panic!("not a comparison: {:?}", comparison)
+    }
+}
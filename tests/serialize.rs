@@ -0,0 +1,143 @@
+//! Checks the JSON and S-expression dumps, including the span of every node.
+
+#![cfg(feature = "serialize")]
+
+extern crate cc;
+
+mod common;
+
+use cc::parser;
+use cc::serialize::{self, Format};
+
+const SOURCE: &str = "int main() {
+    int x = 1;
+    return -x + 2;
+}
+";
+
+#[test]
+fn dumps_json_with_spans() {
+    let expected = r#"{
+  "version": 3,
+  "node": "TranslationUnit",
+  "functions": [
+    {
+      "node": "FunctionDef",
+      "span": {
+        "start": 0,
+        "end": 48
+      },
+      "name": "main",
+      "return_type": "int",
+      "params": [],
+      "body": [
+        {
+          "node": "Decl",
+          "span": {
+            "start": 17,
+            "end": 27
+          },
+          "id": 0,
+          "name": "x",
+          "type": "int",
+          "init": {
+            "node": "Constant",
+            "span": {
+              "start": 25,
+              "end": 26
+            },
+            "value": 1
+          }
+        },
+        {
+          "node": "Return",
+          "span": {
+            "start": 32,
+            "end": 46
+          },
+          "expr": {
+            "node": "Binary",
+            "span": {
+              "start": 39,
+              "end": 45
+            },
+            "operator": "+",
+            "lhs": {
+              "node": "Unary",
+              "span": {
+                "start": 39,
+                "end": 41
+              },
+              "operator": "-",
+              "operand": {
+                "node": "Variable",
+                "span": {
+                  "start": 40,
+                  "end": 41
+                },
+                "name": "x",
+                "decl": null
+              }
+            },
+            "rhs": {
+              "node": "Constant",
+              "span": {
+                "start": 44,
+                "end": 45
+              },
+              "value": 2
+            }
+          }
+        }
+      ]
+    }
+  ]
+}"#;
+
+    assert_eq!(serialize::dump(&parser::parse(SOURCE), Format::Json).trim_end(), expected);
+}
+
+#[test]
+fn reads_back_only_the_current_version() {
+    let json = serialize::to_json(&parser::parse(SOURCE));
+    assert_eq!(serialize::check_json_version(&json), Ok(()));
+
+    // Version 2 had no if, else or while statements
+    let older = json.replacen("\"version\": 3", "\"version\": 2", 1);
+    assert_eq!(serialize::check_json_version(&older), Err("the dump is in version 2 of the layout, but only version 3 can be read".to_string()));
+
+    assert!(serialize::check_json_version("{ \"node\": \"TranslationUnit\" }").is_err());
+}
+
+#[test]
+fn dumps_sexprs_with_spans() {
+    let expected = "(translation-unit
+  (function-def main int (span 0 48) (params)
+    (decl x int #0 (span 17 27)
+      (constant 1 (span 25 26)))
+    (return (span 32 46)
+      (binary + (span 39 45)
+        (unary - (span 39 41)
+          (variable x (span 40 41)))
+        (constant 2 (span 44 45))))))";
+
+    assert_eq!(serialize::dump(&parser::parse(SOURCE), Format::Sexpr).trim_end(), expected);
+}
+
+#[test]
+fn spans_cover_the_source_of_each_node() {
+    let sexpr = serialize::to_sexpr(&parser::parse(SOURCE));
+    let spans: Vec<&str> = sexpr.split("(span ").skip(1).map(|rest| {
+        let mut bounds = rest.split([' ', ')']).map(|bound| bound.parse::<usize>().unwrap());
+        &SOURCE[bounds.next().unwrap()..bounds.next().unwrap()]
+    }).collect();
+
+    assert_eq!(spans, [SOURCE.trim_end(), "int x = 1;", "1", "return -x + 2;", "-x + 2", "-x", "x", "2"]);
+}
+
+#[test]
+fn dumps_resolved_declarations_after_analysis() {
+    let sexpr = serialize::to_sexpr(&common::analyze(SOURCE));
+
+    assert!(sexpr.contains("(variable x #0 (span 40 41))"), "the variable is not resolved:\n{}", sexpr);
+}
@@ -0,0 +1,75 @@
+//! Checks the C translation against golden text, and that gcc builds it
+//! into programs that exit like the compiled ones or report their undefined
+//! behavior.
+
+extern crate cc;
+
+mod common;
+
+use cc::target::Target;
+use cc::transpile;
+
+use std::process::Command;
+
+// Each reaches its undefined behavior on the line reported
+const UNDEFINED: [(&str, &str); 5] = [
+    ("int main() {\n    int x = 2147483647;\n    return x + 1;\n}\n", "3:12: undefined behavior: signed overflow in '+'"),
+    ("int main() {\n    int x = 0;\n    return 7 / x;\n}\n", "3:12: undefined behavior: division by zero"),
+    ("int main() {\n    long x = 1;\n    int n = 64;\n    return x << n;\n}\n", "4:12: undefined behavior: shift count out of range"),
+    ("int main() {\n    int x = 0 - 2147483647 - 1;\n    return -x;\n}\n", "3:12: undefined behavior: signed overflow in '-'"),
+    ("int f(int a) {\n    if (a > 5)\n        return 1;\n}\n\nint main() {\n    return f(2) + 3;\n}\n", "4:1: undefined behavior: missing return")
+];
+
+fn generate(source: &str) -> String {
+    transpile::generate(&common::analyze(source), source, Target::X86_64)
+}
+
+#[test]
+fn matches_golden_text() {
+    for (name, source, _) in common::programs() {
+        common::check_golden(&format!("transpile/{}.c", name), &generate(&source));
+    }
+}
+
+#[test]
+fn runs_like_the_compiled_programs() {
+    if !common::installed("gcc") {
+        return eprintln!("gcc is not installed, skipping");
+    }
+
+    let directory = common::scratch_directory("transpile-run");
+
+    for (name, source, expected) in common::programs() {
+        let path = directory.join(format!("{}.c", name));
+        let program = path.with_extension("");
+        common::write(&path, &generate(&source));
+
+        let output = Command::new("gcc").arg("-std=c99").arg("-O2").arg(&path).arg("-o").arg(&program).output().unwrap();
+        assert!(output.status.success(), "{} does not compile:\n{}", path.display(), String::from_utf8_lossy(&output.stderr));
+
+        let output = Command::new(&program).output().unwrap();
+        assert_eq!(output.status.code(), Some(expected), "{} exits with the wrong status", program.display());
+    }
+}
+
+#[test]
+fn reports_undefined_behavior() {
+    if !common::installed("gcc") {
+        return eprintln!("gcc is not installed, skipping");
+    }
+
+    let directory = common::scratch_directory("transpile-undefined");
+
+    for (index, &(source, message)) in UNDEFINED.iter().enumerate() {
+        let path = directory.join(format!("undefined{}.c", index));
+        let program = path.with_extension("");
+        common::write(&path, &generate(source));
+
+        let output = Command::new("gcc").arg("-std=c99").arg(&path).arg("-o").arg(&program).output().unwrap();
+        assert!(output.status.success(), "{} does not compile:\n{}", path.display(), String::from_utf8_lossy(&output.stderr));
+
+        let output = Command::new(&program).output().unwrap();
+        assert!(!output.status.success(), "{} does not abort", program.display());
+        assert_eq!(String::from_utf8_lossy(&output.stderr).trim_end(), message);
+    }
+}
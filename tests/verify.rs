@@ -0,0 +1,132 @@
+//! Checks that the IR verifier accepts what lowering produces and rejects
+//! each kind of malformed function.
+
+extern crate cc;
+
+mod common;
+
+use cc::ir::{self, BinaryOp, Block, BlockId, Function, Instr, Operand, Terminator, VReg};
+use cc::{opt, Type};
+
+// int f(int %0) { bb0: %1 = add %0, 1; br %1, bb1, bb2  bb1: ret %1  bb2: ret 0 }
+fn function() -> Function {
+    Function {
+        name: "f".to_string(),
+        return_type: Type::Int,
+        params: vec![(VReg(0), Type::Int)],
+        blocks: vec![
+            Block {
+                instrs: vec![Instr::Binary { op: BinaryOp::Add, dst: VReg(1), lhs: Operand::Reg(VReg(0)), rhs: Operand::Imm(1) }],
+                terminator: Terminator::Branch { condition: Operand::Reg(VReg(1)), then_block: BlockId(1), else_block: BlockId(2) }
+            },
+            Block {
+                instrs: Vec::new(),
+                terminator: Terminator::Return(Some(Operand::Reg(VReg(1))))
+            },
+            Block {
+                instrs: Vec::new(),
+                terminator: Terminator::Return(Some(Operand::Imm(0)))
+            }
+        ],
+        vreg_count: 2
+    }
+}
+
+fn rejects<F: FnOnce(&mut Function)>(message: &str, change: F) {
+    let mut function = function();
+    change(&mut function);
+
+    let error = ir::verify_function(&function).expect_err(message);
+    assert_eq!(error.function, "f");
+    assert_eq!(error.message, message);
+}
+
+#[test]
+fn accepts_lowered_and_optimized_programs() {
+    assert_eq!(ir::verify_function(&function()), Ok(()));
+
+    for (name, source, _) in common::programs() {
+        for &level in &[opt::Level::O0, opt::Level::O1, opt::Level::O2] {
+            let module = common::lower(&source, level);
+            assert!(ir::verify(&module).is_ok(), "{} at {:?} does not verify:\n{}", name, level, module);
+        }
+    }
+}
+
+#[test]
+fn rejects_a_function_without_blocks() {
+    rejects("function has no blocks", |function| function.blocks.clear());
+}
+
+#[test]
+fn rejects_bad_parameters() {
+    rejects("a register is used for more than one parameter", |function| function.params.push((VReg(0), Type::Long)));
+    rejects("parameter register out of range", |function| function.params[0].0 = VReg(2));
+}
+
+#[test]
+fn rejects_registers_out_of_range() {
+    rejects("bb0: %2 in '%2 = add %0, 1' is out of range", |function| {
+        *function.blocks[0].instrs[0].dst_mut().unwrap() = VReg(2);
+    });
+    rejects("bb0: %5 in '%1 = add %0, %5' is out of range", |function| {
+        *function.blocks[0].instrs[0].operands_mut()[1] = Operand::Reg(VReg(5));
+    });
+    rejects("bb2: %9 in 'ret %9' is out of range", |function| function.blocks[2].terminator = Terminator::Return(Some(Operand::Reg(VReg(9)))));
+}
+
+#[test]
+fn rejects_jumps_to_missing_blocks() {
+    rejects("bb1: jump to missing block bb3", |function| function.blocks[1].terminator = Terminator::Jump(BlockId(3)));
+    rejects("bb0: jump to missing block bb7", |function| {
+        function.blocks[0].terminator = Terminator::Branch { condition: Operand::Imm(1), then_block: BlockId(1), else_block: BlockId(7) };
+    });
+}
+
+#[test]
+fn rejects_returns_that_do_not_match_the_type() {
+    rejects("bb2: non-void function returns without a value", |function| function.blocks[2].terminator = Terminator::Return(None));
+    rejects("bb1: void function returns a value", |function| {
+        function.return_type = Type::Void;
+        function.blocks[2].terminator = Terminator::Return(None);
+    });
+}
+
+#[test]
+fn rejects_casts_to_void() {
+    rejects("bb1: cast to void in '%1 = cast void %0'", |function| {
+        function.blocks[1].instrs.push(Instr::Cast { dst: VReg(1), ty: Type::Void, src: Operand::Reg(VReg(0)) });
+    });
+}
+
+#[test]
+fn rejects_misplaced_and_mismatched_phis() {
+    let phi = Instr::Phi { dst: VReg(1), sources: vec![(BlockId(1), Operand::Imm(1)), (BlockId(2), Operand::Imm(2))] };
+
+    // A join block whose phi takes a value from each predecessor is fine
+    let join = |function: &mut Function| {
+        function.blocks[1].terminator = Terminator::Jump(BlockId(3));
+        function.blocks[2].terminator = Terminator::Jump(BlockId(3));
+        function.blocks.push(Block { instrs: Vec::new(), terminator: Terminator::Return(Some(Operand::Reg(VReg(1)))) });
+    };
+
+    let mut function = function();
+    join(&mut function);
+    function.blocks[3].instrs.push(phi.clone());
+    assert_eq!(ir::verify_function(&function), Ok(()));
+
+    rejects("bb3: phi after a non-phi instruction", |function| {
+        join(function);
+        function.blocks[3].instrs.push(Instr::Copy { dst: VReg(0), src: Operand::Imm(0) });
+        function.blocks[3].instrs.push(phi.clone());
+    });
+    rejects("bb3: sources of '%1 = phi [bb1, 1]' do not match the predecessors", |function| {
+        join(function);
+        function.blocks[3].instrs.push(Instr::Phi { dst: VReg(1), sources: vec![(BlockId(1), Operand::Imm(1))] });
+    });
+    rejects("bb3: sources of '%1 = phi [bb1, 1], [bb2, 2], [bb0, 3]' do not match the predecessors", |function| {
+        join(function);
+        let sources = vec![(BlockId(1), Operand::Imm(1)), (BlockId(2), Operand::Imm(2)), (BlockId(0), Operand::Imm(3))];
+        function.blocks[3].instrs.push(Instr::Phi { dst: VReg(1), sources });
+    });
+}
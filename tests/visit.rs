@@ -0,0 +1,183 @@
+//! Checks the order the visitors walk the tree in, and that rewrites made
+//! through `VisitorMut` and `Fold` leave the tree they describe.
+
+extern crate cc;
+
+mod common;
+
+use cc::visit::{self, Fold, Visitor, VisitorMut};
+use cc::{BinaryOperator, Decl, Expr, ExprKind, FunctionDef, Param, Stmt, StmtKind};
+
+// Names each node as it is entered
+#[derive(Default)]
+struct Recorder {
+    visited: Vec<String>
+}
+
+impl Visitor for Recorder {
+    fn visit_function_def(&mut self, function: &FunctionDef) {
+        self.visited.push(format!("function {}", function.name));
+        visit::walk_function_def(self, function);
+    }
+
+    fn visit_param(&mut self, param: &Param) {
+        self.visited.push(format!("param {}", param.name.as_ref().unwrap()));
+    }
+
+    fn visit_decl(&mut self, decl: &Decl) {
+        self.visited.push(format!("decl {}", decl.name));
+        visit::walk_decl(self, decl);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        let name = match stmt.kind {
+            StmtKind::Return(_) => "return",
+            StmtKind::Expr(_) => "expression",
+            StmtKind::Compound(_) => "block",
+            StmtKind::If(_, _, _) => "if",
+            StmtKind::While(_, _) => "while"
+        };
+
+        self.visited.push(name.to_string());
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        let name = match expr.kind {
+            ExprKind::Constant(value) => value.to_string(),
+            ExprKind::Variable(ref name, _) => name.clone(),
+            ExprKind::Assignment(_, _) => "=".to_string(),
+            ExprKind::Unary(operator, _) => operator.symbol().to_string(),
+            ExprKind::Binary(operator, _, _) => operator.symbol().to_string(),
+            ExprKind::Call(ref name, _) => format!("{}()", name),
+            ExprKind::ImplicitCast(ty, _) => format!("({})", ty)
+        };
+
+        self.visited.push(name);
+        visit::walk_expr(self, expr);
+    }
+}
+
+// Renames every use and declaration of one variable
+struct Rename;
+
+impl VisitorMut for Rename {
+    fn visit_param_mut(&mut self, param: &mut Param) {
+        if param.name.as_deref() == Some("x") {
+            param.name = Some("y".to_string());
+        }
+    }
+
+    fn visit_decl_mut(&mut self, decl: &mut Decl) {
+        if decl.name == "x" {
+            decl.name = "y".to_string();
+        }
+
+        visit::walk_decl_mut(self, decl);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if let ExprKind::Variable(ref mut name, _) = expr.kind {
+            if name == "x" {
+                *name = "y".to_string();
+            }
+        }
+
+        visit::walk_expr_mut(self, expr);
+    }
+}
+
+// Replaces `e + 0` with `e`, after simplifying the operands
+struct DropAddZero;
+
+impl Fold for DropAddZero {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        let expr = visit::noop_fold_expr(self, expr);
+
+        match expr.kind {
+            ExprKind::Binary(BinaryOperator::Addition, lhs, rhs) if rhs.kind == ExprKind::Constant(0) => *lhs,
+            kind => Expr { kind, ..expr }
+        }
+    }
+}
+
+#[test]
+fn visits_nodes_in_source_order() {
+    let ast = common::analyze("
+int g(int a);
+
+int f(int a, int b) {
+    int c = a + 1;
+    if (c < b)
+        return g(-c);
+    while (b) {
+        b = b - 1;
+    }
+    return 0;
+}
+");
+
+    let mut recorder = Recorder::default();
+    recorder.visit_translation_unit(&ast);
+
+    assert_eq!(recorder.visited, [
+        "function g", "param a",
+        "function f", "param a", "param b",
+        "decl c", "+", "a", "1",
+        "if", "<", "c", "b", "return", "g()", "-", "c",
+        "while", "b", "block", "expression", "=", "b", "-", "b", "1",
+        "return", "0"
+    ]);
+}
+
+#[test]
+fn renames_variables_in_place() {
+    let mut ast = common::parse_without_spans("
+int f(int x) {
+    int z = x;
+    {
+        int x = z * 2;
+        z = x + f(x);
+    }
+    while (x) x = x - 1;
+    return z;
+}
+");
+
+    Rename.visit_translation_unit_mut(&mut ast);
+
+    assert_eq!(ast, common::parse_without_spans("
+int f(int y) {
+    int z = y;
+    {
+        int y = z * 2;
+        z = y + f(y);
+    }
+    while (y) y = y - 1;
+    return z;
+}
+"));
+}
+
+#[test]
+fn folds_nodes_into_ones_of_another_shape() {
+    let ast = common::parse_without_spans("
+int f(int a) {
+    int b = a + 0;
+    if ((a + 0) + 0 < 1)
+        return f(b + 0) * (a + 1);
+    return -(b + 0 + 0);
+}
+");
+
+    let folded = DropAddZero.fold_translation_unit(ast);
+
+    assert_eq!(folded, common::parse_without_spans("
+int f(int a) {
+    int b = a;
+    if (a < 1)
+        return f(b) * (a + 1);
+    return -b;
+}
+"));
+}
@@ -0,0 +1,163 @@
+//! Checks each warning, the source it points at, and which `-W` flags
+//! enable, disable or promote it.
+
+extern crate cc;
+
+use cc::diagnostics::{Severity, Warning, WarningOptions};
+use cc::{parser, sema};
+
+// A source line that triggers each warning, with the text its span covers
+const EXAMPLES: [(Warning, &str, &str, &str); 8] = [
+    (Warning::UnusedVariable, "int main() { int x; return 0; }", "unused variable 'x'", "int x;"),
+    (Warning::UnusedParameter, "int f(int a) { return 1; } int main() { return f(1); }", "unused parameter 'a'", "int a"),
+    (Warning::Conversion, "int main() { long l = 5; int x = l; return x; }",
+     "implicit conversion loses integer precision: 'long' to 'int'", "l"),
+    (Warning::SignCompare, "int main() { int x = -1; unsigned int u = 1; return x < u; }",
+     "comparison of integers of different signs: 'int' and 'unsigned int'", "x < u"),
+    (Warning::UnreachableCode, "int main() { return 1; return 2; }", "code will never be executed", "return 2;"),
+    (Warning::Parentheses, "int main() { int x = 0; if (x = 1) return 2; return x; }",
+     "using the result of an assignment as a condition without parentheses", "x = 1"),
+    (Warning::ReturnType, "int f(int a) { if (a) return 1; } int main() { return f(1); }",
+     "non-void function 'f' does not return a value in all control paths", "int f(int a) { if (a) return 1; }"),
+    (Warning::ShiftCountOverflow, "int main() { return 1 << 32; }", "shift count >= width of type", "32")
+];
+
+// The warnings on without any flags
+const DEFAULT: [Warning; 4] = [Warning::ReturnType, Warning::ShiftCountOverflow, Warning::DivisionByZero, Warning::IntegerOverflow];
+
+// The warnings left once the flags are applied, with their severity
+fn warnings(source: &str, flags: &[&str]) -> Vec<(Warning, Severity)> {
+    let mut options = WarningOptions::default();
+
+    for flag in flags {
+        assert!(options.apply_flag(flag), "{} is not a known flag", flag);
+    }
+
+    options.apply(sema::analyze(&mut parser::parse(source))).into_iter()
+        .map(|diagnostic| (diagnostic.warning.unwrap(), diagnostic.severity))
+        .collect()
+}
+
+fn enabled(flags: &[&str]) -> Vec<Warning> {
+    let mut options = WarningOptions::default();
+
+    for flag in flags {
+        options.apply_flag(flag);
+    }
+
+    Warning::ALL.iter().cloned().filter(|&warning| options.is_enabled(warning)).collect()
+}
+
+#[test]
+fn reports_each_warning_at_its_span() {
+    for &(warning, source, message, text) in &EXAMPLES {
+        let diagnostics = sema::analyze(&mut parser::parse(source));
+        let found: Vec<(Option<Warning>, &str, &str)> = diagnostics.iter()
+            .map(|diagnostic| (diagnostic.warning, &diagnostic.message[..], &source[diagnostic.span.start..diagnostic.span.end]))
+            .collect();
+
+        assert_eq!(found, [(Some(warning), message, text)], "wrong diagnostics for:\n{}", source);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+    }
+}
+
+#[test]
+fn leaves_out_what_does_not_warn() {
+    // Parenthesized assignments, constants that fit and non-negative constants are fine
+    let quiet = [
+        "int main() { int x = 0; if ((x = 1)) return 2; return x; }",
+        "int main() { char c = 100; long l = 5; long r = c + l; return r != 0; }",
+        "int main() { unsigned int u = 1; return u > 0; }",
+        "int main() { int x = 1; if (x) return 1; else return 2; }",
+        "int main() { while (1) { } }",
+        "int main() { int x = 1; return x << 31; }"
+    ];
+
+    for source in &quiet {
+        assert_eq!(sema::analyze(&mut parser::parse(source)), [], "{} warns", source);
+    }
+}
+
+#[test]
+fn reports_only_the_first_unreachable_statement() {
+    let source = "int main() { return 1; return 2; return 3; }";
+    let diagnostics = sema::analyze(&mut parser::parse(source));
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(&source[diagnostics[0].span.start..diagnostics[0].span.end], "return 2;");
+}
+
+#[test]
+fn enables_each_group_of_warnings() {
+    assert_eq!(enabled(&[]), DEFAULT);
+    assert_eq!(enabled(&["-Wall"]), [
+        Warning::UnusedVariable, Warning::Parentheses, Warning::ReturnType, Warning::ShiftCountOverflow,
+        Warning::DivisionByZero, Warning::IntegerOverflow
+    ]);
+
+    // -Wextra adds to -Wall rather than implying it, and neither enables -Wconversion
+    assert_eq!(enabled(&["-Wextra"]), [
+        Warning::UnusedParameter, Warning::SignCompare, Warning::UnreachableCode, Warning::ReturnType, Warning::ShiftCountOverflow,
+        Warning::DivisionByZero, Warning::IntegerOverflow
+    ]);
+    assert_eq!(enabled(&["-Wall", "-Wextra", "-Wconversion"]), [
+        Warning::UnusedVariable, Warning::UnusedParameter, Warning::Conversion, Warning::SignCompare,
+        Warning::UnreachableCode, Warning::Parentheses, Warning::ReturnType, Warning::ShiftCountOverflow,
+        Warning::DivisionByZero, Warning::IntegerOverflow
+    ]);
+}
+
+#[test]
+fn applies_flags_in_command_line_order() {
+    assert_eq!(enabled(&["-Wall", "-Wno-unused-variable"]), [
+        Warning::Parentheses, Warning::ReturnType, Warning::ShiftCountOverflow,
+        Warning::DivisionByZero, Warning::IntegerOverflow
+    ]);
+    assert_eq!(enabled(&["-Wno-unused-variable", "-Wall"]), [
+        Warning::UnusedVariable, Warning::Parentheses, Warning::ReturnType, Warning::ShiftCountOverflow,
+        Warning::DivisionByZero, Warning::IntegerOverflow
+    ]);
+    assert_eq!(enabled(&["-Wno-return-type", "-Wno-shift-count-overflow", "-Wsign-compare"]), [
+        Warning::SignCompare, Warning::DivisionByZero, Warning::IntegerOverflow
+    ]);
+}
+
+#[test]
+fn rejects_unknown_flags() {
+    let mut options = WarningOptions::default();
+
+    assert!(!options.apply_flag("-Wbogus"));
+    assert!(!options.apply_flag("-Wno-bogus"));
+    assert!(!options.apply_flag("-O2"));
+    assert!(options.apply_flag("-Wno-error"));
+}
+
+#[test]
+fn filters_and_promotes_diagnostics() {
+    let (_, source, _, _) = EXAMPLES[0];
+
+    assert_eq!(warnings(source, &[]), []);
+    assert_eq!(warnings(source, &["-Wall"]), [(Warning::UnusedVariable, Severity::Warning)]);
+    assert_eq!(warnings(source, &["-Wall", "-Werror"]), [(Warning::UnusedVariable, Severity::Error)]);
+    assert_eq!(warnings(source, &["-Werror", "-Wall", "-Wno-error"]), [(Warning::UnusedVariable, Severity::Warning)]);
+
+    // -Werror only promotes warnings that are enabled
+    assert_eq!(warnings(source, &["-Werror"]), []);
+}
+
+#[test]
+fn renders_the_flag_with_each_warning() {
+    let (_, source, _, _) = EXAMPLES[0];
+    let mut options = WarningOptions::default();
+    options.apply_flag("-Wall");
+
+    let diagnostic = options.apply(sema::analyze(&mut parser::parse(source))).remove(0);
+    assert_eq!(diagnostic.render("x.c", source), "x.c:1:14: warning: unused variable 'x' [-Wunused-variable]
+    int main() { int x; return 0; }
+                 ^~~~~~
+");
+
+    options.apply_flag("-Werror");
+    let diagnostic = options.apply(sema::analyze(&mut parser::parse(source))).remove(0);
+    assert!(diagnostic.render("x.c", source).starts_with("x.c:1:14: error: unused variable 'x' [-Werror,-Wunused-variable]\n"));
+}
@@ -0,0 +1,331 @@
+//! Checks the WebAssembly backend against golden text, and runs the programs
+//! under node, assembling the text with a small assembler of its own for the
+//! instructions the backend uses, so that wat2wasm is not needed.
+
+extern crate cc;
+
+mod common;
+
+use cc::{opt, wasm32};
+use cc::target::DataLayout;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::prelude::*;
+use std::process::Command;
+
+// Instantiates a module with `putchar` to import, and exits with what `main` returns
+const RUNNER: &str = "
+const bytes = require('fs').readFileSync(process.argv[2]);
+const env = { putchar: c => { process.stdout.write(String.fromCharCode(c & 255)); return c; } };
+WebAssembly.instantiate(bytes, { env }).then(({ instance }) => process.exit(instance.exports.main() & 255));
+";
+
+fn generate(source: &str, level: opt::Level) -> String {
+    wasm32::generate(&common::lower_for(source, level, &DataLayout::ILP32))
+}
+
+// A parenthesized list or a single token of the text format
+#[derive(Debug)]
+enum Sexp {
+    Atom(String),
+    List(Vec<Sexp>)
+}
+
+impl Sexp {
+    fn atom(&self) -> &str {
+        match *self {
+            Sexp::Atom(ref atom) => atom,
+            Sexp::List(_) => panic!("expected an atom, found {:?}", self)
+        }
+    }
+
+    fn list(&self) -> &[Sexp] {
+        match *self {
+            Sexp::List(ref items) => items,
+            Sexp::Atom(_) => panic!("expected a list, found {:?}", self)
+        }
+    }
+
+    // Whether this is a list starting with `keyword`
+    fn is(&self, keyword: &str) -> bool {
+        matches!(*self, Sexp::List(ref items) if matches!(items.first(), Some(Sexp::Atom(atom)) if atom == keyword))
+    }
+}
+
+fn parse(tokens: &mut std::iter::Peekable<std::vec::IntoIter<String>>) -> Sexp {
+    let token = tokens.next().expect("unexpected end of text");
+
+    if token != "(" {
+        return Sexp::Atom(token.trim_matches('"').to_string());
+    }
+
+    let mut items = Vec::new();
+
+    while tokens.peek().map(String::as_str) != Some(")") {
+        items.push(parse(tokens));
+    }
+
+    tokens.next();
+    Sexp::List(items)
+}
+
+fn unsigned(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            return bytes.push(byte);
+        }
+
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn signed(bytes: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            return bytes.push(byte);
+        }
+
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn name(bytes: &mut Vec<u8>, name: &str) {
+    unsigned(bytes, name.len() as u64);
+    bytes.extend_from_slice(name.as_bytes());
+}
+
+// A vector of `count` items already encoded in `contents`
+fn section(bytes: &mut Vec<u8>, id: u8, count: usize, contents: &[u8]) {
+    let mut body = Vec::new();
+    unsigned(&mut body, count as u64);
+    body.extend_from_slice(contents);
+
+    bytes.push(id);
+    unsigned(bytes, body.len() as u64);
+    bytes.extend(body);
+}
+
+// The parameter count and whether there is a result, from the fields of a `func`
+fn func_type(fields: &[Sexp]) -> (usize, bool) {
+    (fields.iter().filter(|field| field.is("param")).count(), fields.iter().any(|field| field.is("result")))
+}
+
+fn opcode(instr: &str) -> u8 {
+    match instr {
+        "unreachable" => 0x00,
+        "else" => 0x05,
+        "end" => 0x0b,
+        "return" => 0x0f,
+        "drop" => 0x1a,
+        "i32.eqz" => 0x45,
+        "i32.eq" => 0x46,
+        "i32.ne" => 0x47,
+        "i32.lt_s" => 0x48,
+        "i32.lt_u" => 0x49,
+        "i32.gt_s" => 0x4a,
+        "i32.gt_u" => 0x4b,
+        "i32.le_s" => 0x4c,
+        "i32.le_u" => 0x4d,
+        "i32.ge_s" => 0x4e,
+        "i32.ge_u" => 0x4f,
+        "i32.add" => 0x6a,
+        "i32.sub" => 0x6b,
+        "i32.mul" => 0x6c,
+        "i32.div_s" => 0x6d,
+        "i32.div_u" => 0x6e,
+        "i32.xor" => 0x73,
+        "i32.shl" => 0x74,
+        "i32.shr_s" => 0x75,
+        "i32.shr_u" => 0x76,
+        "i32.extend8_s" => 0xc0,
+        _ => panic!("unknown instruction {}", instr)
+    }
+}
+
+// Encodes the body of a `func`, whose instructions the backend writes flat
+// after its name and other fields, with `end` closing each `block`, `loop`
+// and `if`
+fn body(fields: &[Sexp], functions: &HashMap<String, usize>) -> Vec<u8> {
+    let locals: Vec<&str> = fields.iter()
+        .filter(|field| field.is("param") || field.is("local"))
+        .map(|field| field.list()[1].atom())
+        .collect();
+
+    let local_index = |local: &str| locals.iter().position(|&other| other == local).unwrap() as u64;
+    let declared = fields.iter().filter(|field| field.is("local")).count();
+
+    let mut bytes = Vec::new();
+
+    // All the locals are i32, so they are declared as one group
+    if declared == 0 {
+        unsigned(&mut bytes, 0);
+    } else {
+        unsigned(&mut bytes, 1);
+        unsigned(&mut bytes, declared as u64);
+        bytes.push(0x7f);
+    }
+
+    // The labels in scope, innermost last; an `if` has none
+    let mut labels: Vec<Option<&str>> = Vec::new();
+    let mut atoms = fields[2..].iter().filter_map(|field| match *field {
+        Sexp::Atom(ref atom) => Some(atom.as_str()),
+        Sexp::List(_) => None
+    });
+
+    while let Some(instr) = atoms.next() {
+        match instr {
+            "block" | "loop" => {
+                labels.push(Some(atoms.next().unwrap()));
+                bytes.extend_from_slice(&[if instr == "block" { 0x02 } else { 0x03 }, 0x40]);
+            },
+            "if" => {
+                labels.push(None);
+                bytes.extend_from_slice(&[0x04, 0x40]);
+            },
+            "br" | "br_if" => {
+                let label = atoms.next().unwrap();
+                let depth = labels.iter().rev().position(|&other| other == Some(label)).unwrap();
+
+                bytes.push(if instr == "br" { 0x0c } else { 0x0d });
+                unsigned(&mut bytes, depth as u64);
+            },
+            "call" => {
+                bytes.push(0x10);
+                unsigned(&mut bytes, functions[atoms.next().unwrap()] as u64);
+            },
+            "local.get" | "local.set" => {
+                bytes.push(if instr == "local.get" { 0x20 } else { 0x21 });
+                unsigned(&mut bytes, local_index(atoms.next().unwrap()));
+            },
+            "i32.const" => {
+                bytes.push(0x41);
+                signed(&mut bytes, atoms.next().unwrap().parse().unwrap());
+            },
+            _ => {
+                if instr == "end" {
+                    labels.pop();
+                }
+
+                bytes.push(opcode(instr));
+            }
+        }
+    }
+
+    bytes.push(0x0b);
+    bytes
+}
+
+// Assembles the text the backend generates into a binary module
+fn assemble(text: &str) -> Vec<u8> {
+    let spaced = text.replace('(', " ( ").replace(')', " ) ");
+    let tokens: Vec<String> = spaced.split_whitespace().map(str::to_string).collect();
+    let module = parse(&mut tokens.into_iter().peekable());
+    let fields = &module.list()[1..];
+
+    let imports: Vec<&[Sexp]> = fields.iter().filter(|field| field.is("import")).map(|field| field.list()).collect();
+    let funcs: Vec<&[Sexp]> = fields.iter().filter(|field| field.is("func")).map(|field| field.list()).collect();
+
+    // Imported functions are numbered first
+    let import_funcs: Vec<&[Sexp]> = imports.iter().map(|import| import[3].list()).collect();
+    let functions: HashMap<String, usize> = import_funcs.iter().chain(funcs.iter()).enumerate()
+        .map(|(index, func)| (func[1].atom().to_string(), index))
+        .collect();
+
+    let mut types: Vec<(usize, bool)> = import_funcs.iter().chain(funcs.iter()).map(|func| func_type(func)).collect();
+    types.sort();
+    types.dedup();
+
+    let type_index = |func: &[Sexp]| types.iter().position(|&ty| ty == func_type(func)).unwrap() as u64;
+
+    let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+
+    let mut contents = Vec::new();
+    for &(params, result) in &types {
+        contents.push(0x60);
+        unsigned(&mut contents, params as u64);
+        contents.extend(std::iter::repeat_n(0x7f, params));
+        unsigned(&mut contents, result as u64);
+        contents.extend(std::iter::repeat_n(0x7f, result as usize));
+    }
+    section(&mut bytes, 1, types.len(), &contents);
+
+    let mut contents = Vec::new();
+    for import in &imports {
+        name(&mut contents, import[1].atom());
+        name(&mut contents, import[2].atom());
+        contents.push(0x00);
+        unsigned(&mut contents, type_index(import[3].list()));
+    }
+    section(&mut bytes, 2, imports.len(), &contents);
+
+    let mut contents = Vec::new();
+    for func in &funcs {
+        unsigned(&mut contents, type_index(func));
+    }
+    section(&mut bytes, 3, funcs.len(), &contents);
+
+    // The backend's one page of memory and its stack pointer at the top
+    section(&mut bytes, 5, 1, &[0x00, 0x01]);
+
+    let mut contents = vec![0x7f, 0x01, 0x41];
+    signed(&mut contents, 65536);
+    contents.push(0x0b);
+    section(&mut bytes, 6, 1, &contents);
+
+    let mut contents = Vec::new();
+    name(&mut contents, "memory");
+    contents.extend_from_slice(&[0x02, 0x00]);
+    for func in &funcs {
+        name(&mut contents, func[1].atom().trim_start_matches('$'));
+        contents.push(0x00);
+        unsigned(&mut contents, functions[func[1].atom()] as u64);
+    }
+    section(&mut bytes, 7, funcs.len() + 1, &contents);
+
+    let mut contents = Vec::new();
+    for func in &funcs {
+        let code = body(func, &functions);
+        unsigned(&mut contents, code.len() as u64);
+        contents.extend(code);
+    }
+    section(&mut bytes, 10, funcs.len(), &contents);
+
+    bytes
+}
+
+#[test]
+fn matches_golden_text() {
+    for (name, source, _) in common::programs() {
+        for &(level_name, level) in &common::LEVELS {
+            common::check_golden(&format!("wasm32/{}-{}.wat", name, level_name), &generate(&source, level));
+        }
+    }
+}
+
+#[test]
+fn runs_under_node() {
+    if !common::installed("node") {
+        return eprintln!("node is not installed, skipping");
+    }
+
+    let directory = common::scratch_directory("wasm32-run");
+    let runner = directory.join("run.js");
+    common::write(&runner, RUNNER);
+
+    for (name, source, expected) in common::programs_for(&DataLayout::ILP32) {
+        for &(level_name, level) in &common::LEVELS {
+            let binary = directory.join(format!("{}-{}.wasm", name, level_name));
+            File::create(&binary).unwrap().write_all(&assemble(&generate(&source, level))).unwrap();
+
+            let output = Command::new("node").arg(&runner).arg(&binary).output().unwrap();
+            assert_eq!(output.status.code(), Some(expected), "{} exits with the wrong status:\n{}", binary.display(), String::from_utf8_lossy(&output.stderr));
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Runs programs using each construct of the language through both x86-64
+//! code generators, with and without optimization.
+
+extern crate cc;
+
+mod common;
+
+use cc::{codegen, generator, opt, peephole};
+use cc::target::DataLayout;
+use cc::x86::{self, Syntax};
+
+use std::process::Command;
+
+const LEVELS: [(&str, opt::Level); 2] = [("O0", opt::Level::O0), ("O2", opt::Level::O2)];
+
+// Compiles and runs a program at each level, checking the status it exits with
+fn check(name: &str, source: &str, expected: i32) {
+    if !common::installed("gcc") {
+        return eprintln!("gcc is not installed, skipping {}", name);
+    }
+
+    let directory = common::scratch_directory("x86_64");
+
+    for &(level_name, level) in &LEVELS {
+        let instrs = match level {
+            opt::Level::O0 => generator::generate(common::analyze(source)),
+            level => codegen::generate(&common::lower(source, level), &DataLayout::LP64)
+        };
+
+        let path = directory.join(format!("{}-{}.s", name, level_name));
+        let program = path.with_extension("");
+        common::write(&path, &x86::print(&peephole::optimize(instrs), Syntax::Att, &DataLayout::LP64));
+
+        let output = Command::new("gcc").arg(&path).arg("-o").arg(&program).output().unwrap();
+        assert!(output.status.success(), "{} does not link:\n{}", path.display(), String::from_utf8_lossy(&output.stderr));
+
+        let status = Command::new(&program).status().unwrap();
+        assert_eq!(status.code(), Some(expected), "{} exits with the wrong status", program.display());
+    }
+}
+
+#[test]
+fn runs_locals_assignment_and_blocks() {
+    check("locals", "
+int main() {
+    int a = 1;
+    int b;
+    b = a = a + 6;
+    {
+        int a = 10;
+        b = b * a;
+    }
+    return a + b;
+}
+", 77);
+}
+
+#[test]
+fn runs_each_integer_type() {
+    check("types", "
+int main() {
+    char c = 300;
+    unsigned int u = 0;
+    long l = 4294967296;
+    u = u - 1;
+    c = c + 1;
+    return (u > 0) + (l / 65536 == 65536) * 2 + (-2 < u) * 4 + c;
+}
+", 52);
+}
+
+#[test]
+fn runs_calls_through_prototypes() {
+    check("calls", "
+int twice(int);
+char low(long x);
+
+int main() {
+    return twice(low(4294967296 + 20)) + 1;
+}
+
+int twice(int x) {
+    return x * 2;
+}
+
+char low(long x) {
+    return x;
+}
+", 41);
+}
+
+#[test]
+fn runs_functions_with_unused_parameters() {
+    // Each parameter arrives in its own register, and extending the unused
+    // char must not clobber the others
+    check("unused", "
+long f(int p0, char p1, int p2, int p3) {
+    p2 = 1000;
+    p1 = p1;
+    return p3;
+}
+
+int main() {
+    return (0 > f(1, 3, 100, 750000000)) + f(4, 5, 6, 7);
+}
+", 7);
+}
+
+#[test]
+fn runs_branches_loops_and_shifts() {
+    check("control", "
+int main() {
+    int n = 0;
+    int bits = 0;
+    unsigned int u = 4294967295;
+    int s = -64;
+    while (n < 10) {
+        if (n > 6)
+            bits = bits + 1;
+        else if (n == 3)
+            bits = bits + (1 << 4);
+        else
+            bits = bits + 2;
+        n = n + 1;
+    }
+    return bits + (u >> 30) + (s >> 3) + 8;
+}
+", 34);
+}